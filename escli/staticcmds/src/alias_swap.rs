@@ -0,0 +1,199 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct AliasSwap {
+    #[arg(short, long, help = "Alias to move")]
+    alias: String,
+
+    #[arg(long, help = "Index the alias should point to")]
+    to: String,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Only remove the alias from these indices, comma separated, defaults to all"
+    )]
+    from: Vec<String>,
+
+    #[arg(long, help = "Make the new index the write index of the alias")]
+    write_index: bool,
+
+    #[arg(
+        long,
+        requires = "write_index",
+        help = "Keep the previous indices in the alias, as read-only members"
+    )]
+    keep_old: bool,
+}
+
+impl AliasSwap {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("alias-swap")
+            .about("Atomically move an alias to another index.")
+            .long_about(
+                r#"
+            Point an alias to a new index in a single atomic _aliases request,
+            so that readers and writers never see the alias missing or
+            pointing to both indices at once.
+
+            The target index must exist. The alias is removed from every index
+            it currently points to, or only from the ones given with --from,
+            and added to the target index.
+
+            With --write-index the target becomes the write index of the
+            alias; adding --keep-old keeps the previous indices in the alias
+            for searches, as a rollover does.
+
+            Example usage:
+                escli utils alias-swap --alias products --to products-v2
+                escli utils alias-swap --alias logs --to logs-000002 --write-index --keep-old
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+
+        let path = format!("/{}", self.to);
+        let (status, _) = send_json(&transport, Method::Head, &path, &[], None, t).await?;
+        if !status.is_success() {
+            eprintln!("Target index {} does not exist", self.to);
+            return Ok(response(404, Vec::new()));
+        }
+
+        let path = format!("/_alias/{}", self.alias);
+        let (status, current) = send_json(&transport, Method::Get, &path, &[], None, t).await?;
+        let current: Vec<String> = match status.as_u16() {
+            404 => Vec::new(),
+            _ if status.is_success() => current
+                .as_object()
+                .map(|o| o.keys().cloned().collect())
+                .unwrap_or_default(),
+            code => return Ok(response(code, current.to_string().into_bytes())),
+        };
+
+        let actions = self.actions(&current);
+        let (status, body) = send_json(
+            &transport,
+            Method::Post,
+            "/_aliases",
+            &[],
+            Some(&json!({ "actions": actions })),
+            t,
+        )
+        .await?;
+        if !status.is_success() {
+            return Ok(response(status.as_u16(), body.to_string().into_bytes()));
+        }
+
+        let previous: Vec<&str> = current
+            .iter()
+            .map(String::as_str)
+            .filter(|i| *i != self.to)
+            .collect();
+        let summary = match previous.is_empty() {
+            true => format!("Alias {} now points to {}\n", self.alias, self.to),
+            false => format!(
+                "Alias {} moved from {} to {}\n",
+                self.alias,
+                previous.join(", "),
+                self.to
+            ),
+        };
+        Ok(response(200, summary.into_bytes()))
+    }
+
+    /// The `_aliases` actions moving the alias from the `current` indices.
+    fn actions(&self, current: &[String]) -> Vec<Value> {
+        let mut actions = Vec::new();
+        for index in current {
+            if *index == self.to || !(self.from.is_empty() || self.from.contains(index)) {
+                continue;
+            }
+            let action = match self.keep_old {
+                true => {
+                    json!({ "add": { "index": index, "alias": self.alias, "is_write_index": false } })
+                }
+                false => json!({ "remove": { "index": index, "alias": self.alias } }),
+            };
+            actions.push(action);
+        }
+        let mut add = json!({ "index": self.to, "alias": self.alias });
+        if self.write_index {
+            add["is_write_index"] = json!(true);
+        }
+        actions.push(json!({ "add": add }));
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swap(args: &[&str]) -> AliasSwap {
+        AliasSwap::try_parse_from(["alias-swap"].iter().chain(args)).unwrap()
+    }
+
+    #[test]
+    fn actions_remove_alias_from_current_indices() {
+        let current = vec!["v1".to_string(), "v2".to_string()];
+        assert_eq!(
+            swap(&["--alias", "a", "--to", "v2"]).actions(&current),
+            vec![
+                json!({"remove": {"index": "v1", "alias": "a"}}),
+                json!({"add": {"index": "v2", "alias": "a"}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn actions_keep_old_indices_as_read_only() {
+        let current = vec!["v1".to_string(), "v2".to_string()];
+        assert_eq!(
+            swap(&[
+                "--alias",
+                "a",
+                "--to",
+                "v3",
+                "--from",
+                "v1",
+                "--write-index",
+                "--keep-old"
+            ])
+            .actions(&current),
+            vec![
+                json!({"add": {"index": "v1", "alias": "a", "is_write_index": false}}),
+                json!({"add": {"index": "v3", "alias": "a", "is_write_index": true}}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+/// Suggested fixes for the allocation deciders that commonly say no.
+const FIXES: &[(&str, &str)] = &[
+    (
+        "awareness",
+        "check the cluster.routing.allocation.awareness settings and the node attributes",
+    ),
+    (
+        "data_tier",
+        "add nodes to the required data tier or change index.routing.allocation.include._tier_preference",
+    ),
+    (
+        "disk_threshold",
+        "free disk space, add nodes or raise the disk watermarks",
+    ),
+    (
+        "enable",
+        "re-enable allocation with cluster.routing.allocation.enable=all",
+    ),
+    (
+        "filter",
+        "check the index.routing.allocation and cluster.routing.allocation filters",
+    ),
+    (
+        "max_retry",
+        "fix the cause of the failures, then run POST _cluster/reroute?retry_failed=true",
+    ),
+    (
+        "node_version",
+        "finish the rolling upgrade, shards cannot move to older nodes",
+    ),
+    (
+        "replica_after_primary_active",
+        "wait for the primary shard to be assigned first",
+    ),
+    (
+        "same_shard",
+        "add nodes or lower number_of_replicas, a node holds one copy of a shard at most",
+    ),
+    (
+        "shards_limit",
+        "raise index.routing.allocation.total_shards_per_node or cluster.routing.allocation.total_shards_per_node",
+    ),
+    (
+        "throttling",
+        "wait, recoveries are throttled by the cluster",
+    ),
+];
+
+const NO_VALID_SHARD_COPY: &str = "no_valid_shard_copy";
+
+#[derive(Parser, Debug)]
+pub struct AllocationExplain {
+    #[arg(
+        short,
+        long,
+        help = "Only explain the unassigned shards of these indices, comma separated"
+    )]
+    index: Option<String>,
+}
+
+/// A distinct reason for shards to stay unassigned.
+#[derive(Debug, Default)]
+struct Cause {
+    shards: BTreeSet<String>,
+    nodes: BTreeSet<String>,
+}
+
+impl AllocationExplain {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("allocation-explain")
+            .about("Summarize why shards are unassigned.")
+            .long_about(
+                r#"
+            Explain the allocation of every unassigned shard and print a
+            summary of the distinct causes, instead of one allocation explain
+            response per shard.
+
+            Each cause is the explanation of an allocation decider that
+            prevents the shard from being assigned to a node, with the number
+            of shards and nodes it concerns and a suggested fix. Node specific
+            values such as disk usage are masked so that similar explanations
+            are grouped.
+
+            Example usage:
+                escli utils allocation-explain
+                escli utils allocation-explain --index 'logs-*'
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let path = match &self.index {
+            Some(index) => format!("/_cat/shards/{index}"),
+            None => "/_cat/shards".to_string(),
+        };
+        let query = [("format", "json"), ("h", "index,shard,prirep,state")];
+        let Some(shards) = send_json_ok(&transport, Method::Get, &path, &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let mut causes: BTreeMap<(String, String), Cause> = BTreeMap::new();
+        let mut unassigned = 0;
+        for shard in shards.as_array().into_iter().flatten() {
+            if shard["state"] != "UNASSIGNED" {
+                continue;
+            }
+            unassigned += 1;
+            let index = shard["index"].as_str().unwrap_or_default();
+            let number = shard["shard"].as_str().unwrap_or_default();
+            let primary = shard["prirep"] == "p";
+            let body = json!({
+                "index": index,
+                "shard": number.parse::<u64>().unwrap_or_default(),
+                "primary": primary,
+            });
+            let Some(explain) = send_json_ok(
+                &transport,
+                Method::Post,
+                "/_cluster/allocation/explain",
+                &[],
+                Some(&body),
+                t,
+            )
+            .await?
+            else {
+                continue;
+            };
+            let label = format!("{index}[{number}]{}", if primary { "p" } else { "r" });
+            for (key, node) in shard_causes(&explain) {
+                let cause = causes.entry(key).or_default();
+                cause.shards.insert(label.clone());
+                if let Some(node) = node {
+                    cause.nodes.insert(node);
+                }
+            }
+        }
+
+        if unassigned == 0 {
+            return Ok(response(200, b"No unassigned shards\n".to_vec()));
+        }
+        Ok(response(200, render(unassigned, &causes).into_bytes()))
+    }
+}
+
+/// The causes keeping a shard unassigned, as `(decider, explanation)` keys
+/// with the node each one was reported for.
+fn shard_causes(explain: &Value) -> Vec<((String, String), Option<String>)> {
+    let mut causes = Vec::new();
+    if explain["can_allocate"] == NO_VALID_SHARD_COPY {
+        let explanation = explain["allocate_explanation"].as_str().unwrap_or_default();
+        causes.push(((NO_VALID_SHARD_COPY.to_string(), mask(explanation)), None));
+    }
+    for node in explain["node_allocation_decisions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        let name = node["node_name"].as_str().map(str::to_string);
+        for decider in node["deciders"].as_array().into_iter().flatten() {
+            if decider["decision"] != "NO" {
+                continue;
+            }
+            let key = (
+                decider["decider"].as_str().unwrap_or_default().to_string(),
+                mask(decider["explanation"].as_str().unwrap_or_default()),
+            );
+            causes.push((key, name.clone()));
+        }
+    }
+    if causes.is_empty() {
+        let explanation = explain["allocate_explanation"].as_str().unwrap_or_default();
+        causes.push((("allocation".to_string(), mask(explanation)), None));
+    }
+    causes
+}
+
+/// Masks the bracketed values holding numbers, such as disk usages, so that
+/// the explanations of different nodes compare equal.
+fn mask(explanation: &str) -> String {
+    let mut out = String::new();
+    let mut rest = explanation;
+    while let Some(start) = rest.find('[') {
+        let Some(end) = closing_bracket(&rest[start..]).map(|len| start + len) else {
+            break;
+        };
+        let inner = &rest[start + 1..end];
+        out.push_str(&rest[..start]);
+        match inner.contains(|c: char| c.is_ascii_digit()) && !inner.contains('=') {
+            true => out.push_str("[...]"),
+            false => out.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The position of the bracket closing the one `s` starts with.
+fn closing_bracket(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 1 => return Some(i),
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn render(unassigned: usize, causes: &BTreeMap<(String, String), Cause>) -> String {
+    let mut causes: Vec<_> = causes.iter().collect();
+    causes.sort_by(|a, b| b.1.shards.len().cmp(&a.1.shards.len()));
+
+    let mut out = format!(
+        "{unassigned} unassigned shard(s), {} distinct cause(s)\n",
+        causes.len()
+    );
+    for ((decider, explanation), cause) in causes {
+        let mut shards: Vec<&str> = cause.shards.iter().map(String::as_str).take(5).collect();
+        if cause.shards.len() > 5 {
+            shards.push("...");
+        }
+        out.push_str(&format!(
+            "\n{decider}: {} shard(s) on {} node(s): {}\n  {explanation}\n",
+            cause.shards.len(),
+            cause.nodes.len(),
+            shards.join(", ")
+        ));
+        let fix = match decider.as_str() {
+            NO_VALID_SHARD_COPY => {
+                Some("bring back the nodes holding the data, or restore the index from a snapshot")
+            }
+            d => FIXES
+                .iter()
+                .find(|(name, _)| *name == d)
+                .map(|(_, fix)| *fix),
+        };
+        if let Some(fix) = fix {
+            out.push_str(&format!("  fix: {fix}\n"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_hides_node_specific_values() {
+        assert_eq!(
+            mask(
+                "above the high watermark [cluster.routing.allocation.disk.watermark.high=90%], actual free: [5.2gb]"
+            ),
+            "above the high watermark [cluster.routing.allocation.disk.watermark.high=90%], actual free: [...]"
+        );
+    }
+
+    #[test]
+    fn causes_are_grouped_across_shards_and_nodes() {
+        let explain = json!({
+            "can_allocate": "no",
+            "node_allocation_decisions": [
+                {"node_name": "n1", "deciders": [
+                    {"decider": "same_shard", "decision": "NO", "explanation": "a copy is already allocated to this node [[logs][0], node[n1]]"},
+                    {"decider": "disk_threshold", "decision": "YES", "explanation": "enough disk"},
+                ]},
+                {"node_name": "n2", "deciders": [
+                    {"decider": "same_shard", "decision": "NO", "explanation": "a copy is already allocated to this node [[logs][0], node[n2]]"},
+                ]},
+            ],
+        });
+        let mut causes: BTreeMap<(String, String), Cause> = BTreeMap::new();
+        for label in ["logs[0]r", "logs[1]r"] {
+            for (key, node) in shard_causes(&explain) {
+                let cause = causes.entry(key).or_default();
+                cause.shards.insert(label.to_string());
+                cause.nodes.extend(node);
+            }
+        }
+        assert_eq!(
+            render(2, &causes),
+            "2 unassigned shard(s), 1 distinct cause(s)\n\
+             \n\
+             same_shard: 2 shard(s) on 2 node(s): logs[0]r, logs[1]r\n  \
+             a copy is already allocated to this node [...]\n  \
+             fix: add nodes or lower number_of_replicas, a node holds one copy of a shard at most\n"
+        );
+    }
+}
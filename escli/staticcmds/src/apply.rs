@@ -0,0 +1,472 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_to_string;
+use crate::request::{response, send_json};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Apply {
+    #[arg(
+        short,
+        long,
+        help = "Manifest file in YAML or JSON format, use - to read from stdin"
+    )]
+    file: PathBuf,
+
+    #[arg(long, help = "Only print the plan, do not change anything")]
+    dry_run: bool,
+}
+
+/// The desired state of the cluster, keyed by object name.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default)]
+    pipelines: BTreeMap<String, Value>,
+    #[serde(default)]
+    templates: BTreeMap<String, Value>,
+    #[serde(default)]
+    indices: BTreeMap<String, IndexSpec>,
+    #[serde(default)]
+    aliases: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IndexSpec {
+    #[serde(default)]
+    settings: Option<Value>,
+    #[serde(default)]
+    mappings: Option<Value>,
+}
+
+/// A single change of the plan, with the requests that carry it out.
+#[derive(Debug)]
+struct Change {
+    symbol: char,
+    description: String,
+    requests: Vec<(Method, String, Value)>,
+}
+
+impl Apply {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("apply")
+            .about("Converge indices, aliases, templates and pipelines to a manifest.")
+            .long_about(
+                r#"
+            Declaratively manage cluster configuration from a manifest file.
+
+            The manifest lists the desired ingest pipelines, index templates,
+            indices (settings and mappings) and aliases. Each object is compared
+            with the cluster and a plan is printed first:
+
+              + the object is missing and will be created
+              ~ the object differs and will be updated
+              - an alias will be removed from an index
+
+            Objects that already match are left untouched, so applying the same
+            manifest twice is a no-op. Only the keys present in the manifest are
+            compared: settings or mappings added on the cluster side are kept.
+            Static index settings, such as number_of_shards, cannot be changed
+            on an existing index and are reported as an error.
+
+            Manifest example:
+                pipelines:
+                  add-timestamp:
+                    processors:
+                      - set: { field: ingested_at, value: "{{_ingest.timestamp}}" }
+                templates:
+                  logs:
+                    index_patterns: ["logs-*"]
+                    template:
+                      settings: { number_of_replicas: 1 }
+                indices:
+                  products:
+                    settings: { number_of_replicas: 1 }
+                    mappings:
+                      properties:
+                        name: { type: text }
+                aliases:
+                  catalog: [products]
+
+            Example usage:
+                escli utils apply -f cluster.yaml --dry-run
+                escli utils apply -f cluster.yaml
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+
+        let manifest: Manifest =
+            serde_yaml::from_str(&read_to_string(&self.file).await?).map_err(|e| {
+                eprintln!("Failed to parse manifest {:?}: {}", self.file, e);
+                IoError::new(IoErrorKind::InvalidData, e)
+            })?;
+
+        let mut changes = Vec::new();
+        let mut errors = Vec::new();
+        for (name, desired) in &manifest.pipelines {
+            let path = format!("/_ingest/pipeline/{name}");
+            let (status, current) = send_json(&transport, Method::Get, &path, &[], None, t).await?;
+            changes.extend(plan_object(
+                "pipeline",
+                &path,
+                desired,
+                status.is_success().then(|| &current[name]),
+            ));
+        }
+        for (name, desired) in &manifest.templates {
+            let path = format!("/_index_template/{name}");
+            let (status, current) = send_json(&transport, Method::Get, &path, &[], None, t).await?;
+            let current = status
+                .is_success()
+                .then(|| &current["index_templates"][0]["index_template"]);
+            changes.extend(plan_object("template", &path, desired, current));
+        }
+        for (name, spec) in &manifest.indices {
+            let path = format!("/{name}");
+            let (status, current) = send_json(
+                &transport,
+                Method::Get,
+                &path,
+                &[("flat_settings", "true")],
+                None,
+                t,
+            )
+            .await?;
+            let current = status.is_success().then(|| &current[name]);
+            match plan_index(name, spec, current) {
+                Ok(c) => changes.extend(c),
+                Err(e) => errors.push(e),
+            }
+        }
+        for (alias, indices) in &manifest.aliases {
+            let path = format!("/_alias/{alias}");
+            let (status, current) = send_json(&transport, Method::Get, &path, &[], None, t).await?;
+            let current: BTreeSet<String> = match status.is_success() {
+                true => current
+                    .as_object()
+                    .map(|o| o.keys().cloned().collect())
+                    .unwrap_or_default(),
+                false => BTreeSet::new(),
+            };
+            changes.extend(plan_alias(alias, indices, &current));
+        }
+
+        let mut out = render_plan(&changes);
+        if !errors.is_empty() {
+            for e in &errors {
+                eprintln!("Error: {e}");
+            }
+            print!("{out}");
+            return Ok(response(400, Vec::new()));
+        }
+        if self.dry_run || changes.is_empty() {
+            return Ok(response(200, out.into_bytes()));
+        }
+
+        out.push('\n');
+        for change in changes {
+            for (method, path, body) in change.requests {
+                let (status, value) =
+                    send_json(&transport, method, &path, &[], Some(&body), t).await?;
+                if !status.is_success() {
+                    eprintln!(
+                        "{} failed with status {} - {}",
+                        change.description, status, value
+                    );
+                    print!("{out}");
+                    return Ok(response(status.as_u16().max(400), Vec::new()));
+                }
+            }
+            out.push_str(&format!("{} applied\n", change.description));
+        }
+        Ok(response(200, out.into_bytes()))
+    }
+}
+
+/// Plans the creation or update of an object replaced as a whole, such as an
+/// ingest pipeline or an index template.
+fn plan_object(kind: &str, path: &str, desired: &Value, current: Option<&Value>) -> Option<Change> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let symbol = match current {
+        None => '+',
+        Some(current) if is_subset(desired, current) => return None,
+        Some(_) => '~',
+    };
+    Some(Change {
+        symbol,
+        description: format!("{kind} {name}"),
+        requests: vec![(Method::Put, path.to_string(), desired.clone())],
+    })
+}
+
+/// Plans the creation of a missing index, or the settings and mappings
+/// updates of an existing one.
+fn plan_index(
+    name: &str,
+    spec: &IndexSpec,
+    current: Option<&Value>,
+) -> Result<Vec<Change>, String> {
+    let Some(current) = current else {
+        let mut body = Map::new();
+        if let Some(settings) = &spec.settings {
+            body.insert("settings".to_string(), settings.clone());
+        }
+        if let Some(mappings) = &spec.mappings {
+            body.insert("mappings".to_string(), mappings.clone());
+        }
+        return Ok(vec![Change {
+            symbol: '+',
+            description: format!("index {name}"),
+            requests: vec![(Method::Put, format!("/{name}"), Value::Object(body))],
+        }]);
+    };
+
+    let mut changes = Vec::new();
+    if let Some(settings) = &spec.settings {
+        let mut desired = BTreeMap::new();
+        flatten_settings("", settings, &mut desired);
+        let current = &current["settings"];
+        let changed: Map<String, Value> = desired
+            .into_iter()
+            .filter(|(k, v)| !is_subset(v, &current[k]))
+            .collect();
+        if let Some(key) = changed
+            .keys()
+            .find(|k| STATIC_SETTINGS.contains(&k.as_str()))
+        {
+            return Err(format!(
+                "index {name}: {key} cannot be changed on an existing index"
+            ));
+        }
+        if !changed.is_empty() {
+            changes.push(Change {
+                symbol: '~',
+                description: format!(
+                    "index {name} settings ({})",
+                    changed.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+                requests: vec![(
+                    Method::Put,
+                    format!("/{name}/_settings"),
+                    Value::Object(changed),
+                )],
+            });
+        }
+    }
+    if let Some(mappings) = &spec.mappings {
+        if !is_subset(mappings, &current["mappings"]) {
+            changes.push(Change {
+                symbol: '~',
+                description: format!("index {name} mappings"),
+                requests: vec![(Method::Put, format!("/{name}/_mapping"), mappings.clone())],
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Index settings that can only be set at index creation.
+const STATIC_SETTINGS: &[&str] = &[
+    "index.number_of_shards",
+    "index.codec",
+    "index.mode",
+    "index.routing_partition_size",
+    "index.sort.field",
+    "index.sort.order",
+];
+
+/// Plans the alias actions that make `alias` point to exactly `desired`.
+fn plan_alias(alias: &str, desired: &[String], current: &BTreeSet<String>) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for index in desired.iter().filter(|i| !current.contains(*i)) {
+        changes.push(Change {
+            symbol: '+',
+            description: format!("alias {alias} -> {index}"),
+            requests: vec![(
+                Method::Post,
+                "/_aliases".to_string(),
+                json!({"actions": [{"add": {"index": index, "alias": alias}}]}),
+            )],
+        });
+    }
+    for index in current.iter().filter(|i| !desired.contains(i)) {
+        changes.push(Change {
+            symbol: '-',
+            description: format!("alias {alias} -> {index}"),
+            requests: vec![(
+                Method::Post,
+                "/_aliases".to_string(),
+                json!({"actions": [{"remove": {"index": index, "alias": alias}}]}),
+            )],
+        });
+    }
+    changes
+}
+
+/// Flattens nested settings into `index.`-prefixed dotted keys, the form
+/// returned with `flat_settings=true`.
+fn flatten_settings(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{prefix}.{k}")
+                };
+                flatten_settings(&key, v, out);
+            }
+        }
+        v => {
+            let key = if prefix.starts_with("index.") {
+                prefix.to_string()
+            } else {
+                format!("index.{prefix}")
+            };
+            out.insert(key, v.clone());
+        }
+    }
+}
+
+/// Checks that every key of `desired` is present in `current` with the same
+/// value. Scalars are compared by their string form because Elasticsearch
+/// returns settings as strings.
+fn is_subset(desired: &Value, current: &Value) -> bool {
+    match (desired, current) {
+        (Value::Object(d), Value::Object(c)) => d
+            .iter()
+            .all(|(k, v)| c.get(k).is_some_and(|cv| is_subset(v, cv))),
+        (Value::Array(d), Value::Array(c)) => {
+            d.len() == c.len() && d.iter().zip(c).all(|(d, c)| is_subset(d, c))
+        }
+        (Value::Object(_) | Value::Array(_), _) | (_, Value::Object(_) | Value::Array(_)) => false,
+        (Value::String(d), c) => c.as_str().map_or(c.to_string(), str::to_string) == *d,
+        (d, Value::String(c)) => d.to_string() == *c,
+        (d, c) => d == c,
+    }
+}
+
+fn render_plan(changes: &[Change]) -> String {
+    let mut out = String::new();
+    for change in changes {
+        out.push_str(&format!("{} {}\n", change.symbol, change.description));
+    }
+    let count = |symbol| changes.iter().filter(|c| c.symbol == symbol).count();
+    out.push_str(&format!(
+        "Plan: {} to create, {} to update, {} to remove.\n",
+        count('+'),
+        count('~'),
+        count('-')
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_subset_ignores_extra_keys_and_string_numbers() {
+        let current = json!({"a": {"b": "1", "c": true}, "d": [1, 2]});
+        assert!(is_subset(&json!({"a": {"b": 1}}), &current));
+        assert!(is_subset(&json!({"d": [1, 2]}), &current));
+        assert!(!is_subset(&json!({"d": [1]}), &current));
+        assert!(!is_subset(&json!({"a": {"b": 2}}), &current));
+        assert!(!is_subset(&json!({"e": 1}), &current));
+    }
+
+    #[test]
+    fn flatten_settings_prefixes_index() {
+        let mut out = BTreeMap::new();
+        flatten_settings(
+            "",
+            &json!({"number_of_replicas": 1, "index": {"refresh_interval": "5s"}}),
+            &mut out,
+        );
+        assert_eq!(out["index.number_of_replicas"], json!(1));
+        assert_eq!(out["index.refresh_interval"], json!("5s"));
+    }
+
+    #[test]
+    fn plan_index_creates_missing_index() {
+        let spec = IndexSpec {
+            settings: Some(json!({"number_of_shards": 1})),
+            mappings: None,
+        };
+        let changes = plan_index("idx", &spec, None).unwrap();
+        assert_eq!(changes[0].symbol, '+');
+        assert_eq!(
+            changes[0].requests[0].2,
+            json!({"settings": {"number_of_shards": 1}})
+        );
+    }
+
+    #[test]
+    fn plan_index_updates_dynamic_settings_only() {
+        let current = json!({"settings": {"index.number_of_replicas": "0", "index.number_of_shards": "1"}, "mappings": {}});
+        let spec = IndexSpec {
+            settings: Some(json!({"number_of_replicas": 1})),
+            mappings: None,
+        };
+        let changes = plan_index("idx", &spec, Some(&current)).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].requests[0].1, "/idx/_settings");
+
+        let spec = IndexSpec {
+            settings: Some(json!({"number_of_shards": 3})),
+            mappings: None,
+        };
+        assert!(plan_index("idx", &spec, Some(&current)).is_err());
+    }
+
+    #[test]
+    fn plan_alias_adds_and_removes() {
+        let current = BTreeSet::from(["old".to_string()]);
+        let changes = plan_alias("a", &["new".to_string()], &current);
+        let symbols: Vec<char> = changes.iter().map(|c| c.symbol).collect();
+        assert_eq!(symbols, vec!['+', '-']);
+    }
+
+    #[test]
+    fn manifest_parses_yaml() {
+        let manifest: Manifest = serde_yaml::from_str(
+            "indices:\n  products:\n    mappings:\n      properties:\n        name: { type: text }\naliases:\n  catalog: [products]\n",
+        )
+        .unwrap();
+        assert!(manifest.indices["products"].mappings.is_some());
+        assert_eq!(manifest.aliases["catalog"], vec!["products".to_string()]);
+    }
+}
@@ -0,0 +1,255 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_json_file;
+use crate::request::{response, send_json};
+use crate::wait_for_health::HealthStatus;
+use clap::{ArgGroup, Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("checks").required(true).multiple(true).args(["health", "min_docs", "exists_index", "query_hits_gt"])))]
+pub struct Assert {
+    #[arg(long, value_enum, help = "Cluster health is at least this status")]
+    health: Option<HealthStatus>,
+
+    #[arg(
+        long,
+        value_name = "INDEX=COUNT",
+        help = "Index holds at least COUNT documents. Repeatable",
+        value_parser = parse_min_docs
+    )]
+    min_docs: Vec<(String, u64)>,
+
+    #[arg(
+        long,
+        value_name = "INDEX",
+        help = "Index, alias or data stream exists. Repeatable"
+    )]
+    exists_index: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "[INDEX=]FILE:COUNT",
+        help = "Search body in FILE matches more than COUNT documents. Repeatable",
+        value_parser = parse_query_hits
+    )]
+    query_hits_gt: Vec<QueryHits>,
+}
+
+/// A `--query-hits-gt` check.
+#[derive(Debug, Clone, PartialEq)]
+struct QueryHits {
+    index: Option<String>,
+    file: PathBuf,
+    count: u64,
+}
+
+fn parse_min_docs(s: &str) -> Result<(String, u64), String> {
+    let (index, count) = s
+        .rsplit_once('=')
+        .ok_or_else(|| format!("invalid check '{s}', expected INDEX=COUNT"))?;
+    let count = count
+        .parse()
+        .map_err(|_| format!("invalid document count '{count}'"))?;
+    Ok((index.to_string(), count))
+}
+
+fn parse_query_hits(s: &str) -> Result<QueryHits, String> {
+    let (target, count) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("invalid check '{s}', expected [INDEX=]FILE:COUNT"))?;
+    let count = count
+        .parse()
+        .map_err(|_| format!("invalid hit count '{count}'"))?;
+    let (index, file) = match target.split_once('=') {
+        Some((index, file)) => (Some(index.to_string()), file),
+        None => (None, target),
+    };
+    Ok(QueryHits {
+        index,
+        file: PathBuf::from(file),
+        count,
+    })
+}
+
+impl Assert {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("assert")
+            .about("Check conditions on the cluster and exit with an error if any fails.")
+            .long_about(
+                r#"
+            Run a set of checks against the cluster, print the outcome of each
+            of them and exit with an error when at least one failed, as a
+            building block for deployment pipelines and smoke tests.
+
+            Every check can be repeated, and all of them are run even when an
+            earlier one fails, so that a single run reports every problem.
+
+            Example usage:
+                escli utils assert --health green
+                escli utils assert --health yellow --exists-index orders --min-docs orders=1000
+                escli utils assert --query-hits-gt logs-*=errors.json:0
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let mut outcomes: Vec<(bool, String)> = Vec::new();
+
+        if let Some(wanted) = self.health {
+            let (status, health) =
+                send_json(&transport, Method::Get, "/_cluster/health", &[], None, t).await?;
+            let current = HealthStatus::from_health(&health).filter(|_| status.is_success());
+            outcomes.push(match current {
+                Some(current) => (
+                    current >= wanted,
+                    format!(
+                        "cluster health is {}, expected {}",
+                        current.as_str(),
+                        wanted.as_str()
+                    ),
+                ),
+                None => (false, format!("cluster health is unavailable: {health}")),
+            });
+        }
+
+        for (index, minimum) in &self.min_docs {
+            let path = format!("/{index}/_count");
+            let (status, body) = send_json(&transport, Method::Get, &path, &[], None, t).await?;
+            outcomes.push(match (status.is_success(), body["count"].as_u64()) {
+                (true, Some(count)) => (
+                    count >= *minimum,
+                    format!("{index} has {count} documents, expected at least {minimum}"),
+                ),
+                _ => (false, format!("{index} cannot be counted: {body}")),
+            });
+        }
+
+        for index in &self.exists_index {
+            let path = format!("/{index}");
+            let (status, _) = send_json(&transport, Method::Head, &path, &[], None, t).await?;
+            outcomes.push(match status.is_success() {
+                true => (true, format!("{index} exists")),
+                false => (false, format!("{index} does not exist")),
+            });
+        }
+
+        for check in &self.query_hits_gt {
+            outcomes.push(self.query_hits(&transport, check, t).await?);
+        }
+
+        let mut report = String::new();
+        for (passed, message) in &outcomes {
+            let label = match passed {
+                true => "PASS",
+                false => "FAIL",
+            };
+            report.push_str(&format!("{label}  {message}\n"));
+        }
+        print!("{report}");
+
+        let failed = outcomes.iter().filter(|(passed, _)| !passed).count();
+        let summary = format!(
+            "{} of {} checks passed\n",
+            outcomes.len() - failed,
+            outcomes.len()
+        );
+        let status = match failed {
+            0 => 200,
+            _ => 400,
+        };
+        Ok(response(status, summary.into_bytes()))
+    }
+
+    async fn query_hits(
+        &self,
+        transport: &Transport,
+        check: &QueryHits,
+        timeout: Duration,
+    ) -> Result<(bool, String), elasticsearch::Error> {
+        let name = label(&check.file);
+        let mut body = read_json_file(&check.file).await?;
+        if !body.is_object() {
+            return Ok((false, format!("{name} is not a search body")));
+        }
+        body["size"] = Value::from(0);
+        body["track_total_hits"] = Value::Bool(true);
+        let path = match &check.index {
+            Some(index) => format!("/{index}/_search"),
+            None => "/_search".to_string(),
+        };
+        let (status, result) =
+            send_json(transport, Method::Post, &path, &[], Some(&body), timeout).await?;
+        Ok(
+            match (
+                status.is_success(),
+                result["hits"]["total"]["value"].as_u64(),
+            ) {
+                (true, Some(hits)) => (
+                    hits > check.count,
+                    format!(
+                        "{name} matches {hits} documents, expected more than {}",
+                        check.count
+                    ),
+                ),
+                _ => (false, format!("{name} failed: {result}")),
+            },
+        )
+    }
+}
+
+fn label(file: &Path) -> String {
+    file.file_name().map_or(file.display().to_string(), |n| {
+        n.to_string_lossy().to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_checks() {
+        assert_eq!(
+            parse_min_docs("logs-*=1000"),
+            Ok(("logs-*".to_string(), 1000))
+        );
+        assert!(parse_min_docs("logs").is_err());
+        assert_eq!(
+            parse_query_hits("logs=q/errors.json:10"),
+            Ok(QueryHits {
+                index: Some("logs".to_string()),
+                file: PathBuf::from("q/errors.json"),
+                count: 10
+            })
+        );
+        assert_eq!(parse_query_hits("errors.json:0").unwrap().index, None);
+        assert!(parse_query_hits("errors.json").is_err());
+    }
+}
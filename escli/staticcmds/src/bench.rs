@@ -0,0 +1,282 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_to_string;
+use crate::request::{response, send_json};
+use crate::table::Table;
+use crate::units::parse_duration;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+#[derive(Parser, Debug)]
+pub struct Bench {
+    #[arg(short, long, help = "Index, alias or pattern the queries run against")]
+    index: String,
+
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        help = "Search body to replay, or NDJSON file of search bodies replayed in turn"
+    )]
+    query_file: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of searches in flight",
+        default_value_t = 8,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    concurrency: u64,
+
+    #[arg(
+        short,
+        long,
+        help = "How long the benchmark runs",
+        default_value = "60s",
+        value_parser = parse_duration
+    )]
+    duration: Duration,
+
+    #[arg(long, help = "Stop after this many searches, even before --duration")]
+    requests: Option<u64>,
+}
+
+/// The outcome of the searches of one query.
+#[derive(Debug, Default)]
+struct QueryStats {
+    latencies: Vec<Duration>,
+    errors: u64,
+}
+
+impl Bench {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("bench")
+            .about("Replay search queries and report throughput and latency percentiles.")
+            .long_about(
+                r#"
+            Replay a search body against an index with --concurrency searches
+            in flight for --duration, or until --requests searches were sent,
+            and report the throughput and the latency percentiles of each
+            query and of all of them, a lightweight alternative to Rally for
+            comparing queries or cluster settings.
+
+            --query-file holds a single search body, or one search body per
+            line which are sent in turn. Latencies are measured on the client
+            and include the network round trip. A search that fails is counted
+            as an error and the command exits with an error status.
+
+            Example usage:
+                escli utils bench --query-file q.json --index logs --concurrency 8 --duration 60s
+                escli utils bench -q queries.ndjson -i products --requests 1000
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let queries = match read_queries(&self.query_file).await? {
+            Ok(queries) => Arc::new(queries),
+            Err(message) => return Ok(response(400, format!("{message}\n").into_bytes())),
+        };
+
+        let path = Arc::new(format!("/{}/_search", self.index));
+        let sent = Arc::new(AtomicU64::new(0));
+        let limit = self.requests.unwrap_or(u64::MAX);
+        let started = Instant::now();
+        let deadline = started + self.duration;
+        let mut workers = JoinSet::new();
+        for _ in 0..self.concurrency {
+            let (transport, queries, path, sent) = (
+                transport.clone(),
+                queries.clone(),
+                path.clone(),
+                sent.clone(),
+            );
+            workers.spawn(async move {
+                let mut stats: Vec<QueryStats> =
+                    queries.iter().map(|_| QueryStats::default()).collect();
+                while Instant::now() < deadline {
+                    let n = sent.fetch_add(1, Ordering::Relaxed);
+                    if n >= limit {
+                        break;
+                    }
+                    let i = (n % queries.len() as u64) as usize;
+                    let begin = Instant::now();
+                    let (status, _) =
+                        send_json(&transport, Method::Post, &path, &[], Some(&queries[i]), t)
+                            .await?;
+                    match status.is_success() {
+                        true => stats[i].latencies.push(begin.elapsed()),
+                        false => stats[i].errors += 1,
+                    }
+                }
+                Ok::<_, elasticsearch::Error>(stats)
+            });
+        }
+
+        let mut stats: Vec<QueryStats> = queries.iter().map(|_| QueryStats::default()).collect();
+        while let Some(result) = workers.join_next().await {
+            for (total, worker) in stats.iter_mut().zip(result.map_err(IoError::other)??) {
+                total.latencies.extend(worker.latencies);
+                total.errors += worker.errors;
+            }
+        }
+        let elapsed = started.elapsed();
+
+        print!("{}", render_table(&stats));
+        let (searches, errors) = stats.iter().fold((0, 0), |(s, e), q| {
+            (s + q.latencies.len() as u64 + q.errors, e + q.errors)
+        });
+        let text = format!(
+            "{searches} searches in {:.1}s, {:.1} searches/s, {errors} errors\n",
+            elapsed.as_secs_f64(),
+            searches as f64 / elapsed.as_secs_f64().max(0.001)
+        );
+        Ok(response(
+            if errors > 0 { 500 } else { 200 },
+            text.into_bytes(),
+        ))
+    }
+}
+
+/// Reads a single search body, or one search body per line.
+async fn read_queries(path: &Path) -> Result<Result<Vec<Value>, String>, IoError> {
+    let text = read_to_string(path).await?;
+    if let Ok(query) = serde_json::from_str::<Value>(&text) {
+        return Ok(Ok(vec![query]));
+    }
+    let mut queries = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(query) => queries.push(query),
+            Err(err) => {
+                return Ok(Err(format!(
+                    "Invalid search body on line {} of {}: {err}",
+                    n + 1,
+                    path.display()
+                )));
+            }
+        }
+    }
+    match queries.is_empty() {
+        true => Ok(Err(format!("No search body in {}", path.display()))),
+        false => Ok(Ok(queries)),
+    }
+}
+
+/// Renders a row of latency percentiles per query, and one for all of them
+/// when there are several.
+fn render_table(stats: &[QueryStats]) -> String {
+    let mut table = Table::new(&["query", "searches", "errors", "p50", "p90", "p99", "max"]);
+    let mut all = QueryStats::default();
+    for (i, query) in stats.iter().enumerate() {
+        table.add_row(row(&format!("#{}", i + 1), query));
+        all.latencies.extend(&query.latencies);
+        all.errors += query.errors;
+    }
+    if stats.len() > 1 {
+        table.add_row(row("all", &all));
+    }
+    table.render()
+}
+
+fn row(label: &str, stats: &QueryStats) -> Vec<String> {
+    let mut latencies = stats.latencies.clone();
+    latencies.sort();
+    let mut row = vec![
+        label.to_string(),
+        (latencies.len() as u64 + stats.errors).to_string(),
+        stats.errors.to_string(),
+    ];
+    for p in [50.0, 90.0, 99.0, 100.0] {
+        row.push(percentile(&latencies, p).map_or("-".to_string(), format_latency));
+    }
+    row
+}
+
+/// The nearest-rank percentile of sorted latencies.
+fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.max(1) - 1).copied()
+}
+
+fn format_latency(d: Duration) -> String {
+    format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|v| Duration::from_millis(*v)).collect()
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let sorted = ms(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(percentile(&sorted, 50.0), Some(Duration::from_millis(5)));
+        assert_eq!(percentile(&sorted, 90.0), Some(Duration::from_millis(9)));
+        assert_eq!(percentile(&sorted, 99.0), Some(Duration::from_millis(10)));
+        assert_eq!(percentile(&sorted, 0.0), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn render_table_adds_total_row() {
+        let stats = [
+            QueryStats {
+                latencies: ms(&[4, 2]),
+                errors: 1,
+            },
+            QueryStats {
+                latencies: ms(&[10]),
+                errors: 0,
+            },
+        ];
+        assert_eq!(
+            render_table(&stats),
+            [
+                "query  searches  errors  p50     p90     p99     max",
+                "#1     3         1       2.0ms   4.0ms   4.0ms   4.0ms",
+                "#2     1         0       10.0ms  10.0ms  10.0ms  10.0ms",
+                "all    4         1       4.0ms   10.0ms  10.0ms  10.0ms",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
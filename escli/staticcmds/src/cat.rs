@@ -0,0 +1,226 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::table::Table;
+use crate::units::parse_bytes;
+use serde_json::{Map, Value};
+use std::cmp::{Ordering, Reverse};
+
+const OPERATORS: &[&str] = &["==", "!=", ">=", "<=", ">", "<", "~"];
+
+/// A `--filter` expression such as `health==green` or `docs.count>1000`.
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    column: String,
+    operator: &'static str,
+    value: String,
+}
+
+impl Filter {
+    fn parse(expr: &str) -> Result<Self, String> {
+        // Look for the earliest operator, preferring two-character ones at the same position.
+        let (pos, operator) = OPERATORS
+            .iter()
+            .filter_map(|op| expr.find(op).map(|pos| (pos, *op)))
+            .min_by_key(|(pos, op)| (*pos, Reverse(op.len())))
+            .ok_or_else(|| {
+                format!(
+                    "invalid filter '{expr}', expected <column><op><value> with op one of {}",
+                    OPERATORS.join(" ")
+                )
+            })?;
+        let column = expr[..pos].trim();
+        if column.is_empty() {
+            return Err(format!("invalid filter '{expr}', missing column"));
+        }
+        Ok(Filter {
+            column: column.to_string(),
+            operator,
+            value: expr[pos + operator.len()..].trim().to_string(),
+        })
+    }
+
+    fn matches(&self, row: &Map<String, Value>) -> bool {
+        let cell = cell(row, &self.column);
+        match self.operator {
+            "~" => cell.contains(&self.value),
+            op => {
+                let ord = compare(&cell, &self.value);
+                match op {
+                    "==" => ord == Ordering::Equal,
+                    "!=" => ord != Ordering::Equal,
+                    ">=" => ord != Ordering::Less,
+                    "<=" => ord != Ordering::Greater,
+                    ">" => ord == Ordering::Greater,
+                    _ => ord == Ordering::Less,
+                }
+            }
+        }
+    }
+}
+
+/// Returns the post-processing step of cat commands invoked with `--columns`,
+/// `--sort` or `--filter`.
+///
+/// The JSON form of the cat response is filtered, sorted and reduced to the
+/// selected columns client-side, then rendered as a table with a header row.
+pub fn cat_view(
+    columns: Vec<String>,
+    sort: Vec<String>,
+    filters: Vec<String>,
+) -> impl FnOnce(&[u8]) -> Result<Vec<u8>, String> + Send {
+    move |body| {
+        let filters = filters
+            .iter()
+            .map(|f| Filter::parse(f))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rows: Vec<Map<String, Value>> = serde_json::from_slice(body).map_err(|e| {
+            format!("--columns, --sort and --filter require a JSON cat response: {e}")
+        })?;
+        render(rows, &columns, &sort, &filters).map(String::into_bytes)
+    }
+}
+
+fn render(
+    mut rows: Vec<Map<String, Value>>,
+    columns: &[String],
+    sort: &[String],
+    filters: &[Filter],
+) -> Result<String, String> {
+    let available: Vec<String> = rows
+        .first()
+        .map(|r| r.keys().cloned().collect())
+        .unwrap_or_default();
+    let columns = if columns.is_empty() {
+        available.clone()
+    } else {
+        columns.to_vec()
+    };
+    let sort: Vec<(&str, bool)> = sort
+        .iter()
+        .map(|s| match s.rsplit_once(':') {
+            Some((column, "desc")) => (column, true),
+            Some((column, "asc")) => (column, false),
+            _ => (s.as_str(), false),
+        })
+        .collect();
+
+    if !rows.is_empty() {
+        let referenced = columns
+            .iter()
+            .map(String::as_str)
+            .chain(sort.iter().map(|(c, _)| *c))
+            .chain(filters.iter().map(|f| f.column.as_str()));
+        for column in referenced {
+            if !available.iter().any(|a| a == column) {
+                return Err(format!(
+                    "unknown column '{column}', available columns: {}",
+                    available.join(",")
+                ));
+            }
+        }
+    }
+
+    rows.retain(|row| filters.iter().all(|f| f.matches(row)));
+    rows.sort_by(|a, b| {
+        sort.iter()
+            .map(|(column, desc)| {
+                let ord = compare(&cell(a, column), &cell(b, column));
+                if *desc { ord.reverse() } else { ord }
+            })
+            .find(|ord| *ord != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let headers: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let mut table = Table::new(&headers);
+    for row in &rows {
+        table.add_row(columns.iter().map(|c| cell(row, c)).collect());
+    }
+    Ok(table.render())
+}
+
+fn cell(row: &Map<String, Value>, column: &str) -> String {
+    match row.get(column) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(v) => v.to_string(),
+    }
+}
+
+/// Compares two cells as numbers or byte sizes when both parse as such,
+/// falling back to a plain string comparison.
+fn compare(a: &str, b: &str) -> Ordering {
+    if let (Ok(x), Ok(y)) = (a.parse::<f64>(), b.parse::<f64>()) {
+        return x.partial_cmp(&y).unwrap_or(Ordering::Equal);
+    }
+    if let (Ok(x), Ok(y)) = (parse_bytes(a), parse_bytes(b)) {
+        return x.cmp(&y);
+    }
+    a.cmp(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows() -> Vec<Map<String, Value>> {
+        serde_json::from_value(json!([
+            {"index": "b", "health": "green", "docs.count": "20", "store.size": "1.5gb"},
+            {"index": "a", "health": "yellow", "docs.count": "100", "store.size": "500mb"},
+            {"index": "c", "health": "green", "docs.count": "3", "store.size": "2gb"},
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn filter_parse_prefers_longest_operator() {
+        let f = Filter::parse("docs.count>=10").unwrap();
+        assert_eq!(
+            (f.column.as_str(), f.operator, f.value.as_str()),
+            ("docs.count", ">=", "10")
+        );
+        assert!(Filter::parse("health").is_err());
+        assert!(Filter::parse("==green").is_err());
+    }
+
+    #[test]
+    fn render_selects_sorts_and_filters() {
+        let filters = vec![Filter::parse("health==green").unwrap()];
+        let out = render(
+            rows(),
+            &["index".into(), "store.size".into()],
+            &["store.size:desc".into()],
+            &filters,
+        )
+        .unwrap();
+        assert_eq!(out, "index  store.size\nc      2gb\nb      1.5gb\n");
+    }
+
+    #[test]
+    fn render_sorts_numbers_numerically() {
+        let out = render(rows(), &["index".into()], &["docs.count".into()], &[]).unwrap();
+        assert_eq!(out, "index\nc\nb\na\n");
+    }
+
+    #[test]
+    fn render_rejects_unknown_columns() {
+        let err = render(rows(), &["nope".into()], &[], &[]).unwrap_err();
+        assert!(err.contains("unknown column 'nope'"), "{err}");
+    }
+}
@@ -0,0 +1,305 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json, send_json_ok};
+use crate::table::Table;
+use crate::units::{format_duration, parse_duration};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Ccr {
+    #[arg(
+        help = "Follower indices to report on, wildcards allowed",
+        default_value = "_all"
+    )]
+    index: String,
+
+    #[arg(
+        long,
+        help = "Time without reading from the leader after which a follower behind is reported as stalled",
+        default_value = "5m",
+        value_parser = parse_duration
+    )]
+    stalled_after: Duration,
+
+    #[arg(long, conflicts_with = "resume", help = "Pause the active followers")]
+    pause: bool,
+
+    #[arg(long, help = "Resume the paused followers")]
+    resume: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Ok,
+    Lagging,
+    Stalled,
+    Failed,
+    Paused,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Lagging => "lagging",
+            Status::Stalled => "stalled",
+            Status::Failed => "failed",
+            Status::Paused => "paused",
+        }
+    }
+}
+
+/// The replication state of a follower index, from the follower info and
+/// the follower stats APIs.
+#[derive(Debug, PartialEq)]
+struct Follower {
+    index: String,
+    leader: String,
+    ops_behind: Option<u64>,
+    last_read: Option<Duration>,
+    status: Status,
+    reason: Option<String>,
+}
+
+impl Ccr {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("ccr")
+            .about("Summarize the replication lag of cross-cluster replication followers.")
+            .long_about(
+                r#"
+            List the follower indices of cross-cluster replication with their
+            leader, the number of operations they are behind the leader and
+            the time since they last read from it, summed up over their
+            shards.
+
+            Followers behind the leader are reported as lagging, and as
+            stalled when they have not read from the leader for longer than
+            --stalled-after. Followers whose replication stopped on an error
+            are reported as failed with the reason of the error.
+
+            With --pause the active followers are paused, and with --resume
+            the paused followers are resumed, once the report is printed.
+
+            Example usage:
+                escli utils ccr
+                escli utils ccr 'follower-*' --stalled-after 1m
+                escli utils ccr follower-logs --resume
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let path = format!("/{}/_ccr/info", self.index);
+        let Some(info) = send_json_ok(&transport, Method::Get, &path, &[], None, t).await? else {
+            return Ok(response(500, Vec::new()));
+        };
+        let Some(stats) =
+            send_json_ok(&transport, Method::Get, "/_ccr/stats", &[], None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let followers = self.followers(&info, &stats);
+        if followers.is_empty() {
+            let text = format!("No follower index matches {}\n", self.index);
+            return Ok(response(200, text.into_bytes()));
+        }
+        print!("{}", render(&followers));
+
+        let count = |status| followers.iter().filter(|f| f.status == status).count();
+        let mut summary = format!(
+            "{} followers, {} lagging, {} stalled, {} failed, {} paused\n",
+            followers.len(),
+            count(Status::Lagging),
+            count(Status::Stalled),
+            count(Status::Failed),
+            count(Status::Paused)
+        );
+
+        let (action, verb, done, request) = match (self.pause, self.resume) {
+            (true, _) => ("pause_follow", "pause", "Paused", None),
+            (_, true) => ("resume_follow", "resume", "Resumed", Some(json!({}))),
+            _ => return Ok(response(200, summary.into_bytes())),
+        };
+        let targets: Vec<&Follower> = followers
+            .iter()
+            .filter(|f| (f.status == Status::Paused) == self.resume)
+            .collect();
+        let mut errors = 0;
+        for follower in &targets {
+            let path = format!("/{}/_ccr/{action}", follower.index);
+            let (status, body) =
+                send_json(&transport, Method::Post, &path, &[], request.as_ref(), t).await?;
+            if !status.is_success() {
+                eprintln!("Failed to {verb} {}: {body}", follower.index);
+                errors += 1;
+            }
+        }
+        summary.push_str(&format!("{done} {} followers\n", targets.len() - errors));
+        let status = match errors {
+            0 => 200,
+            _ => 500,
+        };
+        Ok(response(status, summary.into_bytes()))
+    }
+
+    /// The followers of a follower info response with the lag from the
+    /// follower stats response, sorted by index.
+    fn followers(&self, info: &Value, stats: &Value) -> Vec<Follower> {
+        let shards: BTreeMap<&str, &Value> = stats["follow_stats"]["indices"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|i| Some((i["index"].as_str()?, &i["shards"])))
+            .collect();
+
+        let mut followers = Vec::new();
+        for entry in info["follower_indices"].as_array().into_iter().flatten() {
+            let index = entry["follower_index"].as_str().unwrap_or_default();
+            let leader = format!(
+                "{}:{}",
+                entry["remote_cluster"].as_str().unwrap_or("-"),
+                entry["leader_index"].as_str().unwrap_or("-")
+            );
+            let shards: Vec<&Value> = shards
+                .get(index)
+                .and_then(|s| s.as_array())
+                .into_iter()
+                .flatten()
+                .collect();
+
+            let (mut ops_behind, mut last_read, mut reason) = (None, None, None);
+            for shard in &shards {
+                let leader_checkpoint = shard["leader_global_checkpoint"].as_i64().unwrap_or(0);
+                let follower_checkpoint = shard["follower_global_checkpoint"].as_i64().unwrap_or(0);
+                let behind = leader_checkpoint.saturating_sub(follower_checkpoint).max(0) as u64;
+                *ops_behind.get_or_insert(0) += behind;
+                if let Some(ms) = shard["time_since_last_read_millis"].as_u64() {
+                    let read = Duration::from_millis(ms);
+                    last_read = Some(last_read.map_or(read, |r: Duration| r.max(read)));
+                }
+                let exception = shard["fatal_exception"]["reason"]
+                    .as_str()
+                    .or(shard["read_exceptions"][0]["exception"]["reason"].as_str());
+                if let (None, Some(exception)) = (&reason, exception) {
+                    reason = Some(exception.to_string());
+                }
+            }
+
+            let fatal = shards.iter().any(|s| s["fatal_exception"].is_object());
+            let status = match (entry["status"].as_str(), ops_behind, last_read) {
+                (Some("paused"), _, _) => Status::Paused,
+                _ if fatal => Status::Failed,
+                (_, Some(behind), Some(read)) if behind > 0 && read > self.stalled_after => {
+                    Status::Stalled
+                }
+                (_, Some(behind), _) if behind > 0 => Status::Lagging,
+                _ => Status::Ok,
+            };
+            followers.push(Follower {
+                index: index.to_string(),
+                leader,
+                ops_behind,
+                last_read,
+                status,
+                reason,
+            });
+        }
+        followers.sort_by(|a, b| a.index.cmp(&b.index));
+        followers
+    }
+}
+
+/// Renders the followers as a table, followed by the reasons of the errors.
+fn render(followers: &[Follower]) -> String {
+    let mut table = Table::new(&["follower", "leader", "ops.behind", "last.read", "status"]);
+    for follower in followers {
+        table.add_row(vec![
+            follower.index.clone(),
+            follower.leader.clone(),
+            follower
+                .ops_behind
+                .map_or("-".to_string(), |o| o.to_string()),
+            follower.last_read.map_or("-".to_string(), format_duration),
+            follower.status.as_str().to_string(),
+        ]);
+    }
+    let mut out = table.render();
+    for follower in followers {
+        if let Some(reason) = &follower.reason {
+            out.push_str(&format!("\n{}: {reason}\n", follower.index));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn followers_flag_lagging_stalled_and_failed() {
+        let ccr = Ccr::try_parse_from(["ccr", "--stalled-after", "1m"]).unwrap();
+        let info = json!({"follower_indices": [
+            {"follower_index": "f-ok", "remote_cluster": "eu", "leader_index": "l1", "status": "active"},
+            {"follower_index": "f-stalled", "remote_cluster": "eu", "leader_index": "l2", "status": "active"},
+            {"follower_index": "f-lagging", "remote_cluster": "eu", "leader_index": "l3", "status": "active"},
+            {"follower_index": "f-failed", "remote_cluster": "us", "leader_index": "l4", "status": "active"},
+            {"follower_index": "f-paused", "remote_cluster": "us", "leader_index": "l5", "status": "paused"},
+        ]});
+        let shard = |leader: i64, follower: i64, read: u64| {
+            json!({"leader_global_checkpoint": leader, "follower_global_checkpoint": follower,
+                "time_since_last_read_millis": read, "read_exceptions": []})
+        };
+        let stats = json!({"follow_stats": {"indices": [
+            {"index": "f-ok", "shards": [shard(10, 10, 50), shard(5, 5, 20)]},
+            {"index": "f-stalled", "shards": [shard(10, 4, 120_000), shard(3, 1, 90_000)]},
+            {"index": "f-lagging", "shards": [shard(10, 9, 1_000)]},
+            {"index": "f-failed", "shards": [{"leader_global_checkpoint": 3,
+                "follower_global_checkpoint": 3, "time_since_last_read_millis": 10,
+                "fatal_exception": {"type": "x", "reason": "leader index deleted"}}]},
+        ]}});
+        assert_eq!(
+            render(&ccr.followers(&info, &stats)),
+            [
+                "follower   leader  ops.behind  last.read  status",
+                "f-failed   us:l4   0           10ms       failed",
+                "f-lagging  eu:l3   1           1s         lagging",
+                "f-ok       eu:l1   0           50ms       ok",
+                "f-paused   us:l5   -           -          paused",
+                "f-stalled  eu:l2   8           2m 0s      stalled",
+                "",
+                "f-failed: leader index deleted",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,360 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::profile::Profile;
+use crate::request::send_json;
+use clap::{Command, CommandFactory, Parser, ValueEnum};
+use clap_complete::engine::CompletionCandidate;
+use elasticsearch::http::Method;
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Write};
+use std::path::PathBuf;
+use std::process::{Command as ProcessCommand, Stdio};
+use std::time::{Duration, SystemTime};
+
+/// Environment variable asking escli to refresh the index cache of a profile
+/// and exit, set on the background process spawned while completing.
+pub const REFRESH_INDEX_CACHE_ENV: &str = "ESCLI_REFRESH_INDEX_CACHE";
+
+/// Age after which the cached index names are refreshed in the background.
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Age after which a refresh that never completed is considered dead.
+const REFRESH_LOCK_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Parser, Debug)]
+pub struct Completions {
+    #[arg(value_enum, help = "Shell to generate the completion script for")]
+    shell: Shell,
+
+    #[arg(
+        long,
+        help = "Install the script in the shell's completion directory instead of printing it"
+    )]
+    install: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Shell {
+    Bash,
+    Elvish,
+    Fish,
+    Powershell,
+    Zsh,
+}
+
+impl Completions {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("completions")
+            .about("Print or install shell completion scripts.")
+            .long_about(
+                r#"
+            Print the completion script of a shell, or install it with --install.
+
+            Completions are dynamic: the script calls escli back to complete
+            commands, arguments and values, so it does not need to be
+            regenerated when escli is upgraded.
+
+            --install writes the script where the shell loads it automatically:
+              bash  $XDG_DATA_HOME/bash-completion/completions/escli
+              fish  $XDG_CONFIG_HOME/fish/completions/escli.fish
+              zsh   ~/.zfunc/_escli, with ~/.zfunc in fpath before compinit
+            For elvish and powershell, add the printed script to your profile.
+
+            When a profile is active, with --profile or ESCLI_PROFILE, index
+            arguments also complete with the index, alias and data stream
+            names of its cluster. The names are cached and refreshed in the
+            background every 5 minutes, so completing never waits on the
+            cluster.
+
+            Example usage:
+                escli completions bash --install
+                escli completions zsh > ~/.zfunc/_escli
+                escli completions powershell >> $PROFILE
+            "#,
+            )
+    }
+
+    /// Prints or installs the completion script and returns the exit code of
+    /// the process.
+    pub fn execute(self) -> i32 {
+        let result = self.script().and_then(|script| match self.install {
+            true => self.install_script(&script),
+            false => std::io::stdout().write_all(&script),
+        });
+        match result {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{e}");
+                1
+            }
+        }
+    }
+
+    /// Asks escli itself for the registration script, as `COMPLETE=<shell> escli` does.
+    fn script(&self) -> Result<Vec<u8>, IoError> {
+        let shell = self.shell.to_possible_value().expect("no skipped variants");
+        let output = ProcessCommand::new(std::env::current_exe()?)
+            .env("COMPLETE", shell.get_name())
+            .output()?;
+        if !output.status.success() {
+            return Err(IoError::other(format!(
+                "failed to generate the completion script: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(output.stdout)
+    }
+
+    fn install_script(&self, script: &[u8]) -> Result<(), IoError> {
+        let path = install_path(self.shell)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, script)?;
+        println!("Installed completions to {}", path.display());
+        if let (Shell::Zsh, Some(dir)) = (self.shell, path.parent()) {
+            println!(
+                "Make sure {} is in your fpath, e.g. add `fpath+={}` before compinit in your .zshrc",
+                dir.display(),
+                dir.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn install_path(shell: Shell) -> Result<PathBuf, IoError> {
+    // A directory from the environment, defaulting to a path relative to the home directory.
+    let dir = |var: &str, default: &str| -> Result<PathBuf, IoError> {
+        if let Some(dir) = std::env::var_os(var) {
+            return Ok(PathBuf::from(dir));
+        }
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(default))
+            .ok_or_else(|| IoError::new(IoErrorKind::NotFound, "HOME is not set"))
+    };
+    match shell {
+        Shell::Bash => Ok(dir("XDG_DATA_HOME", ".local/share")?
+            .join("bash-completion")
+            .join("completions")
+            .join("escli")),
+        Shell::Fish => Ok(dir("XDG_CONFIG_HOME", ".config")?
+            .join("fish")
+            .join("completions")
+            .join("escli.fish")),
+        Shell::Zsh => Ok(dir("ZDOTDIR", "")?.join(".zfunc").join("_escli")),
+        Shell::Elvish | Shell::Powershell => Err(IoError::new(
+            IoErrorKind::Unsupported,
+            "--install is not supported for this shell, add the printed script to your profile instead",
+        )),
+    }
+}
+
+/// Completes index, alias and data stream names from the cache of the active
+/// profile, starting a background refresh when the cache is missing or stale.
+///
+/// Comma separated lists are supported: only the last name is completed.
+pub fn complete_index(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(profile) = active_profile() else {
+        return Vec::new();
+    };
+    let path = cache_path(&profile);
+    if is_older_than(&path, CACHE_TTL) {
+        spawn_refresh(&profile);
+    }
+    let Ok(cached) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let current = current.to_string_lossy();
+    matching_names(&current, cached.lines())
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Fetches the index, alias and data stream names of a profile's cluster and
+/// writes them to its completion cache.
+pub async fn refresh_index_cache(profile: &str) -> Result<(), String> {
+    let path = cache_path(profile);
+    let result = fetch_names(profile).await.and_then(|names| {
+        let dir = path.parent().expect("cache path has a parent");
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        // Write then rename so that a completion never reads a partial list.
+        let tmp = path.with_extension("tmp");
+        let mut content = names.into_iter().collect::<Vec<_>>().join("\n");
+        content.push('\n');
+        std::fs::write(&tmp, content).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    });
+    let _ = std::fs::remove_file(lock_path(profile));
+    result
+}
+
+async fn fetch_names(profile: &str) -> Result<BTreeSet<String>, String> {
+    let transport = Profile::load(profile)
+        .map_err(|e| e.to_string())?
+        .transport()?;
+    let t = Duration::from_secs(10);
+    let sources = [
+        ("/_cat/indices", "index"),
+        ("/_cat/aliases", "alias"),
+        ("/_data_stream", "name"),
+    ];
+    let mut names = BTreeSet::new();
+    for (path, key) in sources {
+        let query = [("format", "json"), ("expand_wildcards", "all")];
+        let (status, body) = send_json(&transport, Method::Get, path, &query, None, t)
+            .await
+            .map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            continue;
+        }
+        // `_data_stream` wraps its list in a `data_streams` object.
+        let rows = body
+            .get("data_streams")
+            .unwrap_or(&body)
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        names.extend(
+            rows.iter()
+                .filter_map(|row| row[key].as_str())
+                .map(String::from),
+        );
+    }
+    Ok(names)
+}
+
+/// Names completing the last element of a comma separated list, prefixed with
+/// the elements already typed.
+fn matching_names<'a>(current: &str, names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let (typed, last) = match current.rsplit_once(',') {
+        Some((typed, last)) => (format!("{typed},"), last),
+        None => (String::new(), current),
+    };
+    names
+        .filter(|name| !name.is_empty() && name.starts_with(last))
+        .map(|name| format!("{typed}{name}"))
+        .collect()
+}
+
+/// The profile named on the command line being completed, or in ESCLI_PROFILE.
+fn active_profile() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.windows(2)
+        .find(|w| w[0] == "--profile")
+        .map(|w| w[1].clone())
+        .or_else(|| {
+            args.iter()
+                .find_map(|a| a.strip_prefix("--profile=").map(String::from))
+        })
+        .or_else(|| std::env::var("ESCLI_PROFILE").ok())
+        .filter(|name| !name.is_empty())
+}
+
+/// Starts `escli` in the background to refresh the cache, unless a refresh is
+/// already running.
+fn spawn_refresh(profile: &str) {
+    let lock = lock_path(profile);
+    if !is_older_than(&lock, REFRESH_LOCK_TTL) {
+        return;
+    }
+    if let Some(dir) = lock.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if std::fs::write(&lock, b"").is_err() {
+        return;
+    }
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+    let _ = ProcessCommand::new(exe)
+        .env(REFRESH_INDEX_CACHE_ENV, profile)
+        .env_remove("COMPLETE")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+/// Whether a file is missing or was last modified more than `ttl` ago.
+fn is_older_than(path: &std::path::Path, ttl: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_none_or(|age| age > ttl)
+}
+
+/// Directory of the completion caches: `escli/completions` in the user cache
+/// directory.
+fn cache_dir() -> PathBuf {
+    let cache_dir = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+    };
+    cache_dir
+        .unwrap_or_else(std::env::temp_dir)
+        .join("escli")
+        .join("completions")
+}
+
+fn cache_path(profile: &str) -> PathBuf {
+    cache_dir().join(format!("{profile}.indices"))
+}
+
+fn lock_path(profile: &str) -> PathBuf {
+    cache_dir().join(format!("{profile}.lock"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_names_filters_by_prefix() {
+        let names = ["logs-1", "logs-2", "metrics", ""];
+        assert_eq!(
+            matching_names("lo", names.into_iter()),
+            vec!["logs-1", "logs-2"]
+        );
+        assert_eq!(matching_names("", names.into_iter()).len(), 3);
+    }
+
+    #[test]
+    fn matching_names_completes_last_list_element() {
+        let names = ["logs-1", "metrics"];
+        assert_eq!(
+            matching_names("logs-1,me", names.into_iter()),
+            vec!["logs-1,metrics"]
+        );
+    }
+
+    #[test]
+    fn missing_file_is_stale() {
+        assert!(is_older_than(
+            std::path::Path::new("/nonexistent/escli/cache"),
+            CACHE_TTL
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::profile::profile_transport;
+use crate::request::{print_response, response, send_json, send_json_ok};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+/// Index settings that are set by Elasticsearch and rejected on creation,
+/// as dotted paths under `settings.index`.
+pub(crate) const NON_COPYABLE_SETTINGS: &[&str] = &[
+    "uuid",
+    "creation_date",
+    "creation_date_string",
+    "version",
+    "provided_name",
+    "history.uuid",
+    "resize",
+    "routing.allocation.initial_recovery",
+    "verified_before_close",
+    "blocks.write",
+];
+
+#[derive(Parser, Debug)]
+pub struct CopyIndex {
+    #[arg(help = "Index to copy the settings and mappings of")]
+    source: String,
+
+    #[arg(help = "Name of the index to create, defaults to the source name")]
+    target: Option<String>,
+
+    #[arg(
+        long,
+        help = "Profile of the source cluster, defaults to the cluster of --url"
+    )]
+    from_profile: Option<String>,
+
+    #[arg(
+        long,
+        help = "Profile of the destination cluster, defaults to the source cluster"
+    )]
+    to_profile: Option<String>,
+
+    #[arg(long, help = "Also copy the aliases of the source index")]
+    include_aliases: bool,
+
+    #[arg(long, help = "Print the create index request instead of sending it")]
+    dry_run: bool,
+}
+
+impl CopyIndex {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("copy-index")
+            .about("Create an index with the settings and mappings of another one.")
+            .long_about(
+                r#"
+            Read the settings and mappings of a source index and create a
+            target index with them, on the same cluster or on another one.
+
+            Settings managed by Elasticsearch, such as the index uuid, creation
+            date and version, are stripped since they are rejected on
+            creation. Aliases are only copied with --include-aliases, as they
+            usually point to the source index on purpose.
+
+            With --dry-run, the create index request is printed instead of
+            being sent.
+
+            Example usage:
+                escli utils copy-index logs-2024 logs-2025
+                escli utils copy-index orders --from-profile prod --to-profile staging --include-aliases
+                escli utils copy-index orders orders-v2 --dry-run
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let source = match &self.from_profile {
+            Some(name) => profile_transport(name)?,
+            None => transport,
+        };
+        self.run(source, timeout).await
+    }
+
+    /// Runs a copy whose source is given with --from-profile, without a
+    /// --url connection, and returns the exit code of the process.
+    pub async fn execute_standalone(self, timeout: Option<Duration>) -> i32 {
+        let Some(name) = self.from_profile.clone() else {
+            eprintln!("--from-profile is required without --url");
+            return 1;
+        };
+        let result = match profile_transport(&name) {
+            Ok(source) => self.run(source, timeout).await,
+            Err(e) => Err(e.into()),
+        };
+        print_response(result).await
+    }
+
+    async fn run(
+        &self,
+        source: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let target = self.target.as_deref().unwrap_or(&self.source);
+        if target == self.source && self.to_profile.is_none() {
+            eprintln!("The target index must differ from the source index on the same cluster");
+            return Ok(response(400, Vec::new()));
+        }
+
+        let path = format!("/{}", self.source);
+        let Some(indices) = send_json_ok(&source, Method::Get, &path, &[], None, t).await? else {
+            return Ok(response(404, Vec::new()));
+        };
+        // The response is keyed by the concrete index name, which differs from
+        // the argument when the source is an alias.
+        let Some((_, index)) = indices.as_object().and_then(|o| o.iter().next()) else {
+            eprintln!("Index {} not found", self.source);
+            return Ok(response(404, Vec::new()));
+        };
+        let body = create_body(index, self.include_aliases);
+
+        let path = format!("/{target}");
+        if self.dry_run {
+            let pretty = serde_json::to_string_pretty(&body).unwrap_or_default();
+            return Ok(response(
+                200,
+                format!("PUT {path}\n{pretty}\n").into_bytes(),
+            ));
+        }
+        let destination = match &self.to_profile {
+            Some(name) => profile_transport(name)?,
+            None => source,
+        };
+        let (status, body) =
+            send_json(&destination, Method::Put, &path, &[], Some(&body), t).await?;
+        Ok(response(status.as_u16(), body.to_string().into_bytes()))
+    }
+}
+
+/// Builds the create index body from a `GET /<index>` entry.
+pub(crate) fn create_body(index: &Value, include_aliases: bool) -> Value {
+    let mut settings = index["settings"].clone();
+    if let Some(index_settings) = settings.get_mut("index") {
+        for setting in NON_COPYABLE_SETTINGS {
+            remove_path(index_settings, setting);
+        }
+    }
+    let mut body = json!({
+        "settings": settings,
+        "mappings": index["mappings"],
+    });
+    if include_aliases {
+        body["aliases"] = index["aliases"].clone();
+    }
+    body
+}
+
+/// Removes a dotted path from nested objects, then the objects it leaves empty.
+pub(crate) fn remove_path(value: &mut Value, path: &str) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    match path.split_once('.') {
+        None => {
+            object.remove(path);
+        }
+        Some((head, rest)) => {
+            let Some(child) = object.get_mut(head) else {
+                return;
+            };
+            remove_path(child, rest);
+            if child.as_object().is_some_and(|o| o.is_empty()) {
+                object.remove(head);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_body_strips_non_copyable_settings() {
+        let index = json!({
+            "aliases": {"logs": {}},
+            "mappings": {"properties": {"a": {"type": "keyword"}}},
+            "settings": {"index": {
+                "number_of_shards": "2",
+                "uuid": "abc",
+                "creation_date": "1700000000000",
+                "version": {"created": "8500000"},
+                "provided_name": "logs-1",
+                "routing": {"allocation": {"initial_recovery": {"_id": "x"}, "include": {"tier": "hot"}}},
+            }},
+        });
+        assert_eq!(
+            create_body(&index, false),
+            json!({
+                "mappings": {"properties": {"a": {"type": "keyword"}}},
+                "settings": {"index": {
+                    "number_of_shards": "2",
+                    "routing": {"allocation": {"include": {"tier": "hot"}}},
+                }},
+            })
+        );
+        assert_eq!(create_body(&index, true)["aliases"], json!({"logs": {}}));
+    }
+
+    #[test]
+    fn remove_path_drops_emptied_parents() {
+        let mut value = json!({"history": {"uuid": "x"}, "a": 1});
+        remove_path(&mut value, "history.uuid");
+        assert_eq!(value, json!({"a": 1}));
+    }
+}
@@ -0,0 +1,238 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_json_file;
+use crate::request::{response, send_json, send_json_ok};
+use crate::table::Table;
+use clap::{Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Count {
+    #[arg(help = "Indices, aliases or data streams to count, wildcards allowed")]
+    index: String,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "How counts are grouped",
+        default_value_t = CountBy::Index
+    )]
+    by: CountBy,
+
+    #[arg(
+        short,
+        long,
+        help = "File with the query to count, as a query clause or a body with a query"
+    )]
+    query_file: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum CountBy {
+    /// One row per concrete index
+    Index,
+    /// One row per data stream, other indices on their own
+    DataStream,
+}
+
+impl Count {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("count")
+            .about("Count the documents of each index of a pattern, with a grand total.")
+            .long_about(
+                r#"
+            Resolve a pattern to its concrete indices and count the documents
+            of each of them, optionally matching a query, then print a table of
+            the counts and their grand total.
+
+            Indices are counted one by one, so that empty indices are listed
+            too, which is handy to validate the data after a migration or a
+            reindex. With --by data-stream, the backing indices of a data
+            stream are added up into a single row.
+
+            Example usage:
+                escli utils count 'logs-*'
+                escli utils count 'logs-*' --by index --query-file q.json
+                escli utils count 'metrics-*' --by data-stream
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let body = match &self.query_file {
+            Some(path) => Some(count_body(read_json_file(path).await?)),
+            None => None,
+        };
+
+        let path = format!("/_resolve/index/{}", self.index);
+        let Some(resolved) = send_json_ok(&transport, Method::Get, &path, &[], None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let indices = concrete_indices(&resolved);
+        if indices.is_empty() {
+            let text = format!("No index matches {}\n", self.index);
+            return Ok(response(404, text.into_bytes()));
+        }
+
+        let mut counts = Vec::new();
+        let mut failed = 0;
+        for (index, data_stream) in indices {
+            let path = format!("/{index}/_count");
+            let (status, result) =
+                send_json(&transport, Method::Post, &path, &[], body.as_ref(), t).await?;
+            let count = match (status.is_success(), result["count"].as_u64()) {
+                (true, Some(count)) => Some(count),
+                _ => {
+                    eprintln!("Failed to count {index}: {result}");
+                    failed += 1;
+                    None
+                }
+            };
+            let group = match (self.by, data_stream) {
+                (CountBy::DataStream, Some(data_stream)) => data_stream,
+                _ => index,
+            };
+            counts.push((group, count));
+        }
+
+        let status = match failed {
+            0 => 200,
+            _ => 500,
+        };
+        Ok(response(status, render(&counts).into_bytes()))
+    }
+}
+
+/// Wraps a bare query clause into a count body.
+fn count_body(query: Value) -> Value {
+    match query.get("query") {
+        Some(_) => query,
+        None => json!({ "query": query }),
+    }
+}
+
+/// The concrete indices of a resolve index response, sorted by name, with
+/// the data stream they back.
+fn concrete_indices(resolved: &Value) -> Vec<(String, Option<String>)> {
+    let mut indices = BTreeMap::new();
+    for index in resolved["indices"].as_array().into_iter().flatten() {
+        let Some(name) = index["name"].as_str() else {
+            continue;
+        };
+        let data_stream = index["data_stream"].as_str().map(str::to_string);
+        indices.insert(name.to_string(), data_stream);
+    }
+    for data_stream in resolved["data_streams"].as_array().into_iter().flatten() {
+        let name = data_stream["name"].as_str().unwrap_or_default();
+        for backing in data_stream["backing_indices"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            if let Some(backing) = backing.as_str() {
+                indices.insert(backing.to_string(), Some(name.to_string()));
+            }
+        }
+    }
+    indices.into_iter().collect()
+}
+
+/// Adds up the counts per group and renders them with their grand total.
+/// Groups whose count failed are shown as errors and left out of the total.
+fn render(counts: &[(String, Option<u64>)]) -> String {
+    let mut groups: BTreeMap<&str, Option<u64>> = BTreeMap::new();
+    for (group, count) in counts {
+        let sum = groups.entry(group.as_str()).or_insert(Some(0));
+        *sum = sum.zip(*count).map(|(a, b)| a + b);
+    }
+    let mut table = Table::new(&["index", "count"]);
+    let mut total = 0;
+    for (group, count) in groups {
+        total += count.unwrap_or(0);
+        table.add_row(vec![
+            group.to_string(),
+            count.map_or("error".to_string(), |c| c.to_string()),
+        ]);
+    }
+    table.add_row(vec!["total".to_string(), total.to_string()]);
+    table.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concrete_indices_resolve_data_streams() {
+        let resolved = json!({
+            "indices": [
+                {"name": "logs-old"},
+                {"name": ".ds-logs-app-1", "data_stream": "logs-app"},
+            ],
+            "aliases": [{"name": "logs", "indices": ["logs-old"]}],
+            "data_streams": [{"name": "logs-app", "backing_indices": [".ds-logs-app-1", ".ds-logs-app-2"]}],
+        });
+        assert_eq!(
+            concrete_indices(&resolved),
+            vec![
+                (".ds-logs-app-1".to_string(), Some("logs-app".to_string())),
+                (".ds-logs-app-2".to_string(), Some("logs-app".to_string())),
+                ("logs-old".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_adds_up_groups_and_total() {
+        let counts = vec![
+            ("logs-app".to_string(), Some(3)),
+            ("logs-app".to_string(), Some(4)),
+            ("logs-old".to_string(), None),
+            ("metrics".to_string(), Some(0)),
+        ];
+        assert_eq!(
+            render(&counts),
+            "index     count\n\
+             logs-app  7\n\
+             logs-old  error\n\
+             metrics   0\n\
+             total     7\n"
+        );
+    }
+
+    #[test]
+    fn count_body_wraps_query_clauses() {
+        let clause = json!({"term": {"level": "error"}});
+        assert_eq!(count_body(clause.clone()), json!({"query": clause}));
+        let body = json!({"query": {"match_all": {}}});
+        assert_eq!(count_body(body.clone()), body);
+    }
+}
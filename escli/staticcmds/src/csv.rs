@@ -0,0 +1,151 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::io::Error as IoError;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Reads the records of a delimited file as described by RFC 4180, one at a
+/// time: fields containing the delimiter, quotes or line breaks are quoted,
+/// quotes inside them are doubled, and a quoted field may span several lines.
+pub(crate) struct CsvReader<R> {
+    reader: R,
+    delimiter: char,
+    line: usize,
+}
+
+impl<R: AsyncBufRead + Unpin> CsvReader<R> {
+    pub(crate) fn new(reader: R, delimiter: char) -> Self {
+        CsvReader {
+            reader,
+            delimiter,
+            line: 0,
+        }
+    }
+
+    /// The line number where the last record read ends.
+    pub(crate) fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Reads the next record, skipping blank lines. Returns `None` at the end
+    /// of the input.
+    pub(crate) async fn next_record(&mut self) -> Result<Option<Vec<String>>, IoError> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).await? == 0 {
+                return match text.is_empty() {
+                    true => Ok(None),
+                    false => Ok(Some(parse_record(&text, self.delimiter))),
+                };
+            }
+            self.line += 1;
+            if text.is_empty() && line.trim_end_matches(['\r', '\n']).is_empty() {
+                continue;
+            }
+            text.push_str(&line);
+            // An odd number of quotes leaves a quoted field open on the next line.
+            if text.matches('"').count() % 2 == 0 {
+                let record = text.trim_end_matches(['\r', '\n']);
+                return Ok(Some(parse_record(record, self.delimiter)));
+            }
+        }
+    }
+}
+
+/// Splits a record into its fields, unquoting quoted fields.
+pub(crate) fn parse_record(record: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, c) if c == delimiter => fields.push(std::mem::take(&mut field)),
+            (false, c) => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Formats a record as a line, quoting the fields that need it.
+pub(crate) fn format_record<S: AsRef<str>>(fields: &[S], delimiter: char) -> String {
+    let mut line = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            line.push(delimiter);
+        }
+        let field = field.as_ref();
+        match field.contains([delimiter, '"', '\n', '\r']) {
+            true => {
+                line.push('"');
+                line.push_str(&field.replace('"', "\"\""));
+                line.push('"');
+            }
+            false => line.push_str(field),
+        }
+    }
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_record_unquotes_fields() {
+        assert_eq!(
+            parse_record(r#"a,"b,c","say ""hi""",,d"#, ','),
+            vec!["a", "b,c", "say \"hi\"", "", "d"]
+        );
+        assert_eq!(parse_record("a;b", ';'), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn format_record_quotes_when_needed() {
+        assert_eq!(
+            format_record(&["a", "b,c", "say \"hi\"", "x\ny"], ','),
+            "a,\"b,c\",\"say \"\"hi\"\"\",\"x\ny\"\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn reader_joins_quoted_line_breaks() {
+        let input = "a,b\n\n\"multi\nline\",2\r\nlast,3";
+        let mut reader = CsvReader::new(input.as_bytes(), ',');
+        assert_eq!(reader.next_record().await.unwrap().unwrap(), vec!["a", "b"]);
+        assert_eq!(
+            reader.next_record().await.unwrap().unwrap(),
+            vec!["multi\nline", "2"]
+        );
+        assert_eq!(reader.line(), 4);
+        assert_eq!(
+            reader.next_record().await.unwrap().unwrap(),
+            vec!["last", "3"]
+        );
+        assert!(reader.next_record().await.unwrap().is_none());
+    }
+}
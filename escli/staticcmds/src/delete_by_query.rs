@@ -0,0 +1,215 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_json_arg;
+use crate::request::{response, send_json, send_json_ok};
+use crate::units::{format_bar, format_duration, parse_duration};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+pub struct DeleteByQuery {
+    #[arg(help = "Indices to delete documents from, comma separated")]
+    index: String,
+
+    #[arg(
+        short,
+        long,
+        help = "Query selecting the documents to delete, inline JSON or @file"
+    )]
+    query: String,
+
+    #[arg(long, help = "Throttle the deletion to this many documents per second")]
+    requests_per_second: Option<f64>,
+
+    #[arg(long, help = "Number of slices to split the deletion into, or auto")]
+    slices: Option<String>,
+
+    #[arg(long, help = "Keep deleting on version conflicts instead of aborting")]
+    proceed_on_conflicts: bool,
+
+    #[arg(
+        long,
+        help = "Time between two progress updates",
+        default_value = "2s",
+        value_parser = parse_duration
+    )]
+    interval: Duration,
+}
+
+impl DeleteByQuery {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("delete-by-query")
+            .about("Delete documents matching a query and follow the progress.")
+            .long_about(
+                r#"
+            Start a _delete_by_query task in the background and follow it
+            through the tasks API, showing a progress bar on stderr until it
+            completes.
+
+            Use --requests-per-second to throttle the deletion and limit its
+            impact on the cluster, and --slices to parallelize it. Pressing
+            Ctrl-C cancels the task on the cluster before exiting; documents
+            deleted until then stay deleted.
+
+            Example usage:
+                escli utils delete-by-query logs-app --query '{"range": {"@timestamp": {"lt": "now-90d"}}}'
+                escli utils delete-by-query orders -q @query.json --requests-per-second 500 --slices auto
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let query = read_json_arg(&self.query).await?;
+
+        let rps = self.requests_per_second.map(|r| r.to_string());
+        let mut params = vec![("wait_for_completion", "false")];
+        if let Some(rps) = &rps {
+            params.push(("requests_per_second", rps.as_str()));
+        }
+        if let Some(slices) = &self.slices {
+            params.push(("slices", slices.as_str()));
+        }
+        if self.proceed_on_conflicts {
+            params.push(("conflicts", "proceed"));
+        }
+        let path = format!("/{}/_delete_by_query", self.index);
+        let body = json!({ "query": query });
+        let Some(started) =
+            send_json_ok(&transport, Method::Post, &path, &params, Some(&body), t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let Some(task) = started["task"].as_str().map(str::to_string) else {
+            return Ok(response(500, started.to_string().into_bytes()));
+        };
+        eprintln!("Started task {task}");
+
+        // Listening once from here on, a Ctrl-C pressed while polling the
+        // task is not lost and still cancels it.
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+        let start = Instant::now();
+        let terminal = std::io::stderr().is_terminal();
+        let path = format!("/_tasks/{task}");
+        loop {
+            let status = tokio::select! {
+                status = send_json_ok(&transport, Method::Get, &path, &[], None, t) => status?,
+                _ = &mut ctrl_c => break,
+            };
+            let Some(status) = status else {
+                return Ok(response(500, Vec::new()));
+            };
+            let line = progress(&status["task"]["status"]);
+            match terminal {
+                true => eprint!("\r{line}"),
+                false => eprintln!("{line}"),
+            }
+
+            if status["completed"] == true {
+                if terminal {
+                    eprintln!();
+                }
+                return Ok(summary(&status["response"], start.elapsed()));
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.interval) => {}
+                _ = &mut ctrl_c => break,
+            }
+        }
+
+        if terminal {
+            eprintln!();
+        }
+        let cancel = format!("/_tasks/{task}/_cancel");
+        let (status, body) = send_json(&transport, Method::Post, &cancel, &[], None, t).await?;
+        if !status.is_success() {
+            return Ok(response(status.as_u16(), body.to_string().into_bytes()));
+        }
+        Ok(response(
+            499,
+            format!("Cancelled task {task}\n").into_bytes(),
+        ))
+    }
+}
+
+/// Renders a progress bar from the status of a delete by query task.
+fn progress(status: &Value) -> String {
+    let total = status["total"].as_u64().unwrap_or_default();
+    let done = status["deleted"].as_u64().unwrap_or_default()
+        + status["version_conflicts"].as_u64().unwrap_or_default()
+        + status["noops"].as_u64().unwrap_or_default();
+    let ratio = match total {
+        0 => 0.0,
+        total => done as f64 / total as f64,
+    };
+    format!(
+        "{} {}/{} deleted",
+        format_bar(ratio),
+        status["deleted"].as_u64().unwrap_or_default(),
+        total
+    )
+}
+
+/// Turns the response of a completed task into the command response, an
+/// error when some deletions failed.
+fn summary(res: &Value, elapsed: Duration) -> Response {
+    let text = format!(
+        "Deleted {} of {} documents in {}, {} version conflicts\n",
+        res["deleted"].as_u64().unwrap_or_default(),
+        res["total"].as_u64().unwrap_or_default(),
+        format_duration(elapsed),
+        res["version_conflicts"].as_u64().unwrap_or_default(),
+    );
+    match res["failures"].as_array() {
+        Some(failures) if !failures.is_empty() => {
+            let failures = serde_json::to_string_pretty(failures).unwrap_or_default();
+            response(500, format!("{text}{failures}\n").into_bytes())
+        }
+        _ => response(200, text.into_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_renders_bar() {
+        let status = json!({"total": 200, "deleted": 90, "version_conflicts": 10});
+        assert_eq!(
+            progress(&status),
+            "[###############...............]  50% 90/200 deleted"
+        );
+        assert_eq!(
+            progress(&json!({})),
+            "[..............................]   0% 0/0 deleted"
+        );
+    }
+}
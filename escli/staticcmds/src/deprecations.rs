@@ -0,0 +1,276 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use crate::table::Table;
+use clap::{Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Number of affected resources listed before the others are counted.
+const MAX_LISTED: usize = 3;
+
+/// Sections of the deprecation info response keyed by resource name, with
+/// the scope they are reported under.
+const KEYED_SECTIONS: &[(&str, &str)] = &[
+    ("index_settings", "index"),
+    ("data_streams", "data stream"),
+    ("templates", "template"),
+    ("ilm_policies", "ilm policy"),
+];
+
+/// Sections of the deprecation info response holding a list of issues.
+const LISTED_SECTIONS: &[(&str, &str)] = &[
+    ("cluster_settings", "cluster"),
+    ("node_settings", "node"),
+    ("ml_settings", "ml"),
+];
+
+#[derive(Parser, Debug)]
+pub struct Deprecations {
+    #[arg(
+        long,
+        value_enum,
+        help = "Exit with an error when an issue of at least this level is found"
+    )]
+    fail_on: Option<Level>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum Level {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Level {
+    fn parse(level: &str) -> Option<Self> {
+        match level {
+            "info" => Some(Level::Info),
+            "warning" => Some(Level::Warning),
+            "critical" => Some(Level::Critical),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Critical => "critical",
+        }
+    }
+}
+
+/// A deprecation and the resources it affects.
+#[derive(Debug, Default)]
+struct Issue {
+    url: String,
+    resources: Vec<String>,
+}
+
+impl Deprecations {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("deprecations")
+            .about("Report what to fix before upgrading to the next major version.")
+            .long_about(
+                r#"
+            Combine the deprecation info API and the feature migration API
+            into a single report of everything to fix before upgrading to the
+            next major version, sorted from critical to informational.
+
+            Issues affecting several indices, data streams or templates are
+            reported once with the resources they affect. System features
+            whose indices must be migrated are reported as critical.
+
+            --fail-on makes the command exit with an error when an issue of at
+            least the given level is found, to block an upgrade from CI.
+
+            Example usage:
+                escli utils deprecations
+                escli utils deprecations --fail-on critical
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let Some(deprecations) = send_json_ok(
+            &transport,
+            Method::Get,
+            "/_migration/deprecations",
+            &[],
+            None,
+            t,
+        )
+        .await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let Some(features) = send_json_ok(
+            &transport,
+            Method::Get,
+            "/_migration/system_features",
+            &[],
+            None,
+            t,
+        )
+        .await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let issues = collect(&deprecations, &features);
+        if !issues.is_empty() {
+            print!("{}", render(&issues));
+        }
+        let count = |level| issues.keys().filter(|(l, ..)| *l == level).count();
+        let summary = format!(
+            "{} critical, {} warning, {} info issues\n",
+            count(Level::Critical),
+            count(Level::Warning),
+            count(Level::Info)
+        );
+        let failed = match self.fail_on {
+            Some(fail_on) => issues.keys().any(|(level, ..)| *level >= fail_on),
+            None => false,
+        };
+        Ok(response(
+            if failed { 400 } else { 200 },
+            summary.into_bytes(),
+        ))
+    }
+}
+
+/// Groups the deprecations and system features to migrate by level, scope
+/// and message, most severe first.
+fn collect(
+    deprecations: &Value,
+    features: &Value,
+) -> BTreeMap<(Level, &'static str, String), Issue> {
+    let mut issues: BTreeMap<(Level, &'static str, String), Issue> = BTreeMap::new();
+    let mut add = |scope: &'static str, resource: Option<&str>, entry: &Value| {
+        let Some(level) = entry["level"].as_str().and_then(Level::parse) else {
+            return;
+        };
+        let message = entry["message"].as_str().unwrap_or("-").to_string();
+        let issue = issues.entry((level, scope, message)).or_default();
+        issue.url = entry["url"].as_str().unwrap_or_default().to_string();
+        if let Some(resource) = resource {
+            issue.resources.push(resource.to_string());
+        }
+    };
+
+    for (section, scope) in LISTED_SECTIONS {
+        for entry in deprecations[section].as_array().into_iter().flatten() {
+            add(*scope, None, entry);
+        }
+    }
+    for (section, scope) in KEYED_SECTIONS {
+        for (name, entries) in deprecations[section].as_object().into_iter().flatten() {
+            for entry in entries.as_array().into_iter().flatten() {
+                add(*scope, Some(name.as_str()), entry);
+            }
+        }
+    }
+    for feature in features["features"].as_array().into_iter().flatten() {
+        let level = match feature["migration_status"].as_str() {
+            Some("MIGRATION_NEEDED") | Some("ERROR") => "critical",
+            Some("IN_PROGRESS") => "warning",
+            _ => continue,
+        };
+        let entry = serde_json::json!({
+            "level": level,
+            "message": "System indices must be migrated with POST _migration/system_features",
+        });
+        add("system feature", feature["feature_name"].as_str(), &entry);
+    }
+    issues
+}
+
+fn render(issues: &BTreeMap<(Level, &'static str, String), Issue>) -> String {
+    let mut table = Table::new(&["level", "scope", "affects", "issue", "url"]);
+    let mut sorted: Vec<_> = issues.iter().collect();
+    sorted.sort_by(|((a, ..), _), ((b, ..), _)| b.cmp(a));
+    for ((level, scope, message), issue) in sorted {
+        let mut affects = issue.resources.clone();
+        affects.sort();
+        let hidden = affects.len().saturating_sub(MAX_LISTED);
+        affects.truncate(MAX_LISTED);
+        let mut affects = affects.join(",");
+        if hidden > 0 {
+            affects.push_str(&format!(" and {hidden} more"));
+        }
+        table.add_row(vec![
+            level.as_str().to_string(),
+            scope.to_string(),
+            if affects.is_empty() {
+                "-".to_string()
+            } else {
+                affects
+            },
+            message.clone(),
+            issue.url.clone(),
+        ]);
+    }
+    table.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn render_groups_issues_by_severity() {
+        let old_index =
+            json!({"level": "critical", "message": "Old index", "url": "https://ela.st/old"});
+        let deprecations = json!({
+            "cluster_settings": [{"level": "warning", "message": "Setting x is deprecated", "url": "https://ela.st/x"}],
+            "node_settings": [{"level": "none", "message": "ignored"}],
+            "index_settings": {
+                "logs-1": [old_index], "logs-2": [old_index], "logs-3": [old_index], "logs-4": [old_index],
+            },
+            "data_streams": {},
+        });
+        let features = json!({"features": [
+            {"feature_name": "security", "migration_status": "NO_MIGRATION_NEEDED"},
+            {"feature_name": "watcher", "migration_status": "MIGRATION_NEEDED"},
+        ]});
+        let issues = collect(&deprecations, &features);
+        assert_eq!(
+            render(&issues),
+            [
+                "level     scope           affects                          issue                                                                 url",
+                "critical  index           logs-1,logs-2,logs-3 and 1 more  Old index                                                             https://ela.st/old",
+                "critical  system feature  watcher                          System indices must be migrated with POST _migration/system_features",
+                "warning   cluster         -                                Setting x is deprecated                                               https://ela.st/x",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
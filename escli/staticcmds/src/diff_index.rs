@@ -0,0 +1,312 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::copy_index::{NON_COPYABLE_SETTINGS, remove_path};
+use crate::profile::profile_transport;
+use crate::request::{print_response, response, send_json_ok};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct DiffIndex {
+    #[arg(help = "First index to compare")]
+    a: String,
+
+    #[arg(help = "Second index to compare, defaults to the first one")]
+    b: Option<String>,
+
+    #[arg(
+        long,
+        help = "Profile of the cluster of the first index, defaults to the cluster of --url"
+    )]
+    from_profile: Option<String>,
+
+    #[arg(
+        long,
+        help = "Profile of the cluster of the second index, defaults to the first cluster"
+    )]
+    to_profile: Option<String>,
+
+    #[arg(
+        long,
+        help = "Also compare the settings managed by Elasticsearch, such as uuid"
+    )]
+    all_settings: bool,
+
+    #[arg(long, help = "Never color the output")]
+    no_color: bool,
+}
+
+impl DiffIndex {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("diff-index")
+            .about("Compare the mappings, settings and aliases of two indices.")
+            .long_about(
+                r#"
+            Compare the aliases, mappings and settings of two indices, on the
+            same cluster or across profiles, and print every difference with
+            its full path:
+
+                - only in the first index
+                + only in the second index
+                ~ different values, first -> second
+
+            Settings managed by Elasticsearch, such as the index uuid or
+            creation date, always differ and are ignored unless
+            --all-settings is given. The output is colored when printed to a
+            terminal, unless --no-color is given or NO_COLOR is set.
+
+            Example usage:
+                escli utils diff-index logs-2024 logs-2025
+                escli utils diff-index orders --from-profile prod --to-profile staging
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let first = match &self.from_profile {
+            Some(name) => profile_transport(name)?,
+            None => transport,
+        };
+        self.run(first, timeout).await
+    }
+
+    /// Runs a comparison whose first index is given with --from-profile,
+    /// without a --url connection, and returns the exit code of the process.
+    pub async fn execute_standalone(self, timeout: Option<Duration>) -> i32 {
+        let Some(name) = self.from_profile.clone() else {
+            eprintln!("--from-profile is required without --url");
+            return 1;
+        };
+        let result = match profile_transport(&name) {
+            Ok(first) => self.run(first, timeout).await,
+            Err(e) => Err(e.into()),
+        };
+        print_response(result).await
+    }
+
+    async fn run(
+        &self,
+        first: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let b = self.b.as_deref().unwrap_or(&self.a);
+        if b == self.a && self.to_profile.is_none() {
+            eprintln!("The second index must differ from the first one on the same cluster");
+            return Ok(response(400, Vec::new()));
+        }
+        let second = match &self.to_profile {
+            Some(name) => profile_transport(name)?,
+            None => first.clone(),
+        };
+
+        let Some(left) = self.fetch(&first, &self.a, t).await? else {
+            return Ok(response(404, Vec::new()));
+        };
+        let Some(right) = self.fetch(&second, b, t).await? else {
+            return Ok(response(404, Vec::new()));
+        };
+
+        let color = use_color(self.no_color);
+        let mut out = format!(
+            "--- {}\n+++ {}\n",
+            label(self.from_profile.as_deref(), &self.a),
+            label(
+                self.to_profile.as_deref().or(self.from_profile.as_deref()),
+                b
+            )
+        );
+        let changes = diff(&left, &right);
+        if changes.is_empty() {
+            out.push_str("No differences\n");
+        }
+        for change in changes {
+            out.push_str(&change.render(color));
+            out.push('\n');
+        }
+        Ok(response(200, out.into_bytes()))
+    }
+
+    /// Fetches the definition of an index, without the managed settings
+    /// unless --all-settings is given.
+    async fn fetch(
+        &self,
+        transport: &Transport,
+        index: &str,
+        t: Duration,
+    ) -> Result<Option<Value>, elasticsearch::Error> {
+        let path = format!("/{index}");
+        let Some(indices) = send_json_ok(transport, Method::Get, &path, &[], None, t).await? else {
+            return Ok(None);
+        };
+        let Some((_, definition)) = indices.as_object().and_then(|o| o.iter().next()) else {
+            eprintln!("Index {index} not found");
+            return Ok(None);
+        };
+        let mut definition = definition.clone();
+        if let (false, Some(settings)) =
+            (self.all_settings, definition["settings"].get_mut("index"))
+        {
+            for setting in NON_COPYABLE_SETTINGS {
+                remove_path(settings, setting);
+            }
+        }
+        Ok(Some(definition))
+    }
+}
+
+/// Whether to color the output, when it goes to a terminal and neither
+/// --no-color nor NO_COLOR disable it.
+pub(crate) fn use_color(no_color: bool) -> bool {
+    !no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+fn label(profile: Option<&str>, index: &str) -> String {
+    match profile {
+        Some(profile) => format!("{profile}:{index}"),
+        None => index.to_string(),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Change {
+    Removed(String, Value),
+    Added(String, Value),
+    Changed(String, Value, Value),
+}
+
+impl Change {
+    fn path(&self) -> &str {
+        match self {
+            Change::Removed(path, _) | Change::Added(path, _) | Change::Changed(path, _, _) => path,
+        }
+    }
+
+    pub(crate) fn render(&self, color: bool) -> String {
+        let (code, line) = match self {
+            Change::Removed(path, v) => (31, format!("- {path}: {v}")),
+            Change::Added(path, v) => (32, format!("+ {path}: {v}")),
+            Change::Changed(path, a, b) => (33, format!("~ {path}: {a} -> {b}")),
+        };
+        match color {
+            true => format!("\x1b[{code}m{line}\x1b[0m"),
+            false => line,
+        }
+    }
+}
+
+/// Flattens nested objects into dotted paths. Arrays and empty objects are
+/// kept as values.
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value.as_object() {
+        Some(object) if !object.is_empty() => {
+            for (key, child) in object {
+                flatten(&format!("{prefix}.{key}"), child, out);
+            }
+        }
+        _ => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Lists the differences between two definitions, ordered by path. Each
+/// top level object is compared by its dotted leaf paths.
+pub(crate) fn diff(left: &Value, right: &Value) -> Vec<Change> {
+    let (mut a, mut b) = (BTreeMap::new(), BTreeMap::new());
+    for (value, out) in [(left, &mut a), (right, &mut b)] {
+        let Some(sections) = value.as_object() else {
+            continue;
+        };
+        for (section, value) in sections {
+            match value.as_object() {
+                Some(object) => {
+                    for (key, child) in object {
+                        flatten(&format!("{section}.{key}"), child, out);
+                    }
+                }
+                None => flatten(section, value, out),
+            }
+        }
+    }
+
+    let mut changes = Vec::new();
+    for (path, value) in &a {
+        match b.remove(path) {
+            None => changes.push(Change::Removed(path.clone(), value.clone())),
+            Some(other) if other != *value => {
+                changes.push(Change::Changed(path.clone(), value.clone(), other))
+            }
+            Some(_) => {}
+        }
+    }
+    changes.extend(b.into_iter().map(|(path, v)| Change::Added(path, v)));
+    changes.sort_by(|x, y| x.path().cmp(y.path()));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_reports_removed_added_and_changed_paths() {
+        let left = json!({
+            "aliases": {"logs": {}},
+            "mappings": {"properties": {"a": {"type": "keyword"}}},
+            "settings": {"index": {"number_of_shards": "1", "refresh_interval": "1s"}},
+        });
+        let right = json!({
+            "aliases": {},
+            "mappings": {"properties": {"a": {"type": "text"}, "b": {"type": "long"}}},
+            "settings": {"index": {"number_of_shards": "1"}},
+        });
+        assert_eq!(
+            diff(&left, &right),
+            vec![
+                Change::Removed("aliases.logs".into(), json!({})),
+                Change::Changed(
+                    "mappings.properties.a.type".into(),
+                    json!("keyword"),
+                    json!("text")
+                ),
+                Change::Added("mappings.properties.b.type".into(), json!("long")),
+                Change::Removed("settings.index.refresh_interval".into(), json!("1s")),
+            ]
+        );
+    }
+
+    #[test]
+    fn change_renders_with_color() {
+        let change = Change::Changed("a".into(), json!(1), json!(2));
+        assert_eq!(change.render(false), "~ a: 1 -> 2");
+        assert_eq!(change.render(true), "\x1b[33m~ a: 1 -> 2\x1b[0m");
+    }
+}
@@ -0,0 +1,284 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use crate::units::format_bytes;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Disk {
+    #[arg(short, long, help = "Only report on these indices, comma separated")]
+    index: Option<String>,
+
+    #[arg(
+        long,
+        requires = "index",
+        help = "Also analyze the disk usage of each field, an expensive operation"
+    )]
+    fields: bool,
+
+    #[arg(
+        long,
+        help = "Number of indices shown per node, and fields per index",
+        default_value_t = 10
+    )]
+    top: usize,
+}
+
+/// A line of the tree and the lines below it.
+#[derive(Debug, PartialEq)]
+struct Tree {
+    label: String,
+    children: Vec<Tree>,
+}
+
+impl Tree {
+    fn new(label: String) -> Self {
+        Tree {
+            label,
+            children: Vec::new(),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = format!("{}\n", self.label);
+        self.render_children("", &mut out);
+        out
+    }
+
+    fn render_children(&self, prefix: &str, out: &mut String) {
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i + 1 == self.children.len();
+            let (branch, indent) = match last {
+                true => ("└── ", "    "),
+                false => ("├── ", "│   "),
+            };
+            out.push_str(&format!("{prefix}{branch}{}\n", child.label));
+            child.render_children(&format!("{prefix}{indent}"), out);
+        }
+    }
+}
+
+impl Disk {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("disk")
+            .about("Show the disk usage of nodes, indices and fields as a tree.")
+            .long_about(
+                r#"
+            Show where the disk space of the cluster goes, as a tree of the
+            nodes, the largest indices on each node and, with --fields, the
+            largest fields of each index, with their sizes and share of the
+            level above.
+
+            --fields runs the analyze index disk usage API, which reads every
+            field of the index and is expensive on large indices: it requires
+            --index to be given.
+
+            Example usage:
+                escli utils disk
+                escli utils disk --index logs-2025.01 --fields --top 20
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let query = [("format", "json"), ("bytes", "b")];
+
+        let Some(allocation) =
+            send_json_ok(&transport, Method::Get, "/_cat/allocation", &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let path = match &self.index {
+            Some(index) => format!("/_cat/shards/{index}"),
+            None => "/_cat/shards".to_string(),
+        };
+        let Some(shards) = send_json_ok(&transport, Method::Get, &path, &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let field_usage = match (&self.index, self.fields) {
+            (Some(index), true) => {
+                let path = format!("/{index}/_disk_usage");
+                let query = [("run_expensive_tasks", "true")];
+                let Some(usage) =
+                    send_json_ok(&transport, Method::Post, &path, &query, None, t).await?
+                else {
+                    return Ok(response(500, Vec::new()));
+                };
+                Some(usage)
+            }
+            _ => None,
+        };
+
+        let tree = self.tree(&allocation, &shards, field_usage.as_ref());
+        Ok(response(200, tree.render().into_bytes()))
+    }
+
+    fn tree(&self, allocation: &Value, shards: &Value, field_usage: Option<&Value>) -> Tree {
+        // Sizes of the indices per node, from the sizes of their shards.
+        let mut per_node: BTreeMap<&str, BTreeMap<&str, u64>> = BTreeMap::new();
+        for shard in shards.as_array().into_iter().flatten() {
+            let (Some(node), Some(index)) = (shard["node"].as_str(), shard["index"].as_str())
+            else {
+                continue;
+            };
+            *per_node.entry(node).or_default().entry(index).or_default() += number(&shard["store"]);
+        }
+
+        let (mut used, mut total) = (0, 0);
+        let mut root = Tree::new(String::new());
+        for node in allocation.as_array().into_iter().flatten() {
+            let name = node["node"].as_str().unwrap_or_default();
+            if name == "UNASSIGNED" {
+                continue;
+            }
+            let (node_used, node_total) = (number(&node["disk.used"]), number(&node["disk.total"]));
+            used += node_used;
+            total += node_total;
+            let indices_size = number(&node["disk.indices"]);
+            let mut branch = Tree::new(format!(
+                "{name}  {} used of {} ({}), {} in indices",
+                format_bytes(node_used),
+                format_bytes(node_total),
+                percent(node_used, node_total),
+                format_bytes(indices_size),
+            ));
+
+            let mut indices: Vec<(&str, u64)> = per_node
+                .get(name)
+                .map(|m| m.iter().map(|(i, s)| (*i, *s)).collect())
+                .unwrap_or_default();
+            indices.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            let hidden = indices.len().saturating_sub(self.top);
+            for (index, size) in indices.into_iter().take(self.top) {
+                let mut leaf = Tree::new(format!(
+                    "{index}  {} ({})",
+                    format_bytes(size),
+                    percent(size, indices_size)
+                ));
+                if let Some(usage) = field_usage {
+                    leaf.children = self.field_tree(&usage[index]);
+                }
+                branch.children.push(leaf);
+            }
+            if hidden > 0 {
+                let noun = match hidden {
+                    1 => "index",
+                    _ => "indices",
+                };
+                let label = format!("{hidden} more {noun} not shown");
+                branch.children.push(Tree::new(label));
+            }
+            root.children.push(branch);
+        }
+        root.label = format!(
+            "cluster  {} used of {} ({})",
+            format_bytes(used),
+            format_bytes(total),
+            percent(used, total)
+        );
+        root
+    }
+
+    /// The largest fields of an index, from the analyze index disk usage API.
+    fn field_tree(&self, usage: &Value) -> Vec<Tree> {
+        let total = usage["store_size_in_bytes"].as_u64().unwrap_or_default();
+        let mut fields: Vec<(&String, u64)> = usage["fields"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, stats)| (name, stats["total_in_bytes"].as_u64().unwrap_or_default()))
+            .collect();
+        fields.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        fields
+            .into_iter()
+            .take(self.top)
+            .map(|(name, size)| {
+                Tree::new(format!(
+                    "{name}  {} ({})",
+                    format_bytes(size),
+                    percent(size, total)
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Reads a `_cat` number, returned as a string.
+fn number(value: &Value) -> u64 {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .or(value.as_u64())
+        .unwrap_or_default()
+}
+
+fn percent(part: u64, total: u64) -> String {
+    match total {
+        0 => "-".to_string(),
+        total => format!("{:.0}%", part as f64 * 100.0 / total as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tree_nests_nodes_indices_and_fields() {
+        let disk =
+            Disk::try_parse_from(["disk", "--index", "logs", "--fields", "--top", "1"]).unwrap();
+        let allocation = json!([
+            {"node": "n1", "disk.indices": "3072", "disk.used": "4096", "disk.total": "16384"},
+            {"node": "UNASSIGNED"},
+        ]);
+        let shards = json!([
+            {"index": "logs", "node": "n1", "store": "2048"},
+            {"index": "other", "node": "n1", "store": "1024"},
+        ]);
+        let usage = json!({"logs": {"store_size_in_bytes": 2048, "fields": {
+            "message": {"total_in_bytes": 1536},
+            "@timestamp": {"total_in_bytes": 512},
+        }}});
+        assert_eq!(
+            disk.tree(&allocation, &shards, Some(&usage)).render(),
+            [
+                "cluster  4kb used of 16kb (25%)",
+                "└── n1  4kb used of 16kb (25%), 3kb in indices",
+                "    ├── logs  2kb (67%)",
+                "    │   └── message  1.5kb (75%)",
+                "    └── 1 more index not shown",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::io::Error as IoError;
+use std::process::Command as ProcessCommand;
+
+/// Prints the documentation URL of a command, optionally opening it in the
+/// default browser, and returns the exit code of the process.
+pub fn show_docs(url: Option<&str>, open: bool) -> i32 {
+    let Some(url) = url else {
+        eprintln!("No documentation URL is available for this command");
+        return 1;
+    };
+    println!("{url}");
+    if !open {
+        return 0;
+    }
+    match open_in_browser(url) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Failed to open {url}: {e}");
+            1
+        }
+    }
+}
+
+fn open_in_browser(url: &str) -> Result<(), IoError> {
+    let mut command = if cfg!(target_os = "macos") {
+        ProcessCommand::new("open")
+    } else if cfg!(windows) {
+        let mut command = ProcessCommand::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else {
+        ProcessCommand::new("xdg-open")
+    };
+    let status = command.arg(url).status()?;
+    if !status.success() {
+        return Err(IoError::other(format!(
+            "browser launcher exited with {status}"
+        )));
+    }
+    Ok(())
+}
@@ -15,22 +15,43 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use clap::{Command, CommandFactory, Parser};
+use crate::copy_index::{NON_COPYABLE_SETTINGS, remove_path};
+use crate::input::{read_json_arg, read_json_file};
+use crate::object_store::{ObjectStore, ObjectUrl, Upload};
+use crate::request::{response, send_json_ok};
+use crate::self_update::hex;
+use crate::table::Table;
+use crate::transform::Transform;
+use crate::units::{format_bar, format_bytes, format_duration, parse_bytes};
+use clap::{ArgGroup, Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::Method;
 use elasticsearch::http::response::Response;
 use elasticsearch::http::transport::Transport;
-use elasticsearch::{Elasticsearch, OpenPointInTimeParts, SearchParts};
+use elasticsearch::{
+    ClearScrollParts, CountParts, Elasticsearch, OpenPointInTimeParts, ScrollParts, SearchParts,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::io::{Error as IoError, ErrorKind as IoErrorKind};
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, IsTerminal};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::fs::{File, OpenOptions};
 use tokio::io::Stdout;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("file_output").args(["output", "output_dir"])))]
+#[command(group(ArgGroup::new("fetched_fields").args(["docvalue_fields", "stored_fields", "runtime_mappings"]).multiple(true)))]
 pub struct Dump {
     #[arg(
         required = true,
@@ -39,6 +60,21 @@ pub struct Dump {
     )]
     indices: Vec<String>,
 
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "STATES",
+        help = "Indices matched by the wildcard patterns, comma separated, default is open"
+    )]
+    expand_wildcards: Vec<ExpandWildcards>,
+
+    #[arg(
+        long,
+        help = "Leave out the system indices matched by the wildcard patterns"
+    )]
+    exclude_system: bool,
+
     #[arg(
         short,
         long,
@@ -58,21 +94,330 @@ pub struct Dump {
     #[arg(short, long, help = "Output file location, default is stdout")]
     output: Option<PathBuf>,
 
+    #[arg(
+        long,
+        conflicts_with = "output",
+        help = "Directory to write one <index>.ndjson file per index into"
+    )]
+    output_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        requires = "file_output",
+        value_parser = parse_bytes,
+        help = "Split the output into numbered parts of at most this size, such as 1gb"
+    )]
+    max_file_size: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "COUNT",
+        requires = "file_output",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Split the output into numbered parts of at most this many documents"
+    )]
+    max_docs_per_file: Option<u64>,
+
     #[arg(
         long,
         help = "Omit the index name from action lines (produces {\"index\":{}} instead of {\"index\":{\"_index\":\"...\"}})"
     )]
     skip_index_name: bool,
 
-    #[arg(long, help = "Include the document _id in action lines")]
-    add_id: bool,
+    #[arg(
+        long,
+        visible_alias = "add-id",
+        help = "Include the document _id in action lines"
+    )]
+    with_id: bool,
+
+    #[arg(long, help = "Include the document _routing in action lines")]
+    with_routing: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "skip_index_name",
+        help = "Use the index each document was read from in action lines, instead of the requested index, alias or data stream"
+    )]
+    original_index: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Elasticsearch query clause to filter documents, inline JSON or @file (a bare file path is deprecated)",
+        value_name = "JSON",
+        conflicts_with = "query_file"
+    )]
+    query: Option<String>,
 
     #[arg(
         long,
         help = "Path to a file containing an Elasticsearch query clause to filter documents (use - for stdin)",
         value_name = "FILE"
     )]
-    query: Option<PathBuf>,
+    query_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Number of slices of each index read concurrently",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    slices: u32,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of indices read concurrently",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    concurrency: u16,
+
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "Retries of a search failing with a timeout, 429 or 503, or whose PIT expired"
+    )]
+    max_retries: u32,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_bytes,
+        default_value = "1mb",
+        help = "Size of the buffer of the output, written when full"
+    )]
+    buffer_size: u64,
+
+    #[arg(long, help = "Do not show the progress bar nor the summary on stderr")]
+    no_progress: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with = "no_progress",
+        help = "How progress is reported on stderr",
+        default_value_t = ProgressFormat::Text
+    )]
+    progress_format: ProgressFormat,
+
+    #[arg(
+        long,
+        help = "Maximum number of documents to dump",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    max_docs: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "TIMESTAMP",
+        help = "Only dump documents with a time field at or after this date, such as now-24h or 2024-01-31"
+    )]
+    since: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TIMESTAMP",
+        help = "Only dump documents with a time field before this date"
+    )]
+    until: Option<String>,
+
+    #[arg(
+        long,
+        help = "Time field used by --since and --until",
+        default_value = "@timestamp"
+    )]
+    time_field: String,
+
+    #[arg(
+        long,
+        help = "Maximum number of search requests sent per second",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    requests_per_second: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Maximum number of documents read per second",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    docs_per_second: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "FIELD:ORDER",
+        value_delimiter = ',',
+        value_parser = parse_sort,
+        help = "Sort the documents of each index by these fields, comma separated, such as @timestamp:asc"
+    )]
+    sort: Vec<Value>,
+
+    #[arg(
+        long,
+        value_name = "FIELD:ORDER",
+        value_delimiter = ',',
+        value_parser = parse_sort,
+        conflicts_with = "sort",
+        help = "Sort the documents by these unique fields, such as _id, for an output identical across dumps"
+    )]
+    order_by: Vec<Value>,
+
+    #[arg(
+        long,
+        requires = "file_output",
+        help = "Also write the mapping and settings of each index next to the documents"
+    )]
+    with_metadata: bool,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "FIELDS",
+        help = "Fields replaced with REDACTED in the documents, comma separated"
+    )]
+    redact: Vec<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "FIELDS",
+        help = "Fields replaced with the SHA-256 hash of their value in the documents, comma separated"
+    )]
+    hash_fields: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "EXPR",
+        value_parser = Transform::parse,
+        help = "jq-like expression applied to the source of each document, such as '.owner = .user | del(.user)'"
+    )]
+    transform: Option<Transform>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "FIELDS",
+        help = "Fields read from doc values and added to the documents, comma separated"
+    )]
+    docvalue_fields: Vec<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "FIELDS",
+        help = "Stored fields added to the documents, comma separated"
+    )]
+    stored_fields: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "JSON",
+        help = "Runtime fields computed and added to the documents, as JSON or @file"
+    )]
+    runtime_mappings: Option<String>,
+
+    #[arg(
+        long,
+        requires = "fetched_fields",
+        help = "Write only the fetched fields, without the source of the documents"
+    )]
+    fields_only: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "max_docs",
+        help = "Compare the documents written with a count of each index once dumped"
+    )]
+    verify: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write a JSON summary of the dump of each index to FILE"
+    )]
+    report: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Record the progress of the dump in FILE, to continue it later with --resume"
+    )]
+    checkpoint: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "CHECKPOINT",
+        conflicts_with_all = ["checkpoint", "max_file_size", "max_docs_per_file"],
+        help = "Continue the dump recorded in a checkpoint file, appending to the output"
+    )]
+    resume: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "How the indices are read",
+        default_value_t = Strategy::Auto
+    )]
+    strategy: Strategy,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ProgressFormat {
+    /// A progress bar when stderr is a terminal, and messages
+    Text,
+    /// One JSON event per line, for orchestration systems
+    Json,
+}
+
+impl ProgressFormat {
+    /// Reports a message about an index on stderr, as an event of the given
+    /// kind in the json format.
+    fn report(self, event: &str, index: &str, message: String) {
+        match self {
+            ProgressFormat::Text => eprintln!("{}", message),
+            ProgressFormat::Json => {
+                self.emit(json!({ "event": event, "index": index, "message": message }))
+            }
+        }
+    }
+
+    /// Writes an event on stderr in the json format only.
+    fn emit(self, event: Value) {
+        if self == ProgressFormat::Json {
+            eprintln!("{}", event);
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ExpandWildcards {
+    Open,
+    Closed,
+    Hidden,
+    All,
+}
+
+impl ExpandWildcards {
+    fn as_str(self) -> &'static str {
+        match self {
+            ExpandWildcards::Open => "open",
+            ExpandWildcards::Closed => "closed",
+            ExpandWildcards::Hidden => "hidden",
+            ExpandWildcards::All => "all",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Strategy {
+    /// A PIT, or a scroll when the cluster does not support PITs
+    Auto,
+    /// A PIT read with search_after
+    Pit,
+    /// The scroll API, for clusters older than 7.10
+    Scroll,
 }
 
 #[derive(Deserialize, Debug)]
@@ -89,6 +434,8 @@ enum PointInTimeVariant {
 
 #[derive(Deserialize, Debug)]
 struct SearchResult {
+    /// The id to read the next page with, of the PIT or of the scroll.
+    #[serde(default, alias = "_scroll_id")]
     pit_id: String,
     hits: Hits,
 }
@@ -107,14 +454,175 @@ struct Hits {
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Hit {
+    #[serde(default)]
+    _index: Option<String>,
     _id: String,
+    #[serde(default)]
+    _routing: Option<String>,
+    /// Missing when the source is disabled or not requested.
+    #[serde(default)]
     _source: Value,
-    sort: Vec<u64>,
+    /// The doc values, stored and runtime fields requested.
+    #[serde(default)]
+    fields: serde_json::Map<String, Value>,
+    sort: Vec<Value>,
+}
+
+/// The document metadata written in the action lines.
+#[derive(Debug, Clone, Copy, Default)]
+struct ActionOptions {
+    skip_index_name: bool,
+    with_id: bool,
+    with_routing: bool,
+    original_index: bool,
+}
+
+/// Formats `--order-by` fields as given on the command line, such as
+/// `_id:asc`, `none` when there are none.
+fn format_order_by(order_by: &[Value]) -> String {
+    let fields: Vec<String> = order_by
+        .iter()
+        .filter_map(Value::as_object)
+        .flat_map(|sort| sort.iter())
+        .map(|(field, order)| format!("{}:{}", field, order["order"].as_str().unwrap_or("asc")))
+        .collect();
+    match fields.is_empty() {
+        true => "none".to_string(),
+        false => fields.join(","),
+    }
+}
+
+/// Parses a `--sort` field with an optional `asc` or `desc` order.
+fn parse_sort(s: &str) -> Result<Value, String> {
+    let (field, order) = s.rsplit_once(':').unwrap_or((s, "asc"));
+    match order {
+        "asc" | "desc" if !field.is_empty() => Ok(json!({ field: { "order": order } })),
+        _ => Err(format!(
+            "invalid sort '{s}', expected FIELD, FIELD:asc or FIELD:desc"
+        )),
+    }
+}
+
+/// The limits of the parts of a rotated output.
+#[derive(Debug, Clone, Copy, Default)]
+struct PartLimits {
+    max_bytes: Option<u64>,
+    max_docs: Option<u64>,
+}
+
+impl PartLimits {
+    fn is_set(&self) -> bool {
+        self.max_bytes.is_some() || self.max_docs.is_some()
+    }
+}
+
+/// A file output split into numbered parts, a new part being started before
+/// a document that would exceed the limits of the current one.
+struct Parts {
+    path: PathBuf,
+    limits: PartLimits,
+    capacity: usize,
+    part: u32,
+    file: BufWriter<File>,
+    bytes: u64,
+    docs: u64,
+}
+
+impl Parts {
+    async fn open(path: &Path, limits: PartLimits, capacity: usize) -> Result<Self, IoError> {
+        let file = create_file(&part_path(path, 1)).await?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            limits,
+            capacity,
+            part: 1,
+            file: BufWriter::with_capacity(capacity, file),
+            bytes: 0,
+            docs: 0,
+        })
+    }
+
+    /// Writes a batch of documents of the given lengths, moving to the next
+    /// part before a document that does not fit in the current one.
+    async fn write_documents(&mut self, batch: &[u8], lengths: &[u64]) -> Result<(), IoError> {
+        let (mut start, mut end) = (0, 0);
+        for &len in lengths {
+            let full = self.limits.max_docs.is_some_and(|max| self.docs >= max)
+                || self
+                    .limits
+                    .max_bytes
+                    .is_some_and(|max| self.bytes + len > max);
+            if full && self.docs > 0 {
+                self.file.write_all(&batch[start..end]).await?;
+                self.file.flush().await?;
+                self.file.shutdown().await?;
+                self.part += 1;
+                let file = create_file(&part_path(&self.path, self.part)).await?;
+                self.file = BufWriter::with_capacity(self.capacity, file);
+                self.bytes = 0;
+                self.docs = 0;
+                start = end;
+            }
+            self.bytes += len;
+            self.docs += 1;
+            end += len as usize;
+        }
+        self.file.write_all(&batch[start..end]).await
+    }
+}
+
+/// Returns the path of a numbered part, e.g. `dump-00002.ndjson` for the
+/// second part of `dump.ndjson`.
+fn part_path(path: &Path, part: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}-{part:05}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{part:05}"),
+    };
+    path.with_file_name(name)
+}
+
+/// A destination of documents.
+trait Sink: AsyncWrite + Unpin {
+    /// Writes a batch of serialized documents, `lengths` being the length of
+    /// each of them.
+    async fn write_documents(&mut self, batch: &[u8], _lengths: &[u64]) -> Result<(), IoError> {
+        self.write_all(batch).await
+    }
 }
 
 enum Output {
-    File(File),
-    Stdout(Stdout),
+    File(BufWriter<File>),
+    Parts(Parts),
+    Upload(Upload),
+    Stdout(BufWriter<Stdout>),
+}
+
+impl Output {
+    /// Flushes the output, completing an upload.
+    async fn close(self) -> Result<(), IoError> {
+        match self {
+            Output::Upload(upload) => upload.complete().await,
+            mut output => {
+                output.flush().await?;
+                output.shutdown().await
+            }
+        }
+    }
+}
+
+impl Sink for Output {
+    async fn write_documents(&mut self, batch: &[u8], lengths: &[u64]) -> Result<(), IoError> {
+        match self {
+            Output::Parts(parts) => parts.write_documents(batch, lengths).await,
+            Output::Upload(upload) => {
+                upload.write_all(batch).await?;
+                upload.send_full_part().await
+            }
+            Output::File(file) => file.write_all(batch).await,
+            Output::Stdout(stdout) => stdout.write_all(batch).await,
+        }
+    }
 }
 
 impl AsyncWrite for Output {
@@ -126,6 +634,8 @@ impl AsyncWrite for Output {
         let this = self.get_mut();
         match this {
             Output::File(f) => Pin::new(f).poll_write(cx, buf),
+            Output::Parts(p) => Pin::new(&mut p.file).poll_write(cx, buf),
+            Output::Upload(u) => Pin::new(u).poll_write(cx, buf),
             Output::Stdout(s) => Pin::new(s).poll_write(cx, buf),
         }
     }
@@ -134,6 +644,8 @@ impl AsyncWrite for Output {
         let this = self.get_mut();
         match this {
             Output::File(f) => Pin::new(f).poll_flush(cx),
+            Output::Parts(p) => Pin::new(&mut p.file).poll_flush(cx),
+            Output::Upload(u) => Pin::new(u).poll_flush(cx),
             Output::Stdout(s) => Pin::new(s).poll_flush(cx),
         }
     }
@@ -142,12 +654,13 @@ impl AsyncWrite for Output {
         let this = self.get_mut();
         match this {
             Output::File(f) => Pin::new(f).poll_shutdown(cx),
+            Output::Parts(p) => Pin::new(&mut p.file).poll_shutdown(cx),
+            Output::Upload(u) => Pin::new(u).poll_shutdown(cx),
             Output::Stdout(s) => Pin::new(s).poll_shutdown(cx),
         }
     }
 }
 
-
 impl Dump {
     pub fn new_command() -> Command {
         Self::command()
@@ -160,33 +673,216 @@ impl Dump {
             The action line is in the format:
             { "index": { "_index": "<index_name>" } }
             
-            The documents are sorted by shard and document ID.
+            The documents are sorted by shard and document ID, or by the --sort
+            fields first, with shard and document ID as a tiebreaker.
+
+            The shard document IDs change as segments are merged, so two dumps
+            of the same documents may differ in order. --order-by sorts by
+            fields that identify the documents instead, such as _id or a copy
+            of it with doc values, making repeated dumps of an unchanged index
+            byte-identical and diffable. Sorting on _id requires the
+            indices.id_field_data.enabled cluster setting since 8.0. It cannot
+            be combined with --slices, nor with --concurrency unless each index
+            is written to its own file with --output-dir.
             The command uses point-in-time (PIT) to ensure consistent reads across the index.
             The PIT is kept alive for the duration of the operation.
+
+            Clusters older than 7.10 do not support PITs, and the indices are
+            scrolled instead, with the same output. --strategy scroll uses the
+            scroll API from the start and --strategy pit never falls back to
+            it. A scroll is sorted by the --sort fields then by document ID
+            within each shard, and an index partially dumped with a scroll
+            cannot be continued with --resume.
             
+            With --with-id and --with-routing, the action lines keep the _id and
+            _routing of the documents so that they are restored as they were,
+            and --original-index names the index each document was read from,
+            such as the backing index of a data stream.
+
+            Wildcard patterns, such as logs-*, are resolved before the dump
+            starts, and the indices and data streams they match are reported
+            on stderr and dumped one by one. Only open indices are matched,
+            unless --expand-wildcards lists other states among open, closed,
+            hidden and all, hidden being combined with open or closed. Names
+            starting with - leave out the indices they match, such as
+            logs-*,-logs-debug-*, and --exclude-system the system indices.
+            Aliases are only dumped when named explicitly.
+
+            The documents of all the indices are written to stdout or to the
+            --output file. With --output-dir, each index is written to its own
+            <index>.ndjson file in that directory instead, which is created when
+            missing.
+
+            The --output file and the --output-dir directory can be in an S3 or
+            Google Cloud Storage bucket, such as s3://bucket/dumps/logs.ndjson
+            or gcs://bucket/dumps/. The documents are streamed to the bucket
+            with a multipart upload, without being written to the local disk,
+            and an --output ending with / is a directory. The credentials are
+            read from AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and AWS_REGION,
+            or from the HMAC key in GCS_ACCESS_KEY_ID and GCS_SECRET_ACCESS_KEY.
+            Objects cannot be split into parts nor appended to by --resume.
+
+            The indices are read one after the other, or --concurrency of them
+            at a time. The documents of indices read concurrently are
+            interleaved in a shared output, each keeping its action line.
+
+            Large exports can be split into numbered parts with --max-file-size
+            and --max-docs-per-file: the documents are written to
+            dump-00001.ndjson, dump-00002.ndjson and so on for an --output of
+            dump.ndjson, a new part being started before a document that would
+            exceed either limit. Documents are never split across parts.
+
+            With --slices, the PIT is split into that many slices read
+            concurrently, which speeds up the export of large indices. The
+            documents of the slices are interleaved in the output.
+
+            For indices whose source is disabled, or to export computed values,
+            --docvalue-fields, --stored-fields and --runtime-mappings request
+            fields besides the source and add them to the documents under
+            their dotted names, such as "user.id": 42, a single value being
+            written as is rather than in an array. --runtime-mappings takes
+            runtime field definitions as JSON, or @file, and returns all of
+            them. With --fields-only, the source is left out and the documents
+            only hold the fetched fields.
+
+            To share production data safely, --redact replaces the values of
+            fields with REDACTED and --hash-fields with the SHA-256 hash of the
+            values, which keeps equal values equal across documents. Fields are
+            dotted paths, such as user.email.
+
+            --transform reshapes the source of each document before it is
+            written, after --redact and --hash-fields, with a jq-like pipeline
+            of filters separated by |:
+                .field = value          set a field to a JSON value
+                .field = .other         copy another field, null when missing
+                .field = now            set a field to the current time
+                del(.field, .other)     remove fields
+                drop_nulls              remove the null values at any depth
+            Nested fields are written .user.name and field names with dots or
+            spaces quoted, such as ."user.name". A field is renamed by copying
+            then removing it: .name = .title | del(.title).
+
+            --since and --until restrict the dump to the documents whose
+            --time-field, @timestamp by default, falls in a range, in addition
+            to the query. They accept dates and date math, such as now-24h.
+            --max-docs stops the dump once that many documents were written.
+
+            To keep the load on a production cluster low, --requests-per-second
+            and --docs-per-second slow the dump down, across all the indices and
+            slices read concurrently.
+
+            With --verify, the documents written for each index are compared
+            with a count of the documents matching the query once the dump is
+            done. The counts are reported on stderr and the command fails when
+            one of them differs, such as when documents were indexed or
+            deleted during the dump.
+
+            When stderr is a terminal, a progress bar shows the number of
+            documents written out of the total counted upfront, the speed, the
+            bytes written and the estimated time left. Once done, a summary of
+            each index is printed: its status, the documents and bytes
+            written, how long it took, the average speed and the searches
+            retried. Use --no-progress to hide them.
+
+            --report writes that summary as JSON to a file, for the audit trail
+            of a migration: the totals of the dump and, in "indices", an object
+            per index with its status among done, failed, skipped when already
+            dumped by a resumed dump and incomplete when the dump stopped
+            before its end, with the error of the failed ones.
+
+            With --progress-format json, the progress is reported on stderr as
+            one JSON object per line for orchestration systems, whether stderr
+            is a terminal or not. The "event" field of each object is one of:
+                batch       a page of documents was written: index, slice,
+                            docs, bytes, total_docs, total_bytes, elapsed_ms
+                index_done  all the documents of an index were written
+                skipped     an index was already dumped by a resumed dump
+                resumed     an index is continued by a resumed dump
+                fallback    an index is scrolled as PITs are not supported
+                warning     a PIT or scroll could not be released
+                error       an index could not be read, with a message
+                resolved    a wildcard pattern matched these indices
+                retry       a search is retried or its expired PIT reopened
+                done        the dump ended: docs, bytes, elapsed_ms,
+                            interrupted, error, if any, and the summary
+                            of the indices, as written by --report
+
+            With --with-metadata, the mapping and the settings of each index are
+            written to <index>.mapping.json and <index>.settings.json, next to
+            the --output file or in the --output-dir directory, making a
+            self-contained bundle from which the index can be recreated.
+
+            With --checkpoint, the progress of the dump is recorded in a file
+            every few seconds: the indices dumped, the documents written and
+            the position of each slice. An interrupted dump is continued with
+            --resume and that file, which skips the indices already dumped and
+            appends to the output. The PIT of the interrupted dump has usually
+            expired, so a new one is opened, and documents changed in the
+            meantime may be missed or written twice. Loading with document ids,
+            see --with-id, makes the duplicates harmless. The position of a
+            slice is only meaningful in a new PIT when the documents are sorted
+            by unique fields, so an index partially dumped without --order-by
+            cannot be resumed, and --resume requires the --order-by of the
+            interrupted dump.
+
+            A search failing with a timeout, or with a 429, 502, 503 or 504
+            status of an overloaded cluster, is retried up to --max-retries
+            times, waiting from half a second to 30 seconds in between. When
+            the PIT expired, such as when the output is slower than
+            --keep-alive, a new one is opened and the search continues after
+            the last document written. Without --order-by, the position is only
+            valid in the expired PIT, so the dump of the index fails instead,
+            unless none of its documents were read yet. Other errors stop the
+            dump of the index and are reported.
+
+            The PITs are closed once their index is dumped. On Ctrl-C, the
+            output is flushed, the checkpoint written and the PITs closed
+            before exiting.
+
             The command supports specifying a size for each batch of documents to be dumped.
             The default size is 500 documents per batch.
 
+            Each batch is serialized at once and written to a buffer of
+            --buffer-size, 1mb by default, the output being written when the
+            buffer is full, when a checkpoint is recorded and at the end.
+
             The command also supports specifying a keep-alive duration for the PIT.
             The default keep-alive duration is 1 minute.
 
-            The --query flag accepts a path to a file containing an Elasticsearch
-            query clause (not a full search body). For example, to export only
-            documents where status is "active", create a file query.json:
+            The --query flag accepts an Elasticsearch query clause as inline
+            JSON, and --query-file a path to a file containing one. A --query
+            value that is not a JSON object is still read as a file path, as
+            before inline JSON was accepted, but this is deprecated. A full
+            search body such as { "query": { ... } } is accepted too, only its
+            query is used. For example, to export only documents where status
+            is "active":
 
-                { "term": { "status": "active" } }
-
-            Then run:
-                escli utils dump my-index --query query.json
+                escli utils dump my-index --query '{ "term": { "status": "active" } }'
 
             Use - to read the query from stdin:
-                cat query.json | escli utils dump my-index --query -
+                cat query.json | escli utils dump my-index --query-file -
 
             Example usage:
                 escli utils dump index1,index2 --size 1000 --keep-alive 5m
-                escli utils dump my-index --query query.json
+                escli utils dump my-index --query-file query.json
+                escli utils dump logs --sort @timestamp:asc,host.name
+                escli utils dump users --order-by user.id --output users.ndjson
+                escli utils dump logs-* --since now-24h --max-docs 100000
+                escli utils dump '*,-logs-debug-*' --expand-wildcards open,hidden --exclude-system
+                escli utils dump orders --docs-per-second 2000 --output orders.ndjson
+                escli utils dump users --redact address,phone --hash-fields email,user.id
+                escli utils dump metrics --docvalue-fields host.name,cpu.pct --fields-only
+                escli utils dump logs --runtime-mappings @runtime.json
+                escli utils dump users --transform '.name = .title | del(.title) | .source = "prod" | drop_nulls'
+                escli utils dump index1,index2,index3 --output-dir backup/ --concurrency 3 --with-metadata
+                escli utils dump logs --output logs.ndjson --max-file-size 1gb
+                escli utils dump logs-* --output s3://backups/logs/ --with-metadata
+                escli utils dump big-index --slices 4 --output big-index.ndjson --verify
+                escli utils dump logs-* --output-dir backup/ --report backup/report.json
+                escli utils dump big-index --output big-index.ndjson --checkpoint dump.ckpt
+                escli utils dump big-index --output big-index.ndjson --resume dump.ckpt
                 escli utils dump my-index --skip-index-name | escli utils load --index new-index
-                escli utils dump my-index --add-id | escli utils load --index my-index
+                escli utils dump my-index --with-id --with-routing | escli utils load --index my-index
             "#,
             )
     }
@@ -196,166 +892,1483 @@ impl Dump {
         transport: Transport,
         timeout: Option<Duration>,
     ) -> Result<Response, elasticsearch::Error> {
-        let client = Elasticsearch::new(transport);
-        let indices: Vec<&str> = self.indices.iter().map(String::as_str).collect();
+        let limits = PartLimits {
+            max_bytes: self.max_file_size,
+            max_docs: self.max_docs_per_file,
+        };
+        let actions = ActionOptions {
+            skip_index_name: self.skip_index_name,
+            with_id: self.with_id,
+            with_routing: self.with_routing,
+            original_index: self.original_index,
+        };
         let t = timeout.unwrap_or(Duration::from_secs(60));
 
-        let query: Value = match &self.query {
-            None => json!({ "match_all": {} }),
-            Some(path) => {
-                let is_stdin = path.as_os_str() == "-";
-                let input: Box<dyn AsyncRead + Unpin> = if is_stdin {
-                    Box::new(tokio::io::stdin())
-                } else {
-                    Box::new(File::open(path).await.map_err(|e| {
-                        eprintln!("Failed to open query file {:?}: {}", path, e);
-                        e
-                    })?)
-                };
-                let mut buf = String::new();
-                BufReader::new(input).read_to_string(&mut buf).await.map_err(|e| {
-                    eprintln!("Failed to read query: {}", e);
-                    e
-                })?;
-                serde_json::from_str(&buf).map_err(|e| {
-                    eprintln!("Failed to parse query JSON: {}", e);
-                    IoError::new(IoErrorKind::InvalidData, e)
-                })?
-            }
+        let query = match (&self.query, &self.query_file) {
+            (Some(query), _) => read_query_arg(query).await?,
+            (None, Some(path)) => read_json_file(path).await?,
+            (None, None) => json!({ "match_all": {} }),
         };
-
-        let mut output = match self.output {
-            Some(ref path) => {
-                let file = OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .truncate(true)
-                    .open(path)
-                    .await
-                    .map_err(|e| {
-                        eprintln!("Failed to open output file {:?}: {}", path, e);
-                        e
-                    })?;
-                Output::File(file)
-            }
-            None => Output::Stdout(tokio::io::stdout()),
+        let query = match query.get("query") {
+            Some(clause) => clause.clone(),
+            None => query,
         };
-
-        for index in indices {
-            let pit_response = client
-                .open_point_in_time(OpenPointInTimeParts::Index(&[index]))
-                .keep_alive(&self.keep_alive)
-                .request_timeout(t)
-                .send()
-                .await?;
-
-            if pit_response.status_code() != http::StatusCode::OK {
-                let status = pit_response.status_code();
-                let body = pit_response.text().await.unwrap_or_default();
-                eprintln!(
-                    "Failed to open PIT for index '{}': {} - {}",
-                    index, status, body
-                );
-                continue;
+        let query = match (&self.since, &self.until) {
+            (None, None) => query,
+            (since, until) => {
+                time_filtered(query, &self.time_field, since.as_deref(), until.as_deref())
             }
+        };
+        let fetch = self.fetched_fields().await?;
+        let Some(resolved) = self.resolve_indices(&transport, t).await? else {
+            return Ok(response(500, Vec::new()));
+        };
 
-            let initial_pit = match pit_response.json::<PointInTimeVariant>().await? {
-                PointInTimeVariant::Success(pit) => pit,
-                PointInTimeVariant::Error(err) => {
-                    eprintln!("Error opening PIT for index '{}': {}", index, err);
+        let checkpoint_path = self.resume.as_deref().or(self.checkpoint.as_deref());
+        let mut checkpoint = match &self.resume {
+            Some(path) => Checkpoint::load(path, self.slices, &self.order_by).await?,
+            None => Checkpoint::new(self.slices, self.order_by.clone()),
+        };
+        let mut saved = Instant::now();
+        let mut summary: BTreeMap<String, IndexSummary> = BTreeMap::new();
+        let mut indices = Vec::new();
+        for index in &resolved {
+            match checkpoint.indices.get(index) {
+                Some(state) if state.done => {
+                    let message = format!("Index '{}' was already dumped, skipping", index);
+                    self.progress_format.report("skipped", index, message);
+                    summary.entry(index.clone()).or_default().skipped = true;
                     continue;
                 }
-            };
-
-            let initial_search = client
-                .search(SearchParts::None)
-                .body(json!({
-                    "size": self.size,
-                    "pit": { "id": initial_pit.id, "keep_alive": self.keep_alive },
-                    "query": query,
-                    "sort": [{ "_shard_doc": { "order": "asc" } }]
-                }))
-                .send()
-                .await?;
-
-            let initial_bytes = initial_search.bytes().await?;
-            let initial_documents = match serde_json::from_slice::<SearchResultsVariant>(&initial_bytes)
-                .map_err(|e| IoError::new(IoErrorKind::InvalidData, e))?
-            {
-                SearchResultsVariant::Success(docs) => docs,
-                SearchResultsVariant::Error(err) => {
-                    eprintln!(
-                        "Error during initial search for index '{}': {}",
-                        index, err
+                // The _shard_doc tiebreaker of the position is meaningless in a new PIT.
+                Some(state)
+                    if checkpoint.order_by.is_empty()
+                        && state.search_after.iter().any(Option::is_some) =>
+                {
+                    let message = format!(
+                        "Index '{}' was partially dumped without --order-by and cannot be resumed, \
+                         its position being only valid in the PIT of the interrupted dump\n",
+                        index
                     );
-                    continue;
+                    return Ok(response(400, message.into_bytes()));
                 }
-            };
-
-            if initial_documents.hits.hits.is_empty() {
-                output.write_all(&initial_bytes).await?;
-                output.flush().await?;
-                continue;
+                Some(state) => {
+                    let message =
+                        format!("Resuming index '{}' after {} documents", index, state.docs);
+                    self.progress_format.report("resumed", index, message);
+                }
+                None => {}
             }
+            indices.push(index.clone());
+        }
+        let append = self.resume.is_some();
+        let capacity = self.buffer_size as usize;
 
-            persist_ndjson(&initial_documents, index, self.skip_index_name, self.add_id, &mut output).await?;
-
-            let mut next_pit = initial_documents.pit_id;
-            let mut next_search_after = initial_documents
-                .hits
-                .hits
-                .last()
-                .and_then(|hit| hit.sort.first())
-                .copied();
-
-            loop {
-                let mut payload = json!({
-                    "size": self.size,
-                    "pit": { "id": next_pit, "keep_alive": self.keep_alive },
-                    "query": query,
-                    "sort": [{ "_shard_doc": { "order": "asc" } }]
-                });
-                if let Some(sa) = next_search_after {
-                    payload["search_after"] = json!([sa]);
+        // An object storage prefix given as --output is written like --output-dir.
+        let (output, output_dir) = match self.output.as_deref().and_then(ObjectUrl::parse) {
+            Some(url) if url.is_prefix() => (None, self.output.clone()),
+            _ => (self.output.clone(), self.output_dir.clone()),
+        };
+        let remote = output
+            .iter()
+            .chain(&output_dir)
+            .any(|path| ObjectUrl::parse(path).is_some());
+        if remote && (limits.is_set() || append) {
+            let message = "--max-file-size, --max-docs-per-file and --resume cannot be used with an object storage output\n";
+            return Ok(response(400, message.as_bytes().to_vec()));
+        }
+        // Slices and indices sharing an output interleave their documents.
+        if !self.order_by.is_empty()
+            && (self.slices > 1 || (self.concurrency > 1 && output_dir.is_none()))
+        {
+            let message = "--order-by cannot be used with --slices, nor with --concurrency without --output-dir\n";
+            return Ok(response(400, message.as_bytes().to_vec()));
+        }
+
+        let mut shared = match &output_dir {
+            Some(dir) if remote => {
+                // Fails before the dump starts when the credentials are missing.
+                if let Some(url) = ObjectUrl::parse(dir) {
+                    ObjectStore::from_env(url.scheme)?;
                 }
+                None
+            }
+            Some(dir) => {
+                tokio::fs::create_dir_all(dir).await.map_err(|e| {
+                    eprintln!("Failed to create output directory {:?}: {}", dir, e);
+                    e
+                })?;
+                None
+            }
+            None => Some(open_output(output.as_deref(), limits, append, capacity).await?),
+        };
 
-                let search_response = client
-                    .search(SearchParts::None)
-                    .body(payload)
-                    .send()
-                    .await?;
-
-                let documents: SearchResult =
-                    match search_response.json::<SearchResultsVariant>().await? {
-                        SearchResultsVariant::Success(docs) => docs,
-                        SearchResultsVariant::Error(err) => {
-                            eprintln!("Error during search after for index '{}': {}", index, err);
-                            break;
-                        }
-                    };
+        let client = Elasticsearch::new(transport);
+        if self.with_metadata {
+            let dir = match (&output_dir, &output) {
+                (Some(dir), _) => dir.as_path(),
+                (None, Some(file)) => file.parent().unwrap_or(Path::new("")),
+                (None, None) => unreachable!("--with-metadata requires a file output"),
+            };
+            for index in &indices {
+                write_metadata(client.transport(), index, dir, t).await?;
+            }
+        }
+        let format = self.progress_format;
+        let bar = format == ProgressFormat::Text && !self.no_progress;
+        let mut progress = match bar && std::io::stderr().is_terminal() {
+            true => {
+                let total = count(&client, &indices, &query, t)
+                    .await
+                    .map(|total| self.max_docs.map_or(total, |max| total.min(max)));
+                let resumed = indices
+                    .iter()
+                    .filter_map(|index| checkpoint.indices.get(index))
+                    .map(|state| state.docs)
+                    .sum();
+                Some(Progress::new(total, resumed))
+            }
+            false => None,
+        };
 
-                if documents.hits.hits.is_empty() {
+        let reader = Arc::new(Reader {
+            client: client.clone(),
+            query: query.clone(),
+            keep_alive: self.keep_alive.clone(),
+            size: self
+                .max_docs
+                .map_or(self.size, |max| self.size.min(max as usize)),
+            sort: self.sort.iter().chain(&self.order_by).cloned().collect(),
+            unique_sort: !self.order_by.is_empty(),
+            strategy: self.strategy,
+            format,
+            max_retries: self.max_retries,
+            fetch,
+            slices: self.slices,
+            timeout: t,
+            requests: self.requests_per_second.map(Throttle::new),
+            docs: self.docs_per_second.map(Throttle::new),
+            resume: checkpoint
+                .indices
+                .iter()
+                .map(|(index, state)| (index.clone(), state.search_after.clone()))
+                .collect(),
+        });
+        let concurrency = usize::from(self.concurrency);
+        let (events, mut received) = mpsc::channel(2 * concurrency * self.slices as usize);
+        let scheduler = tokio::spawn(read_indices(reader, indices, concurrency, events));
+
+        let mut per_index: HashMap<String, Output> = HashMap::new();
+        let mut dumped = 0;
+        let mut bytes = 0;
+        let started = Instant::now();
+        let mut interrupted = false;
+        // Listening once keeps a Ctrl-C pressed while a page is written.
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+        loop {
+            let (index, event) = tokio::select! {
+                event = received.recv() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = &mut ctrl_c => {
+                    interrupted = true;
                     break;
-                } else {
-                    persist_ndjson(&documents, index, self.skip_index_name, self.add_id, &mut output).await?;
                 }
-
-                next_pit = documents.pit_id;
-                next_search_after = documents
-                    .hits
-                    .hits
-                    .last()
-                    .and_then(|hit| hit.sort.first())
-                    .copied();
+            };
+            let entry = summary.entry(index.clone()).or_default();
+            match &event {
+                Event::Opened => entry.started = Some(Instant::now()),
+                Event::Retried => {
+                    entry.retries += 1;
+                    continue;
+                }
+                Event::Failed(message) => {
+                    entry.error = Some(message.clone());
+                    continue;
+                }
+                Event::Done => entry.finished = Some(Instant::now()),
+                _ => {}
             }
+            if let (Event::Opened, Some(dir)) = (&event, &output_dir) {
+                let path = dir.join(format!("{index}.ndjson"));
+                per_index.insert(
+                    index,
+                    open_output(Some(&path), limits, append, capacity).await?,
+                );
+                continue;
+            }
+            let output = per_index
+                .get_mut(&index)
+                .or(shared.as_mut())
+                .expect("an output is open");
+            match event {
+                Event::Opened | Event::Retried | Event::Failed(_) => {}
+                Event::Hits(slice, mut result) => {
+                    if let Some(max) = self.max_docs {
+                        result.hits.hits.truncate((max - dumped) as usize);
+                    }
+                    for hit in &mut result.hits.hits {
+                        if !hit.fields.is_empty() || hit._source.is_null() {
+                            merge_fields(&mut hit._source, std::mem::take(&mut hit.fields));
+                        }
+                        self.anonymize(&mut hit._source);
+                        if let Some(transform) = &self.transform {
+                            transform.apply(&mut hit._source);
+                        }
+                    }
+                    let written = persist_ndjson(&result, &index, actions, output).await?;
+                    let docs = result.hits.hits.len() as u64;
+                    if let Some(progress) = &mut progress {
+                        progress.add(docs, written);
+                    }
+                    let entry = summary.entry(index.clone()).or_default();
+                    entry.docs += docs;
+                    entry.bytes += written;
+                    let state = checkpoint.index(&index);
+                    state.docs += docs;
+                    if let Some(slice) = slice {
+                        state.search_after[slice] =
+                            result.hits.hits.last().map(|hit| hit.sort.clone());
+                    }
+                    dumped += docs;
+                    bytes += written;
+                    format.emit(json!({
+                        "event": "batch",
+                        "index": index,
+                        "slice": slice,
+                        "docs": docs,
+                        "bytes": written,
+                        "total_docs": dumped,
+                        "total_bytes": bytes,
+                        "elapsed_ms": started.elapsed().as_millis() as u64,
+                    }));
+                }
+                // The raw response holds the PIT id, which differs from one dump to the other.
+                Event::Empty(_) if !self.order_by.is_empty() => {}
+                Event::Empty(bytes) => output.write_all(&bytes).await?,
+                Event::Done => {
+                    if let Some(file) = per_index.remove(&index) {
+                        file.close().await?;
+                    }
+                    let state = checkpoint.index(&index);
+                    state.done = true;
+                    format
+                        .emit(json!({ "event": "index_done", "index": index, "docs": state.docs }));
+                }
+            }
+            match checkpoint_path {
+                Some(path) if saved.elapsed() >= CHECKPOINT_INTERVAL => {
+                    // The checkpoint must not record documents still in a buffer.
+                    for output in per_index.values_mut().chain(shared.as_mut()) {
+                        output.flush().await?;
+                    }
+                    checkpoint.save(path).await?;
+                    saved = Instant::now();
+                }
+                _ => {}
+            }
+            if self.max_docs.is_some_and(|max| dumped >= max) {
+                break;
+            }
+        }
+        // The readers stop once nothing receives their pages anymore.
+        drop(received);
+        for (_, file) in per_index {
+            file.close().await?;
+        }
+        if let Some(path) = checkpoint_path {
+            checkpoint.save(path).await?;
+        }
+        if let Some(progress) = &progress {
+            progress.finish();
+        }
+        if let Some(output) = shared {
+            output.close().await?;
+        }
+        if interrupted && format == ProgressFormat::Text {
+            eprintln!("Interrupted, closing the PITs");
+        }
+        let scheduled = scheduler.await.expect("index scheduler panicked");
+        let now = Instant::now();
+        if format == ProgressFormat::Text && !self.no_progress && !summary.is_empty() {
+            eprint!("{}", render_summary(&summary, now));
+        }
+        let mut report = json!({
+            "docs": dumped,
+            "bytes": bytes,
+            "elapsed_ms": started.elapsed().as_millis() as u64,
+            "interrupted": interrupted,
+            "error": scheduled.as_ref().err().map(|e| e.to_string()),
+            "indices": summary.iter().map(|(index, entry)| entry.to_json(index, now)).collect::<Vec<_>>(),
+        });
+        if let Some(path) = &self.report {
+            let json = serde_json::to_vec_pretty(&report)
+                .map_err(|e| IoError::new(IoErrorKind::Other, e))?;
+            tokio::fs::write(path, json).await.map_err(|e| {
+                eprintln!("Failed to write report {:?}: {}", path, e);
+                e
+            })?;
+        }
+        report["event"] = json!("done");
+        format.emit(report);
+        scheduled?;
+        if interrupted {
+            let message = match checkpoint_path {
+                Some(path) => format!(
+                    "Dump interrupted, continue it with --resume {}\n",
+                    path.display()
+                ),
+                None => "Dump interrupted\n".to_string(),
+            };
+            return Ok(response(499, message.into_bytes()));
+        }
+        if self.verify {
+            return Ok(verify(&client, &resolved, &query, &checkpoint, t).await);
         }
-        output.flush().await?;
-        output.shutdown().await?;
 
         let hr = http::response::Response::new(Vec::new());
         let rr = reqwest::Response::from(hr);
         Ok(Response::new(rr, elasticsearch::http::Method::Get))
     }
+
+    /// The search parameters requesting the doc values, stored and runtime
+    /// fields, and leaving the source out with --fields-only.
+    async fn fetched_fields(&self) -> Result<serde_json::Map<String, Value>, IoError> {
+        let mut fetch = serde_json::Map::new();
+        if !self.docvalue_fields.is_empty() {
+            fetch.insert("docvalue_fields".to_string(), json!(self.docvalue_fields));
+        }
+        if !self.stored_fields.is_empty() {
+            fetch.insert("stored_fields".to_string(), json!(self.stored_fields));
+        }
+        if let Some(runtime) = &self.runtime_mappings {
+            let mappings = read_json_arg(runtime).await?;
+            // Runtime fields are only returned when requested by name.
+            let names: Vec<&String> = mappings
+                .as_object()
+                .map(|o| o.keys().collect())
+                .unwrap_or_default();
+            fetch.insert("fields".to_string(), json!(names));
+            fetch.insert("runtime_mappings".to_string(), mappings);
+        }
+        // Requesting stored fields leaves the source out unless asked for.
+        if self.fields_only || !self.stored_fields.is_empty() {
+            fetch.insert("_source".to_string(), json!(!self.fields_only));
+        }
+        Ok(fetch)
+    }
+
+    /// Replaces the wildcard patterns among the indices with the indices and
+    /// data streams they match, reported before the dump starts, `None` when
+    /// a pattern cannot be resolved. The exclusions, starting with -, are
+    /// applied to each pattern.
+    async fn resolve_indices(
+        &self,
+        transport: &Transport,
+        timeout: Duration,
+    ) -> Result<Option<Vec<String>>, elasticsearch::Error> {
+        let (exclusions, names): (Vec<&String>, Vec<&String>) = self
+            .indices
+            .iter()
+            .partition(|index| index.starts_with('-'));
+        let expand_wildcards = match self.expand_wildcards.is_empty() {
+            true => "open".to_string(),
+            false => self
+                .expand_wildcards
+                .iter()
+                .map(|e| e.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+        let mut indices: Vec<String> = Vec::new();
+        for name in names {
+            if !name.contains('*') {
+                if !indices.contains(name) {
+                    indices.push(name.clone());
+                }
+                continue;
+            }
+            let expression: Vec<&str> = std::iter::once(name)
+                .chain(exclusions.iter().copied())
+                .map(String::as_str)
+                .collect();
+            let path = format!("/_resolve/index/{}", expression.join(","));
+            let query = [("expand_wildcards", expand_wildcards.as_str())];
+            let Some(result) =
+                send_json_ok(transport, Method::Get, &path, &query, None, timeout).await?
+            else {
+                return Ok(None);
+            };
+            let matched = matched_indices(&result, self.exclude_system);
+            if self.progress_format == ProgressFormat::Text {
+                match matched.is_empty() {
+                    true => eprintln!("No index matches '{}'", name),
+                    false => eprintln!(
+                        "Dumping {} indices matching '{}': {}",
+                        matched.len(),
+                        name,
+                        matched.join(", ")
+                    ),
+                }
+            }
+            self.progress_format
+                .emit(json!({ "event": "resolved", "index": name, "indices": matched }));
+            for index in matched {
+                if !indices.contains(&index) {
+                    indices.push(index);
+                }
+            }
+        }
+        Ok(Some(indices))
+    }
+
+    /// Redacts and hashes the selected fields of a document.
+    fn anonymize(&self, source: &mut Value) {
+        for field in &self.redact {
+            update_path(source, field, &|_| json!("REDACTED"));
+        }
+        for field in &self.hash_fields {
+            update_path(source, field, &hash_value);
+        }
+    }
+}
+
+/// Compares the documents written for each index with the number of
+/// documents matching the query, reporting on stderr not to mix with a
+/// dump written to stdout.
+async fn verify(
+    client: &Elasticsearch,
+    indices: &[String],
+    query: &Value,
+    checkpoint: &Checkpoint,
+    timeout: Duration,
+) -> Response {
+    let mut table = Table::new(&["index", "expected", "written", "status"]);
+    let mut mismatches = 0;
+    for index in indices {
+        let expected = count(client, std::slice::from_ref(index), query, timeout).await;
+        let written = checkpoint.indices.get(index).map_or(0, |state| state.docs);
+        let status = match expected {
+            Some(expected) if expected == written => "ok",
+            Some(_) => "mismatch",
+            None => "unknown",
+        };
+        if status != "ok" {
+            mismatches += 1;
+        }
+        table.add_row(vec![
+            index.clone(),
+            expected.map_or("-".to_string(), |expected| expected.to_string()),
+            written.to_string(),
+            status.to_string(),
+        ]);
+    }
+    eprint!("{}", table.render());
+    match mismatches {
+        0 => {
+            eprintln!("All {} indices verified", indices.len());
+            response(200, Vec::new())
+        }
+        mismatches => response(
+            400,
+            format!(
+                "{} of {} indices do not match their count\n",
+                mismatches,
+                indices.len()
+            )
+            .into_bytes(),
+        ),
+    }
+}
+
+/// The progress of a dump, recorded periodically in a checkpoint file so that
+/// an interrupted dump can be resumed.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Checkpoint {
+    slices: u32,
+    /// The --order-by fields of the dump, without which the position of a
+    /// slice is only valid in the PIT that produced it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    order_by: Vec<Value>,
+    indices: BTreeMap<String, IndexCheckpoint>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct IndexCheckpoint {
+    /// The sort values of the last document written by each slice.
+    search_after: Vec<Option<Vec<Value>>>,
+    docs: u64,
+    done: bool,
+}
+
+impl Checkpoint {
+    fn new(slices: u32, order_by: Vec<Value>) -> Self {
+        Self {
+            slices,
+            order_by,
+            indices: BTreeMap::new(),
+        }
+    }
+
+    /// Loads a checkpoint, which must have been written with the same number
+    /// of slices and the same --order-by fields.
+    async fn load(path: &Path, slices: u32, order_by: &[Value]) -> Result<Self, IoError> {
+        let bytes = tokio::fs::read(path).await.map_err(|e| {
+            eprintln!("Failed to read checkpoint {:?}: {}", path, e);
+            e
+        })?;
+        let checkpoint: Self = serde_json::from_slice(&bytes)
+            .map_err(|e| IoError::new(IoErrorKind::InvalidData, e))?;
+        if checkpoint.slices != slices {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                format!(
+                    "the checkpoint was written with --slices {}",
+                    checkpoint.slices
+                ),
+            ));
+        }
+        if checkpoint.order_by != order_by {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                format!(
+                    "the checkpoint was written with --order-by {}",
+                    format_order_by(&checkpoint.order_by)
+                ),
+            ));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint to a temporary file renamed over `path`, so that
+    /// an interruption never leaves a partial checkpoint.
+    async fn save(&self, path: &Path) -> Result<(), IoError> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let bytes =
+            serde_json::to_vec_pretty(self).map_err(|e| IoError::new(IoErrorKind::Other, e))?;
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, path).await
+    }
+
+    fn index(&mut self, index: &str) -> &mut IndexCheckpoint {
+        let slices = self.slices as usize;
+        self.indices
+            .entry(index.to_string())
+            .or_insert_with(|| IndexCheckpoint {
+                search_after: vec![None; slices],
+                docs: 0,
+                done: false,
+            })
+    }
+}
+
+/// Writes the mapping and the settings of an index to `<index>.mapping.json`
+/// and `<index>.settings.json` in `dir`, the settings set by Elasticsearch
+/// being left out so that they can be used to recreate the index. For an
+/// alias, data stream or pattern, those of the last matching index, usually
+/// the newest, are written.
+async fn write_metadata(
+    transport: &Transport,
+    index: &str,
+    dir: &Path,
+    timeout: Duration,
+) -> Result<(), elasticsearch::Error> {
+    let path = format!("/{index}");
+    let Some(indices) = send_json_ok(transport, Method::Get, &path, &[], None, timeout).await?
+    else {
+        return Ok(());
+    };
+    let Some((_, metadata)) = indices.as_object().and_then(|o| o.iter().next_back()) else {
+        eprintln!("Index '{}' not found, no metadata written", index);
+        return Ok(());
+    };
+    let mut settings = metadata["settings"].clone();
+    if let Some(index_settings) = settings.get_mut("index") {
+        for setting in NON_COPYABLE_SETTINGS {
+            remove_path(index_settings, setting);
+        }
+    }
+    for (kind, value) in [("mapping", &metadata["mappings"]), ("settings", &settings)] {
+        let file = dir.join(format!("{index}.{kind}.json"));
+        let json =
+            serde_json::to_vec_pretty(value).map_err(|e| IoError::new(IoErrorKind::Other, e))?;
+        match ObjectUrl::parse(&file) {
+            Some(url) => ObjectStore::from_env(url.scheme)?.put(&url, json).await?,
+            None => tokio::fs::write(&file, json).await.map_err(|e| {
+                eprintln!("Failed to write {:?}: {}", file, e);
+                e
+            })?,
+        }
+    }
+    Ok(())
+}
+
+/// The indices and data streams of a resolve index response, sorted by
+/// name. The backing indices of the data streams matched are left out, and
+/// the system indices with `exclude_system`.
+fn matched_indices(resolved: &Value, exclude_system: bool) -> Vec<String> {
+    let entries = |kind: &str| resolved[kind].as_array().cloned().unwrap_or_default();
+    let data_streams: Vec<String> = entries("data_streams")
+        .iter()
+        .filter_map(|data_stream| data_stream["name"].as_str().map(str::to_string))
+        .collect();
+    let is_system = |index: &Value| {
+        index["attributes"]
+            .as_array()
+            .is_some_and(|a| a.contains(&json!("system")))
+    };
+    let mut matched: Vec<String> = entries("indices")
+        .iter()
+        .filter(|index| {
+            !index["data_stream"]
+                .as_str()
+                .is_some_and(|d| data_streams.iter().any(|name| name == d))
+        })
+        .filter(|index| !(exclude_system && is_system(*index)))
+        .filter_map(|index| index["name"].as_str().map(str::to_string))
+        .chain(data_streams.iter().cloned())
+        .collect();
+    matched.sort();
+    matched
+}
+
+/// Adds the fields fetched besides the source to a document, under their
+/// dotted names. A single value is written as is rather than in an array.
+fn merge_fields(source: &mut Value, fields: serde_json::Map<String, Value>) {
+    if !source.is_object() {
+        *source = json!({});
+    }
+    let document = source.as_object_mut().expect("an object");
+    for (name, value) in fields {
+        let value = match value {
+            Value::Array(mut values) if values.len() == 1 => values.remove(0),
+            value => value,
+        };
+        document.insert(name, value);
+    }
+}
+
+/// Replaces the value of a dotted path of nested objects, or each of its
+/// values when it is an array. Objects in arrays and field names containing
+/// dots, such as a `user.id` field written as is, are followed too.
+fn update_path(value: &mut Value, path: &str, update: &impl Fn(&Value) -> Value) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                update_path(item, path, update);
+            }
+        }
+        Value::Object(object) => {
+            if let Some(field) = object.get_mut(path) {
+                match field {
+                    Value::Array(items) => items.iter_mut().for_each(|item| *item = update(item)),
+                    field => *field = update(field),
+                }
+                return;
+            }
+            for (i, _) in path.match_indices('.') {
+                if let Some(child) = object.get_mut(&path[..i]) {
+                    update_path(child, &path[i + 1..], update);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Hashes a value with SHA-256, strings as they are and other values as
+/// JSON, so that equal values keep equal hashes. Nulls are kept.
+fn hash_value(value: &Value) -> Value {
+    match value {
+        Value::Null => Value::Null,
+        Value::String(s) => json!(hex(&Sha256::digest(s.as_bytes()))),
+        other => json!(hex(&Sha256::digest(other.to_string().as_bytes()))),
+    }
+}
+
+/// Reads a --query value: inline JSON, @file, or a bare file path as
+/// accepted before inline JSON was, deprecated in favor of --query-file.
+async fn read_query_arg(arg: &str) -> Result<Value, IoError> {
+    if arg.starts_with('@') || arg.trim_start().starts_with('{') {
+        return read_json_arg(arg).await;
+    }
+    eprintln!(
+        "Warning: --query with a file path is deprecated, use --query-file {} instead",
+        arg
+    );
+    read_json_file(Path::new(arg)).await
+}
+
+/// Restricts a query to the documents with a time field in a range, `since`
+/// included and `until` excluded.
+fn time_filtered(query: Value, field: &str, since: Option<&str>, until: Option<&str>) -> Value {
+    let mut range = serde_json::Map::new();
+    if let Some(since) = since {
+        range.insert("gte".to_string(), json!(since));
+    }
+    if let Some(until) = until {
+        range.insert("lt".to_string(), json!(until));
+    }
+    json!({
+        "bool": {
+            "must": [query],
+            "filter": [{ "range": { field: range } }]
+        }
+    })
+}
+
+/// Counts the documents matching the query in the indices, `None` when they
+/// cannot be counted.
+async fn count(
+    client: &Elasticsearch,
+    indices: &[String],
+    query: &Value,
+    timeout: Duration,
+) -> Option<u64> {
+    if indices.is_empty() {
+        return Some(0);
+    }
+    let indices: Vec<&str> = indices.iter().map(String::as_str).collect();
+    let response = client
+        .count(CountParts::Index(&indices))
+        .body(json!({ "query": query }))
+        .request_timeout(timeout)
+        .send()
+        .await
+        .ok()?;
+    if !response.status_code().is_success() {
+        return None;
+    }
+    let body = response.json::<Value>().await.ok()?;
+    body["count"].as_u64()
+}
+
+/// What happened to an index during the dump, for the summary printed once
+/// done and written by --report.
+#[derive(Debug, Default)]
+struct IndexSummary {
+    docs: u64,
+    bytes: u64,
+    retries: u64,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+    /// Already dumped by the dump being resumed.
+    skipped: bool,
+    error: Option<String>,
+}
+
+impl IndexSummary {
+    fn status(&self) -> &'static str {
+        match (&self.error, self.finished, self.skipped) {
+            (Some(_), _, _) => "failed",
+            (None, Some(_), _) => "done",
+            (None, None, true) => "skipped",
+            (None, None, false) => "incomplete",
+        }
+    }
+
+    /// How long the index was read, up to `now` when it was not finished.
+    fn duration(&self, now: Instant) -> Duration {
+        match self.started {
+            Some(started) => self.finished.unwrap_or(now) - started,
+            None => Duration::ZERO,
+        }
+    }
+
+    fn docs_per_second(&self, now: Instant) -> u64 {
+        let secs = self.duration(now).as_secs_f64();
+        match secs > 0.0 {
+            true => (self.docs as f64 / secs).round() as u64,
+            false => 0,
+        }
+    }
+
+    fn to_json(&self, index: &str, now: Instant) -> Value {
+        json!({
+            "index": index,
+            "status": self.status(),
+            "docs": self.docs,
+            "bytes": self.bytes,
+            "duration_ms": self.duration(now).as_millis() as u64,
+            "docs_per_second": self.docs_per_second(now),
+            "retries": self.retries,
+            "error": self.error,
+        })
+    }
+}
+
+fn render_summary(summary: &BTreeMap<String, IndexSummary>, now: Instant) -> String {
+    let mut table = Table::new(&[
+        "index", "status", "docs", "bytes", "duration", "docs/s", "retries",
+    ]);
+    for (index, entry) in summary {
+        table.add_row(vec![
+            index.clone(),
+            entry.status().to_string(),
+            entry.docs.to_string(),
+            format_bytes(entry.bytes),
+            format_duration(entry.duration(now)),
+            entry.docs_per_second(now).to_string(),
+            entry.retries.to_string(),
+        ]);
+    }
+    table.render()
+}
+
+/// The progress of a dump, rendered as a bar on stderr.
+struct Progress {
+    total: Option<u64>,
+    /// The documents written before the dump was resumed.
+    resumed: u64,
+    docs: u64,
+    bytes: u64,
+    started: Instant,
+    rendered: Option<Instant>,
+}
+
+impl Progress {
+    fn new(total: Option<u64>, resumed: u64) -> Self {
+        Self {
+            total,
+            resumed,
+            docs: resumed,
+            bytes: 0,
+            started: Instant::now(),
+            rendered: None,
+        }
+    }
+
+    /// Accounts for written documents, redrawing the bar at most every
+    /// `REDRAW_INTERVAL`.
+    fn add(&mut self, docs: u64, bytes: u64) {
+        self.docs += docs;
+        self.bytes += bytes;
+        if self
+            .rendered
+            .is_none_or(|at| at.elapsed() >= REDRAW_INTERVAL)
+        {
+            eprint!("\r{}", self.render(self.started.elapsed()));
+            self.rendered = Some(Instant::now());
+        }
+    }
+
+    /// Redraws the bar a last time and ends its line.
+    fn finish(&self) {
+        if self.rendered.is_some() {
+            eprintln!("\r{}", self.render(self.started.elapsed()));
+        }
+    }
+
+    fn render(&self, elapsed: Duration) -> String {
+        let rate = match elapsed.is_zero() {
+            true => 0.0,
+            false => (self.docs - self.resumed) as f64 / elapsed.as_secs_f64(),
+        };
+        let speed = format!(
+            "{} docs/s, {} written",
+            rate.round(),
+            format_bytes(self.bytes)
+        );
+        let total = match self.total {
+            Some(total) if total > 0 => total,
+            _ => return format!("{} docs, {speed}", self.docs),
+        };
+        let eta = match rate > 0.0 {
+            true => format_duration(Duration::from_secs_f64(
+                total.saturating_sub(self.docs) as f64 / rate,
+            )),
+            false => "-".to_string(),
+        };
+        format!(
+            "{} {}/{} docs, {speed}, ETA {eta}",
+            format_bar(self.docs as f64 / total as f64),
+            self.docs,
+            total
+        )
+    }
+}
+
+/// The settings shared by the tasks reading the indices.
+struct Reader {
+    client: Elasticsearch,
+    query: Value,
+    keep_alive: String,
+    size: usize,
+    /// The --sort fields, followed by a tiebreaker in the searches.
+    sort: Vec<Value>,
+    /// Whether `sort` holds --order-by fields identifying the documents, so
+    /// that a position can be continued in a new PIT.
+    unique_sort: bool,
+    strategy: Strategy,
+    format: ProgressFormat,
+    max_retries: u32,
+    /// The parameters of the searches selecting the fields besides the source.
+    fetch: serde_json::Map<String, Value>,
+    slices: u32,
+    timeout: Duration,
+    requests: Option<Throttle>,
+    docs: Option<Throttle>,
+    /// The sort values of the last document written by each slice of the
+    /// indices of a resumed dump.
+    resume: HashMap<String, Vec<Option<Vec<Value>>>>,
+}
+
+/// Spaces out units of work, such as requests or documents, to stay under a
+/// rate shared by all the readers.
+struct Throttle {
+    per_unit: Duration,
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    fn new(per_second: u32) -> Self {
+        Self {
+            per_unit: Duration::from_secs(1) / per_second,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Reserves the time of `units` units and waits for the reservation to
+    /// start.
+    async fn acquire(&self, units: u32) {
+        let wait = self.reserve(units, Instant::now());
+        tokio::time::sleep(wait).await;
+    }
+
+    /// Reserves the time of `units` units after the previous reservations and
+    /// returns how long to wait from `now` for it to start.
+    fn reserve(&self, units: u32, now: Instant) -> Duration {
+        let mut next = self.next.lock().expect("throttle lock poisoned");
+        let start = (*next).max(now);
+        *next = start + self.per_unit * units;
+        start - now
+    }
+}
+
+/// What happened to an index, sent by its reading tasks to the writer.
+enum Event {
+    /// The PIT of the index is open and its documents are about to be read.
+    Opened,
+    /// A page of documents read by a slice, `None` for a scroll whose
+    /// position cannot be resumed.
+    Hits(Option<usize>, SearchResult),
+    /// The raw response of an unsliced search that matched no documents.
+    Empty(Vec<u8>),
+    /// A search is retried, or its expired PIT reopened.
+    Retried,
+    /// Reading the index, or one of its slices, failed with this error.
+    Failed(String),
+    /// All the documents of the index were read.
+    Done,
+}
+
+type Events = mpsc::Sender<(String, Event)>;
+
+/// Reads the indices, at most `concurrency` of them at a time.
+async fn read_indices(
+    reader: Arc<Reader>,
+    indices: Vec<String>,
+    concurrency: usize,
+    events: Events,
+) -> Result<(), elasticsearch::Error> {
+    let mut running = JoinSet::new();
+    for index in indices {
+        if running.len() == concurrency {
+            running
+                .join_next()
+                .await
+                .expect("a task is running")
+                .expect("index reader panicked")?;
+        }
+        if events.is_closed() {
+            break;
+        }
+        running.spawn(read_index(reader.clone(), index, events.clone()));
+    }
+    while let Some(done) = running.join_next().await {
+        done.expect("index reader panicked")?;
+    }
+    Ok(())
+}
+
+/// Reads the documents of an index, or of the indices an alias, data stream
+/// or pattern resolves to, in a PIT split into slices read concurrently. The
+/// slices are scrolled instead when PITs are not supported by the cluster.
+async fn read_index(
+    reader: Arc<Reader>,
+    index: String,
+    events: Events,
+) -> Result<(), elasticsearch::Error> {
+    let initial_pit = match reader.strategy {
+        Strategy::Scroll => None,
+        strategy => match open_pit(&reader, &index).await? {
+            PitOpening::Open(id) => Some(id),
+            PitOpening::Unsupported(reason) if strategy == Strategy::Auto => {
+                let message = format!(
+                    "PIT not supported for index '{}', falling back to scroll: {}",
+                    index, reason
+                );
+                reader.format.report("fallback", &index, message);
+                None
+            }
+            PitOpening::Unsupported(reason) => {
+                let message = format!("Failed to open PIT for index '{}': {}", index, reason);
+                reader.format.report("error", &index, message.clone());
+                let _ = events.send((index, Event::Failed(message))).await;
+                return Ok(());
+            }
+            PitOpening::Failed(message) => {
+                let _ = events.send((index, Event::Failed(message))).await;
+                return Ok(());
+            }
+        },
+    };
+    if initial_pit.is_none() && reader.resume.contains_key(&index) {
+        return Err(IoError::new(
+            IoErrorKind::Unsupported,
+            format!(
+                "Index '{}' was partially dumped and cannot be resumed with the scroll API",
+                index
+            ),
+        )
+        .into());
+    }
+
+    if events.send((index.clone(), Event::Opened)).await.is_err() {
+        if let Some(pit_id) = &initial_pit {
+            close_pit(&reader, &index, pit_id).await;
+        }
+        return Ok(());
+    }
+    let resume = reader.resume.get(&index);
+    let mut slices = JoinSet::new();
+    for id in 0..reader.slices {
+        let search = SliceSearch {
+            reader: reader.clone(),
+            index: index.clone(),
+            id: id as usize,
+            pit_id: initial_pit.clone(),
+            search_after: resume.and_then(|starts| starts[id as usize].clone()),
+            slice: (reader.slices > 1).then(|| json!({ "id": id, "max": reader.slices })),
+        };
+        slices.spawn(read_slice(search, events.clone()));
+    }
+    let mut pit_id = initial_pit;
+    let mut failure = None;
+    while let Some(slice) = slices.join_next().await {
+        match slice.expect("slice reader panicked") {
+            Ok(last) => pit_id = last.or(pit_id),
+            Err(e) => failure = failure.or(Some(e)),
+        }
+    }
+    if let Some(pit_id) = &pit_id {
+        close_pit(&reader, &index, pit_id).await;
+    }
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    let _ = events.send((index, Event::Done)).await;
+    Ok(())
+}
+
+/// The outcome of opening a PIT.
+enum PitOpening {
+    Open(String),
+    /// The cluster does not support PITs, such as before 7.10.
+    Unsupported(String),
+    /// Opening the PIT failed with this error, already reported.
+    Failed(String),
+}
+
+async fn open_pit(reader: &Reader, index: &str) -> Result<PitOpening, elasticsearch::Error> {
+    let pit_response = reader
+        .client
+        .open_point_in_time(OpenPointInTimeParts::Index(&[index]))
+        .keep_alive(&reader.keep_alive)
+        .request_timeout(reader.timeout)
+        .send()
+        .await?;
+
+    if pit_response.status_code() != http::StatusCode::OK {
+        let status = pit_response.status_code();
+        let body = pit_response.text().await.unwrap_or_default();
+        // Clusters without PITs reject the unknown endpoint, or take _pit for
+        // a document type, while a missing index is a 404.
+        if matches!(status.as_u16(), 400 | 405) {
+            return Ok(PitOpening::Unsupported(format!("{} - {}", status, body)));
+        }
+        let message = format!(
+            "Failed to open PIT for index '{}': {} - {}",
+            index, status, body
+        );
+        reader.format.report("error", index, message.clone());
+        return Ok(PitOpening::Failed(message));
+    }
+
+    match pit_response.json::<PointInTimeVariant>().await? {
+        PointInTimeVariant::Success(pit) => Ok(PitOpening::Open(pit.id)),
+        PointInTimeVariant::Error(err) => {
+            let message = format!("Error opening PIT for index '{}': {}", index, err);
+            reader.format.report("error", index, message.clone());
+            Ok(PitOpening::Failed(message))
+        }
+    }
+}
+
+/// Closes a PIT to free its resources on the cluster, only warning when it
+/// fails since it expires anyway.
+async fn close_pit(reader: &Reader, index: &str, pit_id: &str) {
+    let closed = reader
+        .client
+        .close_point_in_time()
+        .body(json!({ "id": pit_id }))
+        .request_timeout(reader.timeout)
+        .send()
+        .await;
+    let message = match closed {
+        Ok(response) if response.status_code().is_success() => return,
+        Ok(response) => format!(
+            "Failed to close PIT for index '{}': {}",
+            index,
+            response.status_code()
+        ),
+        Err(e) => format!("Failed to close PIT for index '{}': {}", index, e),
+    };
+    reader.format.report("warning", index, message);
+}
+
+/// Clears a scroll, only warning when it fails since it expires anyway.
+async fn clear_scroll(reader: &Reader, index: &str, scroll_id: &str) {
+    let cleared = reader
+        .client
+        .clear_scroll(ClearScrollParts::None)
+        .body(json!({ "scroll_id": [scroll_id] }))
+        .request_timeout(reader.timeout)
+        .send()
+        .await;
+    let message = match cleared {
+        Ok(response) if response.status_code().is_success() => return,
+        Ok(response) => format!(
+            "Failed to clear scroll for index '{}': {}",
+            index,
+            response.status_code()
+        ),
+        Err(e) => format!("Failed to clear scroll for index '{}': {}", index, e),
+    };
+    reader.format.report("warning", index, message);
+}
+
+/// The search of the documents of a slice of a PIT, or of a scroll when
+/// there is no PIT.
+struct SliceSearch {
+    reader: Arc<Reader>,
+    index: String,
+    id: usize,
+    pit_id: Option<String>,
+    search_after: Option<Vec<Value>>,
+    slice: Option<Value>,
+}
+
+/// Where the next page of a slice is read from.
+enum Cursor {
+    /// The latest id of the PIT, the page following `search_after`, and
+    /// whether the slice reopened the PIT after the initial one expired.
+    Pit { id: String, reopened: bool },
+    /// The id of the scroll, `None` before the first page.
+    Scroll(Option<String>),
+}
+
+/// Reads the documents of a slice page by page, sending the pages until the
+/// slice is exhausted or the writer is gone, and returns the latest id of the
+/// PIT. A scroll, or a PIT reopened by the slice, is released once read.
+async fn read_slice(
+    search: SliceSearch,
+    events: Events,
+) -> Result<Option<String>, elasticsearch::Error> {
+    let mut cursor = match &search.pit_id {
+        Some(pit_id) => Cursor::Pit {
+            id: pit_id.clone(),
+            reopened: false,
+        },
+        None => Cursor::Scroll(None),
+    };
+    let read = read_pages(&search, &events, &mut cursor).await;
+    match cursor {
+        Cursor::Pit {
+            id,
+            reopened: false,
+        } => read.map(|_| Some(id)),
+        // The PIT shared by the slices is closed by the index reader.
+        Cursor::Pit { id, reopened: true } => {
+            close_pit(&search.reader, &search.index, &id).await;
+            read.map(|_| None)
+        }
+        Cursor::Scroll(Some(scroll_id)) => {
+            clear_scroll(&search.reader, &search.index, &scroll_id).await;
+            read.map(|_| None)
+        }
+        Cursor::Scroll(None) => read.map(|_| None),
+    }
+}
+
+/// Reads the pages of a slice with `search_after` in a PIT, or with a
+/// scroll, keeping `cursor` up to date. Searches failing with a timeout or
+/// an overloaded cluster are retried, and an expired PIT is reopened.
+async fn read_pages(
+    search: &SliceSearch,
+    events: &Events,
+    cursor: &mut Cursor,
+) -> Result<(), elasticsearch::Error> {
+    let reader = &search.reader;
+    let mut search_after = search.search_after.clone();
+    let mut first = true;
+    let mut attempt = 0;
+    loop {
+        // _shard_doc only exists in a PIT, _doc is the cheapest order of a scroll.
+        let tiebreaker = match cursor {
+            Cursor::Pit { .. } => json!({ "_shard_doc": { "order": "asc" } }),
+            Cursor::Scroll(_) => json!("_doc"),
+        };
+        let mut payload = json!({
+            "size": reader.size,
+            "query": reader.query,
+            "sort": reader.sort.iter().cloned().chain([tiebreaker]).collect::<Vec<_>>()
+        });
+        if let Some(slice) = &search.slice {
+            payload["slice"] = slice.clone();
+        }
+        for (name, value) in &reader.fetch {
+            payload[name] = value.clone();
+        }
+
+        if let Some(requests) = &reader.requests {
+            requests.acquire(1).await;
+        }
+        let sent = send_page(search, cursor, payload, search_after.as_ref()).await;
+        let transient = match &sent {
+            Ok(response) => matches!(response.status_code().as_u16(), 429 | 502 | 503 | 504),
+            Err(e) => e.is_timeout(),
+        };
+        if transient && attempt < reader.max_retries {
+            attempt += 1;
+            let delay = retry_delay(attempt);
+            let cause = match &sent {
+                Ok(response) => response.status_code().to_string(),
+                Err(e) => e.to_string(),
+            };
+            let message = format!(
+                "Retrying the search of index '{}' in {} after {} (attempt {} of {})",
+                search.index,
+                format_duration(delay),
+                cause,
+                attempt,
+                reader.max_retries
+            );
+            reader.format.report("retry", &search.index, message);
+            let _ = events.send((search.index.clone(), Event::Retried)).await;
+            tokio::time::sleep(delay).await;
+            continue;
+        }
+
+        let bytes = sent?.bytes().await?;
+        let documents = match serde_json::from_slice::<SearchResultsVariant>(&bytes)
+            .map_err(|e| IoError::new(IoErrorKind::InvalidData, e))?
+        {
+            SearchResultsVariant::Success(docs) => docs,
+            // The _shard_doc tiebreaker of `search_after` is meaningless in a new PIT.
+            SearchResultsVariant::Error(err)
+                if matches!(cursor, Cursor::Pit { .. })
+                    && err.to_string().contains("search_context_missing_exception")
+                    && search_after.is_some()
+                    && !reader.unique_sort =>
+            {
+                let message = format!(
+                    "The PIT of index '{}' expired and the dump cannot continue in a new one without --order-by, \
+                     increase --keep-alive",
+                    search.index
+                );
+                reader
+                    .format
+                    .report("error", &search.index, message.clone());
+                let _ = events
+                    .send((search.index.clone(), Event::Failed(message)))
+                    .await;
+                return Ok(());
+            }
+            // The PIT expired, such as when the writer was slower than --keep-alive.
+            SearchResultsVariant::Error(err)
+                if matches!(cursor, Cursor::Pit { .. })
+                    && err.to_string().contains("search_context_missing_exception")
+                    && attempt < reader.max_retries =>
+            {
+                attempt += 1;
+                let id = match open_pit(reader, &search.index).await? {
+                    PitOpening::Open(id) => id,
+                    PitOpening::Unsupported(message) | PitOpening::Failed(message) => {
+                        let _ = events
+                            .send((search.index.clone(), Event::Failed(message)))
+                            .await;
+                        return Ok(());
+                    }
+                };
+                let message = format!(
+                    "The PIT of index '{}' expired, continuing in a new one",
+                    search.index
+                );
+                reader.format.report("retry", &search.index, message);
+                let _ = events.send((search.index.clone(), Event::Retried)).await;
+                if let Cursor::Pit {
+                    id: expired,
+                    reopened: true,
+                } = cursor
+                {
+                    close_pit(reader, &search.index, expired).await;
+                }
+                *cursor = Cursor::Pit { id, reopened: true };
+                continue;
+            }
+            SearchResultsVariant::Error(err) => {
+                let message = match first {
+                    true => format!(
+                        "Error during initial search for index '{}': {}",
+                        search.index, err
+                    ),
+                    false => format!(
+                        "Error during search after for index '{}': {}",
+                        search.index, err
+                    ),
+                };
+                reader
+                    .format
+                    .report("error", &search.index, message.clone());
+                let _ = events
+                    .send((search.index.clone(), Event::Failed(message)))
+                    .await;
+                return Ok(());
+            }
+        };
+        attempt = 0;
+        match cursor {
+            Cursor::Pit { id, .. } => *id = documents.pit_id.clone(),
+            Cursor::Scroll(scroll_id) => *scroll_id = Some(documents.pit_id.clone()),
+        }
+
+        let Some(last) = documents.hits.hits.last() else {
+            if first && search.search_after.is_none() && search.slice.is_none() {
+                let _ = events
+                    .send((search.index.clone(), Event::Empty(bytes.to_vec())))
+                    .await;
+            }
+            return Ok(());
+        };
+        search_after = Some(last.sort.clone());
+        first = false;
+        if let Some(docs) = &reader.docs {
+            docs.acquire(documents.hits.hits.len() as u32).await;
+        }
+        // The position in a scroll cannot be resumed, so it is not recorded.
+        let slice = matches!(cursor, Cursor::Pit { .. }).then_some(search.id);
+        if events
+            .send((search.index.clone(), Event::Hits(slice, documents)))
+            .await
+            .is_err()
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Sends the search of the page following `search_after` in a PIT, or of
+/// the next page of a scroll.
+async fn send_page(
+    search: &SliceSearch,
+    cursor: &Cursor,
+    mut payload: Value,
+    search_after: Option<&Vec<Value>>,
+) -> Result<Response, elasticsearch::Error> {
+    let reader = &search.reader;
+    match cursor {
+        Cursor::Pit { id, .. } => {
+            payload["pit"] = json!({ "id": id, "keep_alive": reader.keep_alive });
+            if let Some(sa) = search_after {
+                payload["search_after"] = json!(sa);
+            }
+            reader
+                .client
+                .search(SearchParts::None)
+                .body(payload)
+                .send()
+                .await
+        }
+        Cursor::Scroll(None) => {
+            reader
+                .client
+                .search(SearchParts::Index(&[&search.index]))
+                .scroll(&reader.keep_alive)
+                .body(payload)
+                .send()
+                .await
+        }
+        Cursor::Scroll(Some(scroll_id)) => {
+            reader
+                .client
+                .scroll(ScrollParts::None)
+                .body(json!({ "scroll": reader.keep_alive, "scroll_id": scroll_id }))
+                .send()
+                .await
+        }
+    }
+}
+
+/// Waits half a second before the first retry, doubling up to 30 seconds.
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_millis(500)
+        .saturating_mul(1 << attempt.saturating_sub(1).min(6))
+        .min(Duration::from_secs(30))
+}
+
+/// Opens the file at `path` for writing, split into parts when limits are
+/// set, or stdout when there is no path. The file is truncated unless
+/// `append` is set. An `s3://` or `gcs://` path is uploaded instead. The
+/// writes are buffered up to `capacity` bytes.
+async fn open_output(
+    path: Option<&Path>,
+    limits: PartLimits,
+    append: bool,
+    capacity: usize,
+) -> Result<Output, IoError> {
+    if let Some(url) = path.and_then(ObjectUrl::parse) {
+        return Ok(Output::Upload(Upload::new(url)?));
+    }
+    let file = match path {
+        None => {
+            return Ok(Output::Stdout(BufWriter::with_capacity(
+                capacity,
+                tokio::io::stdout(),
+            )));
+        }
+        Some(path) if limits.is_set() => {
+            return Ok(Output::Parts(Parts::open(path, limits, capacity).await?));
+        }
+        Some(path) if append => open_file(path, OpenOptions::new().append(true)).await?,
+        Some(path) => create_file(path).await?,
+    };
+    Ok(Output::File(BufWriter::with_capacity(capacity, file)))
+}
+
+/// Creates or truncates the file at `path`.
+async fn create_file(path: &Path) -> Result<File, IoError> {
+    open_file(path, OpenOptions::new().write(true).truncate(true)).await
+}
+
+async fn open_file(path: &Path, options: &mut OpenOptions) -> Result<File, IoError> {
+    options.create(true).open(path).await.map_err(|e| {
+        eprintln!("Failed to open output file {:?}: {}", path, e);
+        e
+    })
 }
 
 /// Writes the search results to the specified output in NDJSON format.
@@ -364,12 +2377,16 @@ impl Dump {
 ///
 /// * `result` - A reference to a `SearchResult` containing the documents to process.
 /// * `index` - A string slice representing the name of the index being processed.
-/// * `output` - A mutable reference to an object implementing the `Write` trait,
+/// * `actions` - The document metadata to write in the action lines.
+/// * `output` - A mutable reference to an object implementing the `Sink` trait,
 ///   where the NDJSON data will be written.
 ///
 /// # Returns
 ///
-/// * `Result<(), Error>` - Returns `Ok(())` if the operation is successful, or an `Error` if an I/O error occurs.
+/// * `Result<u64, Error>` - Returns the number of bytes written if the operation is successful, or an `Error` if an I/O error occurs.
+///
+/// The documents are serialized in a single batch written at once, the output
+/// being flushed when its buffer is full.
 ///
 /// # Errors
 ///
@@ -379,34 +2396,41 @@ impl Dump {
 async fn persist_ndjson(
     result: &SearchResult,
     index: &str,
-    skip_index_name: bool,
-    add_id: bool,
-    output: &mut (impl AsyncWrite + Unpin),
-) -> Result<(), IoError> {
+    actions: ActionOptions,
+    output: &mut impl Sink,
+) -> Result<u64, IoError> {
+    let mut batch = Vec::new();
+    let mut lengths = Vec::with_capacity(result.hits.hits.len());
     for doc in result.hits.hits.iter() {
         let action_line = {
             let mut meta = serde_json::Map::new();
-            if !skip_index_name {
+            if !actions.skip_index_name {
+                let index = match (&doc._index, actions.original_index) {
+                    (Some(original), true) => original.as_str(),
+                    _ => index,
+                };
                 meta.insert("_index".to_string(), json!(index));
             }
-            if add_id {
+            if actions.with_id {
                 meta.insert("_id".to_string(), json!(doc._id));
             }
+            if let (Some(routing), true) = (&doc._routing, actions.with_routing) {
+                meta.insert("routing".to_string(), json!(routing));
+            }
             json!({ "index": meta })
         };
 
-        let action_s =
-            serde_json::to_string(&action_line).map_err(|e| IoError::new(IoErrorKind::Other, e))?;
-        output.write_all(action_s.as_bytes()).await?;
-        output.write_all(b"\n").await?;
-
-        let doc_s =
-            serde_json::to_string(&doc._source).map_err(|e| IoError::new(IoErrorKind::Other, e))?;
-        output.write_all(doc_s.as_bytes()).await?;
-        output.write_all(b"\n").await?;
+        let start = batch.len();
+        serde_json::to_writer(&mut batch, &action_line)
+            .map_err(|e| IoError::new(IoErrorKind::Other, e))?;
+        batch.push(b'\n');
+        serde_json::to_writer(&mut batch, &doc._source)
+            .map_err(|e| IoError::new(IoErrorKind::Other, e))?;
+        batch.push(b'\n');
+        lengths.push((batch.len() - start) as u64);
     }
-    output.flush().await?;
-    Ok(())
+    output.write_documents(&batch, &lengths).await?;
+    Ok(batch.len() as u64)
 }
 
 #[cfg(test)]
@@ -414,20 +2438,28 @@ mod tests {
     use super::*;
     use std::io::Cursor;
 
+    impl Sink for Cursor<Vec<u8>> {}
+
     fn create_sample_search_result() -> SearchResult {
         SearchResult {
             pit_id: "sample_pit_id".to_string(),
             hits: Hits {
                 hits: vec![
                     Hit {
+                        _index: None,
                         _id: "id1".to_string(),
+                        _routing: None,
+                        fields: Default::default(),
                         _source: json!({"field": "value1"}),
-                        sort: vec![1],
+                        sort: vec![json!(1)],
                     },
                     Hit {
+                        _index: None,
                         _id: "id2".to_string(),
+                        _routing: None,
+                        fields: Default::default(),
                         _source: json!({"field": "value2"}),
-                        sort: vec![2],
+                        sort: vec![json!(2)],
                     },
                 ],
             },
@@ -438,7 +2470,14 @@ mod tests {
     async fn test_persist_ndjson() {
         let search_result = create_sample_search_result();
         let mut output = Cursor::new(Vec::new());
-        persist_ndjson(&search_result, "test_index", false, false, &mut output).await.unwrap();
+        persist_ndjson(
+            &search_result,
+            "test_index",
+            ActionOptions::default(),
+            &mut output,
+        )
+        .await
+        .unwrap();
         let output_str = String::from_utf8(output.into_inner()).unwrap();
         let expected_output = r#"{"index":{"_index":"test_index"}}
 {"field":"value1"}
@@ -452,7 +2491,17 @@ mod tests {
     async fn test_persist_ndjson_skip_index_name() {
         let search_result = create_sample_search_result();
         let mut output = Cursor::new(Vec::new());
-        persist_ndjson(&search_result, "test_index", true, false, &mut output).await.unwrap();
+        persist_ndjson(
+            &search_result,
+            "test_index",
+            ActionOptions {
+                skip_index_name: true,
+                ..Default::default()
+            },
+            &mut output,
+        )
+        .await
+        .unwrap();
         let output_str = String::from_utf8(output.into_inner()).unwrap();
         let expected_output = r#"{"index":{}}
 {"field":"value1"}
@@ -466,7 +2515,17 @@ mod tests {
     async fn test_persist_ndjson_add_id() {
         let search_result = create_sample_search_result();
         let mut output = Cursor::new(Vec::new());
-        persist_ndjson(&search_result, "test_index", false, true, &mut output).await.unwrap();
+        persist_ndjson(
+            &search_result,
+            "test_index",
+            ActionOptions {
+                with_id: true,
+                ..Default::default()
+            },
+            &mut output,
+        )
+        .await
+        .unwrap();
         let output_str = String::from_utf8(output.into_inner()).unwrap();
         let expected_output = r#"{"index":{"_id":"id1","_index":"test_index"}}
 {"field":"value1"}
@@ -476,6 +2535,221 @@ mod tests {
         assert_eq!(output_str, expected_output);
     }
 
+    #[tokio::test]
+    async fn test_persist_ndjson_with_routing_and_original_index() {
+        let mut search_result = create_sample_search_result();
+        search_result.hits.hits[0]._index = Some(".ds-logs-000001".to_string());
+        search_result.hits.hits[0]._routing = Some("user1".to_string());
+        let actions = ActionOptions {
+            with_id: true,
+            with_routing: true,
+            original_index: true,
+            ..Default::default()
+        };
+        let mut output = Cursor::new(Vec::new());
+        persist_ndjson(&search_result, "logs", actions, &mut output)
+            .await
+            .unwrap();
+        let output_str = String::from_utf8(output.into_inner()).unwrap();
+        let expected_output = r#"{"index":{"_id":"id1","_index":".ds-logs-000001","routing":"user1"}}
+{"field":"value1"}
+{"index":{"_id":"id2","_index":"logs"}}
+{"field":"value2"}
+"#;
+        assert_eq!(output_str, expected_output);
+    }
+
+    #[test]
+    fn test_progress_render() {
+        let mut progress = Progress::new(Some(2000), 0);
+        progress.docs = 500;
+        progress.bytes = 3 * 512 * 1024;
+        assert_eq!(
+            progress.render(Duration::from_secs(5)),
+            "[########......................]  25% 500/2000 docs, 100 docs/s, 1.5mb written, ETA 15s"
+        );
+        progress.total = None;
+        assert_eq!(
+            progress.render(Duration::ZERO),
+            "500 docs, 0 docs/s, 1.5mb written"
+        );
+    }
+
+    #[test]
+    fn test_matched_indices() {
+        let resolved = json!({
+            "indices": [
+                {"name": "logs-old", "attributes": ["open"]},
+                {"name": ".ds-logs-app-1", "attributes": ["hidden", "open"], "data_stream": "logs-app"},
+                {"name": ".ds-other-1", "attributes": ["hidden", "open"], "data_stream": "other"},
+                {"name": ".tasks", "attributes": ["hidden", "system", "open"]},
+            ],
+            "aliases": [{"name": "logs", "indices": ["logs-old"]}],
+            "data_streams": [{"name": "logs-app", "backing_indices": [".ds-logs-app-1"]}],
+        });
+        assert_eq!(
+            matched_indices(&resolved, false),
+            vec![".ds-other-1", ".tasks", "logs-app", "logs-old"]
+        );
+        assert_eq!(
+            matched_indices(&resolved, true),
+            vec![".ds-other-1", "logs-app", "logs-old"]
+        );
+    }
+
+    #[test]
+    fn test_index_summary_status_and_speed() {
+        let started = Instant::now();
+        let mut entry = IndexSummary {
+            docs: 500,
+            started: Some(started),
+            ..Default::default()
+        };
+        assert_eq!(entry.status(), "incomplete");
+        assert_eq!(entry.docs_per_second(started + Duration::from_secs(2)), 250);
+        entry.finished = Some(started + Duration::from_secs(5));
+        assert_eq!(entry.status(), "done");
+        assert_eq!(
+            entry.duration(started + Duration::from_secs(60)),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            entry.docs_per_second(started + Duration::from_secs(60)),
+            100
+        );
+        entry.error = Some("boom".to_string());
+        assert_eq!(entry.status(), "failed");
+        let skipped = IndexSummary {
+            skipped: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            (skipped.status(), skipped.docs_per_second(started)),
+            ("skipped", 0)
+        );
+    }
+
+    #[test]
+    fn test_merge_fields() {
+        let mut source = json!({"a": 1});
+        let fields = json!({"b.c": [2], "d": [3, 4]})
+            .as_object()
+            .unwrap()
+            .clone();
+        merge_fields(&mut source, fields);
+        assert_eq!(source, json!({"a": 1, "b.c": 2, "d": [3, 4]}));
+
+        let mut missing = Value::Null;
+        merge_fields(&mut missing, serde_json::Map::new());
+        assert_eq!(missing, json!({}));
+    }
+
+    #[test]
+    fn test_update_path() {
+        let mut doc = json!({
+            "email": "a@example.com",
+            "user": {"id": 7, "name": "a"},
+            "tags": ["x", "y"],
+            "orders": [{"card": "1234"}, {"card": "5678"}],
+            "geo.ip": "10.0.0.1",
+        });
+        for field in [
+            "user.name",
+            "tags",
+            "orders.card",
+            "geo.ip",
+            "missing.field",
+        ] {
+            update_path(&mut doc, field, &|_| json!("REDACTED"));
+        }
+        update_path(&mut doc, "user.id", &hash_value);
+        assert_eq!(
+            doc,
+            json!({
+                "email": "a@example.com",
+                "user": {"id": hex(&Sha256::digest(b"7")), "name": "REDACTED"},
+                "tags": ["REDACTED", "REDACTED"],
+                "orders": [{"card": "REDACTED"}, {"card": "REDACTED"}],
+                "geo.ip": "REDACTED",
+            })
+        );
+        assert_eq!(hash_value(&json!("a")), hash_value(&json!("a")));
+        assert_eq!(hash_value(&Value::Null), Value::Null);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Duration::from_millis(500));
+        assert_eq!(retry_delay(2), Duration::from_secs(1));
+        assert_eq!(retry_delay(4), Duration::from_secs(4));
+        assert_eq!(retry_delay(10), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_parse_sort() {
+        assert_eq!(
+            parse_sort("@timestamp:desc"),
+            Ok(json!({"@timestamp": {"order": "desc"}}))
+        );
+        assert_eq!(
+            parse_sort("host.name"),
+            Ok(json!({"host.name": {"order": "asc"}}))
+        );
+        assert!(parse_sort("host:up").is_err());
+        assert!(parse_sort(":asc").is_err());
+    }
+
+    #[test]
+    fn test_format_order_by() {
+        let order_by = [
+            parse_sort("_id").unwrap(),
+            parse_sort("@timestamp:desc").unwrap(),
+        ];
+        assert_eq!(format_order_by(&order_by), "_id:asc,@timestamp:desc");
+        assert_eq!(format_order_by(&[]), "none");
+    }
+
+    #[test]
+    fn test_time_filtered() {
+        assert_eq!(
+            time_filtered(json!({"match_all": {}}), "ts", Some("now-24h"), None),
+            json!({"bool": {
+                "must": [{"match_all": {}}],
+                "filter": [{"range": {"ts": {"gte": "now-24h"}}}]
+            }})
+        );
+        assert_eq!(
+            time_filtered(
+                json!({"match_all": {}}),
+                "ts",
+                Some("2024-01-01"),
+                Some("2024-02-01")
+            )["bool"]["filter"][0],
+            json!({"range": {"ts": {"gte": "2024-01-01", "lt": "2024-02-01"}}})
+        );
+    }
+
+    #[test]
+    fn test_throttle_spaces_out_units() {
+        let throttle = Throttle::new(10);
+        let now = Instant::now();
+        assert_eq!(throttle.reserve(5, now), Duration::ZERO);
+        assert_eq!(throttle.reserve(1, now), Duration::from_millis(500));
+        assert_eq!(
+            throttle.reserve(1, now + Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_part_path() {
+        assert_eq!(
+            part_path(Path::new("out/dump.ndjson"), 2),
+            PathBuf::from("out/dump-00002.ndjson")
+        );
+        assert_eq!(part_path(Path::new("dump"), 1), PathBuf::from("dump-00001"));
+    }
+
     #[tokio::test]
     async fn test_persist_ndjson_with_large_batch() {
         let result = SearchResult {
@@ -483,15 +2757,20 @@ mod tests {
             hits: Hits {
                 hits: (0..10_000)
                     .map(|i| Hit {
+                        _index: None,
                         _id: format!("id{}", i),
+                        _routing: None,
+                        fields: Default::default(),
                         _source: json!({ "field": format!("value{}", i) }),
-                        sort: vec![i as u64],
+                        sort: vec![json!(i)],
                     })
                     .collect(),
             },
         };
         let mut output = Cursor::new(Vec::new());
-        persist_ndjson(&result, "test_index", false, false, &mut output).await.unwrap();
+        persist_ndjson(&result, "test_index", ActionOptions::default(), &mut output)
+            .await
+            .unwrap();
         let output_str = String::from_utf8(output.into_inner()).unwrap();
         let lines: Vec<&str> = output_str.lines().collect();
         assert_eq!(lines.len(), 20_000); // Each document has an action line
@@ -511,22 +2790,42 @@ mod tests {
             hits: Hits {
                 hits: vec![
                     Hit {
+                        _index: None,
                         _id: "id3".to_string(),
+                        _routing: None,
+                        fields: Default::default(),
                         _source: json!({"field": "value3"}),
-                        sort: vec![3],
+                        sort: vec![json!(3)],
                     },
                     Hit {
+                        _index: None,
                         _id: "id4".to_string(),
+                        _routing: None,
+                        fields: Default::default(),
                         _source: json!({"field": "value4"}),
-                        sort: vec![4],
+                        sort: vec![json!(4)],
                     },
                 ],
             },
         };
 
         let mut output = Cursor::new(Vec::new());
-        persist_ndjson(&search_result1, "index1", false, false, &mut output).await.unwrap();
-        persist_ndjson(&search_result2, "index2", false, false, &mut output).await.unwrap();
+        persist_ndjson(
+            &search_result1,
+            "index1",
+            ActionOptions::default(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        persist_ndjson(
+            &search_result2,
+            "index2",
+            ActionOptions::default(),
+            &mut output,
+        )
+        .await
+        .unwrap();
         let output_str = String::from_utf8(output.into_inner()).unwrap();
         let expected_output = r#"{"index":{"_index":"index1"}}
 {"field":"value1"}
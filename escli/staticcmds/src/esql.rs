@@ -0,0 +1,279 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::table::Table;
+use arrow_ipc::reader::StreamReader;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+/// Renders the `profile` section of an ES|QL response as one table of
+/// pipeline stages per driver, with row counts and per-stage timings.
+///
+/// Used as the post-processing step of `esql query --explain-plan`.
+pub fn explain_plan(body: &[u8]) -> Result<Vec<u8>, String> {
+    let response: Value = serde_json::from_slice(body)
+        .map_err(|e| format!("--explain-plan requires a JSON response, use --format json: {e}"))?;
+    let drivers = response["profile"]["drivers"]
+        .as_array()
+        .ok_or("The response does not contain a query profile")?;
+
+    let mut out = String::new();
+    if let Some(took) = response["took"].as_u64() {
+        out.push_str(&format!("Query took {took}ms"));
+        if let Some(values) = response["values"].as_array() {
+            out.push_str(&format!(", returned {} rows", values.len()));
+        }
+        out.push_str("\n\n");
+    }
+
+    for (i, driver) in drivers.iter().enumerate() {
+        out.push_str(&format!(
+            "Driver {}: {} (took {}, cpu {}, {} iterations)\n",
+            i + 1,
+            driver["description"].as_str().unwrap_or("-"),
+            nanos(&driver["took_nanos"]),
+            nanos(&driver["cpu_nanos"]),
+            driver["iterations"].as_u64().unwrap_or_default()
+        ));
+        let mut table = Table::new(&["stage", "operator", "rows.in", "rows.out", "time"]);
+        for (stage, operator) in driver["operators"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            let status = &operator["status"];
+            table.add_row(vec![
+                (stage + 1).to_string(),
+                operator_name(operator["operator"].as_str().unwrap_or("-")).to_string(),
+                count(&status["rows_received"]),
+                count(&status["rows_emitted"]),
+                nanos(&status["process_nanos"]),
+            ]);
+        }
+        out.push_str(&table.render());
+        out.push('\n');
+    }
+    Ok(out.into_bytes())
+}
+
+/// Strips the parameters from an operator description, e.g.
+/// `LuceneSourceOperator[maxPageSize = 1]` becomes `LuceneSourceOperator`.
+fn operator_name(operator: &str) -> &str {
+    operator.split_once('[').map_or(operator, |(name, _)| name)
+}
+
+fn count(value: &Value) -> String {
+    value.as_u64().map_or("-".to_string(), |v| v.to_string())
+}
+
+/// Writes an ES|QL response in the Arrow IPC stream format to a file, as is
+/// or converted to Parquet when the file name ends with `.parquet`, and
+/// returns a summary of what was written.
+///
+/// Used as the post-processing step of `esql query --output-file`.
+pub fn esql_output_file(path: PathBuf) -> impl FnOnce(&[u8]) -> Result<Vec<u8>, String> + Send {
+    move |body| {
+        let reader = StreamReader::try_new(Cursor::new(body), None)
+            .map_err(|e| format!("--output-file requires an Arrow response: {e}"))?;
+        let schema = reader.schema();
+        let batches = reader
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read the Arrow response: {e}"))?;
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+
+        let write_error = |e: &dyn std::fmt::Display| format!("Failed to write {path:?}: {e}");
+        match path.extension().is_some_and(|ext| ext == "parquet") {
+            true => {
+                let file = File::create(&path).map_err(|e| write_error(&e))?;
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let mut writer = ArrowWriter::try_new(file, schema, Some(properties))
+                    .map_err(|e| write_error(&e))?;
+                for batch in &batches {
+                    writer.write(batch).map_err(|e| write_error(&e))?;
+                }
+                writer.close().map_err(|e| write_error(&e))?;
+            }
+            false => std::fs::write(&path, body).map_err(|e| write_error(&e))?,
+        }
+        Ok(format!("Wrote {rows} rows to {}\n", path.display()).into_bytes())
+    }
+}
+
+/// Builds the `params` array of an ES|QL request from `--param` values, such
+/// as `level=error` or `limit:long=10`, bound to `?level` and `?limit` in the
+/// query.
+///
+/// Without a type, numbers, `true`, `false` and `null` keep their JSON type
+/// and anything else is a string. A type of `string`, `long`, `double` or
+/// `boolean` forces the conversion, e.g. `zip:string=01234`.
+pub fn esql_params(params: &[String]) -> Result<Value, String> {
+    let mut bound = Vec::with_capacity(params.len());
+    for param in params {
+        let (name, text) = param
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid parameter '{param}', expected NAME=VALUE"))?;
+        let value = match name.split_once(':') {
+            None => untyped_param(text),
+            Some((_, kind)) => typed_param(text, kind).ok_or_else(|| {
+                format!("Invalid parameter '{param}', '{text}' is not a valid {kind}")
+            })?,
+        };
+        let name = name.split_once(':').map_or(name, |(name, _)| name);
+        let mut object = Map::new();
+        object.insert(name.to_string(), value);
+        bound.push(Value::Object(object));
+    }
+    Ok(Value::Array(bound))
+}
+
+fn untyped_param(text: &str) -> Value {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) => value,
+        _ => Value::String(text.to_string()),
+    }
+}
+
+fn typed_param(text: &str, kind: &str) -> Option<Value> {
+    match kind {
+        "string" | "keyword" => Some(Value::String(text.to_string())),
+        "long" | "integer" => text.parse::<i64>().ok().map(Value::from),
+        "double" => text
+            .parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .map(Value::from),
+        "boolean" => text.parse::<bool>().ok().map(Value::Bool),
+        _ => None,
+    }
+}
+
+/// Formats a nanosecond duration with the most readable unit.
+fn nanos(value: &Value) -> String {
+    let Some(n) = value.as_u64() else {
+        return "-".to_string();
+    };
+    match n {
+        0..1_000 => format!("{n}ns"),
+        1_000..1_000_000 => format!("{:.1}us", n as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1}ms", n as f64 / 1e6),
+        _ => format!("{:.2}s", n as f64 / 1e9),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn explain_plan_renders_stages() {
+        let body = json!({
+            "took": 12,
+            "values": [[1], [2]],
+            "profile": {"drivers": [{
+                "description": "data",
+                "took_nanos": 2_500_000,
+                "cpu_nanos": 1_500,
+                "iterations": 3,
+                "operators": [
+                    {"operator": "LuceneSourceOperator[maxPageSize = 1]", "status": {"rows_emitted": 2, "process_nanos": 900}},
+                    {"operator": "LimitOperator[limit = 2]", "status": {"rows_received": 2, "rows_emitted": 2}}
+                ]
+            }]}
+        });
+        let out = String::from_utf8(explain_plan(body.to_string().as_bytes()).unwrap()).unwrap();
+        assert_eq!(
+            out,
+            "Query took 12ms, returned 2 rows\n\n\
+             Driver 1: data (took 2.5ms, cpu 1.5us, 3 iterations)\n\
+             stage  operator              rows.in  rows.out  time\n\
+             1      LuceneSourceOperator  -        2         900ns\n\
+             2      LimitOperator         2        2         -\n\n"
+        );
+    }
+
+    #[test]
+    fn esql_params_infers_or_forces_types() {
+        let params = [
+            "level=error",
+            "limit=10",
+            "ratio=0.5",
+            "zip:string=01234",
+            "on:boolean=true",
+        ]
+        .map(String::from);
+        assert_eq!(
+            esql_params(&params).unwrap(),
+            json!([{"level": "error"}, {"limit": 10}, {"ratio": 0.5}, {"zip": "01234"}, {"on": true}])
+        );
+        assert!(esql_params(&["limit".to_string()]).is_err());
+        assert!(esql_params(&["limit:long=ten".to_string()]).is_err());
+        assert!(esql_params(&["limit:date=1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn esql_output_file_converts_to_parquet() {
+        use arrow_array::{Int64Array, RecordBatch};
+        use arrow_ipc::writer::StreamWriter;
+        use arrow_schema::{DataType, Field, Schema};
+        use std::sync::Arc;
+
+        let schema = Arc::new(Schema::new(vec![Field::new("n", DataType::Int64, true)]));
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(vec![1, 2]))])
+                .unwrap();
+        let mut body = Vec::new();
+        let mut writer = StreamWriter::try_new(&mut body, &schema).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let dir = std::env::temp_dir().join(format!("escli-esql-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["out.arrow", "out.parquet"] {
+            let path = dir.join(name);
+            let summary = esql_output_file(path.clone())(&body).unwrap();
+            assert_eq!(
+                String::from_utf8(summary).unwrap(),
+                format!("Wrote 2 rows to {}\n", path.display())
+            );
+        }
+        assert_eq!(std::fs::read(dir.join("out.arrow")).unwrap(), body);
+        assert!(
+            std::fs::read(dir.join("out.parquet"))
+                .unwrap()
+                .starts_with(b"PAR1")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(esql_output_file(dir.join("x.arrow"))(b"id | name").is_err());
+    }
+
+    #[test]
+    fn explain_plan_requires_a_profile() {
+        assert!(explain_plan(b"{\"took\": 1}").is_err());
+        assert!(explain_plan(b"id | name").is_err());
+    }
+}
@@ -0,0 +1,487 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::csv::format_record;
+use crate::input::read_json_arg;
+use crate::request::{response, send_json, send_json_ok};
+use crate::transform_preview::mapping_fields;
+use arrow_array::builder::{BooleanBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+use clap::{Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Error as IoError, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Export {
+    #[arg(
+        short,
+        long,
+        help = "Indices to export, comma separated, wildcards allowed"
+    )]
+    index: String,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Fields to export as columns, comma separated, defaults to all mapped fields"
+    )]
+    fields: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        value_enum,
+        help = "Output format",
+        default_value_t = ExportFormat::Csv
+    )]
+    format: ExportFormat,
+
+    #[arg(
+        short,
+        long,
+        help = "Output file, required for parquet, defaults to stdout for csv"
+    )]
+    output: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        help = "Only export documents matching this query, inline JSON or @file"
+    )]
+    query: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of documents per search page",
+        default_value_t = 1000
+    )]
+    size: usize,
+
+    #[arg(
+        long,
+        help = "How long the point in time is kept alive between pages",
+        default_value = "5m"
+    )]
+    keep_alive: String,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+/// A column of the export and the Arrow type its values are converted to,
+/// from the type of the field in the mapping.
+#[derive(Debug, PartialEq)]
+struct Column {
+    name: String,
+    data_type: DataType,
+}
+
+impl Column {
+    fn new(name: &str, mapping_type: Option<&str>) -> Self {
+        let data_type = match mapping_type {
+            Some("long" | "integer" | "short" | "byte" | "unsigned_long") => DataType::Int64,
+            Some("double" | "float" | "half_float" | "scaled_float") => DataType::Float64,
+            Some("boolean") => DataType::Boolean,
+            _ => DataType::Utf8,
+        };
+        Column {
+            name: name.to_string(),
+            data_type,
+        }
+    }
+}
+
+/// Where the rows are written, as they are read page by page.
+enum Sink {
+    Csv(Box<dyn Write + Send>),
+    Parquet(ArrowWriter<File>, Arc<Schema>),
+}
+
+impl Export {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("export")
+            .about("Export the documents of indices as CSV or Parquet, one column per field.")
+            .long_about(
+                r#"
+            Export the documents of indices to a CSV or Parquet file, with one
+            row per document and one column per field, for use in
+            spreadsheets and data analysis tools.
+
+            The _source of each document is flattened into dotted field names,
+            such as user.name. Without --fields, every field of the mappings is
+            exported. Arrays and objects that are not mapped are written as
+            JSON text.
+
+            Parquet columns are typed after the mappings: integer fields are
+            exported as int64, floating point fields as float64, boolean
+            fields as booleans and all the others as strings. Values that do
+            not match the type of their column are left empty.
+
+            Documents are read with a point in time and search_after, so that
+            indices of any size can be exported.
+
+            Example usage:
+                escli utils export --index 'logs-*' --fields @timestamp,level,message > logs.csv
+                escli utils export --index orders --format parquet --output orders.parquet
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        if self.format == ExportFormat::Parquet && self.output.is_none() {
+            eprintln!("--output is required for the parquet format");
+            return Ok(response(400, Vec::new()));
+        }
+        let query = match &self.query {
+            Some(q) => Some(read_json_arg(q).await?),
+            None => None,
+        };
+
+        let path = format!("/{}/_mapping", self.index);
+        let Some(mappings) = send_json_ok(&transport, Method::Get, &path, &[], None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let columns = self.columns(&mappings);
+        let mut sink = self.sink(&columns)?;
+
+        let path = format!("/{}/_pit", self.index);
+        let pit_query = [("keep_alive", self.keep_alive.as_str())];
+        let Some(pit) = send_json_ok(&transport, Method::Post, &path, &pit_query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let mut pit_id = pit["id"].as_str().unwrap_or_default().to_string();
+
+        let mut exported = 0;
+        let mut search_after: Option<Value> = None;
+        let result = loop {
+            let body = self.search_body(&pit_id, &columns, query.as_ref(), search_after.as_ref());
+            let page = match send_json_ok(&transport, Method::Post, "/_search", &[], Some(&body), t)
+                .await
+            {
+                Ok(Some(page)) => page,
+                Ok(None) => break Ok(false),
+                Err(e) => break Err(e),
+            };
+            let hits = page["hits"]["hits"].as_array().cloned().unwrap_or_default();
+            let Some(last) = hits.last() else {
+                break Ok(true);
+            };
+            search_after = Some(last["sort"].clone());
+            if let Some(id) = page["pit_id"].as_str() {
+                pit_id = id.to_string();
+            }
+
+            let rows: Vec<BTreeMap<String, Value>> = hits
+                .iter()
+                .map(|hit| {
+                    let mut row = BTreeMap::new();
+                    flatten_source("", &hit["_source"], &mut row);
+                    row
+                })
+                .collect();
+            if let Err(e) = sink.write(&columns, &rows) {
+                break Err(e.into());
+            }
+            exported += rows.len();
+            eprintln!("Exported {exported} documents");
+        };
+
+        let close = json!({ "id": pit_id });
+        let _ = send_json(&transport, Method::Delete, "/_pit", &[], Some(&close), t).await;
+
+        let completed = result?;
+        sink.finish()?;
+        let status = match completed {
+            true => 200,
+            false => 500,
+        };
+        Ok(response(status, Vec::new()))
+    }
+
+    /// The columns of the export: the requested fields, or all the fields of
+    /// the mappings, typed after the mappings.
+    fn columns(&self, mappings: &Value) -> Vec<Column> {
+        let mut types = BTreeMap::new();
+        for index in mappings.as_object().into_iter().flatten().map(|(_, i)| i) {
+            let mut fields = BTreeMap::new();
+            mapping_fields("", &index["mappings"]["properties"], &mut fields);
+            for (field, kind) in fields {
+                types.entry(field).or_insert(kind);
+            }
+        }
+        match self.fields.is_empty() {
+            true => types
+                .iter()
+                .map(|(name, kind)| Column::new(name, Some(kind)))
+                .collect(),
+            false => self
+                .fields
+                .iter()
+                .map(|name| Column::new(name, types.get(name).map(String::as_str)))
+                .collect(),
+        }
+    }
+
+    fn sink(&self, columns: &[Column]) -> Result<Sink, IoError> {
+        match (self.format, &self.output) {
+            (ExportFormat::Csv, output) => {
+                let mut output: Box<dyn Write + Send> = match output {
+                    Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                    None => Box::new(BufWriter::new(std::io::stdout())),
+                };
+                let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+                output.write_all(format_record(&names, ',').as_bytes())?;
+                Ok(Sink::Csv(output))
+            }
+            (ExportFormat::Parquet, output) => {
+                let path = output.as_ref().expect("checked by execute");
+                let schema = Arc::new(Schema::new(
+                    columns
+                        .iter()
+                        .map(|c| Field::new(&c.name, c.data_type.clone(), true))
+                        .collect::<Vec<_>>(),
+                ));
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer =
+                    ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(properties))
+                        .map_err(IoError::other)?;
+                Ok(Sink::Parquet(writer, schema))
+            }
+        }
+    }
+
+    fn search_body(
+        &self,
+        pit_id: &str,
+        columns: &[Column],
+        query: Option<&Value>,
+        search_after: Option<&Value>,
+    ) -> Value {
+        let mut body = json!({
+            "size": self.size,
+            "pit": { "id": pit_id, "keep_alive": self.keep_alive },
+            "sort": ["_shard_doc"],
+        });
+        if !self.fields.is_empty() {
+            let fields: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+            body["_source"] = json!(fields);
+        }
+        if let Some(query) = query {
+            body["query"] = query.clone();
+        }
+        if let Some(search_after) = search_after {
+            body["search_after"] = search_after.clone();
+        }
+        body
+    }
+}
+
+impl Sink {
+    fn write(
+        &mut self,
+        columns: &[Column],
+        rows: &[BTreeMap<String, Value>],
+    ) -> Result<(), IoError> {
+        match self {
+            Sink::Csv(output) => {
+                for row in rows {
+                    let cells: Vec<String> = columns
+                        .iter()
+                        .map(|c| match row.get(&c.name) {
+                            None | Some(Value::Null) => String::new(),
+                            Some(Value::String(s)) => s.clone(),
+                            Some(other) => other.to_string(),
+                        })
+                        .collect();
+                    output.write_all(format_record(&cells, ',').as_bytes())?;
+                }
+                Ok(())
+            }
+            Sink::Parquet(writer, schema) => {
+                let arrays: Vec<ArrayRef> = columns.iter().map(|c| column_array(c, rows)).collect();
+                let batch = RecordBatch::try_new(schema.clone(), arrays).map_err(IoError::other)?;
+                writer.write(&batch).map_err(IoError::other)
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), IoError> {
+        match self {
+            Sink::Csv(mut output) => output.flush(),
+            Sink::Parquet(writer, _) => writer.close().map(|_| ()).map_err(IoError::other),
+        }
+    }
+}
+
+/// Builds the Arrow array of a column, leaving the values that do not match
+/// its type empty.
+fn column_array(column: &Column, rows: &[BTreeMap<String, Value>]) -> ArrayRef {
+    let values = rows.iter().map(|row| row.get(&column.name));
+    match column.data_type {
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            values.for_each(|v| builder.append_option(v.and_then(number_i64)));
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            values.for_each(|v| builder.append_option(v.and_then(number_f64)));
+            Arc::new(builder.finish())
+        }
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            values.for_each(|v| builder.append_option(v.and_then(Value::as_bool)));
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            values.for_each(|v| match v {
+                None | Some(Value::Null) => builder.append_null(),
+                Some(Value::String(s)) => builder.append_value(s),
+                Some(other) => builder.append_value(other.to_string()),
+            });
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+/// Reads an integer, also from the strings Elasticsearch accepts for numbers.
+fn number_i64(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+fn number_f64(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+/// Flattens nested objects of a `_source` into dotted paths. Arrays are kept
+/// as values.
+fn flatten_source(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                let path = match prefix {
+                    "" => key.clone(),
+                    prefix => format!("{prefix}.{key}"),
+                };
+                flatten_source(&path, child, out);
+            }
+        }
+        other => {
+            out.insert(prefix.to_string(), other.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Array, Int64Array};
+
+    #[test]
+    fn columns_are_typed_after_mappings() {
+        let export =
+            Export::try_parse_from(["export", "-i", "logs", "--fields", "code,user.name,extra"])
+                .unwrap();
+        let mappings = json!({"logs": {"mappings": {"properties": {
+            "code": {"type": "integer"},
+            "user": {"properties": {"name": {"type": "keyword"}}},
+        }}}});
+        assert_eq!(
+            export.columns(&mappings),
+            vec![
+                Column::new("code", Some("long")),
+                Column::new("user.name", Some("keyword")),
+                Column::new("extra", None),
+            ]
+        );
+        assert_eq!(export.columns(&mappings)[0].data_type, DataType::Int64);
+
+        let all = Export::try_parse_from(["export", "-i", "logs"]).unwrap();
+        let names: Vec<String> = all.columns(&mappings).into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["code", "user.name"]);
+    }
+
+    #[test]
+    fn column_array_drops_mismatched_values() {
+        let rows: Vec<BTreeMap<String, Value>> = [
+            json!({"n": 1}),
+            json!({"n": "2"}),
+            json!({"n": "x"}),
+            json!({}),
+        ]
+        .iter()
+        .map(|doc| {
+            let mut row = BTreeMap::new();
+            flatten_source("", doc, &mut row);
+            row
+        })
+        .collect();
+        let array = column_array(&Column::new("n", Some("long")), &rows);
+        let array = array.as_any().downcast_ref::<Int64Array>().unwrap();
+        let values: Vec<Option<i64>> = array.iter().collect();
+        assert_eq!(values, vec![Some(1), Some(2), None, None]);
+    }
+
+    #[test]
+    fn flatten_source_uses_dotted_paths() {
+        let mut row = BTreeMap::new();
+        flatten_source(
+            "",
+            &json!({"a": {"b": 1, "c": [1, 2]}, "d": null}),
+            &mut row,
+        );
+        assert_eq!(
+            row,
+            BTreeMap::from([
+                ("a.b".to_string(), json!(1)),
+                ("a.c".to_string(), json!([1, 2])),
+                ("d".to_string(), Value::Null),
+            ])
+        );
+    }
+}
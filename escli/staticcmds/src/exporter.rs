@@ -0,0 +1,503 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use crate::units::parse_duration;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const HEALTH_COLORS: [&str; 3] = ["green", "yellow", "red"];
+
+#[derive(Parser, Debug)]
+pub struct Exporter {
+    #[arg(
+        long,
+        help = "Address the metrics are served on, such as :9114 or 127.0.0.1:9114",
+        default_value = ":9114"
+    )]
+    listen: String,
+
+    #[arg(
+        long,
+        help = "Time between two scrapes of the cluster",
+        default_value = "15s",
+        value_parser = parse_duration
+    )]
+    interval: Duration,
+
+    #[arg(long, help = "Leave out the metrics of each index")]
+    no_index_metrics: bool,
+
+    #[arg(long, help = "Print the metrics once instead of serving them")]
+    once: bool,
+}
+
+/// The samples of one metric family, rendered under a single HELP and TYPE.
+#[derive(Debug, Default)]
+struct Family {
+    help: &'static str,
+    kind: &'static str,
+    samples: Vec<(String, f64)>,
+}
+
+/// Metric families keyed by name, rendered in the Prometheus text format.
+#[derive(Debug, Default)]
+struct Metrics {
+    families: BTreeMap<&'static str, Family>,
+}
+
+impl Metrics {
+    fn add(
+        &mut self,
+        name: &'static str,
+        kind: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) {
+        let family = self.families.entry(name).or_insert_with(|| Family {
+            help,
+            kind,
+            samples: Vec::new(),
+        });
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", escape_label(v)))
+            .collect();
+        let sample = match labels.is_empty() {
+            true => name.to_string(),
+            false => format!("{name}{{{}}}", labels.join(",")),
+        };
+        family.samples.push((sample, value));
+    }
+
+    fn gauge(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        value: &Value,
+    ) {
+        if let Some(value) = value.as_f64() {
+            self.add(name, "gauge", help, labels, value);
+        }
+    }
+
+    fn counter(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        value: &Value,
+    ) {
+        if let Some(value) = value.as_f64() {
+            self.add(name, "counter", help, labels, value);
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, family) in &self.families {
+            out.push_str(&format!("# HELP {name} {}\n", family.help));
+            out.push_str(&format!("# TYPE {name} {}\n", family.kind));
+            for (sample, value) in &family.samples {
+                out.push_str(&format!("{sample} {value}\n"));
+            }
+        }
+        out
+    }
+}
+
+impl Exporter {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("exporter")
+            .about("Serve cluster, node and index metrics to Prometheus.")
+            .long_about(
+                r#"
+            Scrape the cluster health, node stats and index stats every
+            --interval and serve them as Prometheus metrics on /metrics of the
+            --listen address, so a cluster can be monitored without deploying
+            a separate exporter.
+
+            Per index metrics can be numerous on clusters with many indices,
+            --no-index-metrics leaves them out. With --once the metrics are
+            printed a single time, which suits the textfile collector of the
+            node exporter.
+
+            The exporter runs until interrupted with Ctrl-C.
+
+            Example usage:
+                escli utils exporter --listen :9114 --interval 30s
+                escli utils exporter --once > /var/lib/node_exporter/elasticsearch.prom
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        if self.once {
+            return Ok(response(
+                200,
+                self.scrape(&transport, t).await?.into_bytes(),
+            ));
+        }
+
+        let address = match self.listen.strip_prefix(':') {
+            Some(port) => format!("0.0.0.0:{port}"),
+            None => self.listen.clone(),
+        };
+        let listener = match TcpListener::bind(&address).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                let text = format!("Cannot listen on {address}: {err}\n");
+                return Ok(response(400, text.into_bytes()));
+            }
+        };
+        eprintln!("Serving metrics on http://{address}/metrics");
+
+        let metrics = Arc::new(RwLock::new(self.scrape(&transport, t).await?));
+        let scraped = metrics.clone();
+        let interval = self.interval;
+        let scraper = async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match self.scrape(&transport, t).await {
+                    Ok(text) => *scraped.write().unwrap_or_else(|e| e.into_inner()) = text,
+                    Err(err) => eprintln!("Scrape failed: {err}"),
+                }
+            }
+        };
+        let server = async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let body = metrics.read().unwrap_or_else(|e| e.into_inner()).clone();
+                tokio::spawn(serve(stream, body));
+            }
+        };
+
+        tokio::select! {
+            _ = scraper => {}
+            _ = server => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+        Ok(response(200, Vec::new()))
+    }
+
+    /// Scrapes the cluster once and renders its metrics. A failed request
+    /// only leaves out its metrics and clears `elasticsearch_up`.
+    async fn scrape(
+        &self,
+        transport: &Transport,
+        t: Duration,
+    ) -> Result<String, elasticsearch::Error> {
+        let started = Instant::now();
+        let health = send_json_ok(transport, Method::Get, "/_cluster/health", &[], None, t).await?;
+        let nodes = send_json_ok(
+            transport,
+            Method::Get,
+            "/_nodes/stats/os,jvm,fs,indices",
+            &[],
+            None,
+            t,
+        )
+        .await?;
+        let indices = match self.no_index_metrics {
+            true => None,
+            false => {
+                let query = [("level", "indices")];
+                let path = "/_stats/docs,store,indexing,search";
+                send_json_ok(transport, Method::Get, path, &query, None, t).await?
+            }
+        };
+        let up =
+            health.is_some() && nodes.is_some() && (self.no_index_metrics || indices.is_some());
+
+        let mut metrics = collect(health.as_ref(), nodes.as_ref(), indices.as_ref());
+        metrics.add(
+            "elasticsearch_up",
+            "gauge",
+            "Whether the last scrape of the cluster succeeded",
+            &[],
+            if up { 1.0 } else { 0.0 },
+        );
+        metrics.add(
+            "elasticsearch_scrape_duration_seconds",
+            "gauge",
+            "Duration of the last scrape of the cluster",
+            &[],
+            started.elapsed().as_secs_f64(),
+        );
+        Ok(metrics.render())
+    }
+}
+
+/// Answers one HTTP request with the metrics on /metrics, or a 404.
+async fn serve(mut stream: TcpStream, metrics: String) {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => request.extend_from_slice(&buffer[..n]),
+        }
+    }
+    let answer = match request_path(&request) {
+        Some("/metrics") => http_response("200 OK", &metrics),
+        _ => http_response(
+            "404 Not Found",
+            "Not found, metrics are served on /metrics\n",
+        ),
+    };
+    let _ = stream.write_all(answer.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// The path of a GET request, without its query string.
+fn request_path(request: &[u8]) -> Option<&str> {
+    let line = std::str::from_utf8(request).ok()?.lines().next()?;
+    let mut parts = line.split(' ');
+    let (Some("GET"), Some(target)) = (parts.next(), parts.next()) else {
+        return None;
+    };
+    target.split('?').next()
+}
+
+fn http_response(status: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Builds the metrics of the cluster health, node stats and index stats
+/// responses, leaving out those that could not be fetched.
+fn collect(health: Option<&Value>, nodes: Option<&Value>, indices: Option<&Value>) -> Metrics {
+    let mut m = Metrics::default();
+    if let Some(health) = health {
+        let cluster = health["cluster_name"].as_str().unwrap_or_default();
+        let labels = [("cluster", cluster)];
+        for color in HEALTH_COLORS {
+            m.add(
+                "elasticsearch_cluster_health_status",
+                "gauge",
+                "Health status of the cluster, 1 for the current color",
+                &[("cluster", cluster), ("color", color)],
+                if health["status"] == color { 1.0 } else { 0.0 },
+            );
+        }
+        m.gauge(
+            "elasticsearch_cluster_health_number_of_nodes",
+            "Number of nodes in the cluster",
+            &labels,
+            &health["number_of_nodes"],
+        );
+        m.gauge(
+            "elasticsearch_cluster_health_active_shards",
+            "Number of active shards",
+            &labels,
+            &health["active_shards"],
+        );
+        m.gauge(
+            "elasticsearch_cluster_health_relocating_shards",
+            "Number of relocating shards",
+            &labels,
+            &health["relocating_shards"],
+        );
+        m.gauge(
+            "elasticsearch_cluster_health_initializing_shards",
+            "Number of initializing shards",
+            &labels,
+            &health["initializing_shards"],
+        );
+        m.gauge(
+            "elasticsearch_cluster_health_unassigned_shards",
+            "Number of unassigned shards",
+            &labels,
+            &health["unassigned_shards"],
+        );
+        m.gauge(
+            "elasticsearch_cluster_health_pending_tasks",
+            "Number of pending cluster state tasks",
+            &labels,
+            &health["number_of_pending_tasks"],
+        );
+    }
+
+    let empty = serde_json::Map::new();
+    let nodes = nodes.and_then(|n| n["nodes"].as_object()).unwrap_or(&empty);
+    for (id, node) in nodes {
+        let labels = [("node", node["name"].as_str().unwrap_or(id))];
+        m.gauge(
+            "elasticsearch_os_cpu_percent",
+            "CPU usage of the node",
+            &labels,
+            &node["os"]["cpu"]["percent"],
+        );
+        m.gauge(
+            "elasticsearch_os_load1",
+            "Load average of the node over one minute",
+            &labels,
+            &node["os"]["cpu"]["load_average"]["1m"],
+        );
+        m.gauge(
+            "elasticsearch_jvm_memory_heap_used_bytes",
+            "JVM heap used by the node",
+            &labels,
+            &node["jvm"]["mem"]["heap_used_in_bytes"],
+        );
+        m.gauge(
+            "elasticsearch_jvm_memory_heap_max_bytes",
+            "Maximum JVM heap of the node",
+            &labels,
+            &node["jvm"]["mem"]["heap_max_in_bytes"],
+        );
+        m.gauge(
+            "elasticsearch_fs_total_bytes",
+            "Total disk space of the node",
+            &labels,
+            &node["fs"]["total"]["total_in_bytes"],
+        );
+        m.gauge(
+            "elasticsearch_fs_available_bytes",
+            "Disk space available to the node",
+            &labels,
+            &node["fs"]["total"]["available_in_bytes"],
+        );
+        m.gauge(
+            "elasticsearch_node_docs",
+            "Number of documents on the node",
+            &labels,
+            &node["indices"]["docs"]["count"],
+        );
+        m.counter(
+            "elasticsearch_node_indexing_total",
+            "Number of indexing operations on the node",
+            &labels,
+            &node["indices"]["indexing"]["index_total"],
+        );
+        m.counter(
+            "elasticsearch_node_search_query_total",
+            "Number of search queries on the node",
+            &labels,
+            &node["indices"]["search"]["query_total"],
+        );
+    }
+
+    let indices = indices
+        .and_then(|i| i["indices"].as_object())
+        .unwrap_or(&empty);
+    for (name, index) in indices {
+        let labels = [("index", name.as_str())];
+        let primaries = &index["primaries"];
+        m.gauge(
+            "elasticsearch_index_docs",
+            "Number of documents in the primaries of the index",
+            &labels,
+            &primaries["docs"]["count"],
+        );
+        m.gauge(
+            "elasticsearch_index_store_size_bytes",
+            "Size of the index, replicas included",
+            &labels,
+            &index["total"]["store"]["size_in_bytes"],
+        );
+        m.counter(
+            "elasticsearch_index_indexing_total",
+            "Number of indexing operations on the primaries of the index",
+            &labels,
+            &primaries["indexing"]["index_total"],
+        );
+        m.counter(
+            "elasticsearch_index_search_query_total",
+            "Number of search queries on the index",
+            &labels,
+            &index["total"]["search"]["query_total"],
+        );
+    }
+    m
+}
+
+/// Escapes a label value as the Prometheus text format requires.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn collect_groups_samples_by_family() {
+        let health = json!({"cluster_name": "prod", "status": "yellow", "number_of_nodes": 2});
+        let nodes = json!({"nodes": {
+            "a1": {"name": "node-1", "jvm": {"mem": {"heap_used_in_bytes": 512}}},
+            "b2": {"name": "node-2", "jvm": {"mem": {"heap_used_in_bytes": 1024}}},
+        }});
+        let indices = json!({"indices": {"logs\"1": {"primaries": {"docs": {"count": 7}}}}});
+        assert_eq!(
+            collect(Some(&health), Some(&nodes), Some(&indices)).render(),
+            [
+                "# HELP elasticsearch_cluster_health_number_of_nodes Number of nodes in the cluster",
+                "# TYPE elasticsearch_cluster_health_number_of_nodes gauge",
+                "elasticsearch_cluster_health_number_of_nodes{cluster=\"prod\"} 2",
+                "# HELP elasticsearch_cluster_health_status Health status of the cluster, 1 for the current color",
+                "# TYPE elasticsearch_cluster_health_status gauge",
+                "elasticsearch_cluster_health_status{cluster=\"prod\",color=\"green\"} 0",
+                "elasticsearch_cluster_health_status{cluster=\"prod\",color=\"yellow\"} 1",
+                "elasticsearch_cluster_health_status{cluster=\"prod\",color=\"red\"} 0",
+                "# HELP elasticsearch_index_docs Number of documents in the primaries of the index",
+                "# TYPE elasticsearch_index_docs gauge",
+                "elasticsearch_index_docs{index=\"logs\\\"1\"} 7",
+                "# HELP elasticsearch_jvm_memory_heap_used_bytes JVM heap used by the node",
+                "# TYPE elasticsearch_jvm_memory_heap_used_bytes gauge",
+                "elasticsearch_jvm_memory_heap_used_bytes{node=\"node-1\"} 512",
+                "elasticsearch_jvm_memory_heap_used_bytes{node=\"node-2\"} 1024",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn request_path_reads_get_target() {
+        assert_eq!(
+            request_path(b"GET /metrics?x=1 HTTP/1.1\r\nHost: a\r\n\r\n"),
+            Some("/metrics")
+        );
+        assert_eq!(request_path(b"POST /metrics HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(request_path(b""), None);
+    }
+}
@@ -0,0 +1,170 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::profile::Profile;
+use elasticsearch::http::Method;
+use elasticsearch::http::headers::HeaderMap;
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+/// Result of a request sent to a single profile.
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Response { status: u16, body: Vec<u8> },
+    Error(String),
+}
+
+impl Outcome {
+    fn is_success(&self) -> bool {
+        matches!(self, Outcome::Response { status, .. } if (200..400).contains(status))
+    }
+}
+
+/// Sends the same request to every profile concurrently.
+///
+/// Returns the combined output and whether every request succeeded. When all
+/// the responses are JSON, the output is a JSON object keyed by profile name,
+/// otherwise each response is printed in its own `### <profile> (<status>)`
+/// section.
+pub async fn fan_out(
+    profiles: &[String],
+    method: Method,
+    path: &str,
+    headers: HeaderMap,
+    query: Vec<(String, String)>,
+    body: Option<String>,
+    timeout: Option<Duration>,
+) -> (Vec<u8>, bool) {
+    let handles: Vec<_> = profiles
+        .iter()
+        .map(|name| {
+            let (name, path, headers, query, body) = (
+                name.clone(),
+                path.to_string(),
+                headers.clone(),
+                query.clone(),
+                body.clone(),
+            );
+            tokio::spawn(async move {
+                let transport = match Profile::load(&name) {
+                    Ok(profile) => match profile.transport() {
+                        Ok(t) => t,
+                        Err(e) => return Outcome::Error(e),
+                    },
+                    Err(e) => return Outcome::Error(e.to_string()),
+                };
+                let res = transport
+                    .send(method, &path, headers, Some(&query), body, timeout)
+                    .await;
+                match res {
+                    Ok(res) => {
+                        let status = res.status_code().as_u16();
+                        match res.bytes().await {
+                            Ok(body) => Outcome::Response {
+                                status,
+                                body: body.to_vec(),
+                            },
+                            Err(e) => Outcome::Error(e.to_string()),
+                        }
+                    }
+                    Err(e) => Outcome::Error(e.to_string()),
+                }
+            })
+        })
+        .collect();
+
+    let mut outcomes = Vec::with_capacity(handles.len());
+    for (name, handle) in profiles.iter().zip(handles) {
+        let outcome = handle
+            .await
+            .unwrap_or_else(|e| Outcome::Error(e.to_string()));
+        outcomes.push((name.clone(), outcome));
+    }
+    let ok = outcomes.iter().all(|(_, o)| o.is_success());
+    (render(&outcomes), ok)
+}
+
+fn render(outcomes: &[(String, Outcome)]) -> Vec<u8> {
+    let json: Option<Map<String, Value>> = outcomes
+        .iter()
+        .map(|(name, outcome)| {
+            let value = match outcome {
+                Outcome::Response { body, .. } => serde_json::from_slice(body).ok()?,
+                Outcome::Error(e) => serde_json::json!({ "error": e }),
+            };
+            Some((name.clone(), value))
+        })
+        .collect();
+    if let Some(json) = json {
+        let mut out = serde_json::to_vec(&json).unwrap_or_default();
+        out.push(b'\n');
+        return out;
+    }
+
+    let mut out = Vec::new();
+    for (name, outcome) in outcomes {
+        match outcome {
+            Outcome::Response { status, body } => {
+                out.extend(format!("### {name} ({status})\n").into_bytes());
+                out.extend(body);
+                if !body.ends_with(b"\n") {
+                    out.push(b'\n');
+                }
+            }
+            Outcome::Error(e) => out.extend(format!("### {name} (error)\n{e}\n").into_bytes()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_keys_json_responses_by_profile() {
+        let outcomes = vec![
+            (
+                "eu".to_string(),
+                Outcome::Response {
+                    status: 200,
+                    body: br#"{"status":"green"}"#.to_vec(),
+                },
+            ),
+            ("us".to_string(), Outcome::Error("unreachable".to_string())),
+        ];
+        assert_eq!(
+            String::from_utf8(render(&outcomes)).unwrap(),
+            "{\"eu\":{\"status\":\"green\"},\"us\":{\"error\":\"unreachable\"}}\n"
+        );
+    }
+
+    #[test]
+    fn render_uses_sections_for_text_responses() {
+        let outcomes = vec![(
+            "eu".to_string(),
+            Outcome::Response {
+                status: 200,
+                body: b"green".to_vec(),
+            },
+        )];
+        assert_eq!(
+            String::from_utf8(render(&outcomes)).unwrap(),
+            "### eu (200)\ngreen\n"
+        );
+    }
+}
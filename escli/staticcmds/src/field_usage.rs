@@ -0,0 +1,206 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use crate::table::Table;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+const TOTAL_FIELDS_LIMIT: &str = "index.mapping.total_fields.limit";
+
+#[derive(Parser, Debug)]
+pub struct FieldUsage {
+    #[arg(help = "Indices to report on, comma separated, defaults to all")]
+    index: Option<String>,
+
+    #[arg(
+        long,
+        help = "Flag indices using more than this share of their field limit",
+        default_value_t = 0.8
+    )]
+    warn_at: f64,
+
+    #[arg(long, help = "List the unused fields of each index")]
+    show_unused: bool,
+}
+
+impl FieldUsage {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("field-usage")
+            .about("Report field counts, field limits and unused fields of indices.")
+            .long_about(
+                r#"
+            Count the mapped fields of each index, as counted against
+            index.mapping.total_fields.limit, and compare with the field usage
+            statistics to find the fields that were never queried, aggregated
+            or sorted on.
+
+            Indices using more than --warn-at of their field limit are marked
+            with a !. Field usage statistics are reset when a shard moves or a
+            node restarts, so a field reported as unused may have been used
+            before.
+
+            Example usage:
+                escli utils field-usage
+                escli utils field-usage 'logs-*' --show-unused
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let target = self.index.as_deref().unwrap_or("*");
+
+        let path = format!("/{target}/_mapping");
+        let Some(mappings) = send_json_ok(&transport, Method::Get, &path, &[], None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let path = format!("/{target}/_settings/{TOTAL_FIELDS_LIMIT}");
+        let query = [("include_defaults", "true"), ("flat_settings", "true")];
+        let Some(settings) = send_json_ok(&transport, Method::Get, &path, &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let path = format!("/{target}/_field_usage_stats");
+        let Some(usage) = send_json_ok(&transport, Method::Get, &path, &[], None, t).await? else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let mut table = Table::new(&["index", "fields", "limit", "usage", "used", "unused"]);
+        let mut unused_lists = String::new();
+        let empty = Map::new();
+        for (index, mapping) in mappings.as_object().unwrap_or(&empty) {
+            let mut leaves = BTreeSet::new();
+            let count = count_fields(&mapping["mappings"]["properties"], "", &mut leaves);
+            let limit = settings[index]["settings"][TOTAL_FIELDS_LIMIT]
+                .as_str()
+                .or(settings[index]["defaults"][TOTAL_FIELDS_LIMIT].as_str())
+                .and_then(|l| l.parse::<usize>().ok())
+                .unwrap_or(1000);
+            let used = used_fields(&usage[index]);
+            let unused: Vec<&String> = leaves.iter().filter(|f| !used.contains(*f)).collect();
+
+            let ratio = count as f64 / limit.max(1) as f64;
+            let flag = if ratio >= self.warn_at { " !" } else { "" };
+            table.add_row(vec![
+                index.clone(),
+                count.to_string(),
+                limit.to_string(),
+                format!("{:.0}%{flag}", ratio * 100.0),
+                (leaves.len() - unused.len()).to_string(),
+                unused.len().to_string(),
+            ]);
+            if self.show_unused && !unused.is_empty() {
+                unused_lists.push_str(&format!("\n{index}:\n"));
+                for field in unused {
+                    unused_lists.push_str(&format!("  {field}\n"));
+                }
+            }
+        }
+
+        if table.is_empty() {
+            return Ok(response(200, b"No index found\n".to_vec()));
+        }
+        Ok(response(
+            200,
+            format!("{}{unused_lists}", table.render()).into_bytes(),
+        ))
+    }
+}
+
+/// Counts the fields of a mapping like the total fields limit does: objects,
+/// leaf fields and multi-fields. The paths of the leaf fields and
+/// multi-fields are added to `leaves`.
+fn count_fields(properties: &Value, prefix: &str, leaves: &mut BTreeSet<String>) -> usize {
+    let mut count = 0;
+    for (name, field) in properties.as_object().into_iter().flatten() {
+        let path = format!("{prefix}{name}");
+        count += 1;
+        match field.get("properties") {
+            Some(children) => count += count_fields(children, &format!("{path}."), leaves),
+            None => {
+                leaves.insert(path.clone());
+            }
+        }
+        for sub in field["fields"]
+            .as_object()
+            .map(Map::keys)
+            .into_iter()
+            .flatten()
+        {
+            count += 1;
+            leaves.insert(format!("{path}.{sub}"));
+        }
+    }
+    count
+}
+
+/// The fields with any recorded usage in the field usage stats of an index.
+fn used_fields(stats: &Value) -> BTreeSet<String> {
+    let mut used = BTreeSet::new();
+    for shard in stats["shards"].as_array().into_iter().flatten() {
+        for (field, usage) in shard["stats"]["fields"].as_object().into_iter().flatten() {
+            if usage["any"].as_u64().unwrap_or_default() > 0 {
+                used.insert(field.clone());
+            }
+        }
+    }
+    used
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn count_fields_counts_objects_and_multi_fields() {
+        let properties = json!({
+            "title": {"type": "text", "fields": {"raw": {"type": "keyword"}}},
+            "user": {"properties": {"name": {"type": "keyword"}, "age": {"type": "long"}}},
+        });
+        let mut leaves = BTreeSet::new();
+        assert_eq!(count_fields(&properties, "", &mut leaves), 5);
+        assert_eq!(
+            leaves.into_iter().collect::<Vec<_>>(),
+            vec!["title", "title.raw", "user.age", "user.name"]
+        );
+    }
+
+    #[test]
+    fn used_fields_merges_shards() {
+        let stats = json!({"shards": [
+            {"stats": {"fields": {"title": {"any": 3}, "user.age": {"any": 0}}}},
+            {"stats": {"fields": {"user.name": {"any": 1}}}},
+        ]});
+        assert_eq!(
+            used_fields(&stats).into_iter().collect::<Vec<_>>(),
+            vec!["title", "user.name"]
+        );
+    }
+}
@@ -0,0 +1,504 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use crate::table::Table;
+use crate::units::{format_bytes, format_duration, parse_bytes, parse_duration};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const WATERMARKS: [(&str, &str); 3] = [("low", "85%"), ("high", "90%"), ("flood_stage", "95%")];
+
+const TIERS: [&str; 5] = [
+    "data_hot",
+    "data_warm",
+    "data_cold",
+    "data_frozen",
+    "data_content",
+];
+
+#[derive(Parser, Debug)]
+pub struct Forecast {
+    #[arg(help = "Index pattern to forecast, e.g. logs-*")]
+    pattern: String,
+
+    #[arg(
+        long,
+        help = "Duration over which index stats are sampled",
+        default_value = "1m",
+        value_parser = parse_duration
+    )]
+    window: Duration,
+
+    #[arg(
+        long,
+        help = "Number of stats samples taken during the window",
+        default_value_t = 5,
+        value_parser = clap::value_parser!(u32).range(2..)
+    )]
+    samples: u32,
+
+    #[arg(
+        long,
+        help = "Estimate growth from index sizes and creation dates instead of sampling"
+    )]
+    use_index_age: bool,
+}
+
+/// Disk usage and growth share of a single data node.
+#[derive(Debug, Clone, PartialEq)]
+struct NodeDisk {
+    name: String,
+    tier: String,
+    total: u64,
+    used: u64,
+    /// Bytes of the forecast indices currently stored on the node.
+    pattern_bytes: u64,
+}
+
+impl Forecast {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("forecast")
+            .about("Forecast when disk watermarks will be hit based on index growth.")
+            .long_about(
+                r#"
+            Estimate the growth rate of the indices matching a pattern and
+            forecast, per node and per data tier, when the disk watermarks
+            (low, high and flood stage) will be reached.
+
+            By default the growth rate is measured by sampling the index
+            stats --samples times over --window and fitting a linear trend
+            to the store size. With --use-index-age the rate is instead
+            derived from the current size of the indices and the creation
+            date of the oldest one, which needs no sampling but assumes
+            growth has been steady.
+
+            Growth is attributed to nodes in proportion to the share of the
+            matching indices they currently hold.
+
+            Example usage:
+                escli utils forecast 'logs-*'
+                escli utils forecast 'logs-*' --window 10m --samples 11
+                escli utils forecast 'metrics-*' --use-index-age
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+
+        let rate = if self.use_index_age {
+            self.rate_from_index_age(&transport, t).await?
+        } else {
+            self.rate_from_samples(&transport, t).await?
+        };
+        let Some(rate) = rate else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let Some(nodes) = self.node_disks(&transport, t).await? else {
+            return Ok(response(500, Vec::new()));
+        };
+        let Some(watermarks) = watermarks(&transport, t).await? else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        Ok(response(
+            200,
+            render_report(rate, &nodes, &watermarks).into_bytes(),
+        ))
+    }
+
+    /// Samples the total store size of the pattern and fits a linear trend.
+    async fn rate_from_samples(
+        &self,
+        transport: &Transport,
+        timeout: Duration,
+    ) -> Result<Option<f64>, elasticsearch::Error> {
+        let path = format!("/{}/_stats/store", self.pattern);
+        let interval = self.window / (self.samples - 1);
+        let start = Instant::now();
+        let mut points = Vec::new();
+
+        for i in 0..self.samples {
+            if i > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            let Some(stats) =
+                send_json_ok(transport, Method::Get, &path, &[], None, timeout).await?
+            else {
+                return Ok(None);
+            };
+            let size = stats["_all"]["total"]["store"]["size_in_bytes"]
+                .as_f64()
+                .unwrap_or_default();
+            eprintln!(
+                "Sample {}/{}: {}",
+                i + 1,
+                self.samples,
+                format_bytes(size as u64)
+            );
+            points.push((start.elapsed().as_secs_f64(), size));
+        }
+
+        Ok(Some(linear_slope(&points)))
+    }
+
+    /// Derives the rate from the current size and the age of the oldest index.
+    async fn rate_from_index_age(
+        &self,
+        transport: &Transport,
+        timeout: Duration,
+    ) -> Result<Option<f64>, elasticsearch::Error> {
+        let path = format!("/_cat/indices/{}", self.pattern);
+        let query = [
+            ("format", "json"),
+            ("bytes", "b"),
+            ("h", "index,store.size,creation.date"),
+        ];
+        let Some(indices) =
+            send_json_ok(transport, Method::Get, &path, &query, None, timeout).await?
+        else {
+            return Ok(None);
+        };
+
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as f64;
+        let rows = indices.as_array().cloned().unwrap_or_default();
+        let total: f64 = rows.iter().map(|r| cat_number(&r["store.size"])).sum();
+        let oldest = rows
+            .iter()
+            .map(|r| cat_number(&r["creation.date"]))
+            .filter(|d| *d > 0.0)
+            .fold(f64::INFINITY, f64::min);
+
+        if !oldest.is_finite() || now_ms <= oldest {
+            eprintln!(
+                "No index matching '{}' with a creation date was found",
+                self.pattern
+            );
+            return Ok(None);
+        }
+        Ok(Some(total / ((now_ms - oldest) / 1000.0)))
+    }
+
+    /// Collects disk usage of data nodes and how much of the pattern they hold.
+    async fn node_disks(
+        &self,
+        transport: &Transport,
+        timeout: Duration,
+    ) -> Result<Option<Vec<NodeDisk>>, elasticsearch::Error> {
+        let Some(stats) = send_json_ok(
+            transport,
+            Method::Get,
+            "/_nodes/stats/fs",
+            &[],
+            None,
+            timeout,
+        )
+        .await?
+        else {
+            return Ok(None);
+        };
+        let shards_path = format!("/_cat/shards/{}", self.pattern);
+        let query = [("format", "json"), ("bytes", "b"), ("h", "node,store")];
+        let Some(shards) =
+            send_json_ok(transport, Method::Get, &shards_path, &query, None, timeout).await?
+        else {
+            return Ok(None);
+        };
+
+        let mut pattern_bytes: BTreeMap<String, u64> = BTreeMap::new();
+        for shard in shards.as_array().into_iter().flatten() {
+            if let Some(node) = shard["node"].as_str() {
+                *pattern_bytes.entry(node.to_string()).or_default() +=
+                    cat_number(&shard["store"]) as u64;
+            }
+        }
+
+        let mut nodes: Vec<NodeDisk> = stats["nodes"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(_, node)| {
+                let tier = node_tier(&node["roles"])?;
+                let name = node["name"].as_str().unwrap_or_default().to_string();
+                let total = node["fs"]["total"]["total_in_bytes"]
+                    .as_u64()
+                    .unwrap_or_default();
+                let available = node["fs"]["total"]["available_in_bytes"]
+                    .as_u64()
+                    .unwrap_or_default();
+                Some(NodeDisk {
+                    pattern_bytes: pattern_bytes.get(&name).copied().unwrap_or_default(),
+                    name,
+                    tier,
+                    total,
+                    used: total.saturating_sub(available),
+                })
+            })
+            .collect();
+        nodes.sort_by(|a, b| (&a.tier, &a.name).cmp(&(&b.tier, &b.name)));
+        Ok(Some(nodes))
+    }
+}
+
+/// Reads the effective disk watermarks, falling back to the defaults.
+async fn watermarks(
+    transport: &Transport,
+    timeout: Duration,
+) -> Result<Option<Vec<(String, String)>>, elasticsearch::Error> {
+    let query = [("include_defaults", "true"), ("flat_settings", "true")];
+    let Some(settings) = send_json_ok(
+        transport,
+        Method::Get,
+        "/_cluster/settings",
+        &query,
+        None,
+        timeout,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    Ok(Some(
+        WATERMARKS
+            .iter()
+            .map(|&(name, default)| {
+                let key = format!("cluster.routing.allocation.disk.watermark.{name}");
+                let value = ["transient", "persistent", "defaults"]
+                    .iter()
+                    .find_map(|scope| settings[scope][&key].as_str())
+                    .unwrap_or(default);
+                (name.to_string(), value.to_string())
+            })
+            .collect(),
+    ))
+}
+
+/// Returns the data tier of a node, or `None` when it holds no data.
+fn node_tier(roles: &Value) -> Option<String> {
+    let roles: Vec<&str> = roles.as_array()?.iter().filter_map(Value::as_str).collect();
+    TIERS
+        .iter()
+        .find(|tier| roles.contains(*tier))
+        .map(|tier| tier.trim_start_matches("data_").to_string())
+        .or_else(|| roles.contains(&"data").then(|| "data".to_string()))
+}
+
+/// `_cat` APIs return numbers as strings, even with `format=json`.
+fn cat_number(value: &Value) -> f64 {
+    match value {
+        Value::String(s) => s.parse().unwrap_or_default(),
+        v => v.as_f64().unwrap_or_default(),
+    }
+}
+
+/// Least-squares slope of `(x, y)` points, 0 when it cannot be computed.
+fn linear_slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    if n < 2.0 {
+        return 0.0;
+    }
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let (num, den) = points.iter().fold((0.0, 0.0), |(num, den), (x, y)| {
+        (
+            num + (x - mean_x) * (y - mean_y),
+            den + (x - mean_x).powi(2),
+        )
+    });
+    if den == 0.0 { 0.0 } else { num / den }
+}
+
+/// Converts a watermark into the used-bytes threshold on a disk of `total` bytes.
+/// Watermarks are either a percentage/ratio of used disk or an absolute amount
+/// of free space.
+fn watermark_threshold(watermark: &str, total: u64) -> Option<u64> {
+    let watermark = watermark.trim();
+    if let Some(pct) = watermark.strip_suffix('%') {
+        let pct: f64 = pct.trim().parse().ok()?;
+        return Some((total as f64 * pct / 100.0) as u64);
+    }
+    if let Ok(ratio) = watermark.parse::<f64>() {
+        return Some((total as f64 * ratio) as u64);
+    }
+    parse_bytes(watermark)
+        .ok()
+        .map(|free| total.saturating_sub(free))
+}
+
+/// Time until `used` reaches `threshold` when growing by `rate` bytes/second.
+fn time_to_threshold(used: u64, threshold: u64, rate: f64) -> String {
+    if used >= threshold {
+        "reached".to_string()
+    } else if rate <= 0.0 {
+        "never".to_string()
+    } else {
+        let secs = (threshold - used) as f64 / rate;
+        if secs > 100.0 * 365.0 * 86400.0 {
+            "> 100y".to_string()
+        } else {
+            format_duration(Duration::from_secs_f64(secs))
+        }
+    }
+}
+
+fn render_report(rate: f64, nodes: &[NodeDisk], watermarks: &[(String, String)]) -> String {
+    let mut out = format!(
+        "Growth rate: {}/day\n\n",
+        format_bytes((rate.max(0.0) * 86400.0) as u64)
+    );
+    let pattern_total: u64 = nodes.iter().map(|n| n.pattern_bytes).sum();
+    let share = |bytes: u64| {
+        if pattern_total == 0 {
+            0.0
+        } else {
+            bytes as f64 / pattern_total as f64
+        }
+    };
+
+    let mut headers = vec!["node", "tier", "used", "total", "growth/day"];
+    headers.extend(watermarks.iter().map(|(name, _)| name.as_str()));
+    let mut table = Table::new(&headers);
+    for node in nodes {
+        let node_rate = rate * share(node.pattern_bytes);
+        let mut row = vec![
+            node.name.clone(),
+            node.tier.clone(),
+            format_bytes(node.used),
+            format_bytes(node.total),
+            format_bytes((node_rate.max(0.0) * 86400.0) as u64),
+        ];
+        row.extend(watermarks.iter().map(|(_, value)| {
+            watermark_threshold(value, node.total).map_or("?".to_string(), |t| {
+                time_to_threshold(node.used, t, node_rate)
+            })
+        }));
+        table.add_row(row);
+    }
+    out.push_str(&table.render());
+
+    let mut tiers: BTreeMap<&str, (u64, u64, u64)> = BTreeMap::new();
+    for node in nodes {
+        let entry = tiers.entry(node.tier.as_str()).or_default();
+        entry.0 += node.used;
+        entry.1 += node.total;
+        entry.2 += node.pattern_bytes;
+    }
+    let mut headers = vec!["tier", "used", "total", "growth/day"];
+    headers.extend(watermarks.iter().map(|(name, _)| name.as_str()));
+    let mut table = Table::new(&headers);
+    for (tier, (used, total, bytes)) in tiers {
+        let tier_rate = rate * share(bytes);
+        let mut row = vec![
+            tier.to_string(),
+            format_bytes(used),
+            format_bytes(total),
+            format_bytes((tier_rate.max(0.0) * 86400.0) as u64),
+        ];
+        row.extend(watermarks.iter().map(|(_, value)| {
+            watermark_threshold(value, total)
+                .map_or("?".to_string(), |t| time_to_threshold(used, t, tier_rate))
+        }));
+        table.add_row(row);
+    }
+    out.push('\n');
+    out.push_str(&table.render());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn linear_slope_fits_a_line() {
+        let points = [(0.0, 10.0), (1.0, 12.0), (2.0, 14.0)];
+        assert_eq!(linear_slope(&points), 2.0);
+        assert_eq!(linear_slope(&[(0.0, 1.0)]), 0.0);
+        assert_eq!(linear_slope(&[(1.0, 1.0), (1.0, 2.0)]), 0.0);
+    }
+
+    #[test]
+    fn watermark_threshold_handles_all_formats() {
+        assert_eq!(watermark_threshold("85%", 1000), Some(850));
+        assert_eq!(watermark_threshold("0.9", 1000), Some(900));
+        assert_eq!(watermark_threshold("100b", 1000), Some(900));
+        assert_eq!(watermark_threshold("nonsense", 1000), None);
+    }
+
+    #[test]
+    fn time_to_threshold_reports_reached_and_never() {
+        assert_eq!(time_to_threshold(900, 850, 1.0), "reached");
+        assert_eq!(time_to_threshold(100, 850, 0.0), "never");
+        assert_eq!(time_to_threshold(0, 3600, 1.0), "1h 0m");
+    }
+
+    #[test]
+    fn node_tier_prefers_specific_tiers() {
+        assert_eq!(
+            node_tier(&json!(["master", "data_hot", "data_content"])),
+            Some("hot".to_string())
+        );
+        assert_eq!(node_tier(&json!(["data"])), Some("data".to_string()));
+        assert_eq!(node_tier(&json!(["master"])), None);
+    }
+
+    #[test]
+    fn render_report_attributes_growth_by_share() {
+        let nodes = vec![
+            NodeDisk {
+                name: "a".into(),
+                tier: "hot".into(),
+                total: 1000,
+                used: 100,
+                pattern_bytes: 100,
+            },
+            NodeDisk {
+                name: "b".into(),
+                tier: "hot".into(),
+                total: 1000,
+                used: 100,
+                pattern_bytes: 0,
+            },
+        ];
+        let watermarks = vec![("low".to_string(), "85%".to_string())];
+        let report = render_report(1.0, &nodes, &watermarks);
+        assert!(
+            report.contains("a     hot   100b  1000b  84.4kb      12m 30s"),
+            "{report}"
+        );
+        assert!(
+            report.contains("b     hot   100b  1000b  0b          never"),
+            "{report}"
+        );
+    }
+}
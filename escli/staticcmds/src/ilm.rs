@@ -0,0 +1,264 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json, send_json_ok};
+use crate::table::Table;
+use crate::units::{format_duration, parse_duration};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// ILM phases in the order indices go through them.
+const PHASES: &[&str] = &["new", "hot", "warm", "cold", "frozen", "delete"];
+
+#[derive(Parser, Debug)]
+pub struct Ilm {
+    #[arg(help = "Indices to report on, wildcards allowed", default_value = "*")]
+    index: String,
+
+    #[arg(
+        long,
+        help = "Time after which an index still in the same step is reported as stuck",
+        default_value = "1d",
+        value_parser = parse_duration
+    )]
+    stuck_after: Duration,
+
+    #[arg(long, help = "Retry the failed steps")]
+    retry: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Ok,
+    Stuck,
+    Failed,
+}
+
+/// The ILM state of a managed index, from the explain lifecycle API.
+#[derive(Debug, PartialEq)]
+struct IndexState {
+    index: String,
+    policy: String,
+    phase: String,
+    action: String,
+    step: String,
+    step_age: Option<Duration>,
+    status: Status,
+    reason: Option<String>,
+}
+
+impl Ilm {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("ilm")
+            .about("Show the ILM state of indices grouped by policy and phase.")
+            .long_about(
+                r#"
+            List the indices managed by index lifecycle management, grouped by
+            policy and phase, with their current action and step and the time
+            spent in that step.
+
+            Indices whose step failed are reported as failed, with the reason
+            of the failure, and indices that have been in the same step for
+            longer than --stuck-after as stuck: a rollover waiting for its
+            conditions is expected to last, a shrink or a force merge is not.
+
+            With --retry, the failed steps are retried once the report is
+            printed.
+
+            Example usage:
+                escli utils ilm
+                escli utils ilm 'logs-*' --stuck-after 12h
+                escli utils ilm --retry
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let path = format!("/{}/_ilm/explain", self.index);
+        let query = [("only_managed", "true")];
+        let Some(explain) = send_json_ok(&transport, Method::Get, &path, &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let states = self.states(&explain, now);
+        if states.is_empty() {
+            let text = format!("No index matching {} is managed by ILM\n", self.index);
+            return Ok(response(200, text.into_bytes()));
+        }
+        print!("{}", render(&states));
+
+        let failed: Vec<&IndexState> = states
+            .iter()
+            .filter(|s| s.status == Status::Failed)
+            .collect();
+        let stuck = states.iter().filter(|s| s.status == Status::Stuck).count();
+        let mut summary = format!(
+            "{} managed indices, {} stuck, {} failed\n",
+            states.len(),
+            stuck,
+            failed.len()
+        );
+        if !self.retry {
+            return Ok(response(200, summary.into_bytes()));
+        }
+
+        let mut errors = 0;
+        for state in &failed {
+            let path = format!("/{}/_ilm/retry", state.index);
+            let (status, body) = send_json(&transport, Method::Post, &path, &[], None, t).await?;
+            if !status.is_success() {
+                eprintln!("Failed to retry {}: {body}", state.index);
+                errors += 1;
+            }
+        }
+        summary.push_str(&format!("Retried {} failed steps\n", failed.len() - errors));
+        let status = match errors {
+            0 => 200,
+            _ => 500,
+        };
+        Ok(response(status, summary.into_bytes()))
+    }
+
+    /// The states of the indices of an explain lifecycle response, sorted by
+    /// policy, phase and index. `now` is in milliseconds since the epoch.
+    fn states(&self, explain: &Value, now: u64) -> Vec<IndexState> {
+        let mut states = Vec::new();
+        for (index, entry) in explain["indices"].as_object().into_iter().flatten() {
+            if entry["managed"] != Value::Bool(true) {
+                continue;
+            }
+            let text = |key: &str| entry[key].as_str().unwrap_or("-").to_string();
+            let step_age = entry["step_time_millis"]
+                .as_u64()
+                .map(|ms| Duration::from_millis(now.saturating_sub(ms)));
+            let failed_step = entry["failed_step"].as_str();
+            let status = match (failed_step, step_age) {
+                (Some(_), _) => Status::Failed,
+                (None, Some(age)) if age > self.stuck_after => Status::Stuck,
+                _ => Status::Ok,
+            };
+            let step = match failed_step {
+                Some(failed) => format!("ERROR ({failed})"),
+                None => text("step"),
+            };
+            let reason = entry["step_info"]["reason"]
+                .as_str()
+                .or(entry["step_info"]["message"].as_str())
+                .map(str::to_string);
+            states.push(IndexState {
+                index: index.clone(),
+                policy: text("policy"),
+                phase: text("phase"),
+                action: text("action"),
+                step,
+                step_age,
+                status,
+                reason,
+            });
+        }
+        let phase_order = |phase: &str| PHASES.iter().position(|p| *p == phase);
+        states.sort_by(|a, b| {
+            a.policy
+                .cmp(&b.policy)
+                .then(phase_order(&a.phase).cmp(&phase_order(&b.phase)))
+                .then(a.index.cmp(&b.index))
+        });
+        states
+    }
+}
+
+/// Renders the states as a table, followed by the reasons of the failures.
+fn render(states: &[IndexState]) -> String {
+    let mut table = Table::new(&[
+        "policy", "phase", "index", "action", "step", "step.age", "status",
+    ]);
+    for state in states {
+        table.add_row(vec![
+            state.policy.clone(),
+            state.phase.clone(),
+            state.index.clone(),
+            state.action.clone(),
+            state.step.clone(),
+            state.step_age.map_or("-".to_string(), format_duration),
+            match state.status {
+                Status::Ok => "ok",
+                Status::Stuck => "stuck",
+                Status::Failed => "failed",
+            }
+            .to_string(),
+        ]);
+    }
+    let mut out = table.render();
+    for state in states {
+        if let Some(reason) = &state.reason {
+            out.push_str(&format!("\n{}: {reason}\n", state.index));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn states_flag_failed_and_stuck_steps() {
+        let ilm = Ilm::try_parse_from(["ilm", "--stuck-after", "1h"]).unwrap();
+        let hour = 3_600_000;
+        let explain = json!({"indices": {
+            "logs-2": {"managed": true, "policy": "logs", "phase": "hot", "action": "rollover",
+                "step": "check-rollover-ready", "step_time_millis": 10 * hour - 60_000},
+            "logs-1": {"managed": true, "policy": "logs", "phase": "warm", "action": "shrink",
+                "step": "ERROR", "failed_step": "shrink", "step_time_millis": 8 * hour,
+                "step_info": {"type": "illegal_state_exception", "reason": "no node"}},
+            "logs-0": {"managed": true, "policy": "logs", "phase": "hot", "action": "forcemerge",
+                "step": "forcemerge", "step_time_millis": 7 * hour},
+            "other": {"managed": false},
+        }});
+        let states = ilm.states(&explain, 10 * hour);
+        let summary: Vec<(&str, &str, Status)> = states
+            .iter()
+            .map(|s| (s.index.as_str(), s.step.as_str(), s.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("logs-0", "forcemerge", Status::Stuck),
+                ("logs-2", "check-rollover-ready", Status::Ok),
+                ("logs-1", "ERROR (shrink)", Status::Failed),
+            ]
+        );
+        assert_eq!(states[2].reason.as_deref(), Some("no node"));
+        assert_eq!(states[1].step_age, Some(Duration::from_secs(60)));
+    }
+}
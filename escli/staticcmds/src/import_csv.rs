@@ -0,0 +1,396 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::csv::CsvReader;
+use crate::load::BulkSender;
+use crate::request::response;
+use crate::units::parse_duration;
+use clap::{Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncRead, BufReader};
+
+#[derive(Parser, Debug)]
+pub struct ImportCsv {
+    #[arg(help = "Path to the CSV file, or - to read from stdin")]
+    file: PathBuf,
+
+    #[arg(short, long, help = "Index to load the rows into")]
+    index: String,
+
+    #[arg(
+        short,
+        long,
+        help = "Field delimiter, such as ';' or '\\t' for tabs",
+        default_value = ",",
+        value_parser = parse_delimiter
+    )]
+    delimiter: char,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Field names of the columns, comma separated, instead of the header"
+    )]
+    columns: Vec<String>,
+
+    #[arg(
+        long,
+        requires = "columns",
+        help = "The first line is a row, not a header"
+    )]
+    no_header: bool,
+
+    #[arg(
+        short,
+        long = "type",
+        value_name = "FIELD=TYPE",
+        help = "Type of a field: string, long, double, boolean or json, can be repeated",
+        value_parser = parse_field_type
+    )]
+    types: Vec<(String, FieldType)>,
+
+    #[arg(
+        long,
+        help = "Convert numbers and booleans of the fields without a --type"
+    )]
+    infer_types: bool,
+
+    #[arg(long, help = "Column holding the document ids")]
+    id_column: Option<String>,
+
+    #[arg(short, long, help = "Ingest pipeline to use")]
+    pipeline: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of rows per bulk request",
+        default_value_t = 500
+    )]
+    size: usize,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of bulk requests sent concurrently",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    concurrency: u16,
+
+    #[arg(
+        long,
+        help = "Maximum number of retries of documents rejected with 429 Too Many Requests",
+        default_value_t = 3
+    )]
+    max_retries: u32,
+
+    #[arg(
+        long,
+        help = "Delay before the first retry, doubled on each attempt",
+        default_value = "1s",
+        value_parser = parse_duration
+    )]
+    retry_backoff: Duration,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum FieldType {
+    String,
+    Long,
+    Double,
+    Boolean,
+    Json,
+}
+
+fn parse_delimiter(s: &str) -> Result<char, String> {
+    let s = match s {
+        "\\t" | "tab" => "\t",
+        s => s,
+    };
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c != '"' => Ok(c),
+        _ => Err(format!(
+            "invalid delimiter '{s}', expected a single character"
+        )),
+    }
+}
+
+fn parse_field_type(s: &str) -> Result<(String, FieldType), String> {
+    let (field, kind) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid type '{s}', expected FIELD=TYPE"))?;
+    let kind = FieldType::from_str(kind, true).map_err(|_| {
+        format!("invalid type '{kind}', expected string, long, double, boolean or json")
+    })?;
+    Ok((field.to_string(), kind))
+}
+
+impl ImportCsv {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("import-csv")
+            .about("Load the rows of a CSV file into an index via the bulk API.")
+            .long_about(
+                r#"
+            Load a CSV file into an index, one document per row, using the
+            bulk API. The file is streamed, so that arbitrarily large files can
+            be imported.
+
+            The fields of the documents are named after the header line, or
+            after --columns, with --no-header when the file has no header.
+            Empty cells are left out of the documents.
+
+            Values are loaded as strings unless a type is given for their
+            field with --type, or --infer-types is used to convert the values
+            that look like numbers or booleans. Rows with a value that cannot
+            be converted are reported and skipped.
+
+            Example usage:
+                escli utils import-csv users.csv --index users
+                escli utils import-csv sales.csv --index sales --delimiter ';' \
+                    --type amount=double --type quantity=long --id-column order_id
+                escli utils import-csv - --index events --no-header --columns ts,level,message
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let input: Box<dyn AsyncRead + Unpin> = match self.file.as_os_str() == "-" {
+            true => Box::new(tokio::io::stdin()),
+            false => Box::new(tokio::fs::File::open(&self.file).await.map_err(|e| {
+                eprintln!("Failed to open file {:?}: {}", self.file, e);
+                e
+            })?),
+        };
+        let mut reader = CsvReader::new(BufReader::new(input), self.delimiter);
+
+        let header = match self.no_header {
+            true => Vec::new(),
+            false => reader.next_record().await?.unwrap_or_default(),
+        };
+        let headers = match self.columns.is_empty() {
+            true => header,
+            false => self.columns.clone(),
+        };
+        if headers.is_empty() {
+            eprintln!("No header found in {:?}, use --columns", self.file);
+            return Ok(response(400, Vec::new()));
+        }
+        let types: HashMap<&str, FieldType> =
+            self.types.iter().map(|(f, k)| (f.as_str(), *k)).collect();
+
+        let mut path = format!("/{}/_bulk", self.index);
+        if let Some(pipeline) = &self.pipeline {
+            path.push_str(&format!("?pipeline={pipeline}"));
+        }
+        let mut sender = BulkSender::new(
+            transport,
+            path,
+            t,
+            usize::from(self.concurrency),
+            self.max_retries,
+            self.retry_backoff,
+        );
+
+        let terminal = std::io::stderr().is_terminal();
+        let (mut rows, mut skipped) = (0, 0);
+        let mut body = String::new();
+        let mut pending = 0;
+        while let Some(record) = reader.next_record().await? {
+            let (id, doc) = match self.document(&headers, record, &types) {
+                Ok(doc) => doc,
+                Err(e) => {
+                    eprintln!("Line {}: {e}, row skipped", reader.line());
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let action = match id {
+                Some(id) => json!({ "index": { "_id": id } }),
+                None => json!({ "index": {} }),
+            };
+            body.push_str(&action.to_string());
+            body.push('\n');
+            body.push_str(&Value::Object(doc).to_string());
+            body.push('\n');
+            rows += 1;
+            pending += 1;
+            if pending == self.size {
+                sender.send(std::mem::take(&mut body)).await?;
+                pending = 0;
+                if terminal {
+                    eprint!("\rRead {rows} rows");
+                }
+            }
+        }
+        if !body.is_empty() {
+            sender.send(body).await?;
+        }
+        if terminal && rows >= self.size {
+            eprintln!();
+        }
+        let (stats, batches) = sender.finish().await?;
+
+        eprintln!(
+            "Done: {} documents indexed, {} errors, {} retried across {} batch(es), {} rows skipped",
+            stats.indexed, stats.errors, stats.retried, batches, skipped
+        );
+        let status = match stats.errors > 0 || stats.http_errors > 0 || skipped > 0 {
+            true => 400,
+            false => 200,
+        };
+        Ok(response(status, Vec::new()))
+    }
+
+    /// Builds the document of a row and returns it with its id.
+    fn document(
+        &self,
+        headers: &[String],
+        record: Vec<String>,
+        types: &HashMap<&str, FieldType>,
+    ) -> Result<(Option<String>, Map<String, Value>), String> {
+        if record.len() > headers.len() {
+            return Err(format!(
+                "{} values for {} columns",
+                record.len(),
+                headers.len()
+            ));
+        }
+        let mut id = None;
+        let mut doc = Map::new();
+        for (header, value) in headers.iter().zip(record) {
+            if value.is_empty() {
+                continue;
+            }
+            if self.id_column.as_ref() == Some(header) {
+                id = Some(value);
+                continue;
+            }
+            let value = match (types.get(header.as_str()), self.infer_types) {
+                (Some(kind), _) => convert(&value, *kind).ok_or_else(|| {
+                    let kind = kind.to_possible_value().expect("no skipped variant");
+                    format!(
+                        "cannot convert '{value}' of {header} to {}",
+                        kind.get_name()
+                    )
+                })?,
+                (None, true) => infer(value),
+                (None, false) => Value::String(value),
+            };
+            doc.insert(header.clone(), value);
+        }
+        Ok((id, doc))
+    }
+}
+
+fn convert(value: &str, kind: FieldType) -> Option<Value> {
+    match kind {
+        FieldType::String => Some(Value::String(value.to_string())),
+        FieldType::Long => value.trim().parse::<i64>().ok().map(Value::from),
+        FieldType::Double => value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .map(Value::from),
+        FieldType::Boolean => match value.trim().to_ascii_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        FieldType::Json => serde_json::from_str(value).ok(),
+    }
+}
+
+/// Converts a value that looks like a number or a boolean, keeping the
+/// others as strings. Numbers with leading zeros, such as zip codes, stay
+/// strings.
+fn infer(value: String) -> Value {
+    let leading_zero = value.len() > 1 && value.starts_with('0') && !value.starts_with("0.");
+    let converted = match leading_zero {
+        true => None,
+        false => [FieldType::Long, FieldType::Double, FieldType::Boolean]
+            .into_iter()
+            .find_map(|kind| convert(&value, kind)),
+    };
+    converted.unwrap_or(Value::String(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_converts_typed_and_inferred_values() {
+        let import = ImportCsv::try_parse_from([
+            "import-csv",
+            "f.csv",
+            "-i",
+            "x",
+            "--type",
+            "qty=long",
+            "--infer-types",
+            "--id-column",
+            "id",
+        ])
+        .unwrap();
+        let headers: Vec<String> = ["id", "qty", "price", "zip", "ok", "note"]
+            .map(String::from)
+            .to_vec();
+        let types = import.types.iter().map(|(f, k)| (f.as_str(), *k)).collect();
+        let row = ["a1", "3", "9.5", "01234", "true", ""]
+            .map(String::from)
+            .to_vec();
+        let (id, doc) = import.document(&headers, row, &types).unwrap();
+        assert_eq!(id.as_deref(), Some("a1"));
+        assert_eq!(
+            Value::Object(doc),
+            json!({"qty": 3, "price": 9.5, "zip": "01234", "ok": true})
+        );
+
+        let row = ["a2", "three"].map(String::from).to_vec();
+        assert_eq!(
+            import.document(&headers, row, &types).unwrap_err(),
+            "cannot convert 'three' of qty to long"
+        );
+    }
+
+    #[test]
+    fn parse_arguments() {
+        assert_eq!(parse_delimiter("\\t"), Ok('\t'));
+        assert_eq!(parse_delimiter(";"), Ok(';'));
+        assert!(parse_delimiter(";;").is_err());
+        assert_eq!(
+            parse_field_type("n=Long"),
+            Ok(("n".to_string(), FieldType::Long))
+        );
+        assert!(parse_field_type("n").is_err());
+    }
+}
@@ -0,0 +1,306 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_to_string;
+use crate::request::{print_response, response};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Strings longer than this are not given a keyword sub-field, as for the
+/// default dynamic mapping.
+const IGNORE_ABOVE: usize = 256;
+
+#[derive(Parser, Debug)]
+pub struct InferMapping {
+    #[arg(help = "NDJSON file of sample documents, use - to read from stdin")]
+    file: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of documents to sample",
+        default_value_t = 1000
+    )]
+    sample: usize,
+}
+
+/// What was observed in the values of a field across the sampled documents.
+#[derive(Debug, Default)]
+struct FieldStats {
+    booleans: usize,
+    longs: usize,
+    doubles: usize,
+    dates: usize,
+    ips: usize,
+    keywords: usize,
+    texts: usize,
+    geo_points: usize,
+    max_len: usize,
+    in_array: bool,
+    properties: BTreeMap<String, FieldStats>,
+}
+
+impl InferMapping {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("infer-mapping")
+            .about("Propose an explicit mapping from sample documents.")
+            .long_about(
+                r#"
+            Read sample documents in NDJSON format and propose an explicit
+            mapping for them, printed as a create index body:
+
+              - numbers become long, or double when a decimal value is seen
+              - strings holding ISO 8601 dates or IP addresses become date or
+                ip fields
+              - strings with whitespace become text, with a keyword
+                sub-field when short enough, others become keyword
+              - objects with only lat and lon become geo_point
+              - arrays of objects become nested fields
+
+            Fields holding values of different kinds are mapped as keyword.
+            Fields that are always null are left out. Review the proposal
+            before using it, the sample may not show every variant.
+
+            Example usage:
+                escli utils infer-mapping docs.ndjson
+                head -n 500 docs.ndjson | escli utils infer-mapping - | escli indices create --index products
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        _transport: Transport,
+        _timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        self.run().await
+    }
+
+    /// Infers the mapping without a cluster, when no --url is given, and
+    /// returns the exit code of the process.
+    pub async fn execute_standalone(self) -> i32 {
+        print_response(self.run().await).await
+    }
+
+    async fn run(&self) -> Result<Response, elasticsearch::Error> {
+        let input = read_to_string(&self.file).await?;
+        let mut root = FieldStats::default();
+        let lines = input.lines().filter(|l| !l.trim().is_empty());
+        for (i, line) in lines.take(self.sample).enumerate() {
+            let doc: Value = serde_json::from_str(line).map_err(|e| {
+                eprintln!("Failed to parse document {}: {}", i + 1, e);
+                IoError::new(IoErrorKind::InvalidData, e)
+            })?;
+            root.observe(&doc);
+        }
+
+        let body = json!({"mappings": {"properties": root.properties()}});
+        let pretty = serde_json::to_string_pretty(&body).unwrap_or_default();
+        Ok(response(200, format!("{pretty}\n").into_bytes()))
+    }
+}
+
+impl FieldStats {
+    fn observe(&mut self, value: &Value) {
+        match value {
+            Value::Null => {}
+            Value::Bool(_) => self.booleans += 1,
+            Value::Number(n) if n.is_f64() => self.doubles += 1,
+            Value::Number(_) => self.longs += 1,
+            Value::String(s) if is_date(s) => self.dates += 1,
+            Value::String(s) if s.parse::<IpAddr>().is_ok() => self.ips += 1,
+            Value::String(s) => {
+                match s.contains(char::is_whitespace) {
+                    true => self.texts += 1,
+                    false => self.keywords += 1,
+                }
+                self.max_len = self.max_len.max(s.chars().count());
+            }
+            Value::Array(items) => {
+                for item in items {
+                    if item.is_object() {
+                        self.in_array = true;
+                    }
+                    self.observe(item);
+                }
+            }
+            Value::Object(object) if is_geo_point(object) => self.geo_points += 1,
+            Value::Object(object) => {
+                for (key, child) in object {
+                    self.properties
+                        .entry(key.clone())
+                        .or_default()
+                        .observe(child);
+                }
+            }
+        }
+    }
+
+    /// The mappings of the sub-fields, leaving out the ones without values.
+    fn properties(&self) -> Map<String, Value> {
+        self.properties
+            .iter()
+            .filter_map(|(name, stats)| Some((name.clone(), stats.mapping()?)))
+            .collect()
+    }
+
+    fn mapping(&self) -> Option<Value> {
+        if !self.properties.is_empty() {
+            let mut mapping = json!({"properties": self.properties()});
+            if self.in_array {
+                mapping["type"] = json!("nested");
+            }
+            return Some(mapping);
+        }
+
+        let kinds = [
+            ("boolean", self.booleans),
+            ("date", self.dates),
+            ("ip", self.ips),
+            ("geo_point", self.geo_points),
+            ("keyword", self.keywords),
+            ("text", self.texts),
+        ];
+        let numbers = self.longs + self.doubles;
+        let seen = kinds.iter().filter(|(_, n)| *n > 0).count() + usize::from(numbers > 0);
+        let field_type = match (seen, kinds.iter().find(|(_, n)| *n > 0)) {
+            (0, _) => return None,
+            (1, None) if self.doubles > 0 => "double",
+            (1, None) => "long",
+            (1, Some((t, _))) => *t,
+            _ if self.texts > 0 => "text",
+            _ => "keyword",
+        };
+
+        let mut mapping = json!({"type": field_type});
+        if field_type == "text" && self.max_len <= IGNORE_ABOVE {
+            mapping["fields"] =
+                json!({"keyword": {"type": "keyword", "ignore_above": IGNORE_ABOVE}});
+        }
+        Some(mapping)
+    }
+}
+
+/// Whether an object is a geo point given as `{"lat": .., "lon": ..}`.
+fn is_geo_point(object: &Map<String, Value>) -> bool {
+    object.len() == 2
+        && object.get("lat").is_some_and(Value::is_number)
+        && object.get("lon").is_some_and(Value::is_number)
+}
+
+/// Whether a string is a date in the `strict_date_optional_time` format,
+/// such as `2025-01-31` or `2025-01-31T12:00:00.000Z`.
+fn is_date(s: &str) -> bool {
+    let digits = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_digit());
+    let (date, time) = match s.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+    let date_ok = matches!(
+        date.split('-').collect::<Vec<_>>()[..],
+        [y, m, d] if digits(y, 4) && digits(m, 2) && digits(d, 2)
+    );
+    let Some(time) = time else {
+        return date_ok;
+    };
+
+    let time = time.strip_suffix('Z').unwrap_or(time);
+    let time = match time.rfind(['+', '-']) {
+        Some(i) => {
+            let zone = time[i + 1..].replace(':', "");
+            if !digits(&zone, 4) && !digits(&zone, 2) {
+                return false;
+            }
+            &time[..i]
+        }
+        None => time,
+    };
+    let (hms, fraction) = time.split_once('.').unwrap_or((time, "0"));
+    let parts: Vec<&str> = hms.split(':').collect();
+    date_ok
+        && (2..=3).contains(&parts.len())
+        && parts.iter().all(|p| digits(p, 2))
+        && digits(fraction, fraction.len().max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(docs: &[Value]) -> Value {
+        let mut root = FieldStats::default();
+        for doc in docs {
+            root.observe(doc);
+        }
+        Value::Object(root.properties())
+    }
+
+    #[test]
+    fn infers_scalar_types() {
+        let mapping = infer(&[
+            json!({"count": 1, "price": 1, "ts": "2025-01-31T12:00:00Z", "host": "10.0.0.1", "tag": "a", "title": "hello world", "ok": true, "none": null}),
+            json!({"count": 2, "price": 1.5, "ts": "2025-02-01", "tag": 3}),
+        ]);
+        assert_eq!(
+            mapping,
+            json!({
+                "count": {"type": "long"},
+                "price": {"type": "double"},
+                "ts": {"type": "date"},
+                "host": {"type": "ip"},
+                "tag": {"type": "keyword"},
+                "title": {"type": "text", "fields": {"keyword": {"type": "keyword", "ignore_above": 256}}},
+                "ok": {"type": "boolean"},
+            })
+        );
+    }
+
+    #[test]
+    fn infers_objects_nested_and_geo_points() {
+        let mapping = infer(&[json!({
+            "user": {"name": "bob"},
+            "items": [{"sku": "x1"}],
+            "location": {"lat": 48.8, "lon": 2.3},
+        })]);
+        assert_eq!(
+            mapping,
+            json!({
+                "user": {"properties": {"name": {"type": "keyword"}}},
+                "items": {"type": "nested", "properties": {"sku": {"type": "keyword"}}},
+                "location": {"type": "geo_point"},
+            })
+        );
+    }
+
+    #[test]
+    fn is_date_accepts_iso_dates_only() {
+        assert!(is_date("2025-01-31"));
+        assert!(is_date("2025-01-31T12:00"));
+        assert!(is_date("2025-01-31T12:00:00.123+02:00"));
+        assert!(!is_date("2025-1-31"));
+        assert!(!is_date("2025-01-31T"));
+        assert!(!is_date("hello"));
+    }
+}
@@ -0,0 +1,108 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use serde_json::Value;
+use std::io::{BufRead, Error as IoError, ErrorKind as IoErrorKind, IsTerminal, Write};
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+/// Reads the whole content of a file, or of stdin when the path is `-`.
+pub(crate) async fn read_to_string(path: &Path) -> Result<String, IoError> {
+    let input: Box<dyn AsyncRead + Unpin> = if path.as_os_str() == "-" {
+        Box::new(tokio::io::stdin())
+    } else {
+        Box::new(File::open(path).await.map_err(|e| {
+            eprintln!("Failed to open {:?}: {}", path, e);
+            e
+        })?)
+    };
+    let mut buf = String::new();
+    BufReader::new(input)
+        .read_to_string(&mut buf)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to read {:?}: {}", path, e);
+            e
+        })?;
+    Ok(buf)
+}
+
+/// Reads and parses a JSON file, or stdin when the path is `-`.
+pub(crate) async fn read_json_file(path: &Path) -> Result<Value, IoError> {
+    let buf = read_to_string(path).await?;
+    serde_json::from_str(&buf).map_err(|e| {
+        eprintln!("Failed to parse JSON from {:?}: {}", path, e);
+        IoError::new(IoErrorKind::InvalidData, e)
+    })
+}
+
+/// Resolves a JSON command line argument: `@path` reads the file (`@-` reads
+/// stdin), anything else is parsed as inline JSON.
+pub(crate) async fn read_json_arg(arg: &str) -> Result<Value, IoError> {
+    match arg.strip_prefix('@') {
+        Some(path) => read_json_file(Path::new(path)).await,
+        None => serde_json::from_str(arg).map_err(|e| {
+            eprintln!("Failed to parse JSON argument: {}", e);
+            IoError::new(IoErrorKind::InvalidData, e)
+        }),
+    }
+}
+
+/// Asks a yes or no question on the terminal. Without a terminal the answer
+/// is no, --yes is required instead.
+pub(crate) fn confirm(question: &str) -> bool {
+    if !std::io::stdin().is_terminal() {
+        eprintln!("Refusing to continue without confirmation, use --yes");
+        return false;
+    }
+    eprint!("{question} [y/N] ");
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_json_arg_parses_inline_json() {
+        let value = read_json_arg(r#"{"term":{"a":1}}"#).await.unwrap();
+        assert_eq!(value["term"]["a"], 1);
+    }
+
+    #[tokio::test]
+    async fn read_json_arg_reads_file_with_at_prefix() {
+        let path = std::env::temp_dir().join("escli-read-json-arg-test.json");
+        std::fs::write(&path, "[1, 2, 3]").unwrap();
+        let value = read_json_arg(&format!("@{}", path.display()))
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(value, serde_json::json!([1, 2, 3]));
+    }
+
+    #[tokio::test]
+    async fn read_json_arg_rejects_invalid_json() {
+        let err = read_json_arg("{not json").await.unwrap_err();
+        assert_eq!(err.kind(), IoErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,310 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::{read_json_arg, read_json_file};
+use crate::request::{response, send_json};
+use clap::{ArgGroup, Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Map, Value, json};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("vector").required(true).args(["query_vector", "vector_file", "text"])))]
+#[command(group(ArgGroup::new("target").required(true).multiple(true).args(["indices", "index"])))]
+pub struct Knn {
+    #[arg(
+        value_delimiter = ',',
+        help = "List of indices to search, comma separated"
+    )]
+    indices: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Indices to search, comma separated, as an alternative to the positional argument"
+    )]
+    index: Vec<String>,
+
+    #[arg(long, help = "Name of the dense_vector field to search")]
+    field: String,
+
+    #[arg(
+        long,
+        value_name = "JSON",
+        help = "Query vector as a JSON array, or @file to read it from a file (@- for stdin)"
+    )]
+    query_vector: Option<String>,
+
+    #[arg(
+        long,
+        help = "File holding the query vector as a JSON array (- for stdin)"
+    )]
+    vector_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "model_id",
+        help = "Text to embed with the inference endpoint given by --model-id, the vector being searched"
+    )]
+    text: Option<String>,
+
+    #[arg(long, help = "text_embedding inference endpoint used to embed --text")]
+    model_id: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of nearest neighbors to return",
+        default_value_t = 10
+    )]
+    k: usize,
+
+    #[arg(
+        long,
+        help = "Number of candidates per shard, defaults to the server value"
+    )]
+    num_candidates: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "JSON",
+        help = "Query clause to pre-filter documents, inline JSON or @file"
+    )]
+    filter: Option<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Source fields to display, comma separated (default: the whole _source)"
+    )]
+    fields: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Print the raw search response instead of the hits summary"
+    )]
+    raw: bool,
+}
+
+impl Knn {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("knn")
+            .about("Run a kNN vector search and print the nearest neighbors with their scores.")
+            .long_about(
+                r#"
+            Run an approximate kNN search against a dense_vector field without
+            hand-writing the search DSL.
+
+            The query vector is either given explicitly with --query-vector,
+            as a JSON array or @file, read from --vector-file, or computed from
+            --text by the text_embedding inference endpoint named by --model-id,
+            called through the _inference API before the search.
+
+            Hits are printed one per line as: score, index, id and source.
+
+            Example usage:
+                escli utils knn my-index --field embedding --query-vector @vec.json --k 10
+                escli utils knn --index my-index --field embedding --vector-file vec.json --k 10
+                escli utils knn my-index --field embedding --query-vector '[0.1, 0.2, 0.3]'
+                escli utils knn my-index --field embedding --text "red shoes" --model-id my-e5
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+
+        let vector = match (&self.query_vector, &self.vector_file, &self.text) {
+            (Some(arg), _, _) => parse_vector(read_json_arg(arg).await?)?,
+            (None, Some(file), _) => parse_vector(read_json_file(file).await?)?,
+            (None, None, Some(text)) => {
+                let model_id = self
+                    .model_id
+                    .as_deref()
+                    .expect("--text requires --model-id");
+                let path = format!("/_inference/text_embedding/{model_id}");
+                let body = json!({ "input": [text] });
+                let (status, result) =
+                    send_json(&transport, Method::Post, &path, &[], Some(&body), t).await?;
+                if !status.is_success() {
+                    return Ok(response(status.as_u16(), result.to_string().into_bytes()));
+                }
+                parse_vector(result["text_embedding"][0]["embedding"].clone())?
+            }
+            (None, None, None) => unreachable!("a vector or a text is required"),
+        };
+        let filter = match &self.filter {
+            Some(arg) => Some(read_json_arg(arg).await?),
+            None => None,
+        };
+
+        let body = build_knn_body(&self, vector, filter);
+        let indices: Vec<&str> = self
+            .indices
+            .iter()
+            .chain(&self.index)
+            .map(String::as_str)
+            .collect();
+        let path = format!("/{}/_search", indices.join(","));
+        let (status, result) =
+            send_json(&transport, Method::Post, &path, &[], Some(&body), t).await?;
+
+        if !status.is_success() || self.raw {
+            return Ok(response(status.as_u16(), result.to_string().into_bytes()));
+        }
+
+        Ok(response(200, render_hits(&result).into_bytes()))
+    }
+}
+
+/// Validates that the value is a non-empty array of numbers.
+fn parse_vector(value: Value) -> Result<Vec<f64>, IoError> {
+    let invalid = || {
+        IoError::new(
+            IoErrorKind::InvalidInput,
+            "query vector must be a non-empty JSON array of numbers",
+        )
+    };
+    let items = value.as_array().ok_or_else(invalid)?;
+    if items.is_empty() {
+        return Err(invalid());
+    }
+    items
+        .iter()
+        .map(|v| v.as_f64().ok_or_else(invalid))
+        .collect()
+}
+
+/// Builds the search body using the top-level `knn` section.
+fn build_knn_body(knn: &Knn, vector: Vec<f64>, filter: Option<Value>) -> Value {
+    let mut section = Map::new();
+    section.insert("field".to_string(), json!(knn.field));
+    section.insert("k".to_string(), json!(knn.k));
+    if let Some(num_candidates) = knn.num_candidates {
+        section.insert("num_candidates".to_string(), json!(num_candidates));
+    }
+    section.insert("query_vector".to_string(), json!(vector));
+    if let Some(filter) = filter {
+        section.insert("filter".to_string(), filter);
+    }
+
+    let mut body = json!({ "knn": section, "size": knn.k });
+    if !knn.fields.is_empty() {
+        body["_source"] = json!(knn.fields);
+    }
+    body
+}
+
+/// Renders one line per hit: score, index, id and compact source.
+fn render_hits(result: &Value) -> String {
+    let mut out = String::new();
+    let hits = result["hits"]["hits"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for hit in &hits {
+        let score = hit["_score"].as_f64().unwrap_or_default();
+        out.push_str(&format!(
+            "{:.6}\t{}\t{}\t{}\n",
+            score,
+            hit["_index"].as_str().unwrap_or_default(),
+            hit["_id"].as_str().unwrap_or_default(),
+            hit.get("_source").unwrap_or(&Value::Null)
+        ));
+    }
+    if hits.is_empty() {
+        out.push_str("No hits\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn knn_args(args: &[&str]) -> Knn {
+        Knn::try_parse_from(std::iter::once("knn").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn parse_vector_accepts_numbers() {
+        assert_eq!(parse_vector(json!([1, 0.5])).unwrap(), vec![1.0, 0.5]);
+    }
+
+    #[test]
+    fn parse_vector_rejects_empty_and_non_numeric() {
+        assert!(parse_vector(json!([])).is_err());
+        assert!(parse_vector(json!(["a"])).is_err());
+        assert!(parse_vector(json!({"a": 1})).is_err());
+    }
+
+    #[test]
+    fn build_knn_body_with_vector() {
+        let knn = knn_args(&[
+            "idx",
+            "--field",
+            "emb",
+            "--query-vector",
+            "[1]",
+            "-k",
+            "3",
+            "--fields",
+            "title",
+        ]);
+        let body = build_knn_body(&knn, vec![1.0], Some(json!({"term": {"a": 1}})));
+        assert_eq!(
+            body,
+            json!({
+                "knn": { "field": "emb", "k": 3, "query_vector": [1.0], "filter": {"term": {"a": 1}} },
+                "size": 3,
+                "_source": ["title"]
+            })
+        );
+    }
+
+    #[test]
+    fn build_knn_body_with_text_uses_the_embedded_vector() {
+        let knn = knn_args(&[
+            "idx",
+            "--field",
+            "emb",
+            "--text",
+            "hello",
+            "--model-id",
+            "e5",
+        ]);
+        let body = build_knn_body(&knn, vec![0.5], None);
+        assert_eq!(body["knn"]["query_vector"], json!([0.5]));
+        assert!(body["knn"].get("query_vector_builder").is_none());
+    }
+
+    #[test]
+    fn render_hits_prints_score_and_id() {
+        let result = json!({"hits": {"hits": [{"_index": "i", "_id": "1", "_score": 0.5, "_source": {"a": 1}}]}});
+        assert_eq!(render_hits(&result), "0.500000\ti\t1\t{\"a\":1}\n");
+    }
+}
@@ -15,18 +15,216 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod alias_swap;
+mod allocation_explain;
+mod apply;
+mod assert;
+mod bench;
+mod cat;
+mod ccr;
+mod completions;
+mod copy_index;
+mod count;
+mod csv;
+mod delete_by_query;
+mod deprecations;
+mod diff_index;
+mod disk;
+mod docs;
 mod dump;
+mod esql;
+mod export;
+mod exporter;
+mod fan_out;
+mod field_usage;
+mod forecast;
+mod ilm;
+mod import_csv;
+mod infer_mapping;
+mod input;
+mod knn;
+mod license;
+mod lint_mapping;
 mod load;
+mod msearch;
+mod object_store;
+mod ping;
+mod pit;
+mod plugin;
+mod privileges;
+mod profile;
+mod purge;
+mod request;
+mod reroute;
+mod resize;
+mod rolling_restart;
+mod rotate_api_key;
+mod security_audit;
+mod seed;
+mod self_update;
+mod shard_advisor;
+mod snapshot;
+mod sql;
+mod table;
+mod tail;
+mod templates;
+mod top;
+mod transfer;
+mod transform;
+mod transform_preview;
+mod units;
+mod wait_for_health;
+mod watch;
 
+pub use crate::alias_swap::AliasSwap;
+pub use crate::allocation_explain::AllocationExplain;
+pub use crate::apply::Apply;
+pub use crate::assert::Assert;
+pub use crate::bench::Bench;
+pub use crate::cat::cat_view;
+pub use crate::ccr::Ccr;
+pub use crate::completions::{
+    Completions, REFRESH_INDEX_CACHE_ENV, complete_index, refresh_index_cache,
+};
+pub use crate::copy_index::CopyIndex;
+pub use crate::count::Count;
+pub use crate::delete_by_query::DeleteByQuery;
+pub use crate::deprecations::Deprecations;
+pub use crate::diff_index::DiffIndex;
+pub use crate::disk::Disk;
+pub use crate::docs::show_docs;
 pub use crate::dump::Dump;
+pub use crate::esql::{esql_output_file, esql_params, explain_plan};
+pub use crate::export::Export;
+pub use crate::exporter::Exporter;
+pub use crate::fan_out::fan_out;
+pub use crate::field_usage::FieldUsage;
+pub use crate::forecast::Forecast;
+pub use crate::ilm::Ilm;
+pub use crate::import_csv::ImportCsv;
+pub use crate::infer_mapping::InferMapping;
+pub use crate::knn::Knn;
+pub use crate::license::License;
+pub use crate::lint_mapping::LintMapping;
 pub use crate::load::Load;
+pub use crate::msearch::Msearch;
+pub use crate::ping::Ping;
+pub use crate::pit::Pit;
+pub use crate::plugin::{list_plugins, run_plugin};
+pub use crate::privileges::{RequiredPrivileges, check_privileges};
+pub use crate::profile::{Profile, list_profiles, profile_path, profiles_dir};
+pub use crate::purge::Purge;
+pub use crate::reroute::Reroute;
+pub use crate::resize::Resize;
+pub use crate::rolling_restart::RollingRestart;
+pub use crate::rotate_api_key::RotateApiKey;
+pub use crate::security_audit::SecurityAudit;
+pub use crate::seed::Seed;
+pub use crate::self_update::SelfUpdate;
+pub use crate::shard_advisor::ShardAdvisor;
+pub use crate::snapshot::Snapshot;
+pub use crate::sql::Sql;
+pub use crate::tail::Tail;
+pub use crate::templates::Templates;
+pub use crate::top::Top;
+pub use crate::transfer::Transfer;
+pub use crate::transform_preview::TransformPreview;
+pub use crate::units::{ByteSize, TimeValue};
+pub use crate::wait_for_health::WaitForHealth;
+pub use crate::watch::Watch;
 use clap::error::ErrorKind;
 use clap::{ArgMatches, Command, FromArgMatches};
 use elasticsearch::http::response::Response;
 use elasticsearch::http::transport::Transport;
 
-pub fn commands() -> [Command; 2] {
-    [Dump::new_command(), Load::new_command()]
+pub fn commands() -> [Command; 44] {
+    [
+        AliasSwap::new_command(),
+        AllocationExplain::new_command(),
+        Apply::new_command(),
+        Assert::new_command(),
+        Bench::new_command(),
+        Ccr::new_command(),
+        CopyIndex::new_command(),
+        Count::new_command(),
+        DeleteByQuery::new_command(),
+        Deprecations::new_command(),
+        DiffIndex::new_command(),
+        Disk::new_command(),
+        Dump::new_command(),
+        Export::new_command(),
+        Exporter::new_command(),
+        FieldUsage::new_command(),
+        Forecast::new_command(),
+        Ilm::new_command(),
+        ImportCsv::new_command(),
+        InferMapping::new_command(),
+        Knn::new_command(),
+        License::new_command(),
+        LintMapping::new_command(),
+        Load::new_command(),
+        Msearch::new_command(),
+        Ping::new_command(),
+        Pit::new_command(),
+        Purge::new_command(),
+        Reroute::new_command(),
+        Resize::new_command(),
+        RollingRestart::new_command(),
+        RotateApiKey::new_command(),
+        SecurityAudit::new_command(),
+        Seed::new_command(),
+        ShardAdvisor::new_command(),
+        Snapshot::new_command(),
+        Sql::new_command(),
+        Tail::new_command(),
+        Templates::new_command(),
+        Top::new_command(),
+        Transfer::new_command(),
+        TransformPreview::new_command(),
+        WaitForHealth::new_command(),
+        Watch::new_command(),
+    ]
+}
+
+/// Runs the utilities that can connect through profiles alone, when no --url
+/// is given, and returns their exit code. Returns `None` for the others.
+pub async fn run_standalone_command(
+    matches: &ArgMatches,
+    timeout: Option<std::time::Duration>,
+) -> Option<i32> {
+    match matches.subcommand() {
+        Some(("copy-index", sub_matches)) if sub_matches.contains_id("from_profile") => Some(
+            CopyIndex::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute_standalone(timeout)
+                .await,
+        ),
+        Some(("diff-index", sub_matches)) if sub_matches.contains_id("from_profile") => Some(
+            DiffIndex::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute_standalone(timeout)
+                .await,
+        ),
+        Some(("infer-mapping", sub_matches)) => Some(
+            InferMapping::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute_standalone()
+                .await,
+        ),
+        Some(("lint-mapping", sub_matches)) => Some(
+            LintMapping::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute_standalone()
+                .await,
+        ),
+        Some(("transfer", sub_matches)) if sub_matches.contains_id("from_profile") => Some(
+            Transfer::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute_standalone(timeout)
+                .await,
+        ),
+        _ => None,
+    }
 }
 
 pub async fn run_command(
@@ -36,18 +234,270 @@ pub async fn run_command(
     timeout: Option<std::time::Duration>,
 ) -> Result<Response, elasticsearch::Error> {
     match matches.subcommand() {
+        Some(("alias-swap", sub_matches)) => {
+            AliasSwap::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("allocation-explain", sub_matches)) => {
+            AllocationExplain::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("apply", sub_matches)) => {
+            Apply::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("assert", sub_matches)) => {
+            Assert::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("bench", sub_matches)) => {
+            Bench::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("ccr", sub_matches)) => {
+            Ccr::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("copy-index", sub_matches)) => {
+            CopyIndex::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("count", sub_matches)) => {
+            Count::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("delete-by-query", sub_matches)) => {
+            DeleteByQuery::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("deprecations", sub_matches)) => {
+            Deprecations::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("diff-index", sub_matches)) => {
+            DiffIndex::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("disk", sub_matches)) => {
+            Disk::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
         Some(("dump", sub_matches)) => {
             Dump::from_arg_matches(sub_matches)
                 .expect("argument parsing failed")
                 .execute(transport, timeout)
                 .await
         }
+        Some(("export", sub_matches)) => {
+            Export::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("exporter", sub_matches)) => {
+            Exporter::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("field-usage", sub_matches)) => {
+            FieldUsage::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("forecast", sub_matches)) => {
+            Forecast::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("ilm", sub_matches)) => {
+            Ilm::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("import-csv", sub_matches)) => {
+            ImportCsv::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("infer-mapping", sub_matches)) => {
+            InferMapping::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("knn", sub_matches)) => {
+            Knn::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("license", sub_matches)) => {
+            License::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("lint-mapping", sub_matches)) => {
+            LintMapping::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
         Some(("load", sub_matches)) => {
             Load::from_arg_matches(sub_matches)
                 .expect("argument parsing failed")
                 .execute(transport, timeout)
                 .await
         }
+        Some(("msearch", sub_matches)) => {
+            Msearch::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("ping", sub_matches)) => {
+            Ping::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("pit", sub_matches)) => {
+            Pit::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("purge", sub_matches)) => {
+            Purge::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("reroute", sub_matches)) => {
+            Reroute::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("resize", sub_matches)) => {
+            Resize::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("rolling-restart", sub_matches)) => {
+            RollingRestart::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("rotate-api-key", sub_matches)) => {
+            RotateApiKey::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("security-audit", sub_matches)) => {
+            SecurityAudit::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("seed", sub_matches)) => {
+            Seed::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("shard-advisor", sub_matches)) => {
+            ShardAdvisor::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("snapshot", sub_matches)) => {
+            Snapshot::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("sql", sub_matches)) => {
+            Sql::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("tail", sub_matches)) => {
+            Tail::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("templates", sub_matches)) => {
+            Templates::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("top", sub_matches)) => {
+            Top::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("transfer", sub_matches)) => {
+            Transfer::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("transform-preview", sub_matches)) => {
+            TransformPreview::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("wait-for-health", sub_matches)) => {
+            WaitForHealth::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
+        Some(("watch", sub_matches)) => {
+            Watch::from_arg_matches(sub_matches)
+                .expect("argument parsing failed")
+                .execute(transport, timeout)
+                .await
+        }
         _ => {
             if let Some(namespace_command) = cmd.find_subcommand_mut("utils") {
                 let _ = namespace_command.print_help();
@@ -0,0 +1,193 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json, send_json_ok};
+use crate::table::Table;
+use crate::units::{format_duration, format_timestamp};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Parser, Debug)]
+pub struct License {
+    #[arg(
+        long,
+        help = "Exit with an error when the license expires within this number of days"
+    )]
+    warn_days: Option<u64>,
+}
+
+impl License {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("license")
+            .about("Show the license of the cluster, its expiry and the licensed features in use.")
+            .long_about(
+                r#"
+            Print the type, status and expiry date of the cluster license,
+            followed by the licensed features that were used recently.
+
+            With --warn-days, the command exits with an error when the license
+            expires within that number of days, which makes it suitable for a
+            cron job or a monitoring check. An expired license is always an
+            error.
+
+            Example usage:
+                escli utils license
+                escli utils license --warn-days 30
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let Some(license) =
+            send_json_ok(&transport, Method::Get, "/_license", &[], None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        // Feature usage is only tracked for non basic licenses, and needs
+        // the manage cluster privilege: it is shown when available.
+        let (status, usage) = send_json(
+            &transport,
+            Method::Get,
+            "/_license/feature_usage",
+            &[],
+            None,
+            t,
+        )
+        .await?;
+        let usage = match status.is_success() {
+            true => usage,
+            false => Value::Null,
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        print!("{}", render(&license["license"], &usage));
+        let (status, summary) = self.check(&license["license"], now);
+        Ok(response(status, summary.into_bytes()))
+    }
+
+    /// The exit status and the summary line of the expiry check.
+    fn check(&self, license: &Value, now: u64) -> (u16, String) {
+        let kind = license["type"].as_str().unwrap_or("unknown");
+        let Some(expiry) = license["expiry_date_in_millis"].as_u64() else {
+            return (200, format!("The {kind} license does not expire\n"));
+        };
+        if expiry <= now || license["status"] == "expired" {
+            return (400, format!("The {kind} license has expired\n"));
+        }
+        let left = Duration::from_millis(expiry - now);
+        let summary = format!("The {kind} license expires in {}\n", format_duration(left));
+        match self.warn_days {
+            Some(days) if left.as_secs() < days * 86400 => (400, summary),
+            _ => (200, summary),
+        }
+    }
+}
+
+/// Renders the license details and the feature usage table.
+fn render(license: &Value, usage: &Value) -> String {
+    let text = |key: &str| license[key].as_str().unwrap_or("-").to_string();
+    let date = |key: &str| {
+        license[key]
+            .as_u64()
+            .map_or("-".to_string(), |ms| format_timestamp(ms / 1000))
+    };
+    let mut details = Table::new(&[
+        "type",
+        "status",
+        "issued_to",
+        "issue_date",
+        "expiry_date",
+        "max_nodes",
+    ]);
+    details.add_row(vec![
+        text("type"),
+        text("status"),
+        text("issued_to"),
+        date("issue_date_in_millis"),
+        date("expiry_date_in_millis"),
+        match &license["max_nodes"] {
+            Value::Null => "-".to_string(),
+            n => n.to_string(),
+        },
+    ]);
+    let mut out = details.render();
+
+    let mut features = Table::new(&["feature", "family", "context", "license_level", "last_used"]);
+    for feature in usage["features"].as_array().into_iter().flatten() {
+        let field = |key: &str| feature[key].as_str().unwrap_or("-").to_string();
+        features.add_row(vec![
+            field("name"),
+            field("family"),
+            field("context"),
+            field("license_level"),
+            field("last_used"),
+        ]);
+    }
+    if !features.is_empty() {
+        out.push('\n');
+        out.push_str(&features.render());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn check_warns_before_expiry() {
+        let day = 86_400_000;
+        let license =
+            json!({"type": "platinum", "status": "active", "expiry_date_in_millis": 20 * day});
+        let warn = |args: &[&str]| {
+            let mut argv = vec!["license"];
+            argv.extend_from_slice(args);
+            License::try_parse_from(argv).unwrap()
+        };
+        assert_eq!(
+            warn(&[]).check(&license, 10 * day),
+            (200, "The platinum license expires in 10d 0h\n".to_string())
+        );
+        assert_eq!(
+            warn(&["--warn-days", "30"]).check(&license, 10 * day).0,
+            400
+        );
+        assert_eq!(warn(&["--warn-days", "5"]).check(&license, 10 * day).0, 200);
+        assert_eq!(
+            warn(&[]).check(&license, 30 * day),
+            (400, "The platinum license has expired\n".to_string())
+        );
+        assert_eq!(
+            warn(&["--warn-days", "5"]).check(&json!({"type": "basic"}), 0),
+            (200, "The basic license does not expire\n".to_string())
+        );
+    }
+}
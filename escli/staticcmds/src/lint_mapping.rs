@@ -0,0 +1,434 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_json_file;
+use crate::request::{print_response, response, send_json_ok};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Field types of the current Elasticsearch release, with the version that
+/// introduced the ones added after 7.0.
+const FIELD_TYPES: &[(&str, Option<(u32, u32)>)] = &[
+    ("aggregate_metric_double", Some((7, 11))),
+    ("alias", None),
+    ("binary", None),
+    ("boolean", None),
+    ("byte", None),
+    ("completion", None),
+    ("constant_keyword", Some((7, 7))),
+    ("counted_keyword", Some((8, 12))),
+    ("date", None),
+    ("date_nanos", None),
+    ("date_range", None),
+    ("dense_vector", None),
+    ("double", None),
+    ("double_range", None),
+    ("flattened", Some((7, 3))),
+    ("float", None),
+    ("float_range", None),
+    ("geo_point", None),
+    ("geo_shape", None),
+    ("half_float", None),
+    ("histogram", Some((7, 6))),
+    ("integer", None),
+    ("integer_range", None),
+    ("ip", None),
+    ("ip_range", None),
+    ("join", None),
+    ("keyword", None),
+    ("long", None),
+    ("long_range", None),
+    ("match_only_text", Some((7, 14))),
+    ("nested", None),
+    ("object", None),
+    ("passthrough", Some((8, 13))),
+    ("percolator", None),
+    ("point", Some((7, 4))),
+    ("rank_feature", None),
+    ("rank_features", None),
+    ("scaled_float", None),
+    ("search_as_you_type", Some((7, 2))),
+    ("semantic_text", Some((8, 15))),
+    ("shape", Some((7, 4))),
+    ("short", None),
+    ("sparse_vector", Some((8, 11))),
+    ("text", None),
+    ("token_count", None),
+    ("unsigned_long", Some((7, 10))),
+    ("version", Some((7, 10))),
+    ("wildcard", Some((7, 9))),
+];
+
+const NUMERIC_TYPES: &[&str] = &["byte", "short", "integer", "long", "unsigned_long"];
+
+#[derive(Parser, Debug)]
+pub struct LintMapping {
+    #[arg(
+        help = "Mapping file in JSON format, either the mapping itself or a create index body, use - to read from stdin"
+    )]
+    file: PathBuf,
+
+    #[arg(long, help = "Fail on warnings too")]
+    strict: bool,
+
+    #[arg(long, help = "Maximum number of fields", default_value_t = 1000)]
+    total_fields_limit: usize,
+
+    #[arg(long, help = "Maximum depth of objects", default_value_t = 20)]
+    depth_limit: usize,
+
+    #[arg(long, help = "Maximum number of nested fields", default_value_t = 50)]
+    nested_fields_limit: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq)]
+struct Finding {
+    severity: Severity,
+    path: String,
+    message: String,
+}
+
+/// State of a walk through the mapping.
+#[derive(Debug, Default)]
+struct Lint {
+    version: Option<(u32, u32)>,
+    findings: Vec<Finding>,
+    fields: usize,
+    nested: usize,
+    depth_exceeded: bool,
+}
+
+impl LintMapping {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("lint-mapping")
+            .about("Validate a mapping before applying it.")
+            .long_about(
+                r#"
+            Check a mapping file locally and report errors and warnings:
+
+              - unknown field types
+              - more fields, object depth or nested fields than the index
+                limits allow, see --total-fields-limit, --depth-limit and
+                --nested-fields-limit
+              - keyword fields with an analyzer, which only text fields accept
+              - text fields with fielddata enabled
+              - identifiers mapped as text or as numbers, which are better
+                searched as keyword
+              - fields neither indexed nor stored in doc values
+
+            The file holds either the mapping itself or a create index body
+            with a mappings key. When a cluster is given with --url, its
+            version is also checked against the field types in use.
+
+            Exits with 1 when errors are found, or warnings with --strict.
+
+            Example usage:
+                escli utils lint-mapping mapping.json
+                escli --url http://localhost:9200 utils lint-mapping index.json --strict
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let Some(info) = send_json_ok(&transport, Method::Get, "/", &[], None, t).await? else {
+            return Ok(response(500, Vec::new()));
+        };
+        let version = info["version"]["number"].as_str().and_then(parse_version);
+        self.run(version).await
+    }
+
+    /// Lints the mapping without a cluster, when no --url is given, and
+    /// returns the exit code of the process.
+    pub async fn execute_standalone(self) -> i32 {
+        print_response(self.run(None).await).await
+    }
+
+    async fn run(&self, version: Option<(u32, u32)>) -> Result<Response, elasticsearch::Error> {
+        let file = read_json_file(&self.file).await?;
+        let mapping = file.get("mappings").unwrap_or(&file);
+        let findings = self.lint(mapping, version);
+
+        let errors = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count();
+        let warnings = findings.len() - errors;
+        let mut out = String::new();
+        for finding in &findings {
+            let severity = match finding.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            match finding.path.is_empty() {
+                true => out.push_str(&format!("{severity}: {}\n", finding.message)),
+                false => out.push_str(&format!(
+                    "{severity}: {}: {}\n",
+                    finding.path, finding.message
+                )),
+            }
+        }
+        print!("{out}");
+
+        let summary = format!("{errors} error(s), {warnings} warning(s)\n");
+        let failed = errors > 0 || (self.strict && warnings > 0);
+        Ok(response(
+            if failed { 400 } else { 200 },
+            summary.into_bytes(),
+        ))
+    }
+
+    fn lint(&self, mapping: &Value, version: Option<(u32, u32)>) -> Vec<Finding> {
+        let mut lint = Lint {
+            version,
+            ..Default::default()
+        };
+        if let Some(properties) = mapping["properties"].as_object() {
+            self.walk(properties, "", 1, &mut lint);
+        }
+        if lint.fields > self.total_fields_limit {
+            lint.error(
+                "",
+                format!(
+                    "{} fields exceed the limit of {}",
+                    lint.fields, self.total_fields_limit
+                ),
+            );
+        }
+        if lint.nested > self.nested_fields_limit {
+            lint.error(
+                "",
+                format!(
+                    "{} nested fields exceed the limit of {}",
+                    lint.nested, self.nested_fields_limit
+                ),
+            );
+        }
+        lint.findings.sort_by(|a, b| a.path.cmp(&b.path));
+        lint.findings
+    }
+
+    fn walk(&self, properties: &Map<String, Value>, prefix: &str, depth: usize, lint: &mut Lint) {
+        if depth > self.depth_limit && !lint.depth_exceeded {
+            lint.depth_exceeded = true;
+            lint.error(
+                prefix,
+                format!(
+                    "objects nested deeper than the limit of {}",
+                    self.depth_limit
+                ),
+            );
+        }
+        for (name, field) in properties {
+            let path = match prefix.is_empty() {
+                true => name.clone(),
+                false => format!("{prefix}.{name}"),
+            };
+            lint.field(name, &path, field);
+            if field["type"] == "nested" {
+                lint.nested += 1;
+            }
+            if let Some(children) = field["properties"].as_object() {
+                self.walk(children, &path, depth + 1, lint);
+            }
+            for (sub, sub_field) in field["fields"].as_object().into_iter().flatten() {
+                lint.field(sub, &format!("{path}.{sub}"), sub_field);
+            }
+        }
+    }
+}
+
+impl Lint {
+    fn error(&mut self, path: &str, message: String) {
+        self.findings.push(Finding {
+            severity: Severity::Error,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn warning(&mut self, path: &str, message: String) {
+        self.findings.push(Finding {
+            severity: Severity::Warning,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    /// Checks a single field definition.
+    fn field(&mut self, name: &str, path: &str, field: &Value) {
+        self.fields += 1;
+        let field_type = match field["type"].as_str() {
+            Some(t) => t,
+            None if field.get("properties").is_some() => "object",
+            None => {
+                self.error(path, "missing type".to_string());
+                return;
+            }
+        };
+
+        match FIELD_TYPES.iter().find(|(t, _)| *t == field_type) {
+            None => self.error(path, format!("unknown field type {field_type}")),
+            Some((_, Some(since))) if self.version.is_some_and(|v| v < *since) => {
+                let (major, minor) = since;
+                let (cluster_major, cluster_minor) = self.version.unwrap_or_default();
+                self.error(
+                    path,
+                    format!(
+                        "type {field_type} requires Elasticsearch {major}.{minor}, the cluster runs {cluster_major}.{cluster_minor}"
+                    ),
+                );
+            }
+            Some(_) => {}
+        }
+
+        let identifier = is_identifier(name);
+        match field_type {
+            "keyword" if field.get("analyzer").is_some() => self.error(
+                path,
+                "keyword fields do not accept an analyzer, use a normalizer or a text field"
+                    .to_string(),
+            ),
+            "text" if field["fielddata"] == true => self.warning(
+                path,
+                "fielddata on text fields uses a lot of heap, aggregate on a keyword sub-field instead"
+                    .to_string(),
+            ),
+            "text" if identifier && field.get("fields").is_none() => self.warning(
+                path,
+                "identifiers are usually searched exactly, map them as keyword".to_string(),
+            ),
+            t if identifier && NUMERIC_TYPES.contains(&t) => self.warning(
+                path,
+                "identifiers are rarely used in range queries, keyword is faster for term queries"
+                    .to_string(),
+            ),
+            _ => {}
+        }
+        if field["index"] == false && field["doc_values"] == false {
+            self.warning(
+                path,
+                "the field is neither indexed nor in doc values, it cannot be searched or aggregated"
+                    .to_string(),
+            );
+        }
+    }
+}
+
+/// Whether a field name designates an identifier, such as `id` or `user_id`.
+fn is_identifier(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name == "id" || name.ends_with("_id") || name.ends_with("-id") || name.ends_with("uuid")
+}
+
+/// Parses the major and minor parts of a version number such as `8.15.1`.
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn linter(args: &[&str]) -> LintMapping {
+        LintMapping::try_parse_from(["lint-mapping", "mapping.json"].iter().chain(args)).unwrap()
+    }
+
+    #[test]
+    fn lint_reports_types_and_anti_patterns() {
+        let mapping = json!({"properties": {
+            "user_id": {"type": "long"},
+            "name": {"type": "keyword", "analyzer": "standard"},
+            "body": {"type": "txt"},
+            "meta": {"properties": {
+                "order_id": {"type": "text", "fields": {"raw": {"type": "keyword"}}},
+                "vector": {"type": "semantic_text"},
+            }},
+        }});
+        let findings = linter(&[]).lint(&mapping, Some((8, 11)));
+        let summary: Vec<(&str, &Severity)> = findings
+            .iter()
+            .map(|f| (f.path.as_str(), &f.severity))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("body", &Severity::Error),
+                ("meta.vector", &Severity::Error),
+                ("name", &Severity::Error),
+                ("user_id", &Severity::Warning),
+            ]
+        );
+        assert_eq!(
+            findings[1].message,
+            "type semantic_text requires Elasticsearch 8.15, the cluster runs 8.11"
+        );
+    }
+
+    #[test]
+    fn lint_checks_field_limits() {
+        let mapping = json!({"properties": {
+            "a": {"type": "nested", "properties": {"b": {"properties": {"c": {"type": "keyword"}}}}},
+        }});
+        let findings = linter(&[
+            "--total-fields-limit",
+            "2",
+            "--depth-limit",
+            "2",
+            "--nested-fields-limit",
+            "0",
+        ])
+        .lint(&mapping, None);
+        let messages: Vec<&str> = findings.iter().map(|f| f.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "3 fields exceed the limit of 2",
+                "1 nested fields exceed the limit of 0",
+                "objects nested deeper than the limit of 2",
+            ]
+        );
+        assert_eq!(findings[2].path, "a.b");
+    }
+
+    #[test]
+    fn parse_version_reads_major_and_minor() {
+        assert_eq!(parse_version("8.15.1"), Some((8, 15)));
+        assert_eq!(parse_version("9.0.0-SNAPSHOT"), Some((9, 0)));
+        assert_eq!(parse_version("x"), None);
+    }
+}
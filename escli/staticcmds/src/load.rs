@@ -15,19 +15,29 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use clap::{Command, CommandFactory, Parser, ValueEnum};
+use crate::copy_index::create_body;
+use crate::input::read_json_file;
+use crate::request::{response, send_json, send_json_ok};
+use crate::transform::Transform;
+use crate::units::parse_duration;
+use clap::{ArgGroup, Command, CommandFactory, Parser, ValueEnum};
 use elasticsearch::http::headers::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use elasticsearch::http::response::Response;
 use elasticsearch::http::transport::Transport;
 use elasticsearch::http::Method;
-use serde::Deserialize;
-use serde_json::Value;
-use std::path::PathBuf;
-use std::time::Duration;
-use tokio::fs;
-use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::task::JoinSet;
 
 const DEFAULT_BATCH_SIZE: usize = 500;
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
 pub enum Format {
@@ -35,7 +45,25 @@ pub enum Format {
     Ndjson,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum BulkOp {
+    /// Add the documents, replacing those with the same _id
+    Index,
+    /// Add the documents, leaving those with the same _id as they are
+    Create,
+}
+
+impl BulkOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            BulkOp::Index => "index",
+            BulkOp::Create => "create",
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("target").args(["index", "index_override"]).multiple(true)))]
 pub struct Load {
     #[arg(help = "Path to the file to load, or - to read from stdin (default when omitted)")]
     file: Option<PathBuf>,
@@ -47,6 +75,30 @@ pub struct Load {
     )]
     index: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "PATTERN=REPLACEMENT",
+        value_parser = parse_rename,
+        conflicts_with = "index_override",
+        help = "Rename the target indices matching a regex, such as 'logs-(.*)=archive-$1'"
+    )]
+    rename: Option<(Regex, String)>,
+
+    #[arg(
+        long,
+        value_name = "INDEX",
+        help = "Load all the documents into this index, whatever the index of their action lines"
+    )]
+    index_override: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "EXPR",
+        value_parser = Transform::parse,
+        help = "jq-like expression applied to each document, such as '.restored_at = now | del(.tmp)'"
+    )]
+    transform: Option<Transform>,
+
     #[arg(
         short,
         long,
@@ -69,8 +121,78 @@ pub struct Load {
         value_enum
     )]
     format: Option<Format>,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of bulk requests sent concurrently",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    concurrency: u16,
+
+    #[arg(
+        long,
+        help = "Maximum number of retries of documents rejected with 429 Too Many Requests",
+        default_value_t = 3
+    )]
+    max_retries: u32,
+
+    #[arg(
+        long,
+        help = "Delay before the first retry, doubled on each attempt",
+        default_value = "1s",
+        value_parser = parse_duration
+    )]
+    retry_backoff: Duration,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = BulkOp::Index,
+        help = "Bulk operation of the documents, create leaving the existing ones as they are"
+    )]
+    op: BulkOp,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Record the lines of the input loaded in FILE, to continue the load later with --resume"
+    )]
+    checkpoint: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "CHECKPOINT",
+        conflicts_with = "checkpoint",
+        help = "Continue the load recorded in a checkpoint file, skipping the lines already loaded"
+    )]
+    resume: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the rejected documents with their error to FILE, an NDJSON file to load again once fixed"
+    )]
+    failures: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Create the target index from the mapping and settings files written next to the file by dump --with-metadata"
+    )]
+    create_index: bool,
+
+    #[arg(
+        long,
+        requires = "target",
+        help = "Disable the refreshes and replicas of the target index during the load"
+    )]
+    optimize_ingest: bool,
 }
 
+/// The settings changed by --optimize-ingest during the load.
+const INGEST_SETTINGS: [&str; 2] = ["index.refresh_interval", "index.number_of_replicas"];
+
 #[derive(Deserialize)]
 struct BulkResponse {
     errors: bool,
@@ -112,13 +234,78 @@ impl Load {
             for JSON Lines, .ndjson for bulk NDJSON) unless overridden with
             --format.
 
+            A dump taken under one naming scheme can be loaded under another.
+            --rename PATTERN=REPLACEMENT renames the indices of the action
+            lines, and of --index, whose whole name matches the regex
+            PATTERN, $1 or ${1} in REPLACEMENT being replaced with the first
+            group of the match. --index-override loads all the documents into
+            a single index instead.
+
+            --transform reshapes each document before it is sent, with the
+            jq-like filters of `escli utils dump --transform`, such as
+            '.restored_at = now | del(.tmp) | drop_nulls'. The _index, _id and
+            _routing of the action line are available as top-level fields of
+            the document, to route documents by one of their fields with
+            ._routing = .user.id for instance, and removing them removes them
+            from the action line.
+
             Documents are batched into chunks (default 500) to avoid hitting
-            the Elasticsearch HTTP request size limit.
+            the Elasticsearch HTTP request size limit. Up to --concurrency
+            chunks are sent at the same time.
+
+            Documents rejected with 429 Too Many Requests, because the
+            cluster is overloaded, are retried up to --max-retries times,
+            waiting --retry-backoff before the first retry and twice as long
+            before each of the next ones.
+
+            With --checkpoint, the number of lines of the input loaded is
+            recorded in a file every few seconds and at the end. An
+            interrupted load is continued with --resume and that file, which
+            skips those lines of the same input and keeps the file up to date.
+            The batches in flight when the load was interrupted may be sent
+            again: with --op create and documents with an _id, such as those
+            dumped with --with-id, the documents already loaded are left as
+            they are and counted apart rather than as errors, instead of
+            being indexed twice or overwritten.
+
+            With --failures, the documents Elasticsearch rejected, including
+            those still rejected with 429 once the retries run out, are
+            written to an NDJSON file: the action line of each one, with the
+            "status" and "error" of its bulk item added, followed by its
+            original source line. Once fixed, the file is loaded again as it
+            is, the "status" and "error" being removed from its action lines.
+            With --resume, the rejected documents are appended to the file.
+
+            With --create-index, the index is created before the load from
+            the bundle written by `escli utils dump --with-metadata`: the
+            <index>.mapping.json and <index>.settings.json files next to the
+            loaded file, the index being named like the file, or else like
+            the index of its first action line. The settings that cannot be
+            set on a new index, such as its uuid or creation date, are left
+            out. The index created is the --index or --index-override, or
+            else the index of the bundle, renamed with --rename. The load is
+            aborted when the index cannot be created, such as when it exists.
+
+            Large loads into a new index are faster with --optimize-ingest,
+            which sets the refresh_interval of the --index to -1 and its
+            number_of_replicas to 0 during the load. The original settings
+            are restored once done, even when the load failed, and the index
+            is refreshed to make the documents searchable. The replicas are
+            then recovered from the primaries.
 
             Example usage:
                 escli utils load data.ndjson
                 escli utils load docs.json --index my-index
                 escli utils load docs.jsonl --index my-index --pipeline my-pipeline --size 1000
+                escli utils load data.ndjson --concurrency 4 --max-retries 5
+                escli utils load big.ndjson --index-override big --optimize-ingest --concurrency 4
+                escli utils load big.ndjson --op create --checkpoint load.ckpt
+                escli utils load big.ndjson --op create --resume load.ckpt
+                escli utils load docs.ndjson --failures failed.ndjson
+                escli utils load backup/logs.ndjson --create-index --rename 'logs=logs-restored'
+                escli utils load logs.ndjson --rename 'logs-(.*)=archive-$1'
+                escli utils load users.ndjson --index-override users-restored
+                escli utils load logs.ndjson --transform '.restored_at = now | del(.debug)'
             "#,
             )
     }
@@ -150,8 +337,8 @@ impl Load {
             }
         });
 
-        let mut path = match &self.index {
-            Some(idx) => format!("/{}/_bulk", idx),
+        let mut path = match self.index.as_deref().or(self.index_override.as_deref()) {
+            Some(idx) => format!("/{}/_bulk", self.target_index(idx)),
             None => "/_bulk".to_string(),
         };
 
@@ -159,9 +346,6 @@ impl Load {
             path.push_str(&format!("?pipeline={}", pipeline));
         }
 
-        let mut headers = HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/x-ndjson"));
-
         let input: Box<dyn AsyncRead + Unpin> = if is_stdin {
             Box::new(tokio::io::stdin())
         } else {
@@ -173,21 +357,92 @@ impl Load {
         };
         let mut reader = BufReader::new(input);
 
-        let (total_indexed, total_errors, total_batches, total_http_errors) = match format {
-            Format::Json => {
-                self.load_json(&mut reader, &transport, &path, &headers, t).await?
+        if self.create_index {
+            let Some(file) = self.file.as_deref().filter(|_| !is_stdin) else {
+                eprintln!("--create-index requires a file, next to which the bundle was written");
+                return Ok(response(400, Vec::new()));
+            };
+            let Some((source, body)) = read_bundle(file).await? else {
+                eprintln!("No <index>.mapping.json file found next to {:?}", file);
+                return Ok(response(404, Vec::new()));
+            };
+            let target = self.index.as_deref().or(self.index_override.as_deref()).unwrap_or(&source);
+            let path = format!("/{}", self.target_index(target));
+            let (status, result) = send_json(&transport, Method::Put, &path, &[], Some(&body), t).await?;
+            if !status.is_success() {
+                eprintln!("Failed to create index {} from the bundle of {}", &path[1..], source);
+                return Ok(response(status.as_u16(), result.to_string().into_bytes()));
+            }
+            eprintln!("Created index {} from the bundle of {}", &path[1..], source);
+        }
+
+        let skip = match &self.resume {
+            Some(path) => {
+                let checkpoint = LoadCheckpoint::load(path).await?;
+                eprintln!("Resuming the load after {} lines", checkpoint.lines);
+                checkpoint.lines
             }
-            Format::Ndjson => {
-                self.load_ndjson(&mut reader, &transport, &path, &headers, t).await?
+            None => 0,
+        };
+        let mut sender = BulkSender::new(
+            transport.clone(),
+            path,
+            t,
+            usize::from(self.concurrency),
+            self.max_retries,
+            self.retry_backoff,
+        );
+        if let Some(checkpoint) = self.resume.as_ref().or(self.checkpoint.as_ref()) {
+            sender = sender.with_checkpoint(checkpoint.clone(), skip);
+        }
+        if let Some(failures) = &self.failures {
+            let mut options = OpenOptions::new();
+            match self.resume.is_some() {
+                true => options.append(true),
+                false => options.write(true).truncate(true),
+            };
+            let file = options.create(true).open(failures).await.map_err(|e| {
+                eprintln!("Failed to open failures file {:?}: {}", failures, e);
+                e
+            })?;
+            sender = sender.with_failures(failures.clone(), file);
+        }
+        // Tuned last, once nothing can fail before the settings are restored.
+        let tuned = match self.optimize_ingest {
+            true => {
+                let target = self.index.as_deref().or(self.index_override.as_deref());
+                let target = self.target_index(target.expect("--optimize-ingest requires a target"));
+                let Some(original) = tune_for_ingest(&transport, &target, t).await? else {
+                    return Ok(response(500, Vec::new()));
+                };
+                Some((target, original))
             }
+            false => None,
         };
+        let loaded = match format {
+            Format::Json => self.load_json(&mut reader, &mut sender, skip).await,
+            Format::Ndjson => self.load_ndjson(&mut reader, &mut sender, skip).await,
+        };
+        let finished = sender.finish().await;
+        let restored = match &tuned {
+            Some((target, original)) => restore_settings(&transport, target, original, t).await,
+            None => true,
+        };
+        loaded?;
+        let (stats, batches) = finished?;
 
         eprintln!(
-            "Done: {} documents indexed, {} errors across {} batch(es)",
-            total_indexed, total_errors, total_batches
+            "Done: {} documents indexed, {} errors, {} retried, {} already existed across {} batch(es)",
+            stats.indexed, stats.errors, stats.retried, stats.existing, batches
         );
 
-        let status = if total_errors > 0 || total_http_errors > 0 { 400u16 } else { 200u16 };
+        let status = if stats.errors > 0 || stats.http_errors > 0 {
+            400u16
+        } else if !restored {
+            500u16
+        } else {
+            200u16
+        };
         let hr = http::response::Builder::new()
             .status(status)
             .body(Vec::new())
@@ -197,173 +452,739 @@ impl Load {
     }
 
     /// JSON Lines format: one raw JSON document per line. Streamed
-    /// line-by-line so arbitrarily large files can be ingested.
+    /// line-by-line so arbitrarily large files can be ingested. The first
+    /// `skip` lines were loaded by the load being resumed.
     async fn load_json(
         &self,
         reader: &mut (impl AsyncBufReadExt + Unpin),
-        transport: &Transport,
-        path: &str,
-        headers: &HeaderMap,
-        timeout: Duration,
-    ) -> Result<(usize, usize, usize, usize), elasticsearch::Error> {
-        let index = self.index.as_deref().unwrap_or_else(|| {
+        sender: &mut BulkSender,
+        skip: u64,
+    ) -> Result<(), elasticsearch::Error> {
+        let index = self.index.as_deref().or(self.index_override.as_deref()).unwrap_or_else(|| {
             eprintln!("Error: --index is required for JSON format");
             std::process::exit(1);
         });
+        let index = self.target_index(index);
 
-        let action_line = serde_json::to_string(&serde_json::json!({ "index": { "_index": index } })).unwrap();
+        let action_line =
+            serde_json::to_string(&serde_json::json!({ self.op.as_str(): { "_index": index } })).unwrap();
 
         let mut lines = reader.lines();
-
-        let mut total_indexed: usize = 0;
-        let mut total_errors: usize = 0;
-        let mut total_http_errors: usize = 0;
-        let mut batch_num: usize = 0;
         let mut body = String::new();
         let mut doc_count: usize = 0;
+        let mut position: u64 = 0;
 
         while let Some(line) = lines.next_line().await.map_err(|e| {
             eprintln!("Failed to read line: {}", e);
             e
         })? {
-            if line.is_empty() {
+            position += 1;
+            if line.is_empty() || position <= skip {
                 continue;
             }
-            body.push_str(&action_line);
+            let (action, source) = match &self.transform {
+                Some(transform) => transform_document(transform, &action_line, &line),
+                None => (action_line.clone(), line),
+            };
+            body.push_str(&action);
             body.push('\n');
-            body.push_str(&line);
+            body.push_str(&source);
             body.push('\n');
             doc_count += 1;
 
             if doc_count >= self.size {
-                batch_num += 1;
-                let (ok, err, http_fail) = send_bulk_batch(transport, path, headers, &body, batch_num, timeout).await?;
-                total_indexed += ok;
-                total_errors += err;
-                if http_fail { total_http_errors += 1; }
-                body.clear();
+                sender.send_at(std::mem::take(&mut body), position).await?;
                 doc_count = 0;
             }
         }
 
         if !body.is_empty() {
-            batch_num += 1;
-            let (ok, err, http_fail) = send_bulk_batch(transport, path, headers, &body, batch_num, timeout).await?;
-            total_indexed += ok;
-            total_errors += err;
-            if http_fail { total_http_errors += 1; }
+            sender.send_at(body, position).await?;
         }
-
-        Ok((total_indexed, total_errors, batch_num, total_http_errors))
+        Ok(())
     }
 
     /// NDJSON format streams the file line-by-line, so it can handle
     /// arbitrarily large files without loading them entirely into memory.
+    /// The first `skip` lines were loaded by the load being resumed.
     async fn load_ndjson(
         &self,
         reader: &mut (impl AsyncBufReadExt + Unpin),
-        transport: &Transport,
-        path: &str,
-        headers: &HeaderMap,
-        timeout: Duration,
-    ) -> Result<(usize, usize, usize, usize), elasticsearch::Error> {
+        sender: &mut BulkSender,
+        skip: u64,
+    ) -> Result<(), elasticsearch::Error> {
+        // The action lines of a --failures file carry the errors to remove.
+        let buffered = reader.fill_buf().await.map_err(|e| {
+            eprintln!("Failed to read line: {}", e);
+            e
+        })?;
+        let first = buffered.split(|b| *b == b'\n').next().unwrap_or_default();
+        let failures = serde_json::from_slice::<Value>(first).is_ok_and(|a| a.get("error").is_some());
         let mut lines = reader.lines();
 
         let lines_per_batch = self.size * 2;
-        let mut total_indexed: usize = 0;
-        let mut total_errors: usize = 0;
-        let mut total_http_errors: usize = 0;
-        let mut batch_num: usize = 0;
         let mut body = String::new();
         let mut line_count: usize = 0;
+        let mut position: u64 = 0;
+        let create = self.op == BulkOp::Create;
+        let rewriting = self.rename.is_some()
+            || self.index_override.is_some()
+            || self.transform.is_some()
+            || create
+            || failures;
+        let mut expect_source = false;
+        // With --transform, an action waits for its source to be rewritten with it.
+        let mut pending = None;
 
         while let Some(line) = lines.next_line().await.map_err(|e| {
             eprintln!("Failed to read line: {}", e);
             e
         })? {
-            if line.is_empty() {
+            position += 1;
+            if line.is_empty() || position <= skip {
                 continue;
             }
-            body.push_str(&line);
-            body.push('\n');
-            line_count += 1;
+            let rewritten = match (rewriting, expect_source) {
+                (false, _) => vec![line],
+                (true, false) => {
+                    let (action, has_source) =
+                        rewrite_action(&line, create, |index| self.target_index(index));
+                    expect_source = has_source;
+                    match has_source && self.transform.is_some() {
+                        true => {
+                            pending = Some(action);
+                            Vec::new()
+                        }
+                        false => vec![action],
+                    }
+                }
+                (true, true) => {
+                    expect_source = false;
+                    match (pending.take(), &self.transform) {
+                        (Some(action), Some(transform)) => {
+                            let (action, source) = transform_document(transform, &action, &line);
+                            vec![action, source]
+                        }
+                        _ => vec![line],
+                    }
+                }
+            };
+            for line in rewritten {
+                body.push_str(&line);
+                body.push('\n');
+                line_count += 1;
+            }
 
             if line_count >= lines_per_batch {
-                batch_num += 1;
-                let (ok, err, http_fail) = send_bulk_batch(transport, path, headers, &body, batch_num, timeout).await?;
-                total_indexed += ok;
-                total_errors += err;
-                if http_fail { total_http_errors += 1; }
-                body.clear();
+                sender.send_at(std::mem::take(&mut body), position).await?;
                 line_count = 0;
             }
         }
 
         if !body.is_empty() {
-            batch_num += 1;
-            let (ok, err, http_fail) = send_bulk_batch(transport, path, headers, &body, batch_num, timeout).await?;
-            total_indexed += ok;
-            total_errors += err;
-            if http_fail { total_http_errors += 1; }
+            sender.send_at(body, position).await?;
         }
+        Ok(())
+    }
 
-        Ok((total_indexed, total_errors, batch_num, total_http_errors))
+    /// The index the documents read for `index` are loaded into.
+    fn target_index(&self, index: &str) -> String {
+        match (&self.index_override, &self.rename) {
+            (Some(target), _) => target.clone(),
+            (None, Some((pattern, replacement))) => {
+                pattern.replace(index, replacement.as_str()).into_owned()
+            }
+            (None, None) => index.to_string(),
+        }
+    }
+}
+
+/// Reads the bundle written by `dump --with-metadata` next to `file`, that of
+/// the index named like the file or else that of the index of its first
+/// action line, returning that index and its create index body.
+async fn read_bundle(file: &Path) -> Result<Option<(String, Value)>, IoError> {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let stem = file.file_stem().and_then(|s| s.to_str()).map(str::to_string);
+    let mut lines = BufReader::new(fs::File::open(file).await?).lines();
+    let mut first_action = None;
+    while let Some(line) = lines.next_line().await? {
+        if !line.is_empty() {
+            first_action = serde_json::from_str::<Value>(&line).ok();
+            break;
+        }
+    }
+    let first_index = first_action
+        .as_ref()
+        .and_then(Value::as_object)
+        .and_then(|a| a.values().next())
+        .and_then(|metadata| metadata["_index"].as_str())
+        .map(str::to_string);
+    for index in stem.into_iter().chain(first_index) {
+        let mapping = dir.join(format!("{index}.mapping.json"));
+        if !fs::try_exists(&mapping).await? {
+            continue;
+        }
+        let settings = dir.join(format!("{index}.settings.json"));
+        let settings = match fs::try_exists(&settings).await? {
+            true => read_json_file(&settings).await?,
+            false => json!({}),
+        };
+        let definition = json!({ "settings": settings, "mappings": read_json_file(&mapping).await? });
+        return Ok(Some((index, create_body(&definition, false))));
     }
+    Ok(None)
 }
 
-/// Returns `(indexed, doc_errors, http_failed)` where `http_failed` is true
-/// when the bulk endpoint itself returned a non-2xx status.
-async fn send_bulk_batch(
+/// Disables the refreshes and the replicas of the indices of `target`,
+/// returning their original settings, `None` when they cannot be changed.
+async fn tune_for_ingest(
     transport: &Transport,
-    path: &str,
-    headers: &HeaderMap,
-    body: &str,
-    batch_num: usize,
+    target: &str,
     timeout: Duration,
-) -> Result<(usize, usize, bool), elasticsearch::Error> {
-    let response: Response = transport
-        .send(
-            Method::Post,
-            path,
-            headers.clone(),
-            Option::<&()>::None,
-            Some(body),
-            Some(timeout),
-        )
-        .await?;
-
-    if !response.status_code().is_success() {
-        let status = response.status_code();
-        let text = response.text().await.unwrap_or_default();
-        eprintln!(
-            "Batch {}: bulk request failed with status {} - {}",
-            batch_num, status, text
-        );
-        return Ok((0, 0, true));
+) -> Result<Option<Vec<(String, Value)>>, elasticsearch::Error> {
+    let path = format!("/{target}/_settings");
+    let query = [("flat_settings", "true")];
+    let Some(current) = send_json_ok(transport, Method::Get, &path, &query, None, timeout).await? else {
+        return Ok(None);
+    };
+    // A setting left to its default is restored by setting it to null.
+    let original = current
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(index, definition)| {
+            let settings: serde_json::Map<String, Value> = INGEST_SETTINGS
+                .iter()
+                .map(|name| (name.to_string(), definition["settings"].get(*name).cloned().unwrap_or(Value::Null)))
+                .collect();
+            (index.clone(), Value::Object(settings))
+        })
+        .collect();
+    let body = json!({ "index.refresh_interval": "-1", "index.number_of_replicas": 0 });
+    if send_json_ok(transport, Method::Put, &path, &[], Some(&body), timeout).await?.is_none() {
+        return Ok(None);
     }
+    eprintln!("Disabled the refreshes and replicas of {} during the load", target);
+    Ok(Some(original))
+}
 
-    let bulk_resp: BulkResponse = response.json().await?;
-    let batch_errors: usize = bulk_resp
-        .items
+/// Restores the settings changed by [`tune_for_ingest`] and refreshes the
+/// indices, returning whether it succeeded. Every index is restored even when
+/// another one failed, the errors being reported rather than returned so that
+/// they do not hide the error of the load.
+async fn restore_settings(
+    transport: &Transport,
+    target: &str,
+    original: &[(String, Value)],
+    timeout: Duration,
+) -> bool {
+    let requests = original
         .iter()
-        .filter(|item| item.action.status >= 400)
-        .count();
-    let batch_ok = bulk_resp.items.len() - batch_errors;
+        .map(|(index, settings)| (Method::Put, format!("/{index}/_settings"), Some(settings)))
+        .chain([(Method::Post, format!("/{target}/_refresh"), None)]);
+    let mut restored = true;
+    for (method, path, body) in requests {
+        match send_json_ok(transport, method, &path, &[], body, timeout).await {
+            Ok(Some(_)) => {}
+            Ok(None) => restored = false,
+            Err(e) => {
+                eprintln!("Request to {} failed: {}", path, e);
+                restored = false;
+            }
+        }
+    }
+    match restored {
+        true => eprintln!("Restored the settings of {} and refreshed it", target),
+        false => eprintln!("Failed to restore the settings of {}, set them back by hand", target),
+    }
+    restored
+}
 
-    if bulk_resp.errors {
-        for item in &bulk_resp.items {
-            if let Some(ref err) = item.action.error {
+/// Parses a `PATTERN=REPLACEMENT` rename, the pattern having to match the
+/// whole index name.
+fn parse_rename(s: &str) -> Result<(Regex, String), String> {
+    match s.split_once('=') {
+        Some((pattern, replacement)) if !pattern.is_empty() => {
+            let regex = Regex::new(&format!("^(?:{pattern})$"))
+                .map_err(|e| format!("invalid rename pattern '{pattern}': {e}"))?;
+            Ok((regex, replacement.to_string()))
+        }
+        _ => Err(format!(
+            "invalid rename '{s}', expected PATTERN=REPLACEMENT, e.g. 'logs-(.*)=archive-$1'"
+        )),
+    }
+}
+
+/// Replaces the `_index` of a bulk action line with `target(_index)`, and
+/// index actions with create ones when `create` is set, returning the line
+/// and whether a source line follows it. The status and error recorded in
+/// the lines of a --failures file are removed. Lines that are not valid
+/// actions are kept for Elasticsearch to report.
+fn rewrite_action(line: &str, create: bool, target: impl Fn(&str) -> String) -> (String, bool) {
+    let Ok(mut action) = serde_json::from_str::<Value>(line) else {
+        return (line.to_string(), true);
+    };
+    if let Some(object) = action.as_object_mut() {
+        object.remove("status");
+        object.remove("error");
+    }
+    let has_source = action.get("delete").is_none();
+    let replaced = action.as_object_mut().filter(|_| create).and_then(|a| a.remove("index"));
+    if let Some(metadata) = replaced {
+        action["create"] = metadata;
+    }
+    let metadata = action
+        .as_object_mut()
+        .and_then(|a| a.values_mut().next())
+        .and_then(Value::as_object_mut);
+    match metadata {
+        Some(metadata) => {
+            if let Some(index) = metadata.get("_index").and_then(Value::as_str) {
+                let index = target(index);
+                metadata.insert("_index".to_string(), json!(index));
+            }
+            (action.to_string(), has_source)
+        }
+        None => (line.to_string(), has_source),
+    }
+}
+
+/// The metadata of the action lines exposed to --transform as fields.
+const TRANSFORMED_METADATA: [&str; 3] = ["_index", "_id", "_routing"];
+
+/// Applies a transform to the source line of a document, the metadata of its
+/// action line being readable and writable as top-level fields. Lines that
+/// are not valid JSON objects are kept for Elasticsearch to report.
+fn transform_document(transform: &Transform, action_line: &str, source_line: &str) -> (String, String) {
+    let unchanged = || (action_line.to_string(), source_line.to_string());
+    let (Ok(mut action), Ok(mut source)) = (
+        serde_json::from_str::<Value>(action_line),
+        serde_json::from_str::<Value>(source_line),
+    ) else {
+        return unchanged();
+    };
+    let metadata = action
+        .as_object_mut()
+        .and_then(|a| a.values_mut().next())
+        .and_then(Value::as_object_mut);
+    let (Some(metadata), Some(document)) = (metadata, source.as_object_mut()) else {
+        return unchanged();
+    };
+    for field in TRANSFORMED_METADATA {
+        if let Some(value) = metadata.remove(field) {
+            document.insert(field.to_string(), value);
+        }
+    }
+    transform.apply(&mut source);
+    if let Some(document) = source.as_object_mut() {
+        for field in TRANSFORMED_METADATA {
+            match document.remove(field) {
+                Some(Value::Null) | None => {}
+                Some(value) => {
+                    metadata.insert(field.to_string(), value);
+                }
+            }
+        }
+    }
+    (action.to_string(), source.to_string())
+}
+
+/// Outcome of one or more bulk requests.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct BatchStats {
+    pub(crate) indexed: usize,
+    pub(crate) errors: usize,
+    pub(crate) retried: usize,
+    /// Bulk requests that failed as a whole with a non-2xx status.
+    pub(crate) http_errors: usize,
+    /// Documents created with a create action whose _id already existed.
+    pub(crate) existing: usize,
+}
+
+impl std::ops::AddAssign for BatchStats {
+    fn add_assign(&mut self, other: Self) {
+        self.indexed += other.indexed;
+        self.errors += other.errors;
+        self.retried += other.retried;
+        self.http_errors += other.http_errors;
+        self.existing += other.existing;
+    }
+}
+
+/// The lines of the input loaded, recorded to resume an interrupted load.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct LoadCheckpoint {
+    lines: u64,
+}
+
+impl LoadCheckpoint {
+    async fn load(path: &Path) -> Result<Self, IoError> {
+        let bytes = fs::read(path).await.map_err(|e| {
+            eprintln!("Failed to read checkpoint {:?}: {}", path, e);
+            e
+        })?;
+        serde_json::from_slice(&bytes).map_err(|e| IoError::new(IoErrorKind::InvalidData, e))
+    }
+
+    /// Writes the checkpoint to a temporary file renamed over `path`, so that
+    /// an interruption never leaves a partial checkpoint.
+    async fn save(&self, path: &Path) -> Result<(), IoError> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let bytes = serde_json::to_vec_pretty(self).map_err(IoError::other)?;
+        fs::write(&tmp, bytes).await?;
+        fs::rename(&tmp, path).await
+    }
+}
+
+/// Sends bulk bodies with at most `concurrency` requests in flight.
+pub(crate) struct BulkSender {
+    transport: Transport,
+    path: String,
+    headers: HeaderMap,
+    timeout: Duration,
+    concurrency: usize,
+    max_retries: u32,
+    retry_backoff: Duration,
+    tasks: JoinSet<(usize, Result<(BatchStats, Vec<String>), elasticsearch::Error>)>,
+    batches: usize,
+    stats: BatchStats,
+    /// The input position reached by the batches not done yet, or done after
+    /// a batch that is not, and whether they are done.
+    positions: BTreeMap<usize, (u64, bool)>,
+    /// The input position up to which all the batches are done.
+    committed: u64,
+    checkpoint: Option<PathBuf>,
+    saved: Instant,
+    /// The file the rejected items are written to, and how many were.
+    failures: Option<(PathBuf, File)>,
+    failed: usize,
+}
+
+impl BulkSender {
+    pub(crate) fn new(
+        transport: Transport,
+        path: String,
+        timeout: Duration,
+        concurrency: usize,
+        max_retries: u32,
+        retry_backoff: Duration,
+    ) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/x-ndjson"));
+        BulkSender {
+            transport,
+            path,
+            headers,
+            timeout,
+            concurrency,
+            max_retries,
+            retry_backoff,
+            tasks: JoinSet::new(),
+            batches: 0,
+            stats: BatchStats::default(),
+            positions: BTreeMap::new(),
+            committed: 0,
+            checkpoint: None,
+            saved: Instant::now(),
+            failures: None,
+            failed: 0,
+        }
+    }
+
+    /// Records the input position up to which the batches are done in a
+    /// checkpoint file, starting from the position `resumed` from.
+    pub(crate) fn with_checkpoint(mut self, path: PathBuf, resumed: u64) -> Self {
+        self.checkpoint = Some(path);
+        self.committed = resumed;
+        self
+    }
+
+    /// Writes the rejected items to `file`, opened from `path`.
+    pub(crate) fn with_failures(mut self, path: PathBuf, file: File) -> Self {
+        self.failures = Some((path, file));
+        self
+    }
+
+    pub(crate) async fn send(&mut self, body: String) -> Result<(), elasticsearch::Error> {
+        self.send_at(body, 0).await
+    }
+
+    /// Sends a body holding the input up to `position`.
+    pub(crate) async fn send_at(&mut self, body: String, position: u64) -> Result<(), elasticsearch::Error> {
+        while self.tasks.len() >= self.concurrency {
+            self.join_next().await?;
+        }
+        self.batches += 1;
+        self.positions.insert(self.batches, (position, false));
+        let batch = BulkBatch {
+            transport: self.transport.clone(),
+            path: self.path.clone(),
+            headers: self.headers.clone(),
+            timeout: self.timeout,
+            max_retries: self.max_retries,
+            retry_backoff: self.retry_backoff,
+            batch_num: self.batches,
+            record_failures: self.failures.is_some(),
+        };
+        self.tasks.spawn(async move { (batch.batch_num, batch.send(body).await) });
+        Ok(())
+    }
+
+    async fn join_next(&mut self) -> Result<(), elasticsearch::Error> {
+        if let Some(result) = self.tasks.join_next().await {
+            let (batch, result) = result.map_err(IoError::other)?;
+            let (stats, failed) = result?;
+            self.stats += stats;
+            if let Some((_, file)) = &mut self.failures {
+                for record in &failed {
+                    file.write_all(record.as_bytes()).await?;
+                }
+                self.failed += failed.len();
+            }
+            if let Some(position) = self.positions.get_mut(&batch) {
+                position.1 = true;
+            }
+            // Batches finish in any order, the position only moves past done ones.
+            while let Some(first) = self.positions.first_entry() {
+                if !first.get().1 {
+                    break;
+                }
+                self.committed = self.committed.max(first.remove().0);
+            }
+            if self.saved.elapsed() >= CHECKPOINT_INTERVAL {
+                self.save_checkpoint().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn save_checkpoint(&mut self) -> Result<(), IoError> {
+        if let Some(path) = &self.checkpoint {
+            LoadCheckpoint { lines: self.committed }.save(path).await?;
+            self.saved = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Waits for the requests in flight, returning the totals and the number
+    /// of batches.
+    pub(crate) async fn finish(mut self) -> Result<(BatchStats, usize), elasticsearch::Error> {
+        while !self.tasks.is_empty() {
+            self.join_next().await?;
+        }
+        self.save_checkpoint().await?;
+        if let Some((path, file)) = &mut self.failures {
+            file.flush().await?;
+            eprintln!("Wrote {} rejected item(s) to {:?}", self.failed, path);
+        }
+        Ok((self.stats, self.batches))
+    }
+}
+
+/// A single bulk request and its retry settings.
+struct BulkBatch {
+    transport: Transport,
+    path: String,
+    headers: HeaderMap,
+    timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+    batch_num: usize,
+    /// Whether to return the rejected items, as --failures records.
+    record_failures: bool,
+}
+
+impl BulkBatch {
+    /// Sends the body, resending the items rejected with 429 with an
+    /// exponential backoff. Returns the stats and the failure records of
+    /// the rejected items when they are recorded.
+    async fn send(self, mut body: String) -> Result<(BatchStats, Vec<String>), elasticsearch::Error> {
+        let mut stats = BatchStats::default();
+        let mut failed = Vec::new();
+        let mut attempt = 0;
+        loop {
+            let (batch, rejected, batch_failed) = self.send_once(&body).await?;
+            stats.indexed += batch.indexed;
+            stats.http_errors += batch.http_errors;
+            stats.existing += batch.existing;
+            failed.extend(batch_failed);
+            if rejected.is_empty() {
+                stats.errors += batch.errors;
+                return Ok((stats, failed));
+            }
+            if attempt >= self.max_retries {
+                eprintln!(
+                    "Batch {}: giving up on {} rejected item(s) after {} retries",
+                    self.batch_num,
+                    rejected.len(),
+                    attempt
+                );
+                if self.record_failures {
+                    failed.extend(rejected.iter().map(|(item, error)| failure_record(item, 429, error)));
+                }
+                stats.errors += batch.errors;
+                return Ok((stats, failed));
+            }
+            // Rejected items are counted as errors only once retries run out.
+            stats.errors += batch.errors - rejected.len();
+            stats.retried += rejected.len();
+            let delay = self
+                .retry_backoff
+                .saturating_mul(2u32.saturating_pow(attempt));
+            eprintln!(
+                "Batch {}: {} item(s) rejected with 429, retrying in {:?}",
+                self.batch_num,
+                rejected.len(),
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            body = rejected.into_iter().map(|(item, _)| item).collect();
+            attempt += 1;
+        }
+    }
+
+    /// Sends the body once, returning its stats, the items to retry with
+    /// their error and the failure records of the other rejected items.
+    async fn send_once(
+        &self,
+        body: &str,
+    ) -> Result<(BatchStats, Vec<(String, Value)>, Vec<String>), elasticsearch::Error> {
+        let response: Response = self
+            .transport
+            .send(
+                Method::Post,
+                &self.path,
+                self.headers.clone(),
+                Option::<&()>::None,
+                Some(body),
+                Some(self.timeout),
+            )
+            .await?;
+
+        let status = response.status_code();
+        if status.as_u16() == 429 {
+            let items = split_items(body);
+            let stats = BatchStats {
+                errors: items.len(),
+                ..Default::default()
+            };
+            let rejected = items.into_iter().map(|item| (item, Value::Null)).collect();
+            return Ok((stats, rejected, Vec::new()));
+        }
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            eprintln!(
+                "Batch {}: bulk request failed with status {} - {}",
+                self.batch_num, status, text
+            );
+            let stats = BatchStats {
+                http_errors: 1,
+                ..Default::default()
+            };
+            let failed = match self.record_failures {
+                true => split_items(body)
+                    .iter()
+                    .map(|item| failure_record(item, status.as_u16(), &Value::String(text.clone())))
+                    .collect(),
+                false => Vec::new(),
+            };
+            return Ok((stats, Vec::new(), failed));
+        }
+
+        let bulk_resp: BulkResponse = response.json().await?;
+        let items = split_items(body);
+        // A create conflicting with an existing document leaves it as it is.
+        let existed = |i: usize| {
+            bulk_resp.items[i].action.status == 409 && items.get(i).is_some_and(|item| is_create(item))
+        };
+        let batch_existing = (0..bulk_resp.items.len()).filter(|&i| existed(i)).count();
+        let batch_errors: usize = bulk_resp
+            .items
+            .iter()
+            .filter(|item| item.action.status >= 400)
+            .count()
+            - batch_existing;
+        let batch_ok = bulk_resp.items.len() - batch_errors - batch_existing;
+
+        let mut rejected = Vec::new();
+        let mut failed = Vec::new();
+        for (i, item) in bulk_resp.items.iter().enumerate() {
+            if item.action.status == 429 {
+                let error = item.action.error.clone().unwrap_or_default();
+                rejected.extend(items.get(i).map(|source| (source.clone(), error)));
+            } else if existed(i) {
+                continue;
+            } else if let Some(ref err) = item.action.error {
                 eprintln!("  Error: {}", err);
+                if self.record_failures {
+                    failed.extend(items.get(i).map(|source| failure_record(source, item.action.status, err)));
+                }
             }
         }
+
+        match batch_existing {
+            0 => eprintln!(
+                "Batch {}: {} indexed, {} errors",
+                self.batch_num, batch_ok, batch_errors
+            ),
+            existing => eprintln!(
+                "Batch {}: {} indexed, {} errors, {} already existed",
+                self.batch_num, batch_ok, batch_errors, existing
+            ),
+        }
+
+        let stats = BatchStats {
+            indexed: batch_ok,
+            errors: batch_errors,
+            existing: batch_existing,
+            ..Default::default()
+        };
+        Ok((stats, rejected, failed))
     }
+}
 
-    eprintln!(
-        "Batch {}: {} indexed, {} errors",
-        batch_num, batch_ok, batch_errors
-    );
+/// A rejected bulk item as written to the --failures file: its action line
+/// with the status and error of the item added, then its source line.
+fn failure_record(item: &str, status: u16, error: &Value) -> String {
+    let (action, source) = item.split_once('\n').unwrap_or((item, ""));
+    let action = match serde_json::from_str::<Value>(action) {
+        Ok(Value::Object(mut action)) => {
+            action.insert("status".to_string(), json!(status));
+            action.insert("error".to_string(), error.clone());
+            Value::Object(action).to_string()
+        }
+        _ => action.to_string(),
+    };
+    format!("{action}\n{source}")
+}
 
-    Ok((batch_ok, batch_errors, false))
+/// Whether a bulk item is a create action.
+fn is_create(item: &str) -> bool {
+    let action = item.lines().next().unwrap_or_default();
+    serde_json::from_str::<Value>(action).is_ok_and(|a| a.get("create").is_some())
+}
+
+/// Splits a bulk body into its items: an action line followed by a source
+/// line, except for deletes which have none. Each item keeps its newlines.
+fn split_items(body: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut lines = body.lines().filter(|l| !l.is_empty());
+    while let Some(action) = lines.next() {
+        let mut item = format!("{action}\n");
+        let is_delete =
+            serde_json::from_str::<Value>(action).is_ok_and(|a| a.get("delete").is_some());
+        let source = match is_delete {
+            true => None,
+            false => lines.next(),
+        };
+        if let Some(source) = source {
+            item.push_str(source);
+            item.push('\n');
+        }
+        items.push(item);
+    }
+    items
 }
 
 #[cfg(test)]
@@ -488,6 +1309,91 @@ mod tests {
         assert_eq!(lines2.len(), 2);
     }
 
+    #[test]
+    fn split_items_pairs_actions_with_sources() {
+        let body =
+            "{\"index\":{}}\n{\"a\":1}\n{\"delete\":{\"_id\":\"1\"}}\n{\"create\":{}}\n{\"a\":2}\n";
+        assert_eq!(
+            super::split_items(body),
+            vec![
+                "{\"index\":{}}\n{\"a\":1}\n",
+                "{\"delete\":{\"_id\":\"1\"}}\n",
+                "{\"create\":{}}\n{\"a\":2}\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_rename_matches_whole_index_names() {
+        let (pattern, replacement) = super::parse_rename("logs-(.*)=archive-$1").unwrap();
+        assert_eq!(pattern.replace("logs-2024", replacement.as_str()), "archive-2024");
+        assert_eq!(pattern.replace("old-logs-2024", replacement.as_str()), "old-logs-2024");
+        assert!(super::parse_rename("logs-(=x").is_err());
+        assert!(super::parse_rename("=x").is_err());
+    }
+
+    #[test]
+    fn rewrite_action_renames_index_and_op() {
+        let upper = |index: &str| index.to_uppercase();
+        assert_eq!(
+            super::rewrite_action(r#"{"index":{"_index":"a","_id":"1"}}"#, false, upper),
+            (r#"{"index":{"_id":"1","_index":"A"}}"#.to_string(), true)
+        );
+        assert_eq!(
+            super::rewrite_action(r#"{"delete":{"_index":"a","_id":"1"}}"#, true, upper),
+            (r#"{"delete":{"_id":"1","_index":"A"}}"#.to_string(), false)
+        );
+        assert_eq!(
+            super::rewrite_action(r#"{"index":{}}"#, true, upper),
+            (r#"{"create":{}}"#.to_string(), true)
+        );
+    }
+
+    #[test]
+    fn failure_records_load_again_without_their_error() {
+        let record = super::failure_record(
+            "{\"index\":{\"_id\":\"1\"}}\n{\"n\":\"x\"}\n",
+            400,
+            &serde_json::json!({"type": "mapper_parsing_exception"}),
+        );
+        assert_eq!(
+            record,
+            "{\"error\":{\"type\":\"mapper_parsing_exception\"},\"index\":{\"_id\":\"1\"},\"status\":400}\n{\"n\":\"x\"}\n"
+        );
+        let action = record.lines().next().unwrap();
+        assert_eq!(
+            super::rewrite_action(action, false, |index| index.to_string()),
+            (r#"{"index":{"_id":"1"}}"#.to_string(), true)
+        );
+    }
+
+    #[test]
+    fn is_create_reads_the_action_line() {
+        assert!(super::is_create("{\"create\":{\"_id\":\"1\"}}\n{\"a\":1}\n"));
+        assert!(!super::is_create("{\"index\":{}}\n{\"create\":1}\n"));
+    }
+
+    #[test]
+    fn transform_document_exposes_action_metadata() {
+        let transform =
+            super::Transform::parse("._routing = .user | .archived = true | del(._id, .tmp)").unwrap();
+        assert_eq!(
+            super::transform_document(
+                &transform,
+                r#"{"index":{"_index":"a","_id":"1"}}"#,
+                r#"{"user":"u1","tmp":1}"#
+            ),
+            (
+                r#"{"index":{"_index":"a","_routing":"u1"}}"#.to_string(),
+                r#"{"archived":true,"user":"u1"}"#.to_string()
+            )
+        );
+        assert_eq!(
+            super::transform_document(&transform, r#"{"index":{}}"#, "not json"),
+            (r#"{"index":{}}"#.to_string(), "not json".to_string())
+        );
+    }
+
     #[test]
     fn test_ndjson_batching_empty() {
         let batches = build_ndjson_batches("", 100);
@@ -0,0 +1,338 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_json_file;
+use crate::request::response;
+use crate::table::Table;
+use clap::{ArgGroup, Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::headers::{CONTENT_TYPE, HeaderMap, HeaderValue};
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("queries").required(true).multiple(true).args(["query", "dir"])))]
+pub struct Msearch {
+    #[arg(
+        short,
+        long,
+        value_name = "[INDEX=]FILE",
+        help = "Search body file, optionally prefixed with its own target index. Repeatable"
+    )]
+    query: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Directory whose *.json files are each used as a search body"
+    )]
+    dir: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Default target indices, comma separated"
+    )]
+    index: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Write each response to <label>.json in this directory instead of stdout"
+    )]
+    output_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with = "output_dir",
+        help = "Print a table of the status, took and hit count of each search instead of the responses"
+    )]
+    summary: bool,
+}
+
+/// A single search of the batch, labeled after its file name.
+#[derive(Debug, Clone, PartialEq)]
+struct Search {
+    label: String,
+    index: Option<String>,
+    body: Value,
+}
+
+impl Msearch {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("msearch")
+            .about(
+                "Run several searches in one _msearch request and split the responses per query.",
+            )
+            .long_about(
+                r#"
+            Assemble an _msearch NDJSON payload from several search body files,
+            send it in a single request and de-multiplex the responses.
+
+            Each search is labeled after its file name (without extension).
+            By default the responses are printed to stdout, each preceded by a
+            `### <label> (<status>)` line. With --output-dir every response is
+            written to <label>.json in that directory instead.
+
+            With --summary, a table of the status, duration and total hits of
+            each search is printed instead, to compare queries at a glance.
+
+            A query can target its own indices with the INDEX=FILE form;
+            otherwise the indices given with --index are used.
+
+            The command exits with a non-zero status if any of the searches
+            failed.
+
+            Example usage:
+                escli utils msearch --index logs-* --query errors.json --query slow.json
+                escli utils msearch --dir queries/ --index my-index --output-dir results/
+                escli utils msearch --query logs-*=errors.json --query metrics-*=cpu.json
+                escli utils msearch --dir queries/ --index my-index --summary
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+
+        let searches = self.collect_searches().await?;
+        let path = if self.index.is_empty() {
+            "/_msearch".to_string()
+        } else {
+            format!("/{}/_msearch", self.index.join(","))
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        let res = transport
+            .send(
+                Method::Post,
+                &path,
+                headers,
+                Option::<&()>::None,
+                Some(build_payload(&searches)),
+                Some(t),
+            )
+            .await?;
+
+        if !res.status_code().is_success() {
+            let status = res.status_code().as_u16();
+            return Ok(response(status, res.bytes().await?.to_vec()));
+        }
+        let body: Value = res.json().await?;
+        let responses = body["responses"].as_array().cloned().unwrap_or_default();
+
+        let mut out = String::new();
+        let mut table = Table::new(&["label", "status", "took", "hits", "error"]);
+        let mut failed = 0;
+        for (search, res) in searches.iter().zip(responses.iter()) {
+            let status = res["status"].as_u64().unwrap_or(200);
+            if status >= 400 || res.get("error").is_some() {
+                failed += 1;
+                eprintln!("Search '{}' failed: {}", search.label, res["error"]);
+            }
+            match &self.output_dir {
+                Some(dir) => {
+                    let file = dir.join(format!("{}.json", search.label));
+                    let pretty = serde_json::to_vec_pretty(res)
+                        .map_err(|e| IoError::new(IoErrorKind::InvalidData, e))?;
+                    tokio::fs::write(&file, pretty).await.map_err(|e| {
+                        eprintln!("Failed to write {:?}: {}", file, e);
+                        e
+                    })?;
+                }
+                None if self.summary => table.add_row(summary_row(&search.label, status, res)),
+                None => {
+                    out.push_str(&format!("### {} ({})\n{}\n", search.label, status, res));
+                }
+            }
+        }
+
+        if self.summary {
+            out = table.render();
+        }
+        if failed > 0 {
+            eprintln!("{} of {} searches failed", failed, searches.len());
+            print!("{out}");
+            return Ok(response(400, Vec::new()));
+        }
+        Ok(response(200, out.into_bytes()))
+    }
+
+    /// Reads every search body from `--query` files and the `--dir` directory.
+    async fn collect_searches(&self) -> Result<Vec<Search>, IoError> {
+        let mut files: Vec<(Option<String>, PathBuf)> = self
+            .query
+            .iter()
+            .map(|q| match q.split_once('=') {
+                Some((index, file)) => (Some(index.to_string()), PathBuf::from(file)),
+                None => (None, PathBuf::from(q)),
+            })
+            .collect();
+
+        if let Some(dir) = &self.dir {
+            let mut entries = tokio::fs::read_dir(dir).await.map_err(|e| {
+                eprintln!("Failed to read directory {:?}: {}", dir, e);
+                e
+            })?;
+            let mut dir_files = Vec::new();
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    dir_files.push(path);
+                }
+            }
+            dir_files.sort();
+            files.extend(dir_files.into_iter().map(|p| (None, p)));
+        }
+
+        let mut labels = HashSet::new();
+        let mut searches = Vec::with_capacity(files.len());
+        for (index, file) in files {
+            let body = read_json_file(&file).await?;
+            searches.push(Search {
+                label: unique_label(&file, &mut labels),
+                index,
+                body,
+            });
+        }
+        Ok(searches)
+    }
+}
+
+/// Labels a search after its file stem, de-duplicating with a numeric suffix.
+fn unique_label(file: &Path, seen: &mut HashSet<String>) -> String {
+    let stem = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "query".to_string());
+    let mut label = stem.clone();
+    let mut n = 2;
+    while !seen.insert(label.clone()) {
+        label = format!("{stem}-{n}");
+        n += 1;
+    }
+    label
+}
+
+/// The summary of a response: its label, status, duration, total hits and
+/// the reason of its error.
+fn summary_row(label: &str, status: u64, res: &Value) -> Vec<String> {
+    let total = &res["hits"]["total"];
+    let hits = match (total["value"].as_u64(), total["relation"].as_str()) {
+        (Some(value), Some("gte")) => format!("{value}+"),
+        (Some(value), _) => value.to_string(),
+        (None, _) => total.as_u64().map_or("-".to_string(), |v| v.to_string()),
+    };
+    vec![
+        label.to_string(),
+        status.to_string(),
+        res["took"]
+            .as_u64()
+            .map_or("-".to_string(), |ms| format!("{ms}ms")),
+        hits,
+        res["error"]["reason"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    ]
+}
+
+/// Builds the `_msearch` NDJSON payload: a header line and a body line per search.
+fn build_payload(searches: &[Search]) -> String {
+    let mut payload = String::new();
+    for search in searches {
+        let header = match &search.index {
+            Some(index) => json!({ "index": index }),
+            None => json!({}),
+        };
+        payload.push_str(&header.to_string());
+        payload.push('\n');
+        payload.push_str(&search.body.to_string());
+        payload.push('\n');
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_payload_writes_header_and_body_lines() {
+        let searches = vec![
+            Search {
+                label: "a".into(),
+                index: None,
+                body: json!({"size": 0}),
+            },
+            Search {
+                label: "b".into(),
+                index: Some("logs".into()),
+                body: json!({"query": {"match_all": {}}}),
+            },
+        ];
+        assert_eq!(
+            build_payload(&searches),
+            "{}\n{\"size\":0}\n{\"index\":\"logs\"}\n{\"query\":{\"match_all\":{}}}\n"
+        );
+    }
+
+    #[test]
+    fn summary_row_reports_hits_and_errors() {
+        let ok = json!({"took": 5, "status": 200, "hits": {"total": {"value": 10000, "relation": "gte"}}});
+        assert_eq!(
+            summary_row("errors", 200, &ok),
+            vec!["errors", "200", "5ms", "10000+", ""]
+        );
+        let failed = json!({"status": 400, "error": {"reason": "unknown field"}});
+        assert_eq!(
+            summary_row("bad", 400, &failed),
+            vec!["bad", "400", "-", "-", "unknown field"]
+        );
+    }
+
+    #[test]
+    fn unique_label_deduplicates() {
+        let mut seen = HashSet::new();
+        assert_eq!(
+            unique_label(Path::new("q/errors.json"), &mut seen),
+            "errors"
+        );
+        assert_eq!(
+            unique_label(Path::new("other/errors.json"), &mut seen),
+            "errors-2"
+        );
+        assert_eq!(
+            unique_label(Path::new("errors.json"), &mut seen),
+            "errors-3"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json};
+use crate::units::parse_duration;
+use crate::wait_for_health::HealthStatus;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Ping {
+    #[arg(
+        long,
+        value_enum,
+        help = "Also require the cluster health to be at least this status"
+    )]
+    require_status: Option<HealthStatus>,
+
+    #[arg(
+        long,
+        help = "Time to wait for the response",
+        default_value = "5s",
+        value_parser = parse_duration
+    )]
+    timeout: Duration,
+}
+
+impl Ping {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("ping")
+            .about("Check that the cluster answers, for health probes.")
+            .long_about(
+                r#"
+            Send a single request to the cluster and report the result through
+            the exit code only: 0 when the cluster answered in time, 1
+            otherwise. Nothing is printed, which makes it suitable for Docker
+            HEALTHCHECK instructions and Kubernetes probes.
+
+            Without --require-status a HEAD request is sent to the root
+            endpoint. With it, _cluster/health is queried and the cluster must
+            be at least at the given status.
+
+            Example usage:
+                escli utils ping
+                escli utils ping --require-status green --timeout 2s
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        _timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let (method, path) = match self.require_status {
+            Some(_) => (Method::Get, "/_cluster/health"),
+            None => (Method::Head, "/"),
+        };
+        let healthy = match send_json(&transport, method, path, &[], None, self.timeout).await {
+            Ok((status, health)) if status.is_success() => match self.require_status {
+                Some(required) => HealthStatus::from_health(&health).is_some_and(|s| s >= required),
+                None => true,
+            },
+            _ => false,
+        };
+        Ok(response(if healthy { 200 } else { 503 }, Vec::new()))
+    }
+}
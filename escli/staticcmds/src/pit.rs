@@ -0,0 +1,113 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json};
+use clap::{Command, CommandFactory, Parser, Subcommand};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::json;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Pit {
+    #[command(subcommand)]
+    action: PitAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum PitAction {
+    #[command(about = "Open a point in time and print its id")]
+    Open {
+        #[arg(
+            required = true,
+            value_delimiter = ',',
+            help = "List of indices to open the point in time on, comma separated"
+        )]
+        indices: Vec<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "How long the point in time is kept alive, default is 1 minute",
+            default_value = "1m"
+        )]
+        keep_alive: String,
+    },
+    #[command(about = "Close a point in time")]
+    Close {
+        #[arg(help = "Id of the point in time to close")]
+        id: String,
+    },
+}
+
+impl Pit {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("pit")
+            .about("Open and close point-in-time sessions.")
+            .long_about(
+                r#"
+            Manage point-in-time (PIT) sessions from shell scripts.
+
+            `pit open` prints only the PIT id so that it can be captured in a
+            variable, then passed to search commands with --pit to paginate
+            over a consistent view of the data. `pit close` releases it.
+
+            Example usage:
+                PIT=$(escli utils pit open my-index --keep-alive 10m)
+                escli search --pit "$PIT" <<< '{"size": 100, "sort": ["_shard_doc"]}'
+                escli utils pit close "$PIT"
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+
+        match self.action {
+            PitAction::Open {
+                indices,
+                keep_alive,
+            } => {
+                let path = format!("/{}/_pit", indices.join(","));
+                let query = [("keep_alive", keep_alive.as_str())];
+                let (status, body) =
+                    send_json(&transport, Method::Post, &path, &query, None, t).await?;
+                match body["id"].as_str() {
+                    Some(id) if status.is_success() => {
+                        Ok(response(200, format!("{id}\n").into_bytes()))
+                    }
+                    _ => Ok(response(
+                        status.as_u16().max(400),
+                        body.to_string().into_bytes(),
+                    )),
+                }
+            }
+            PitAction::Close { id } => {
+                let body = json!({ "id": id });
+                let (status, body) =
+                    send_json(&transport, Method::Delete, "/_pit", &[], Some(&body), t).await?;
+                Ok(response(status.as_u16(), body.to_string().into_bytes()))
+            }
+        }
+    }
+}
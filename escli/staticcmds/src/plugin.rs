@@ -0,0 +1,114 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::PathBuf;
+use std::process::Command as ProcessCommand;
+
+/// Prefix of the executables providing external subcommands.
+const PLUGIN_PREFIX: &str = "escli-";
+
+/// Runs the `escli-<name>` executable found on PATH with the remaining
+/// arguments, forwarding the connection settings as `ESCLI_*` environment
+/// variables, and returns its exit code.
+pub fn run_plugin(name: &str, args: &[OsString], env: &[(&str, String)]) -> Result<i32, IoError> {
+    let Some(path) = find_plugin(name) else {
+        return Err(IoError::new(
+            IoErrorKind::NotFound,
+            format!("no {PLUGIN_PREFIX}{name} executable found on PATH"),
+        ));
+    };
+    let status = ProcessCommand::new(&path)
+        .args(args)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .status()
+        .map_err(|e| IoError::new(e.kind(), format!("failed to run {}: {e}", path.display())))?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Names of the plugins found on PATH, without the `escli-` prefix.
+pub fn list_plugins() -> Vec<String> {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut names = BTreeSet::new();
+    for dir in std::env::split_paths(&paths) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(name) = plugin_name(&file_name) else {
+                continue;
+            };
+            if is_executable(&entry.path()) {
+                names.insert(name.to_string());
+            }
+        }
+    }
+    names.into_iter().collect()
+}
+
+fn find_plugin(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    let file_name = format!("{PLUGIN_PREFIX}{name}{}", std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| is_executable(path))
+}
+
+/// The plugin name of an executable file name, if it is one.
+fn plugin_name(file_name: &str) -> Option<&str> {
+    let name = file_name.strip_prefix(PLUGIN_PREFIX)?;
+    let name = name
+        .strip_suffix(std::env::consts::EXE_SUFFIX)
+        .unwrap_or(name);
+    // Skip editor backups and the like, plugin names are plain words.
+    match name.is_empty() || name.contains('.') || name.ends_with('~') {
+        true => None,
+        false => Some(name),
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_name_strips_prefix() {
+        assert_eq!(
+            plugin_name(&format!("escli-audit{}", std::env::consts::EXE_SUFFIX)),
+            Some("audit")
+        );
+        assert_eq!(plugin_name("escli-"), None);
+        assert_eq!(plugin_name("escli-audit.bak"), None);
+        assert_eq!(plugin_name("cargo-audit"), None);
+    }
+}
@@ -0,0 +1,120 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::send_json;
+use elasticsearch::http::Method;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+/// Privileges an endpoint requires, as listed in the Elasticsearch specification.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequiredPrivileges {
+    pub cluster: Vec<String>,
+    pub index: Vec<String>,
+    /// Index targets the index privileges are checked against.
+    pub indices: Vec<String>,
+}
+
+/// Checks the required privileges of the current user with
+/// `_security/user/_has_privileges`.
+///
+/// Returns an explanation of the missing privileges, or `None` when they are
+/// all granted. If the check itself cannot be performed, for example because
+/// security is disabled, a warning is printed and `None` is returned.
+pub async fn check_privileges(
+    transport: &Transport,
+    required: &RequiredPrivileges,
+    timeout: Option<Duration>,
+) -> Result<Option<String>, elasticsearch::Error> {
+    let t = timeout.unwrap_or(Duration::from_secs(60));
+
+    let mut body = json!({ "cluster": required.cluster });
+    if !required.index.is_empty() {
+        body["index"] = json!([{ "names": required.indices, "privileges": required.index }]);
+    }
+    let (status, value) = send_json(
+        transport,
+        Method::Post,
+        "/_security/user/_has_privileges",
+        &[],
+        Some(&body),
+        t,
+    )
+    .await?;
+    if !status.is_success() {
+        eprintln!("Skipping privilege check, request failed with status {status} - {value}");
+        return Ok(None);
+    }
+    Ok(explain(&value))
+}
+
+/// Lists the privileges reported as not granted by `_has_privileges`.
+fn explain(response: &Value) -> Option<String> {
+    if response["has_all_requested"].as_bool() != Some(false) {
+        return None;
+    }
+    let denied = |privileges: &Value| -> Vec<String> {
+        privileges
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, granted)| granted.as_bool() == Some(false))
+            .map(|(name, _)| name.clone())
+            .collect()
+    };
+
+    let mut out = format!(
+        "User '{}' is missing privileges required by this command:\n",
+        response["username"].as_str().unwrap_or("unknown")
+    );
+    let cluster = denied(&response["cluster"]);
+    if !cluster.is_empty() {
+        out.push_str(&format!("  cluster: {}\n", cluster.join(", ")));
+    }
+    for (index, privileges) in response["index"].as_object().into_iter().flatten() {
+        let missing = denied(privileges);
+        if !missing.is_empty() {
+            out.push_str(&format!("  index '{index}': {}\n", missing.join(", ")));
+        }
+    }
+    Some(out.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explain_lists_denied_privileges() {
+        let response = json!({
+            "username": "bob",
+            "has_all_requested": false,
+            "cluster": {"monitor": true, "manage": false},
+            "index": {"logs-*": {"read": true, "write": false}}
+        });
+        assert_eq!(
+            explain(&response).unwrap(),
+            "User 'bob' is missing privileges required by this command:\n  cluster: manage\n  index 'logs-*': write"
+        );
+    }
+
+    #[test]
+    fn explain_returns_none_when_all_granted() {
+        assert_eq!(explain(&json!({"has_all_requested": true})), None);
+    }
+}
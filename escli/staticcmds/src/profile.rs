@@ -0,0 +1,240 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use elasticsearch::auth::Credentials;
+use elasticsearch::cert::CertificateValidation;
+use elasticsearch::http::Url;
+use elasticsearch::http::transport::{SingleNodeConnectionPool, Transport, TransportBuilder};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::PathBuf;
+
+/// Connection settings of a named cluster.
+///
+/// A profile is an env file named `<name>.env` in the profiles directory,
+/// holding the same `ESCLI_*` variables as a `.env` file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub url: Option<String>,
+    pub api_key: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub insecure: bool,
+}
+
+impl Profile {
+    /// Loads a profile from the profiles directory.
+    pub fn load(name: &str) -> Result<Self, IoError> {
+        let path = profile_path(name);
+        if !path.is_file() {
+            return Err(IoError::new(
+                IoErrorKind::NotFound,
+                format!("profile '{name}' not found, expected {}", path.display()),
+            ));
+        }
+        let mut profile = Profile {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let entries = dotenv::from_path_iter(&path)
+            .map_err(|e| IoError::new(IoErrorKind::InvalidData, e.to_string()))?;
+        for entry in entries {
+            let (key, value) =
+                entry.map_err(|e| IoError::new(IoErrorKind::InvalidData, e.to_string()))?;
+            match key.as_str() {
+                "ESCLI_URL" => profile.url = Some(value),
+                "ESCLI_API_KEY" => profile.api_key = Some(value),
+                "ESCLI_USERNAME" => profile.username = Some(value),
+                "ESCLI_PASSWORD" => profile.password = Some(value),
+                "ESCLI_INSECURE" => profile.insecure = value == "true",
+                _ => {}
+            }
+        }
+        Ok(profile)
+    }
+
+    /// Writes the profile back to its env file. Other variables and comments
+    /// of the file are kept in place.
+    pub fn save(&self) -> Result<(), IoError> {
+        let path = profile_path(&self.name);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == IoErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let vars = [
+            ("ESCLI_URL", self.url.clone()),
+            ("ESCLI_API_KEY", self.api_key.clone()),
+            ("ESCLI_USERNAME", self.username.clone()),
+            ("ESCLI_PASSWORD", self.password.clone()),
+            ("ESCLI_INSECURE", self.insecure.then(|| "true".to_string())),
+        ];
+        std::fs::write(&path, update_env(&text, &vars))
+    }
+
+    /// Builds a transport connected and authenticated to the profile's cluster.
+    pub fn transport(&self) -> Result<Transport, String> {
+        let url = self
+            .url
+            .as_deref()
+            .ok_or_else(|| format!("profile '{}' has no ESCLI_URL", self.name))?;
+        let url = Url::parse(url)
+            .map_err(|e| format!("profile '{}' has an invalid ESCLI_URL: {e}", self.name))?;
+        let mut builder = TransportBuilder::new(SingleNodeConnectionPool::new(url));
+        if self.insecure {
+            builder = builder.cert_validation(CertificateValidation::None);
+        }
+        match (&self.api_key, &self.username, &self.password) {
+            (Some(api_key), None, None) => {
+                builder = builder.auth(Credentials::EncodedApiKey(api_key.clone()));
+            }
+            (None, Some(username), Some(password)) => {
+                builder = builder.auth(Credentials::Basic(username.clone(), password.clone()));
+            }
+            (None, None, None) => {}
+            _ => {
+                return Err(format!(
+                    "profile '{}' must set either ESCLI_API_KEY or both ESCLI_USERNAME and ESCLI_PASSWORD",
+                    self.name
+                ));
+            }
+        }
+        builder.build().map_err(|e| e.to_string())
+    }
+}
+
+/// Sets the variables of an env file, replacing their current line or
+/// appending one, and removes those set to `None`.
+fn update_env(text: &str, vars: &[(&str, Option<String>)]) -> String {
+    let mut out = String::new();
+    let mut written = Vec::new();
+    for line in text.lines() {
+        let key = line
+            .trim_start()
+            .trim_start_matches("export ")
+            .split_once('=')
+            .map(|(key, _)| key.trim());
+        match vars.iter().find(|(name, _)| Some(*name) == key) {
+            Some((name, value)) => {
+                if let (Some(value), false) = (value, written.contains(name)) {
+                    out.push_str(&format!("{name}={value}\n"));
+                }
+                written.push(*name);
+            }
+            None => out.push_str(&format!("{line}\n")),
+        }
+    }
+    for (name, value) in vars {
+        if let (Some(value), false) = (value, written.contains(name)) {
+            out.push_str(&format!("{name}={value}\n"));
+        }
+    }
+    out
+}
+
+/// Loads a profile and builds a transport to its cluster.
+pub(crate) fn profile_transport(name: &str) -> Result<Transport, IoError> {
+    Profile::load(name)?
+        .transport()
+        .map_err(|e| IoError::new(IoErrorKind::InvalidInput, e))
+}
+
+/// Directory holding the profiles: `$ESCLI_PROFILES_DIR` when set, otherwise
+/// `escli/profiles` in the user configuration directory.
+pub fn profiles_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("ESCLI_PROFILES_DIR") {
+        return PathBuf::from(dir);
+    }
+    let config_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+    };
+    config_dir
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("escli")
+        .join("profiles")
+}
+
+/// Path of the env file backing a profile.
+pub fn profile_path(name: &str) -> PathBuf {
+    profiles_dir().join(format!("{name}.env"))
+}
+
+/// Names of all the profiles, sorted.
+pub fn list_profiles() -> Result<Vec<String>, IoError> {
+    let dir = profiles_dir();
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(&dir).map_err(|e| {
+        IoError::new(
+            e.kind(),
+            format!("failed to read profiles directory {}: {e}", dir.display()),
+        )
+    })? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "env") {
+            continue;
+        }
+        if let Some(stem) = path.file_stem() {
+            names.push(stem.to_string_lossy().to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transport_rejects_incomplete_credentials() {
+        let profile = Profile {
+            name: "p".into(),
+            url: Some("http://localhost:9200".into()),
+            username: Some("elastic".into()),
+            ..Default::default()
+        };
+        assert!(profile.transport().unwrap_err().contains("ESCLI_PASSWORD"));
+    }
+
+    #[test]
+    fn update_env_replaces_variables_in_place() {
+        let text = "# production\nESCLI_URL=https://prod:9200\nexport ESCLI_API_KEY=old\nOTHER=1\n";
+        let vars = [
+            ("ESCLI_URL", Some("https://prod:9200".to_string())),
+            ("ESCLI_API_KEY", Some("bmV3".to_string())),
+            ("ESCLI_USERNAME", None),
+            ("ESCLI_INSECURE", Some("true".to_string())),
+        ];
+        assert_eq!(
+            update_env(text, &vars),
+            "# production\nESCLI_URL=https://prod:9200\nESCLI_API_KEY=bmV3\nOTHER=1\nESCLI_INSECURE=true\n"
+        );
+    }
+
+    #[test]
+    fn transport_requires_url() {
+        let profile = Profile {
+            name: "p".into(),
+            ..Default::default()
+        };
+        assert!(profile.transport().unwrap_err().contains("ESCLI_URL"));
+    }
+}
@@ -0,0 +1,279 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::confirm;
+use crate::request::{response, send_json, send_json_ok};
+use crate::table::Table;
+use crate::units::{
+    days_from_civil, format_bytes, format_duration, format_timestamp, parse_duration,
+};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Parser, Debug)]
+pub struct Purge {
+    #[arg(short, long, help = "Pattern of the indices to purge, such as logs-*")]
+    pattern: String,
+
+    #[arg(
+        long,
+        help = "Only purge the indices older than this, such as 30d",
+        value_parser = parse_duration
+    )]
+    older_than: Duration,
+
+    #[arg(
+        long,
+        help = "Date format in the index names, such as yyyy.MM.dd, used instead of the creation date"
+    )]
+    name_date_format: Option<String>,
+
+    #[arg(short, long, help = "Delete without asking for confirmation")]
+    yes: bool,
+
+    #[arg(long, help = "Only print the indices that would be deleted")]
+    dry_run: bool,
+}
+
+impl Purge {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("purge")
+            .about("Delete the indices of a pattern older than a given age.")
+            .long_about(
+                r#"
+            Find the indices matching a pattern that are older than a given
+            age, print them and delete them.
+
+            The age of an index is computed from its creation date, or from a
+            date in its name with --name-date-format, where yyyy, MM and dd
+            stand for the year, month and day, which suits indices created
+            ahead of time or restored from a snapshot. Indices whose name does
+            not contain a date in that format are left alone.
+
+            The deletion is confirmed interactively, use --yes to skip the
+            question in scripts or --dry-run to only print the indices.
+
+            Example usage:
+                escli utils purge --pattern 'logs-*' --older-than 30d --dry-run
+                escli utils purge --pattern 'metrics-*' --older-than 90d --name-date-format yyyy.MM.dd --yes
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let path = format!("/_cat/indices/{}", self.pattern);
+        let query = [
+            ("format", "json"),
+            ("h", "index,creation.date,docs.count,store.size"),
+            ("bytes", "b"),
+            ("s", "index"),
+        ];
+        let Some(indices) = send_json_ok(&transport, Method::Get, &path, &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let cutoff = now.saturating_sub(self.older_than.as_secs());
+        let candidates = self.candidates(&indices, cutoff);
+        if candidates.is_empty() {
+            let text = format!(
+                "No index matching {} is older than {}\n",
+                self.pattern,
+                format_duration(self.older_than)
+            );
+            return Ok(response(200, text.into_bytes()));
+        }
+
+        let mut table = Table::new(&["index", "date", "age", "docs.count", "store.size"]);
+        for (index, date) in &candidates {
+            table.add_row(vec![
+                index["index"].as_str().unwrap_or_default().to_string(),
+                format_timestamp(*date),
+                format_duration(Duration::from_secs(now.saturating_sub(*date))),
+                index["docs.count"].as_str().unwrap_or("-").to_string(),
+                index["store.size"]
+                    .as_str()
+                    .and_then(|s| s.parse().ok())
+                    .map_or("-".to_string(), format_bytes),
+            ]);
+        }
+        print!("{}", table.render());
+
+        if self.dry_run {
+            let text = format!("{} indices would be deleted\n", candidates.len());
+            return Ok(response(200, text.into_bytes()));
+        }
+        if !self.yes && !confirm(&format!("Delete {} indices?", candidates.len())) {
+            return Ok(response(400, b"Aborted, nothing was deleted\n".to_vec()));
+        }
+
+        let mut failed = 0;
+        for (index, _) in &candidates {
+            let name = index["index"].as_str().unwrap_or_default();
+            let (status, body) = send_json(
+                &transport,
+                Method::Delete,
+                &format!("/{name}"),
+                &[],
+                None,
+                t,
+            )
+            .await?;
+            if !status.is_success() {
+                eprintln!("Failed to delete {name}: {body}");
+                failed += 1;
+            }
+        }
+        let text = format!("Deleted {} indices\n", candidates.len() - failed);
+        Ok(response(
+            if failed > 0 { 500 } else { 200 },
+            text.into_bytes(),
+        ))
+    }
+
+    /// The `_cat/indices` rows dated before `cutoff`, with their date in
+    /// seconds since the epoch.
+    fn candidates<'a>(&self, indices: &'a Value, cutoff: u64) -> Vec<(&'a Value, u64)> {
+        let mut candidates = Vec::new();
+        for index in indices.as_array().into_iter().flatten() {
+            let date = match &self.name_date_format {
+                Some(format) => index["index"]
+                    .as_str()
+                    .and_then(|name| date_in_name(name, format)),
+                None => index["creation.date"]
+                    .as_str()
+                    .and_then(|d| d.parse::<u64>().ok())
+                    .map(|ms| ms / 1000),
+            };
+            match date {
+                Some(date) if date < cutoff => candidates.push((index, date)),
+                _ => {}
+            }
+        }
+        candidates
+    }
+}
+
+/// Finds a date written in `format` in an index name and returns it in
+/// seconds since the epoch. `yyyy`, `MM` and `dd` stand for the year, month
+/// and day, other characters must match literally.
+fn date_in_name(name: &str, format: &str) -> Option<u64> {
+    (0..name.len())
+        .filter(|i| name.is_char_boundary(*i))
+        .find_map(|i| parse_date(&name[i..], format))
+}
+
+fn parse_date(s: &str, format: &str) -> Option<u64> {
+    let (mut year, mut month, mut day) = (None, 1, 1);
+    let (mut s, mut format) = (s, format);
+    while !format.is_empty() {
+        let (token, width) = match format {
+            f if f.starts_with("yyyy") => ("yyyy", 4),
+            f if f.starts_with("MM") => ("MM", 2),
+            f if f.starts_with("dd") => ("dd", 2),
+            _ => {
+                let c = format.chars().next()?;
+                s = s.strip_prefix(c)?;
+                format = &format[c.len_utf8()..];
+                continue;
+            }
+        };
+        let digits = s.get(..width)?;
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let value: i64 = digits.parse().ok()?;
+        match token {
+            "yyyy" => year = Some(value),
+            "MM" if (1..=12).contains(&value) => month = value,
+            "dd" if (1..=31).contains(&value) => day = value,
+            _ => return None,
+        }
+        s = &s[width..];
+        format = &format[token.len()..];
+    }
+    let days = days_from_civil(year?, month, day);
+    u64::try_from(days).ok().map(|d| d * 86400)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn date_in_name_finds_formatted_date() {
+        assert_eq!(
+            date_in_name("logs-2024.02.29", "yyyy.MM.dd"),
+            Some(1_709_164_800)
+        );
+        assert_eq!(
+            date_in_name("logs-app-2024-02", "yyyy-MM"),
+            Some(1_706_745_600)
+        );
+        assert_eq!(date_in_name("logs-2024.13.01", "yyyy.MM.dd"), None);
+        assert_eq!(date_in_name("logs-current", "yyyy.MM.dd"), None);
+    }
+
+    #[test]
+    fn candidates_compare_dates_with_cutoff() {
+        let indices = json!([
+            {"index": "logs-2024.01.01", "creation.date": "1735689600000"},
+            {"index": "logs-2024.03.01", "creation.date": "1704067200000"},
+        ]);
+        let by_creation =
+            Purge::try_parse_from(["purge", "-p", "logs-*", "--older-than", "1d"]).unwrap();
+        let cutoff = 1_709_164_800;
+        let names: Vec<&Value> = by_creation
+            .candidates(&indices, cutoff)
+            .iter()
+            .map(|(i, _)| &i["index"])
+            .collect();
+        assert_eq!(names, vec!["logs-2024.03.01"]);
+
+        let by_name = Purge::try_parse_from([
+            "purge",
+            "-p",
+            "logs-*",
+            "--older-than",
+            "1d",
+            "--name-date-format",
+            "yyyy.MM.dd",
+        ])
+        .unwrap();
+        let names: Vec<&Value> = by_name
+            .candidates(&indices, cutoff)
+            .iter()
+            .map(|(i, _)| &i["index"])
+            .collect();
+        assert_eq!(names, vec!["logs-2024.01.01"]);
+    }
+}
@@ -0,0 +1,111 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use elasticsearch::http::Method;
+use elasticsearch::http::headers::HeaderMap;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use http::StatusCode;
+use serde_json::Value;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::time::Duration;
+
+/// Sends a request with an optional JSON body and decodes the JSON response.
+///
+/// The status code is returned alongside the body so that callers can decide
+/// how to report a failure. An empty response body decodes to `Value::Null`.
+pub(crate) async fn send_json(
+    transport: &Transport,
+    method: Method,
+    path: &str,
+    query: &[(&str, &str)],
+    body: Option<&Value>,
+    timeout: Duration,
+) -> Result<(StatusCode, Value), elasticsearch::Error> {
+    let response = transport
+        .send(
+            method,
+            path,
+            HeaderMap::new(),
+            Some(&query),
+            body.map(|b| b.to_string()),
+            Some(timeout),
+        )
+        .await?;
+
+    let status = response.status_code();
+    let bytes = response.bytes().await?;
+    if bytes.is_empty() {
+        return Ok((status, Value::Null));
+    }
+    let value = serde_json::from_slice(&bytes).map_err(|e| {
+        eprintln!("Failed to decode response from {}: {}", path, e);
+        IoError::new(IoErrorKind::InvalidData, e)
+    })?;
+    Ok((status, value))
+}
+
+/// Like [`send_json`] but treats any non-2xx status as a failure, printing the
+/// error body on stderr and returning `None`.
+pub(crate) async fn send_json_ok(
+    transport: &Transport,
+    method: Method,
+    path: &str,
+    query: &[(&str, &str)],
+    body: Option<&Value>,
+    timeout: Duration,
+) -> Result<Option<Value>, elasticsearch::Error> {
+    let (status, value) = send_json(transport, method, path, query, body, timeout).await?;
+    if !status.is_success() {
+        eprintln!(
+            "Request to {} failed with status {} - {}",
+            path, status, value
+        );
+        return Ok(None);
+    }
+    Ok(Some(value))
+}
+
+/// Builds the synthetic response returned by static commands to `main`, which
+/// writes the body to stdout for 2xx statuses and to stderr otherwise.
+pub(crate) fn response(status: u16, body: Vec<u8>) -> Response {
+    let hr = http::response::Builder::new()
+        .status(status)
+        .body(body)
+        .unwrap();
+    let rr = reqwest::Response::from(hr);
+    Response::new(rr, Method::Get)
+}
+
+/// Prints the body of a response run outside of the main request flow, on
+/// stdout on success and stderr otherwise, and returns the exit code.
+pub(crate) async fn print_response(result: Result<Response, elasticsearch::Error>) -> i32 {
+    match result {
+        Ok(res) if res.status_code().is_success() => {
+            print!("{}", res.text().await.unwrap_or_default());
+            0
+        }
+        Ok(res) => {
+            eprint!("{}", res.text().await.unwrap_or_default());
+            1
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
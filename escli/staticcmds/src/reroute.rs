@@ -0,0 +1,302 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::confirm;
+use crate::request::{response, send_json, send_json_ok};
+use crate::table::Table;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+const NO_VALID_SHARD_COPY: &str = "no_valid_shard_copy";
+
+#[derive(Parser, Debug)]
+pub struct Reroute {
+    #[arg(
+        short,
+        long,
+        help = "Only consider the unassigned shards of these indices, comma separated"
+    )]
+    index: Option<String>,
+
+    #[arg(
+        long,
+        help = "Also allocate empty primaries for shards without any copy left, losing all their data"
+    )]
+    allow_empty_primary: bool,
+
+    #[arg(
+        long,
+        help = "Apply the reroute commands after asking for confirmation"
+    )]
+    apply: bool,
+
+    #[arg(
+        short,
+        long,
+        requires = "apply",
+        help = "Apply without asking for confirmation"
+    )]
+    yes: bool,
+}
+
+/// What to do about an unassigned shard, from its allocation explanation.
+#[derive(Debug, PartialEq)]
+struct Plan {
+    shard: String,
+    reason: String,
+    decision: String,
+    action: String,
+    command: Option<Value>,
+}
+
+impl Reroute {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("reroute")
+            .about("Generate reroute commands for unassigned primary shards.")
+            .long_about(
+                r#"
+            List the unassigned shards, explain the allocation of each, and
+            generate the _cluster/reroute commands that bring back the
+            primaries without a valid shard copy, for disaster recovery.
+
+            A primary whose in-sync copies are lost but that still has a stale
+            copy on a node is allocated from that copy with
+            allocate_stale_primary, losing the writes it missed. With
+            --allow-empty-primary, primaries without any copy left are
+            allocated empty with allocate_empty_primary, losing all their
+            data. Other shards are left to the cluster, see
+            allocation-explain for why they are unassigned.
+
+            The commands are printed, and with --apply sent once confirmed
+            interactively, use --yes to skip the question in scripts.
+
+            Example usage:
+                escli utils reroute
+                escli utils reroute --index logs-2025.01 --apply
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let path = match &self.index {
+            Some(index) => format!("/_cat/shards/{index}"),
+            None => "/_cat/shards".to_string(),
+        };
+        let query = [("format", "json"), ("h", "index,shard,prirep,state")];
+        let Some(shards) = send_json_ok(&transport, Method::Get, &path, &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let mut plans = Vec::new();
+        for shard in shards.as_array().into_iter().flatten() {
+            if shard["state"] != "UNASSIGNED" {
+                continue;
+            }
+            let body = json!({
+                "index": shard["index"],
+                "shard": shard["shard"].as_str().and_then(|s| s.parse::<u64>().ok()),
+                "primary": shard["prirep"] == "p",
+            });
+            let Some(explain) = send_json_ok(
+                &transport,
+                Method::Post,
+                "/_cluster/allocation/explain",
+                &[],
+                Some(&body),
+                t,
+            )
+            .await?
+            else {
+                continue;
+            };
+            plans.push(self.plan(&explain));
+        }
+        if plans.is_empty() {
+            return Ok(response(200, b"No unassigned shards\n".to_vec()));
+        }
+
+        let mut table = Table::new(&["shard", "reason", "decision", "action"]);
+        for plan in &plans {
+            table.add_row(vec![
+                plan.shard.clone(),
+                plan.reason.clone(),
+                plan.decision.clone(),
+                plan.action.clone(),
+            ]);
+        }
+        print!("{}", table.render());
+
+        let commands: Vec<&Value> = plans.iter().filter_map(|p| p.command.as_ref()).collect();
+        let mut summary = format!(
+            "{} unassigned shards, {} reroute commands\n",
+            plans.len(),
+            commands.len()
+        );
+        if commands.is_empty() {
+            return Ok(response(200, summary.into_bytes()));
+        }
+        let reroute = json!({ "commands": commands });
+        println!(
+            "\nPOST _cluster/reroute\n{}",
+            serde_json::to_string_pretty(&reroute).unwrap_or_default()
+        );
+        if !self.apply {
+            return Ok(response(200, summary.into_bytes()));
+        }
+        let question = format!(
+            "Apply {} reroute commands, losing the data missing from their copies?",
+            commands.len()
+        );
+        if !self.yes && !confirm(&question) {
+            return Ok(response(400, b"Aborted, nothing was rerouted\n".to_vec()));
+        }
+
+        let (status, body) = send_json(
+            &transport,
+            Method::Post,
+            "/_cluster/reroute",
+            &[],
+            Some(&reroute),
+            t,
+        )
+        .await?;
+        if !status.is_success() {
+            return Ok(response(status.as_u16(), body.to_string().into_bytes()));
+        }
+        summary.push_str(&format!("Applied {} reroute commands\n", commands.len()));
+        Ok(response(200, summary.into_bytes()))
+    }
+
+    /// The action for the shard of an allocation explain response. Only
+    /// primaries without a valid shard copy get a reroute command.
+    fn plan(&self, explain: &Value) -> Plan {
+        let index = explain["index"].as_str().unwrap_or_default();
+        let number = explain["shard"].as_u64().unwrap_or_default();
+        let primary = explain["primary"] == true;
+        let decision = explain["can_allocate"].as_str().unwrap_or("-");
+        let mut plan = Plan {
+            shard: format!("{index}[{number}]{}", if primary { "p" } else { "r" }),
+            reason: explain["unassigned_info"]["reason"]
+                .as_str()
+                .unwrap_or("-")
+                .to_string(),
+            decision: decision.to_string(),
+            action: "-".to_string(),
+            command: None,
+        };
+        if !primary || decision != NO_VALID_SHARD_COPY {
+            return plan;
+        }
+
+        let nodes: Vec<&Value> = explain["node_allocation_decisions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .collect();
+        let stale = nodes
+            .iter()
+            .find(|n| n["store"]["allocation_id"].is_string());
+        let (kind, node) = match (stale, nodes.first()) {
+            (Some(node), _) => ("allocate_stale_primary", node),
+            (None, Some(node)) if self.allow_empty_primary => ("allocate_empty_primary", node),
+            (None, Some(_)) => {
+                plan.action = "no copy left, see --allow-empty-primary".to_string();
+                return plan;
+            }
+            (None, None) => return plan,
+        };
+        let name = node["node_name"].as_str().unwrap_or_default();
+        plan.action = format!("{kind} on {name}");
+        plan.command = Some(json!({ kind: {
+            "index": index,
+            "shard": number,
+            "node": name,
+            "accept_data_loss": true,
+        }}));
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain(index: &str, primary: bool, decision: &str, nodes: Value) -> Value {
+        json!({
+            "index": index,
+            "shard": 0,
+            "primary": primary,
+            "can_allocate": decision,
+            "unassigned_info": {"reason": "NODE_LEFT"},
+            "node_allocation_decisions": nodes,
+        })
+    }
+
+    #[test]
+    fn plan_allocates_stale_copies() {
+        let reroute = Reroute::try_parse_from(["reroute"]).unwrap();
+        let nodes = json!([
+            {"node_name": "n1", "store": {"found": false}},
+            {"node_name": "n2", "store": {"in_sync": false, "allocation_id": "abc"}},
+        ]);
+        let plan = reroute.plan(&explain("logs", true, NO_VALID_SHARD_COPY, nodes));
+        assert_eq!(plan.shard, "logs[0]p");
+        assert_eq!(plan.action, "allocate_stale_primary on n2");
+        assert_eq!(
+            plan.command,
+            Some(json!({"allocate_stale_primary": {
+                "index": "logs", "shard": 0, "node": "n2", "accept_data_loss": true
+            }}))
+        );
+
+        let replica = reroute.plan(&explain("logs", false, "no", json!([])));
+        assert_eq!(
+            (replica.shard.as_str(), replica.command),
+            ("logs[0]r", None)
+        );
+    }
+
+    #[test]
+    fn plan_allocates_empty_primary_only_when_allowed() {
+        let nodes = json!([{"node_name": "n1", "store": {"found": false}}]);
+        let lost = explain("logs", true, NO_VALID_SHARD_COPY, nodes);
+
+        let reroute = Reroute::try_parse_from(["reroute"]).unwrap();
+        let plan = reroute.plan(&lost);
+        assert_eq!(plan.action, "no copy left, see --allow-empty-primary");
+        assert_eq!(plan.command, None);
+
+        let reroute = Reroute::try_parse_from(["reroute", "--allow-empty-primary"]).unwrap();
+        let plan = reroute.plan(&lost);
+        assert_eq!(plan.action, "allocate_empty_primary on n1");
+        assert_eq!(
+            plan.command.unwrap()["allocate_empty_primary"]["node"],
+            "n1"
+        );
+    }
+}
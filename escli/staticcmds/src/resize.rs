@@ -0,0 +1,310 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json};
+use crate::units::{format_duration, parse_duration};
+use clap::{Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+pub struct Resize {
+    #[arg(help = "Index to resize")]
+    source: String,
+
+    #[arg(help = "Name of the new index")]
+    target: String,
+
+    #[arg(short, long, value_enum, help = "Kind of resize")]
+    mode: ResizeMode,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of primary shards of the new index, required to shrink or split"
+    )]
+    shards: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Node to gather a copy of every shard on before shrinking, if not done already"
+    )]
+    node: Option<String>,
+
+    #[arg(long, help = "Move this alias from the source to the new index")]
+    alias: Option<String>,
+
+    #[arg(
+        long,
+        help = "How long to wait for the new index to be green",
+        default_value = "30m",
+        value_parser = parse_duration
+    )]
+    wait_timeout: Duration,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ResizeMode {
+    Clone,
+    Shrink,
+    Split,
+}
+
+impl ResizeMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            ResizeMode::Clone => "clone",
+            ResizeMode::Shrink => "shrink",
+            ResizeMode::Split => "split",
+        }
+    }
+}
+
+/// Why the resize stopped: a step failed or a request could not be sent.
+enum StepError {
+    Failed(String),
+    Request(elasticsearch::Error),
+}
+
+impl From<elasticsearch::Error> for StepError {
+    fn from(e: elasticsearch::Error) -> Self {
+        StepError::Request(e)
+    }
+}
+
+impl Resize {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("resize")
+            .about("Clone, shrink or split an index in one go.")
+            .long_about(
+                r#"
+            Run every step of a clone, shrink or split in order:
+
+              1. with --node, move a copy of every shard to that node, as
+                 shrinking requires, and wait for the relocations
+              2. block writes on the source index
+              3. clone, shrink or split it into the target index
+              4. wait for the target index to be green
+              5. with --alias, move the alias to the target index atomically
+              6. unblock writes on the source index
+
+            The write block and the allocation filter are removed from the
+            source index even when a step fails, and are never copied to the
+            target index.
+
+            Example usage:
+                escli utils resize logs-1 logs-1-shrunk --mode shrink --shards 1 --node es-data-1
+                escli utils resize orders orders-split --mode split --shards 12 --alias orders-current
+                escli utils resize products products-copy --mode clone
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        if !matches!(self.mode, ResizeMode::Clone) && self.shards.is_none() {
+            eprintln!("--shards is required to {}", self.mode.as_str());
+            return Ok(response(400, Vec::new()));
+        }
+
+        let result = self.run(&transport, t).await;
+
+        eprintln!("Unblocking writes on {}", self.source);
+        let mut settings = json!({"index.blocks.write": null});
+        if self.node.is_some() {
+            settings["index.routing.allocation.require._name"] = Value::Null;
+        }
+        let path = format!("/{}/_settings", self.source);
+        let (status, body) =
+            send_json(&transport, Method::Put, &path, &[], Some(&settings), t).await?;
+        if !status.is_success() {
+            eprintln!("Failed to unblock writes on {}: {}", self.source, body);
+        }
+
+        match result {
+            Ok(()) => {
+                let text = format!(
+                    "{} {} into {}\n",
+                    match self.mode {
+                        ResizeMode::Clone => "Cloned",
+                        ResizeMode::Shrink => "Shrunk",
+                        ResizeMode::Split => "Split",
+                    },
+                    self.source,
+                    self.target
+                );
+                Ok(response(200, text.into_bytes()))
+            }
+            Err(StepError::Failed(message)) => {
+                Ok(response(500, format!("{message}\n").into_bytes()))
+            }
+            Err(StepError::Request(e)) => Err(e),
+        }
+    }
+
+    async fn run(&self, transport: &Transport, t: Duration) -> Result<(), StepError> {
+        let settings_path = format!("/{}/_settings", self.source);
+
+        if let Some(node) = &self.node {
+            eprintln!(
+                "Moving a copy of every shard of {} to {}",
+                self.source, node
+            );
+            let settings = json!({"index.routing.allocation.require._name": node});
+            step(
+                transport,
+                Method::Put,
+                &settings_path,
+                &[],
+                Some(&settings),
+                t,
+            )
+            .await?;
+            let path = format!("/_cluster/health/{}", self.source);
+            let query = [("wait_for_no_relocating_shards", "true")];
+            self.wait(transport, &path, &query, t).await?;
+        }
+
+        eprintln!("Blocking writes on {}", self.source);
+        let settings = json!({"index.blocks.write": true});
+        step(
+            transport,
+            Method::Put,
+            &settings_path,
+            &[],
+            Some(&settings),
+            t,
+        )
+        .await?;
+
+        eprintln!(
+            "Running {} of {} into {}",
+            self.mode.as_str(),
+            self.source,
+            self.target
+        );
+        let path = format!("/{}/_{}/{}", self.source, self.mode.as_str(), self.target);
+        let body = self.resize_body();
+        step(transport, Method::Post, &path, &[], Some(&body), t).await?;
+
+        eprintln!("Waiting for {} to be green", self.target);
+        let path = format!("/_cluster/health/{}", self.target);
+        let query = [("wait_for_status", "green")];
+        self.wait(transport, &path, &query, t).await?;
+
+        if let Some(alias) = &self.alias {
+            eprintln!("Moving alias {} to {}", alias, self.target);
+            let actions = json!({"actions": [
+                {"remove": {"index": self.source, "alias": alias}},
+                {"add": {"index": self.target, "alias": alias}},
+            ]});
+            step(transport, Method::Post, "/_aliases", &[], Some(&actions), t).await?;
+        }
+        Ok(())
+    }
+
+    /// Settings of the target index, resetting the ones set on the source
+    /// for the duration of the resize.
+    fn resize_body(&self) -> Value {
+        let mut settings = json!({
+            "index.blocks.write": null,
+            "index.routing.allocation.require._name": null,
+        });
+        if let Some(shards) = self.shards {
+            settings["index.number_of_shards"] = json!(shards);
+        }
+        json!({ "settings": settings })
+    }
+
+    /// Polls a cluster health endpoint until its condition is met or
+    /// --wait-timeout expires.
+    async fn wait(
+        &self,
+        transport: &Transport,
+        path: &str,
+        query: &[(&str, &str)],
+        t: Duration,
+    ) -> Result<(), StepError> {
+        let start = Instant::now();
+        let wait = format!("{}s", (t / 2).min(Duration::from_secs(30)).as_secs().max(1));
+        let mut query = query.to_vec();
+        query.push(("timeout", wait.as_str()));
+        loop {
+            let (status, health) = send_json(transport, Method::Get, path, &query, None, t).await?;
+            // Cluster health answers 408 when its own timeout expires first.
+            if status.is_success() && health["timed_out"] == false {
+                return Ok(());
+            }
+            if health["timed_out"] == true && start.elapsed() < self.wait_timeout {
+                continue;
+            }
+            return Err(StepError::Failed(format!(
+                "Gave up waiting on {} after {}, status {}: {}",
+                path,
+                format_duration(start.elapsed()),
+                status,
+                health
+            )));
+        }
+    }
+}
+
+/// Sends the request of a step, turning a non-2xx status into a failure.
+async fn step(
+    transport: &Transport,
+    method: Method,
+    path: &str,
+    query: &[(&str, &str)],
+    body: Option<&Value>,
+    t: Duration,
+) -> Result<(), StepError> {
+    let (status, res) = send_json(transport, method, path, query, body, t).await?;
+    match status.is_success() {
+        true => Ok(()),
+        false => Err(StepError::Failed(format!(
+            "Request to {path} failed with status {status} - {res}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_body_resets_source_settings() {
+        let resize =
+            Resize::try_parse_from(["resize", "a", "b", "--mode", "shrink", "--shards", "1"])
+                .unwrap();
+        assert_eq!(
+            resize.resize_body(),
+            json!({"settings": {
+                "index.blocks.write": null,
+                "index.routing.allocation.require._name": null,
+                "index.number_of_shards": 1,
+            }})
+        );
+    }
+}
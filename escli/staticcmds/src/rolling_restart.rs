@@ -0,0 +1,279 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::confirm;
+use crate::request::{response, send_json, send_json_ok};
+use crate::units::{format_duration, parse_duration};
+use crate::wait_for_health::HealthStatus;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+
+const ALLOCATION_ENABLE: &str = "cluster.routing.allocation.enable";
+
+#[derive(Parser, Debug)]
+pub struct RollingRestart {
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Names of the nodes to restart, comma separated, defaults to all nodes"
+    )]
+    nodes: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Go on with each node without asking, for restarts driven by another tool"
+    )]
+    yes: bool,
+
+    #[arg(
+        long,
+        help = "How long to wait for a node to leave or rejoin the cluster, and for the cluster to turn green",
+        default_value = "30m",
+        value_parser = parse_duration
+    )]
+    wait_timeout: Duration,
+
+    #[arg(
+        long,
+        help = "Time between two checks of the nodes and of the cluster health",
+        default_value = "5s",
+        value_parser = parse_duration
+    )]
+    interval: Duration,
+}
+
+impl RollingRestart {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("rolling-restart")
+            .about("Walk through the rolling restart of the nodes of a cluster.")
+            .long_about(
+                r#"
+            Guide a rolling restart, one node at a time, following the
+            documented steps: restrict shard allocation to primaries, flush,
+            wait for the operator to stop the node and for it to rejoin the
+            cluster once restarted, enable allocation again and wait for the
+            cluster to turn green before moving to the next node.
+
+            Before each node, the command asks whether to go on, which gives
+            the operator time to check the cluster, use --yes when the nodes
+            are restarted by another tool. The elected master is restarted
+            last. The command stops at the first node that does not leave or
+            rejoin the cluster within --wait-timeout, leaving allocation
+            restricted to primaries.
+
+            Example usage:
+                escli utils rolling-restart
+                escli utils rolling-restart --nodes es-data-1,es-data-2 --wait-timeout 1h
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let query = [("format", "json"), ("h", "name,master")];
+        let Some(nodes) =
+            send_json_ok(&transport, Method::Get, "/_cat/nodes", &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let order = match restart_order(&nodes, &self.nodes) {
+            Ok(order) => order,
+            Err(message) => return Ok(response(400, format!("{message}\n").into_bytes())),
+        };
+
+        for (i, node) in order.iter().enumerate() {
+            eprintln!("Node {node} ({}/{})", i + 1, order.len());
+            if !self.yes && !confirm(&format!("Restart {node}?")) {
+                let text = format!("Aborted, restarted {i} of {} nodes\n", order.len());
+                return Ok(response(400, text.into_bytes()));
+            }
+
+            eprintln!("  restricting allocation to primaries");
+            if let Some(failure) = set_allocation(&transport, json!("primaries"), t).await? {
+                return Ok(failure);
+            }
+            eprintln!("  flushing");
+            let (status, flushed) =
+                send_json(&transport, Method::Post, "/_flush", &[], None, t).await?;
+            if !status.is_success() {
+                eprintln!("  flush failed, going on: {flushed}");
+            }
+
+            eprintln!("  waiting for {node} to be stopped");
+            if !self.wait_for_node(&transport, node, false, t).await? {
+                return Ok(self.timed_out(&format!("{node} to leave the cluster")));
+            }
+            eprintln!("  waiting for {node} to rejoin the cluster");
+            if !self.wait_for_node(&transport, node, true, t).await? {
+                return Ok(self.timed_out(&format!("{node} to rejoin the cluster")));
+            }
+
+            eprintln!("  enabling allocation");
+            if let Some(failure) = set_allocation(&transport, Value::Null, t).await? {
+                return Ok(failure);
+            }
+            eprintln!("  waiting for the cluster to turn green");
+            if !self.wait_for_green(&transport, t).await? {
+                return Ok(self.timed_out("the cluster to turn green"));
+            }
+        }
+        let text = format!("Restarted {} nodes\n", order.len());
+        Ok(response(200, text.into_bytes()))
+    }
+
+    /// Polls the nodes of the cluster until `node` is present or absent.
+    /// Returns false after --wait-timeout.
+    async fn wait_for_node(
+        &self,
+        transport: &Transport,
+        node: &str,
+        present: bool,
+        t: Duration,
+    ) -> Result<bool, elasticsearch::Error> {
+        let started = Instant::now();
+        let query = [("format", "json"), ("h", "name")];
+        loop {
+            // The cat API fails while the cluster has no elected master,
+            // which is expected when a master node restarts.
+            if let Some(nodes) =
+                send_json_ok(transport, Method::Get, "/_cat/nodes", &query, None, t).await?
+            {
+                let found = nodes
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|n| n["name"] == node);
+                if found == present {
+                    return Ok(true);
+                }
+            }
+            if started.elapsed() >= self.wait_timeout {
+                return Ok(false);
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    /// Polls the cluster health until it is green. Returns false after
+    /// --wait-timeout.
+    async fn wait_for_green(
+        &self,
+        transport: &Transport,
+        t: Duration,
+    ) -> Result<bool, elasticsearch::Error> {
+        let started = Instant::now();
+        loop {
+            let health =
+                send_json_ok(transport, Method::Get, "/_cluster/health", &[], None, t).await?;
+            if health.as_ref().and_then(HealthStatus::from_health) == Some(HealthStatus::Green) {
+                return Ok(true);
+            }
+            if started.elapsed() >= self.wait_timeout {
+                return Ok(false);
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    fn timed_out(&self, what: &str) -> Response {
+        let text = format!(
+            "Timed out after {} waiting for {what}, allocation is still restricted to primaries\n",
+            format_duration(self.wait_timeout)
+        );
+        response(504, text.into_bytes())
+    }
+}
+
+/// Sets `cluster.routing.allocation.enable`, `null` resets it to all.
+/// Returns the response to exit with when the update failed.
+async fn set_allocation(
+    transport: &Transport,
+    value: Value,
+    t: Duration,
+) -> Result<Option<Response>, elasticsearch::Error> {
+    let body = json!({ "persistent": { ALLOCATION_ENABLE: value } });
+    let (status, updated) = send_json(
+        transport,
+        Method::Put,
+        "/_cluster/settings",
+        &[],
+        Some(&body),
+        t,
+    )
+    .await?;
+    match status.is_success() {
+        true => Ok(None),
+        false => Ok(Some(response(
+            status.as_u16(),
+            updated.to_string().into_bytes(),
+        ))),
+    }
+}
+
+/// The names of the nodes to restart from a `_cat/nodes` response, sorted by
+/// name with the elected master last. `only` restricts them to some nodes.
+fn restart_order(nodes: &Value, only: &[String]) -> Result<Vec<String>, String> {
+    let mut known: Vec<(bool, String)> = nodes
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|n| Some((n["master"] == "*", n["name"].as_str()?.to_string())))
+        .collect();
+    if let Some(missing) = only.iter().find(|o| !known.iter().any(|(_, n)| n == *o)) {
+        return Err(format!("No node named {missing} in the cluster"));
+    }
+    known.retain(|(_, name)| only.is_empty() || only.contains(name));
+    known.sort();
+    Ok(known.into_iter().map(|(_, name)| name).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restart_order_puts_elected_master_last() {
+        let nodes = json!([
+            {"name": "es-2", "master": "-"},
+            {"name": "es-1", "master": "*"},
+            {"name": "es-3", "master": "-"},
+        ]);
+        assert_eq!(
+            restart_order(&nodes, &[]).unwrap(),
+            vec!["es-2", "es-3", "es-1"]
+        );
+        assert_eq!(
+            restart_order(&nodes, &["es-1".to_string(), "es-3".to_string()]).unwrap(),
+            vec!["es-3", "es-1"]
+        );
+        assert_eq!(
+            restart_order(&nodes, &["es-9".to_string()]).unwrap_err(),
+            "No node named es-9 in the cluster"
+        );
+    }
+}
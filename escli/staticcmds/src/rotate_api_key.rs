@@ -0,0 +1,307 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::profile::Profile;
+use crate::request::{response, send_json, send_json_ok};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Parser, Debug)]
+pub struct RotateApiKey {
+    #[arg(
+        long,
+        env = "ESCLI_PROFILE",
+        help = "Profile whose API key is rotated, the new key is saved to it"
+    )]
+    profile: String,
+
+    #[arg(
+        long,
+        help = "Name of the API key to rotate, defaults to the key the profile authenticates with"
+    )]
+    name: Option<String>,
+
+    #[arg(
+        long,
+        help = "Expiration of the new key, such as 90d, defaults to the lifetime of the old key"
+    )]
+    expiration: Option<String>,
+
+    #[arg(long, help = "Do not invalidate the old key")]
+    keep_old: bool,
+}
+
+impl RotateApiKey {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("rotate-api-key")
+            .about("Replace the API key of a profile with a new one and invalidate the old key.")
+            .long_about(
+                r#"
+            Rotate the API key of a profile in one command: create a new key
+            with the same name, role descriptors and metadata as the old one,
+            check that the cluster accepts it, save it to the profile and
+            invalidate the old key.
+
+            The old key is the active key named --name, or the key the
+            profile authenticates with. The new key is created by the profile
+            itself, so it is limited by the same privileges, and keeps the
+            lifetime of the old key unless --expiration is given. When the new
+            key is not accepted, it is invalidated and the profile is left
+            unchanged.
+
+            Example usage:
+                escli utils rotate-api-key --profile prod
+                escli utils rotate-api-key --profile prod --name ci --expiration 90d
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        _transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let profile = Profile::load(&self.profile)?;
+        if profile.api_key.is_none() {
+            let text = format!("Profile '{}' does not use an API key\n", self.profile);
+            return Ok(response(400, text.into_bytes()));
+        }
+        let transport = profile
+            .transport()
+            .map_err(|e| IoError::new(IoErrorKind::InvalidInput, e))?;
+
+        let (key, value) = match &self.name {
+            Some(name) => ("name", name.clone()),
+            None => {
+                let Some(me) = send_json_ok(
+                    &transport,
+                    Method::Get,
+                    "/_security/_authenticate",
+                    &[],
+                    None,
+                    t,
+                )
+                .await?
+                else {
+                    return Ok(response(500, Vec::new()));
+                };
+                let Some(id) = me["api_key"]["id"].as_str() else {
+                    let text = "The profile is not authenticated with an API key, use --name\n";
+                    return Ok(response(400, text.as_bytes().to_vec()));
+                };
+                ("id", id.to_string())
+            }
+        };
+        let query = [(key, value.as_str())];
+        let Some(keys) = send_json_ok(
+            &transport,
+            Method::Get,
+            "/_security/api_key",
+            &query,
+            None,
+            t,
+        )
+        .await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let old = match active_keys(&keys, now).as_slice() {
+            [key] => (*key).clone(),
+            [] => return Ok(response(404, b"No active API key to rotate\n".to_vec())),
+            keys => {
+                let text = format!(
+                    "{} active API keys match, select one with --name\n",
+                    keys.len()
+                );
+                return Ok(response(400, text.into_bytes()));
+            }
+        };
+        let old_id = old["id"].as_str().unwrap_or_default();
+
+        let body = self.new_key_body(&old);
+        let (status, created) = send_json(
+            &transport,
+            Method::Post,
+            "/_security/api_key",
+            &[],
+            Some(&body),
+            t,
+        )
+        .await?;
+        if !status.is_success() {
+            return Ok(response(status.as_u16(), created.to_string().into_bytes()));
+        }
+        let new_id = created["id"].as_str().unwrap_or_default();
+        let mut out = format!("Created API key {new_id} to replace {old_id}\n");
+
+        let rotated = Profile {
+            api_key: created["encoded"].as_str().map(str::to_string),
+            ..profile
+        };
+        if !verify(&rotated, new_id, t).await {
+            invalidate(&transport, new_id, t).await?;
+            out.push_str("The new API key was not accepted, it was invalidated and the profile left unchanged\n");
+            return Ok(response(500, out.into_bytes()));
+        }
+        rotated.save()?;
+        out.push_str(&format!(
+            "Verified and saved the new key to profile '{}'\n",
+            self.profile
+        ));
+
+        if self.keep_old {
+            return Ok(response(200, out.into_bytes()));
+        }
+        if !invalidate(&transport, old_id, t).await? {
+            out.push_str(&format!("Failed to invalidate API key {old_id}\n"));
+            return Ok(response(500, out.into_bytes()));
+        }
+        out.push_str(&format!("Invalidated API key {old_id}\n"));
+        Ok(response(200, out.into_bytes()))
+    }
+
+    /// The create API key request of the key replacing `old`.
+    fn new_key_body(&self, old: &Value) -> Value {
+        let mut body = json!({
+            "name": old["name"],
+            "role_descriptors": old.get("role_descriptors").cloned().unwrap_or(json!({})),
+        });
+        if let Some(metadata) = old.get("metadata") {
+            body["metadata"] = metadata.clone();
+        }
+        let lifetime = match (old["expiration"].as_u64(), old["creation"].as_u64()) {
+            (Some(expiration), Some(creation)) => {
+                Some(format!("{}ms", expiration.saturating_sub(creation)))
+            }
+            _ => None,
+        };
+        if let Some(expiration) = self.expiration.clone().or(lifetime) {
+            body["expiration"] = json!(expiration);
+        }
+        body
+    }
+}
+
+/// The keys of a get API key response that are neither invalidated nor
+/// expired at `now`, in milliseconds since the epoch.
+fn active_keys(keys: &Value, now: u64) -> Vec<&Value> {
+    keys["api_keys"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|k| k["invalidated"] != true)
+        .filter(|k| k["expiration"].as_u64().is_none_or(|e| e > now))
+        .collect()
+}
+
+/// Whether the cluster of a profile authenticates it with the key `id`.
+async fn verify(profile: &Profile, id: &str, t: Duration) -> bool {
+    let Ok(transport) = profile.transport() else {
+        return false;
+    };
+    match send_json_ok(
+        &transport,
+        Method::Get,
+        "/_security/_authenticate",
+        &[],
+        None,
+        t,
+    )
+    .await
+    {
+        Ok(Some(me)) => me["api_key"]["id"] == id,
+        _ => false,
+    }
+}
+
+async fn invalidate(
+    transport: &Transport,
+    id: &str,
+    t: Duration,
+) -> Result<bool, elasticsearch::Error> {
+    let body = json!({ "ids": [id] });
+    let (status, invalidated) = send_json(
+        transport,
+        Method::Delete,
+        "/_security/api_key",
+        &[],
+        Some(&body),
+        t,
+    )
+    .await?;
+    if !status.is_success() {
+        eprintln!("Failed to invalidate API key {id}: {invalidated}");
+    }
+    Ok(status.is_success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_keys_skip_invalidated_and_expired() {
+        let keys = json!({"api_keys": [
+            {"id": "a", "invalidated": false},
+            {"id": "b", "invalidated": true},
+            {"id": "c", "invalidated": false, "expiration": 1000},
+            {"id": "d", "invalidated": false, "expiration": 3000},
+        ]});
+        let ids: Vec<&Value> = active_keys(&keys, 2000).iter().map(|k| &k["id"]).collect();
+        assert_eq!(ids, vec!["a", "d"]);
+    }
+
+    #[test]
+    fn new_key_body_keeps_roles_and_lifetime() {
+        let old = json!({
+            "id": "a", "name": "ci", "creation": 1000, "expiration": 61000,
+            "role_descriptors": {"reader": {"indices": [{"names": ["logs"], "privileges": ["read"]}]}},
+            "metadata": {"team": "ops"},
+        });
+        let rotate = RotateApiKey::try_parse_from(["rotate-api-key", "--profile", "p"]).unwrap();
+        assert_eq!(
+            rotate.new_key_body(&old),
+            json!({
+                "name": "ci",
+                "role_descriptors": old["role_descriptors"],
+                "metadata": {"team": "ops"},
+                "expiration": "60000ms",
+            })
+        );
+
+        let rotate = RotateApiKey::try_parse_from([
+            "rotate-api-key",
+            "--profile",
+            "p",
+            "--expiration",
+            "90d",
+        ])
+        .unwrap();
+        assert_eq!(rotate.new_key_body(&old)["expiration"], "90d");
+    }
+}
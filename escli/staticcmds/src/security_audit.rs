@@ -0,0 +1,360 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use crate::table::Table;
+use crate::units::{format_duration, format_timestamp, parse_duration};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Parser, Debug)]
+pub struct SecurityAudit {
+    #[arg(
+        long,
+        help = "Flag the API keys expiring within this time",
+        default_value = "30d",
+        value_parser = parse_duration
+    )]
+    expiry_warning: Duration,
+
+    #[arg(long, help = "Also list the reserved built-in roles")]
+    include_reserved: bool,
+}
+
+/// The security objects of a cluster, as returned by the security APIs.
+struct Inventory {
+    users: Value,
+    roles: Value,
+    role_mappings: Value,
+    api_keys: Value,
+}
+
+impl SecurityAudit {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("security-audit")
+            .about("List users, roles, role mappings and API keys and flag risky grants.")
+            .long_about(
+                r#"
+            List the native users, roles, role mappings and active API keys of
+            the cluster with their privileges and expirations.
+
+            Grants of superuser privileges are flagged: the superuser role and
+            roles with all cluster privileges and all privileges on all
+            indices, the users and role mappings granting them, and the API
+            keys without role descriptors owned by such users, which inherit
+            every privilege of their owner. Expired API keys, keys expiring
+            within --expiry-warning and keys that never expire are flagged too.
+
+            Example usage:
+                escli utils security-audit
+                escli utils security-audit --expiry-warning 7d --include-reserved
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let mut fetched = Vec::new();
+        for path in [
+            "/_security/user",
+            "/_security/role",
+            "/_security/role_mapping",
+            "/_security/api_key",
+        ] {
+            let Some(value) = send_json_ok(&transport, Method::Get, path, &[], None, t).await?
+            else {
+                return Ok(response(500, Vec::new()));
+            };
+            fetched.push(value);
+        }
+        let [users, roles, role_mappings, api_keys] =
+            <[Value; 4]>::try_from(fetched).expect("four security APIs are read");
+        let inventory = Inventory {
+            users,
+            roles,
+            role_mappings,
+            api_keys,
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let (report, flags) = self.report(&inventory, now);
+        print!("{report}");
+        let summary = format!("{flags} flagged item(s)\n");
+        Ok(response(200, summary.into_bytes()))
+    }
+
+    /// Renders the four sections of the audit and returns them with the
+    /// number of flagged items. `now` is in milliseconds since the epoch.
+    fn report(&self, inventory: &Inventory, now: u64) -> (String, usize) {
+        let superuser_roles = superuser_roles(&inventory.roles);
+        let is_superuser = |roles: &Value| {
+            roles
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .any(|r| superuser_roles.contains(r))
+        };
+        let mut flags = 0;
+        let mut flag = |cond: bool, text: &str| match cond {
+            true => {
+                flags += 1;
+                text.to_string()
+            }
+            false => String::new(),
+        };
+
+        let mut users = Table::new(&["user", "enabled", "roles", "flags"]);
+        let mut superusers = BTreeSet::new();
+        for (name, user) in entries(&inventory.users) {
+            let superuser = is_superuser(&user["roles"]);
+            if superuser {
+                superusers.insert(name.as_str());
+            }
+            users.add_row(vec![
+                name.clone(),
+                user["enabled"].to_string(),
+                join(&user["roles"]),
+                flag(superuser, "superuser"),
+            ]);
+        }
+
+        let mut roles = Table::new(&["role", "cluster", "indices", "flags"]);
+        for (name, role) in entries(&inventory.roles) {
+            let reserved = role["metadata"]["_reserved"] == Value::Bool(true);
+            if reserved && !self.include_reserved {
+                continue;
+            }
+            let indices: Vec<String> = role["indices"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|i| format!("{}:{}", join(&i["names"]), join(&i["privileges"])))
+                .collect();
+            roles.add_row(vec![
+                name.clone(),
+                join(&role["cluster"]),
+                indices.join(" "),
+                flag(superuser_roles.contains(name.as_str()), "superuser"),
+            ]);
+        }
+
+        let mut mappings = Table::new(&["mapping", "enabled", "roles", "flags"]);
+        for (name, mapping) in entries(&inventory.role_mappings) {
+            mappings.add_row(vec![
+                name.clone(),
+                mapping["enabled"].to_string(),
+                join(&mapping["roles"]),
+                flag(is_superuser(&mapping["roles"]), "superuser"),
+            ]);
+        }
+
+        let mut keys = Table::new(&[
+            "id",
+            "name",
+            "owner",
+            "created",
+            "expires",
+            "privileges",
+            "flags",
+        ]);
+        let soon = now + self.expiry_warning.as_millis() as u64;
+        for key in inventory.api_keys["api_keys"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            if key["invalidated"] == Value::Bool(true) {
+                continue;
+            }
+            let owner = key["username"].as_str().unwrap_or("-");
+            let descriptors: Vec<&String> = key["role_descriptors"]
+                .as_object()
+                .into_iter()
+                .flat_map(|o| o.keys())
+                .collect();
+            let expiration = key["expiration"].as_u64();
+            let mut key_flags = Vec::new();
+            match expiration {
+                None => key_flags.push("never expires".to_string()),
+                Some(ms) if ms <= now => key_flags.push("expired".to_string()),
+                Some(ms) if ms <= soon => key_flags.push(format!(
+                    "expires in {}",
+                    format_duration(Duration::from_millis(ms - now))
+                )),
+                Some(_) => {}
+            }
+            if descriptors.is_empty() && superusers.contains(owner) {
+                key_flags.push("superuser".to_string());
+            }
+            let privileges = match descriptors.is_empty() {
+                true => "inherited".to_string(),
+                false => descriptors
+                    .iter()
+                    .map(|d| d.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            };
+            keys.add_row(vec![
+                key["id"].as_str().unwrap_or("-").to_string(),
+                key["name"].as_str().unwrap_or("-").to_string(),
+                owner.to_string(),
+                key["creation"]
+                    .as_u64()
+                    .map_or("-".to_string(), |ms| format_timestamp(ms / 1000)),
+                expiration.map_or("never".to_string(), |ms| format_timestamp(ms / 1000)),
+                privileges,
+                flag(!key_flags.is_empty(), &key_flags.join(", ")),
+            ]);
+        }
+
+        let mut out = String::new();
+        for (title, table) in [
+            ("Users", users),
+            ("Roles", roles),
+            ("Role mappings", mappings),
+            ("API keys", keys),
+        ] {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("{title}\n"));
+            match table.is_empty() {
+                true => out.push_str("none\n"),
+                false => out.push_str(&table.render()),
+            }
+        }
+        (out, flags)
+    }
+}
+
+/// The roles granting superuser privileges: the built-in superuser role and
+/// any role with all cluster privileges and all privileges on all indices.
+fn superuser_roles(roles: &Value) -> BTreeSet<&str> {
+    let mut superuser = BTreeSet::from(["superuser"]);
+    for (name, role) in entries(roles) {
+        let has = |value: &Value, item: &str| {
+            value
+                .as_array()
+                .is_some_and(|a| a.iter().any(|v| v == item))
+        };
+        let all_indices = role["indices"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|i| has(&i["names"], "*") && has(&i["privileges"], "all"));
+        if has(&role["cluster"], "all") && all_indices {
+            superuser.insert(name.as_str());
+        }
+    }
+    superuser
+}
+
+/// The entries of an object keyed by name, sorted by name.
+fn entries(value: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    value.as_object().into_iter().flatten()
+}
+
+/// Joins an array of strings with commas.
+fn join(value: &Value) -> String {
+    let items: Vec<&str> = value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    match items.is_empty() {
+        true => "-".to_string(),
+        false => items.join(","),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn superuser_roles_include_equivalent_roles() {
+        let roles = json!({
+            "admin": {"cluster": ["all"], "indices": [{"names": ["*"], "privileges": ["all"]}]},
+            "reader": {"cluster": ["monitor"], "indices": [{"names": ["*"], "privileges": ["read"]}]},
+        });
+        assert_eq!(
+            superuser_roles(&roles),
+            BTreeSet::from(["admin", "superuser"])
+        );
+    }
+
+    #[test]
+    fn report_flags_superusers_and_expiring_keys() {
+        let audit = SecurityAudit::try_parse_from(["security-audit"]).unwrap();
+        let day = 86_400_000;
+        let inventory = Inventory {
+            users: json!({
+                "elastic": {"enabled": true, "roles": ["superuser"]},
+                "reader": {"enabled": true, "roles": ["viewer"]},
+            }),
+            roles: json!({"viewer": {"metadata": {"_reserved": true}}}),
+            role_mappings: json!({}),
+            api_keys: json!({"api_keys": [
+                {"id": "k1", "name": "ci", "username": "elastic", "creation": 0,
+                    "expiration": 40 * day, "role_descriptors": {}},
+                {"id": "k2", "name": "old", "username": "reader", "creation": 0,
+                    "expiration": 5 * day, "role_descriptors": {"read": {}}},
+                {"id": "k3", "name": "gone", "username": "reader", "invalidated": true},
+            ]}),
+        };
+        let (report, flags) = audit.report(&inventory, 20 * day);
+        assert_eq!(flags, 3);
+        assert_eq!(
+            report,
+            [
+                "Users",
+                "user     enabled  roles      flags",
+                "elastic  true     superuser  superuser",
+                "reader   true     viewer",
+                "",
+                "Roles",
+                "none",
+                "",
+                "Role mappings",
+                "none",
+                "",
+                "API keys",
+                "id  name  owner    created               expires               privileges  flags",
+                "k1  ci    elastic  1970-01-01T00:00:00Z  1970-02-10T00:00:00Z  inherited   expires in 20d 0h, superuser",
+                "k2  old   reader   1970-01-01T00:00:00Z  1970-01-06T00:00:00Z  read        expired",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
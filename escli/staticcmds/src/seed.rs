@@ -0,0 +1,405 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_to_string;
+use crate::load::BulkSender;
+use crate::request::response;
+use crate::units::format_timestamp;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Map, Value, json};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Bob", "Chloe", "David", "Emma", "Farid", "Grace", "Hugo", "Ines", "Jonas", "Kenji",
+    "Laura", "Mateo", "Nora", "Omar", "Priya", "Quentin", "Rosa", "Sven", "Yuki",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Anderson", "Bernard", "Costa", "Dubois", "Evans", "Fischer", "Garcia", "Hansen", "Ito",
+    "Jansen", "Kowalski", "Lopez", "Martin", "Nakamura", "Olsen", "Petrov", "Rossi", "Smith",
+    "Tanaka", "Weber",
+];
+
+const CITIES: &[(&str, f64, f64)] = &[
+    ("Amsterdam", 52.37, 4.90),
+    ("Berlin", 52.52, 13.40),
+    ("Buenos Aires", -34.60, -58.38),
+    ("Cape Town", -33.92, 18.42),
+    ("London", 51.51, -0.13),
+    ("Madrid", 40.42, -3.70),
+    ("Mumbai", 19.08, 72.88),
+    ("New York", 40.71, -74.01),
+    ("Paris", 48.86, 2.35),
+    ("San Francisco", 37.77, -122.42),
+    ("Sydney", -33.87, 151.21),
+    ("Tokyo", 35.68, 139.69),
+];
+
+const WORDS: &[&str] = &[
+    "alpha", "bright", "cloud", "data", "engine", "fast", "green", "harbor", "index", "jolly",
+    "kernel", "light", "matrix", "node", "orbit", "pixel", "quick", "river", "search", "token",
+    "update", "vector", "window", "yellow", "zone",
+];
+
+const DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+/// Options of the generators given by name only.
+static NULL: Value = Value::Null;
+
+#[derive(Parser, Debug)]
+pub struct Seed {
+    #[arg(short, long, help = "Index to load the documents into")]
+    index: String,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of documents to generate",
+        default_value_t = 1000
+    )]
+    count: u64,
+
+    #[arg(
+        short,
+        long,
+        help = "Field specification in YAML or JSON format, use - to read from stdin"
+    )]
+    schema: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of documents per bulk request",
+        default_value_t = 1000
+    )]
+    batch_size: u64,
+
+    #[arg(
+        long,
+        help = "Number of bulk requests sent concurrently",
+        default_value_t = 4,
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    concurrency: u16,
+
+    #[arg(
+        long,
+        help = "Seed of the random generator, to generate the same documents again"
+    )]
+    seed: Option<u64>,
+}
+
+/// A small xorshift generator, good enough for fake data.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `min..=max`.
+    fn range(&mut self, min: i64, max: i64) -> i64 {
+        let span = max.abs_diff(min).saturating_add(1);
+        min.wrapping_add((self.next() % span) as i64)
+    }
+
+    /// A number in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.next() as usize % items.len()]
+    }
+}
+
+impl Seed {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("seed")
+            .about("Generate fake documents and load them into an index.")
+            .long_about(
+                r#"
+            Generate documents from a field specification and bulk load them,
+            for load tests and demos.
+
+            The specification maps each field to a generator, either by name
+            or with options:
+
+                id: uuid
+                name: name
+                email: email
+                city: city
+                location: geo_point
+                age: { type: integer, min: 18, max: 90 }
+                price: { type: float, min: 1, max: 500 }
+                status: { type: keyword, values: [active, suspended, closed] }
+                created_at: { type: date, days: 30 }
+                description: { type: text, words: 12 }
+                premium: boolean
+                client_ip: ip
+                address: { type: object, fields: { city: city, zip: { type: integer, min: 10000, max: 99999 } } }
+
+            Dates are spread over the last days given by their days option,
+            365 by default. Use --seed to generate the same documents on every
+            run.
+
+            Example usage:
+                escli utils seed --index test --count 100000 --schema schema.yaml
+                escli utils seed -i demo -c 500 -s schema.json --seed 42
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let schema = read_schema(&self.schema).await?;
+        if let Err(e) = validate(&schema, "") {
+            eprintln!("Invalid schema: {e}");
+            return Ok(response(400, Vec::new()));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut rng = Rng::new(self.seed.unwrap_or(now.as_nanos() as u64));
+        let mut sender = BulkSender::new(
+            transport,
+            format!("/{}/_bulk", self.index),
+            t,
+            usize::from(self.concurrency),
+            3,
+            Duration::from_secs(1),
+        );
+
+        let mut body = String::new();
+        for i in 1..=self.count {
+            let doc = generate(&schema, &mut rng, now.as_secs());
+            body.push_str("{\"index\":{}}\n");
+            body.push_str(&doc.to_string());
+            body.push('\n');
+            if i % self.batch_size == 0 {
+                sender.send(std::mem::take(&mut body)).await?;
+            }
+        }
+        if !body.is_empty() {
+            sender.send(body).await?;
+        }
+        let (stats, batches) = sender.finish().await?;
+
+        eprintln!(
+            "Done: {} documents indexed, {} errors, {} retried across {} batch(es)",
+            stats.indexed, stats.errors, stats.retried, batches
+        );
+        let status = match stats.errors > 0 || stats.http_errors > 0 {
+            true => 400,
+            false => 200,
+        };
+        Ok(response(status, Vec::new()))
+    }
+}
+
+async fn read_schema(path: &Path) -> Result<Map<String, Value>, IoError> {
+    serde_yaml::from_str(&read_to_string(path).await?).map_err(|e| {
+        eprintln!("Failed to parse schema {:?}: {}", path, e);
+        IoError::new(IoErrorKind::InvalidData, e)
+    })
+}
+
+/// The generator name and options of a field specification.
+fn spec(value: &Value) -> (&str, &Value) {
+    match value {
+        Value::String(kind) => (kind.as_str(), &NULL),
+        _ => (value["type"].as_str().unwrap_or_default(), value),
+    }
+}
+
+/// Checks that every field uses a known generator, so that a typo fails
+/// before anything is loaded.
+fn validate(schema: &Map<String, Value>, prefix: &str) -> Result<(), String> {
+    for (name, value) in schema {
+        let path = format!("{prefix}{name}");
+        match spec(value) {
+            ("object", options) => {
+                let Some(fields) = options["fields"].as_object() else {
+                    return Err(format!("{path}: object fields need a fields map"));
+                };
+                validate(fields, &format!("{path}."))?;
+            }
+            ("keyword", options)
+                if !options["values"].is_null() && !options["values"].is_array() =>
+            {
+                return Err(format!("{path}: values must be a list"));
+            }
+            (
+                "uuid" | "name" | "first_name" | "last_name" | "email" | "city" | "geo_point"
+                | "ip" | "boolean" | "integer" | "long" | "float" | "double" | "date" | "keyword"
+                | "text",
+                _,
+            ) => {}
+            ("", _) => return Err(format!("{path}: missing type")),
+            (kind, _) => return Err(format!("{path}: unknown type {kind}")),
+        }
+    }
+    Ok(())
+}
+
+/// Generates a document from the schema. `now` is the current time in seconds
+/// since the epoch, dates are generated before it.
+fn generate(schema: &Map<String, Value>, rng: &mut Rng, now: u64) -> Value {
+    let mut doc = Map::new();
+    for (name, value) in schema {
+        let (kind, options) = spec(value);
+        let int = |key: &str, default: i64| options[key].as_i64().unwrap_or(default);
+        let float = |key: &str, default: f64| options[key].as_f64().unwrap_or(default);
+        let value = match kind {
+            "uuid" => json!(uuid(rng)),
+            "name" => json!(format!(
+                "{} {}",
+                rng.pick(FIRST_NAMES),
+                rng.pick(LAST_NAMES)
+            )),
+            "first_name" => json!(rng.pick(FIRST_NAMES)),
+            "last_name" => json!(rng.pick(LAST_NAMES)),
+            "email" => json!(format!(
+                "{}.{}@{}",
+                rng.pick(FIRST_NAMES).to_lowercase(),
+                rng.pick(LAST_NAMES).to_lowercase(),
+                rng.pick(DOMAINS)
+            )),
+            "city" => json!(rng.pick(CITIES).0),
+            "geo_point" => {
+                let (_, lat, lon) = rng.pick(CITIES);
+                let lat = lat + rng.unit() * 0.2 - 0.1;
+                let lon = lon + rng.unit() * 0.2 - 0.1;
+                json!({"lat": (lat * 1e4).round() / 1e4, "lon": (lon * 1e4).round() / 1e4})
+            }
+            "ip" => json!(format!(
+                "10.{}.{}.{}",
+                rng.range(0, 255),
+                rng.range(0, 255),
+                rng.range(1, 254)
+            )),
+            "boolean" => json!(rng.next() % 2 == 0),
+            "integer" | "long" => json!(rng.range(int("min", 0), int("max", 1000))),
+            "float" | "double" => {
+                let (min, max) = (float("min", 0.0), float("max", 1000.0));
+                json!(((min + rng.unit() * (max - min)) * 100.0).round() / 100.0)
+            }
+            "date" => {
+                let span = int("days", 365).max(0) as u64 * 86400;
+                let secs = now.saturating_sub(rng.next() % span.max(1));
+                json!(format_timestamp(secs))
+            }
+            "keyword" => match options["values"].as_array() {
+                Some(values) if !values.is_empty() => rng.pick(values).clone(),
+                _ => json!(rng.pick(WORDS)),
+            },
+            "text" => {
+                let words: Vec<&str> = (0..int("words", 8).max(1))
+                    .map(|_| *rng.pick(WORDS))
+                    .collect();
+                json!(words.join(" "))
+            }
+            "object" => match options["fields"].as_object() {
+                Some(fields) => generate(fields, rng, now),
+                None => Value::Null,
+            },
+            _ => Value::Null,
+        };
+        doc.insert(name.clone(), value);
+    }
+    Value::Object(doc)
+}
+
+fn uuid(rng: &mut Rng) -> String {
+    let (a, b) = (rng.next(), rng.next());
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        a >> 32,
+        (a >> 16) & 0xffff,
+        a & 0xfff,
+        ((b >> 48) & 0x3fff) | 0x8000,
+        b & 0xffff_ffff_ffff
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn generate_follows_the_schema() {
+        let schema = schema(json!({
+            "age": {"type": "integer", "min": 18, "max": 20},
+            "status": {"type": "keyword", "values": ["active"]},
+            "address": {"type": "object", "fields": {"city": "city"}},
+            "created_at": {"type": "date", "days": 1},
+        }));
+        let mut rng = Rng::new(42);
+        for _ in 0..100 {
+            let doc = generate(&schema, &mut rng, 1_709_210_096);
+            assert!((18..=20).contains(&doc["age"].as_i64().unwrap()));
+            assert_eq!(doc["status"], "active");
+            assert!(doc["address"]["city"].is_string());
+            assert!(doc["created_at"].as_str().unwrap().starts_with("2024-02-2"));
+        }
+    }
+
+    #[test]
+    fn generate_is_deterministic_for_a_seed() {
+        let schema = schema(json!({"id": "uuid", "name": "name"}));
+        let a = generate(&schema, &mut Rng::new(7), 0);
+        let b = generate(&schema, &mut Rng::new(7), 0);
+        assert_eq!(a, b);
+        assert_eq!(a["id"].as_str().unwrap().len(), 36);
+    }
+
+    #[test]
+    fn validate_rejects_unknown_types() {
+        assert_eq!(
+            validate(
+                &schema(json!({"a": {"type": "object", "fields": {"b": "nme"}}})),
+                ""
+            ),
+            Err("a.b: unknown type nme".to_string())
+        );
+        assert_eq!(
+            validate(&schema(json!({"a": {"min": 1}})), ""),
+            Err("a: missing type".to_string())
+        );
+    }
+}
@@ -0,0 +1,314 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use clap::{Command, CommandFactory, Parser};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::Path;
+
+/// GitHub repository the releases are published to.
+const REPOSITORY: &str = "Anaethelion/escli-rs";
+
+#[derive(Parser, Debug)]
+pub struct SelfUpdate {
+    #[arg(long, help = "Only check whether a newer version is available")]
+    check: bool,
+
+    #[arg(
+        long,
+        value_name = "TAG",
+        help = "Install this release instead of the latest one, e.g. v0.3.0"
+    )]
+    version: Option<String>,
+
+    #[arg(
+        long,
+        hide = true,
+        env = "ESCLI_UPDATE_API_URL",
+        default_value = "https://api.github.com"
+    )]
+    api_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl SelfUpdate {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("self-update")
+            .about("Update escli to the latest release.")
+            .long_about(
+                r#"
+            Check the GitHub releases for a newer version of escli, download
+            the binary built for this platform, verify it against the SHA-256
+            checksum published with the release and replace the running
+            executable with it.
+
+            With --check, only report whether an update is available; the
+            exit status is 0 when up to date and 1 otherwise.
+
+            Example usage:
+                escli self-update
+                escli self-update --check
+                escli self-update --version v0.3.0
+            "#,
+            )
+    }
+
+    /// Updates the executable and returns the exit code of the process.
+    pub async fn execute(self, current_version: &str) -> i32 {
+        match self.update(current_version).await {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("{e}");
+                1
+            }
+        }
+    }
+
+    async fn update(&self, current_version: &str) -> Result<i32, IoError> {
+        let client = reqwest::Client::builder()
+            .user_agent(format!("escli/{current_version}"))
+            .build()
+            .map_err(IoError::other)?;
+
+        let release = self.find_release(&client).await?;
+        let newer = compare_versions(&release.tag_name, current_version) == Ordering::Greater;
+        if self.version.is_none() && !newer {
+            println!("escli {current_version} is up to date");
+            return Ok(0);
+        }
+        if self.check {
+            println!(
+                "escli {} is available, currently running {current_version}",
+                release.tag_name
+            );
+            return Ok(1);
+        }
+
+        let name = asset_name()?;
+        let asset = find_asset(&release, name)?;
+        let checksum = find_asset(&release, &format!("{name}.sha256")).map_err(|_| {
+            IoError::new(
+                IoErrorKind::NotFound,
+                format!(
+                    "release {} has no checksum for {name}, refusing to install it",
+                    release.tag_name
+                ),
+            )
+        })?;
+
+        let expected = download(&client, &checksum.browser_download_url).await?;
+        let expected = parse_checksum(&String::from_utf8_lossy(&expected)).ok_or_else(|| {
+            IoError::new(
+                IoErrorKind::InvalidData,
+                format!("invalid checksum file {}", checksum.name),
+            )
+        })?;
+        let binary = download(&client, &asset.browser_download_url).await?;
+        let actual = hex(&Sha256::digest(&binary));
+        if actual != expected {
+            return Err(IoError::new(
+                IoErrorKind::InvalidData,
+                format!("checksum mismatch for {name}: expected {expected}, got {actual}"),
+            ));
+        }
+
+        replace_executable(&std::env::current_exe()?, &binary)?;
+        println!(
+            "Updated escli from {current_version} to {}",
+            release.tag_name
+        );
+        Ok(0)
+    }
+
+    /// The release named with --version, or the most recent published one.
+    ///
+    /// Releases are published as pre-releases, which `releases/latest` skips,
+    /// so the most recent one is taken from the release list.
+    async fn find_release(&self, client: &reqwest::Client) -> Result<Release, IoError> {
+        let base = format!("{}/repos/{REPOSITORY}/releases", self.api_url);
+        match &self.version {
+            Some(tag) => get_json(client, &format!("{base}/tags/{tag}")).await,
+            None => {
+                let releases: Vec<Release> = get_json(client, &base).await?;
+                releases.into_iter().find(|r| !r.draft).ok_or_else(|| {
+                    IoError::new(IoErrorKind::NotFound, "no release found".to_string())
+                })
+            }
+        }
+    }
+}
+
+async fn get_json<T: for<'de> Deserialize<'de>>(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<T, IoError> {
+    let bytes = download(client, url).await?;
+    serde_json::from_slice(&bytes).map_err(|e| IoError::new(IoErrorKind::InvalidData, e))
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, IoError> {
+    let res = client.get(url).send().await.map_err(IoError::other)?;
+    if !res.status().is_success() {
+        return Err(IoError::other(format!(
+            "request to {url} failed with status {}",
+            res.status()
+        )));
+    }
+    Ok(res.bytes().await.map_err(IoError::other)?.to_vec())
+}
+
+fn find_asset<'a>(release: &'a Release, name: &str) -> Result<&'a Asset, IoError> {
+    release
+        .assets
+        .iter()
+        .find(|a| a.name == name)
+        .ok_or_else(|| {
+            IoError::new(
+                IoErrorKind::NotFound,
+                format!("release {} has no {name} asset", release.tag_name),
+            )
+        })
+}
+
+/// Name of the release asset built for the running platform.
+fn asset_name() -> Result<&'static str, IoError> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Ok("escli-linux-amd64"),
+        ("linux", "aarch64") => Ok("escli-linux-arm64"),
+        ("macos", _) => Ok("escli-macos"),
+        ("windows", _) => Ok("escli-windows.exe"),
+        (os, arch) => Err(IoError::new(
+            IoErrorKind::Unsupported,
+            format!("no release is published for {os}/{arch}"),
+        )),
+    }
+}
+
+/// Extracts the digest of a `sha256sum` output line.
+fn parse_checksum(content: &str) -> Option<String> {
+    let digest = content.split_whitespace().next()?.to_lowercase();
+    match digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Some(digest),
+        false => None,
+    }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Compares two versions such as `v0.3.0` and `0.2.0-alpha`, a pre-release
+/// being older than the release of the same number.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |v: &str| {
+        let v = v.trim_start_matches('v');
+        let (number, pre) = match v.split_once('-') {
+            Some((number, pre)) => (number, Some(pre.to_string())),
+            None => (v, None),
+        };
+        let number: Vec<u64> = number
+            .split('.')
+            .map(|n| n.parse().unwrap_or_default())
+            .collect();
+        (number, pre)
+    };
+    let (a_number, a_pre) = parse(a);
+    let (b_number, b_pre) = parse(b);
+    a_number.cmp(&b_number).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a.cmp(&b),
+    })
+}
+
+/// Writes the new binary next to the executable, then moves it in place.
+fn replace_executable(exe: &Path, binary: &[u8]) -> Result<(), IoError> {
+    let tmp = exe.with_extension("update");
+    std::fs::write(&tmp, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755))?;
+    }
+    // A running executable cannot be overwritten on Windows, but it can be renamed.
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old.exe");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(exe, &old)?;
+    }
+    std::fs::rename(&tmp, exe).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        IoError::new(
+            e.kind(),
+            format!("failed to replace {}: {e}", exe.display()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_versions_handles_prefix_and_pre_releases() {
+        assert_eq!(compare_versions("v0.3.0", "0.2.0-alpha"), Ordering::Greater);
+        assert_eq!(compare_versions("v0.2.0", "0.2.0-alpha"), Ordering::Greater);
+        assert_eq!(
+            compare_versions("0.2.0-alpha", "0.2.0-alpha"),
+            Ordering::Equal
+        );
+        assert_eq!(compare_versions("v0.2.0-alpha", "0.10.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn parse_checksum_reads_sha256sum_output() {
+        let digest = "a".repeat(64);
+        assert_eq!(
+            parse_checksum(&format!("{digest}  escli-linux-amd64\n")),
+            Some(digest)
+        );
+        assert_eq!(parse_checksum("not-a-digest escli"), None);
+        assert_eq!(parse_checksum(""), None);
+    }
+
+    #[test]
+    fn hex_encodes_digest() {
+        assert_eq!(
+            hex(&Sha256::digest(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
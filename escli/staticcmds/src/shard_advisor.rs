@@ -0,0 +1,400 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use crate::table::Table;
+use crate::units::{format_bytes, parse_bytes};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// `_cat/nodes` role abbreviations of the data, hot, warm, cold, frozen and
+/// content roles.
+const DATA_ROLES: &str = "dhwcfs";
+
+#[derive(Parser, Debug)]
+pub struct ShardAdvisor {
+    #[arg(help = "Index pattern to analyze, e.g. logs-*", default_value = "*")]
+    pattern: String,
+
+    #[arg(
+        long,
+        help = "Shards smaller than this are considered tiny",
+        default_value = "1gb",
+        value_parser = parse_bytes
+    )]
+    min_shard_size: u64,
+
+    #[arg(
+        long,
+        help = "Shards larger than this are considered too large",
+        default_value = "50gb",
+        value_parser = parse_bytes
+    )]
+    max_shard_size: u64,
+
+    #[arg(
+        long,
+        help = "Shard size used to compute the suggested number of primaries",
+        default_value = "30gb",
+        value_parser = parse_bytes
+    )]
+    target_shard_size: u64,
+
+    #[arg(
+        long,
+        help = "Maximum recommended number of documents per shard",
+        default_value_t = 200_000_000
+    )]
+    max_docs_per_shard: u64,
+
+    #[arg(long, help = "Only list indices that violate a recommendation")]
+    only_issues: bool,
+}
+
+/// Primary shard statistics of a single index.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct IndexShards {
+    primaries: u64,
+    replicas: u64,
+    primary_bytes: u64,
+    largest_shard: u64,
+    docs: u64,
+}
+
+impl IndexShards {
+    fn average_shard(&self) -> u64 {
+        self.primary_bytes / self.primaries.max(1)
+    }
+}
+
+/// What should happen to an index, if anything.
+#[derive(Debug, Clone, PartialEq)]
+enum Advice {
+    Ok,
+    Shrink { target: u64, reason: String },
+    Split { target: u64, reason: String },
+}
+
+impl ShardAdvisor {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("shard-advisor")
+            .about("Flag indices whose shard sizes fall outside best-practice ranges.")
+            .long_about(
+                r#"
+            Analyze the primary shards of the indices matching a pattern and
+            flag the ones outside the recommended sizing ranges:
+
+              - too many tiny shards (average below --min-shard-size), which
+                waste heap and slow down searches;
+              - oversized shards (above --max-shard-size, 50gb by default) or
+                shards holding more than --max-docs-per-shard documents, which
+                recover and relocate slowly.
+
+            For each flagged index a target primary shard count is suggested,
+            based on --target-shard-size, together with the shrink or split
+            API calls that achieve it. Shrink targets are always a factor of
+            the current count and split targets a multiple, as required by
+            Elasticsearch.
+
+            Example usage:
+                escli utils shard-advisor 'logs-*'
+                escli utils shard-advisor --only-issues --target-shard-size 40gb
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+
+        let path = format!("/_cat/shards/{}", self.pattern);
+        let query = [
+            ("format", "json"),
+            ("bytes", "b"),
+            ("h", "index,prirep,store,docs"),
+        ];
+        let Some(shards) = send_json_ok(&transport, Method::Get, &path, &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let query = [("format", "json"), ("h", "name,node.role")];
+        let Some(nodes) =
+            send_json_ok(&transport, Method::Get, "/_cat/nodes", &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        let indices = collect_indices(&shards);
+        let data_nodes = nodes
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|n| {
+                n["node.role"]
+                    .as_str()
+                    .is_some_and(|r| r.chars().any(|c| DATA_ROLES.contains(c)))
+            })
+            .count();
+
+        Ok(response(
+            200,
+            self.render(&indices, data_nodes).into_bytes(),
+        ))
+    }
+
+    fn advise(&self, shards: &IndexShards) -> Advice {
+        let ideal = shards
+            .primary_bytes
+            .div_ceil(self.target_shard_size.max(1))
+            .max(1);
+        let docs_per_shard = shards.docs / shards.primaries.max(1);
+
+        if shards.largest_shard > self.max_shard_size || docs_per_shard > self.max_docs_per_shard {
+            let by_docs = shards.docs.div_ceil(self.max_docs_per_shard.max(1));
+            let target = smallest_multiple_at_least(shards.primaries, ideal.max(by_docs));
+            let reason = if shards.largest_shard > self.max_shard_size {
+                format!("largest shard is {}", format_bytes(shards.largest_shard))
+            } else {
+                format!("{} docs per shard", docs_per_shard)
+            };
+            if target > shards.primaries {
+                return Advice::Split { target, reason };
+            }
+        }
+
+        if shards.primaries > 1 && shards.average_shard() < self.min_shard_size {
+            let target = smallest_factor_at_least(shards.primaries, ideal);
+            if target < shards.primaries {
+                return Advice::Shrink {
+                    target,
+                    reason: format!("average shard is {}", format_bytes(shards.average_shard())),
+                };
+            }
+        }
+
+        Advice::Ok
+    }
+
+    fn render(&self, indices: &BTreeMap<String, IndexShards>, data_nodes: usize) -> String {
+        let total_shards: u64 = indices
+            .values()
+            .map(|s| s.primaries * (1 + s.replicas))
+            .sum();
+        let mut out = format!(
+            "{} indices, {} shards across {} data node(s)\n\n",
+            indices.len(),
+            total_shards,
+            data_nodes
+        );
+
+        let mut table = Table::new(&[
+            "index",
+            "pri",
+            "rep",
+            "pri.size",
+            "avg.shard",
+            "max.shard",
+            "docs/shard",
+            "advice",
+        ]);
+        let mut commands = Vec::new();
+        for (index, shards) in indices {
+            let advice = self.advise(shards);
+            let label = match &advice {
+                Advice::Ok => "ok".to_string(),
+                Advice::Shrink { target, reason } => {
+                    commands.push(shrink_commands(index, *target));
+                    format!("shrink to {target} ({reason})")
+                }
+                Advice::Split { target, reason } => {
+                    commands.push(split_commands(index, *target));
+                    format!("split to {target} ({reason})")
+                }
+            };
+            if self.only_issues && advice == Advice::Ok {
+                continue;
+            }
+            table.add_row(vec![
+                index.clone(),
+                shards.primaries.to_string(),
+                shards.replicas.to_string(),
+                format_bytes(shards.primary_bytes),
+                format_bytes(shards.average_shard()),
+                format_bytes(shards.largest_shard),
+                (shards.docs / shards.primaries.max(1)).to_string(),
+                label,
+            ]);
+        }
+        out.push_str(&table.render());
+
+        if !commands.is_empty() {
+            out.push_str("\nSuggested API calls:\n\n");
+            out.push_str(&commands.join("\n"));
+        }
+        out
+    }
+}
+
+/// Aggregates `_cat/shards` rows per index, counting primaries only for sizes.
+fn collect_indices(shards: &Value) -> BTreeMap<String, IndexShards> {
+    let mut indices: BTreeMap<String, IndexShards> = BTreeMap::new();
+    for shard in shards.as_array().into_iter().flatten() {
+        let Some(index) = shard["index"].as_str() else {
+            continue;
+        };
+        let entry = indices.entry(index.to_string()).or_default();
+        if shard["prirep"].as_str() == Some("p") {
+            let store = cat_u64(&shard["store"]);
+            entry.primaries += 1;
+            entry.primary_bytes += store;
+            entry.largest_shard = entry.largest_shard.max(store);
+            entry.docs += cat_u64(&shard["docs"]);
+        } else {
+            entry.replicas += 1;
+        }
+    }
+    // `_cat/shards` lists every replica copy, turn that into a per-primary count.
+    for shards in indices.values_mut() {
+        shards.replicas /= shards.primaries.max(1);
+    }
+    indices
+}
+
+fn cat_u64(value: &Value) -> u64 {
+    match value {
+        Value::String(s) => s.parse().unwrap_or_default(),
+        v => v.as_u64().unwrap_or_default(),
+    }
+}
+
+/// Smallest factor of `n` that is at least `min`, as required by shrink.
+fn smallest_factor_at_least(n: u64, min: u64) -> u64 {
+    (min.max(1)..=n).find(|f| n % f == 0).unwrap_or(n)
+}
+
+/// Smallest multiple of `n` that is at least `min`, as required by split.
+fn smallest_multiple_at_least(n: u64, min: u64) -> u64 {
+    let n = n.max(1);
+    min.div_ceil(n).max(1) * n
+}
+
+fn shrink_commands(index: &str, target: u64) -> String {
+    format!(
+        "PUT /{index}/_settings\n{{\"index.blocks.write\": true}}\n\
+         POST /{index}/_shrink/{index}-shrunk\n{{\"settings\": {{\"index.number_of_shards\": {target}, \"index.blocks.write\": null}}}}\n"
+    )
+}
+
+fn split_commands(index: &str, target: u64) -> String {
+    format!(
+        "PUT /{index}/_settings\n{{\"index.blocks.write\": true}}\n\
+         POST /{index}/_split/{index}-split\n{{\"settings\": {{\"index.number_of_shards\": {target}, \"index.blocks.write\": null}}}}\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const GB: u64 = 1 << 30;
+
+    fn advisor() -> ShardAdvisor {
+        ShardAdvisor::try_parse_from(["shard-advisor"]).unwrap()
+    }
+
+    #[test]
+    fn collect_indices_counts_primaries_and_replicas() {
+        let shards = json!([
+            {"index": "a", "prirep": "p", "store": "100", "docs": "10"},
+            {"index": "a", "prirep": "r", "store": "100", "docs": "10"},
+            {"index": "a", "prirep": "p", "store": "300", "docs": "30"},
+            {"index": "a", "prirep": "r", "store": "300", "docs": "30"},
+        ]);
+        let indices = collect_indices(&shards);
+        assert_eq!(
+            indices["a"],
+            IndexShards {
+                primaries: 2,
+                replicas: 1,
+                primary_bytes: 400,
+                largest_shard: 300,
+                docs: 40
+            }
+        );
+    }
+
+    #[test]
+    fn factors_and_multiples() {
+        assert_eq!(smallest_factor_at_least(12, 5), 6);
+        assert_eq!(smallest_factor_at_least(7, 2), 7);
+        assert_eq!(smallest_factor_at_least(6, 0), 1);
+        assert_eq!(smallest_multiple_at_least(3, 7), 9);
+        assert_eq!(smallest_multiple_at_least(3, 1), 3);
+    }
+
+    #[test]
+    fn advise_shrinks_tiny_shards() {
+        let shards = IndexShards {
+            primaries: 10,
+            replicas: 1,
+            primary_bytes: GB,
+            largest_shard: GB / 10,
+            docs: 100,
+        };
+        assert!(matches!(
+            advisor().advise(&shards),
+            Advice::Shrink { target: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn advise_splits_large_shards() {
+        let shards = IndexShards {
+            primaries: 1,
+            replicas: 1,
+            primary_bytes: 90 * GB,
+            largest_shard: 90 * GB,
+            docs: 100,
+        };
+        assert!(matches!(
+            advisor().advise(&shards),
+            Advice::Split { target: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn advise_accepts_healthy_index() {
+        let shards = IndexShards {
+            primaries: 2,
+            replicas: 1,
+            primary_bytes: 40 * GB,
+            largest_shard: 20 * GB,
+            docs: 100,
+        };
+        assert_eq!(advisor().advise(&shards), Advice::Ok);
+    }
+}
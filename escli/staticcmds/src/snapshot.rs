@@ -0,0 +1,707 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json, send_json_ok};
+use crate::table::Table;
+use crate::units::{format_bytes, format_timestamp, parse_duration};
+use clap::{Args, Command, CommandFactory, Parser, Subcommand};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Snapshot states after which `_status` no longer changes.
+const FINAL_STATES: &[&str] = &["SUCCESS", "FAILED", "PARTIAL", "ABORTED"];
+
+/// Number of polls without any restored shard after which a restore is
+/// considered to have restored nothing.
+const MAX_EMPTY_POLLS: usize = 5;
+
+#[derive(Parser, Debug)]
+pub struct Snapshot {
+    #[command(subcommand)]
+    action: SnapshotAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum SnapshotAction {
+    #[command(about = "Create a snapshot and follow its progress until it completes")]
+    Create(CreateArgs),
+    #[command(about = "Restore a snapshot, wait for the recovery and verify the document counts")]
+    Restore(RestoreArgs),
+    #[command(
+        about = "Verify repositories, clean up their unreferenced data and summarize their snapshots"
+    )]
+    Verify(VerifyArgs),
+}
+
+#[derive(Args, Debug)]
+struct CreateArgs {
+    #[arg(help = "Name of the snapshot repository")]
+    repository: String,
+
+    #[arg(help = "Name of the snapshot")]
+    snapshot: String,
+
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Indices and data streams to include, comma separated, defaults to all"
+    )]
+    indices: Vec<String>,
+
+    #[arg(long, help = "Include the cluster state in the snapshot")]
+    include_global_state: bool,
+
+    #[arg(long, help = "Snapshot the available shards when some are missing")]
+    partial: bool,
+
+    #[arg(
+        long,
+        help = "Time between two progress updates",
+        default_value = "2s",
+        value_parser = parse_duration
+    )]
+    interval: Duration,
+}
+
+#[derive(Args, Debug)]
+struct RestoreArgs {
+    #[arg(help = "Name of the snapshot repository")]
+    repository: String,
+
+    #[arg(help = "Name of the snapshot")]
+    snapshot: String,
+
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Indices and data streams to restore, comma separated, defaults to all"
+    )]
+    indices: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "PATTERN=REPLACEMENT",
+        value_parser = parse_rename,
+        help = "Rename the restored indices, e.g. 'logs-(.*)=restored-$1'"
+    )]
+    rename: Option<(String, String)>,
+
+    #[arg(long, help = "Restore the aliases of the indices")]
+    include_aliases: bool,
+
+    #[arg(
+        long,
+        help = "Time between two progress updates",
+        default_value = "2s",
+        value_parser = parse_duration
+    )]
+    interval: Duration,
+}
+
+#[derive(Args, Debug)]
+struct VerifyArgs {
+    #[arg(
+        value_delimiter = ',',
+        help = "Names of the snapshot repositories, comma separated, defaults to all"
+    )]
+    repositories: Vec<String>,
+
+    #[arg(long, help = "Only verify the repositories, without cleaning them up")]
+    no_cleanup: bool,
+}
+
+/// The verification, cleanup and snapshots of a repository.
+#[derive(Debug)]
+struct RepositoryReport {
+    name: String,
+    kind: String,
+    verified: Result<usize, String>,
+    reclaimed: Option<u64>,
+    snapshots: Option<Value>,
+}
+
+impl Snapshot {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("snapshot")
+            .about("Create and restore snapshots and follow their progress.")
+            .long_about(
+                r#"
+            Snapshot helpers that start an operation and follow it to the end,
+            instead of polling the snapshot APIs by hand.
+
+            `snapshot create` starts a snapshot, then polls its _status every
+            --interval, printing the shards in progress on stderr. Once the
+            snapshot is over, a summary is printed and the command exits with
+            0 if its state is SUCCESS and 1 otherwise.
+
+            `snapshot restore` starts a restore, optionally renaming the
+            indices with --rename PATTERN=REPLACEMENT, where PATTERN is a
+            regular expression and REPLACEMENT may refer to its groups with
+            $1, $2... The recovery of the restored shards is followed until
+            they are all done, then the document count of each restored index
+            is printed. When indices are renamed, the counts are compared to
+            the original indices if they still exist, and the command fails on
+            a mismatch.
+
+            `snapshot verify` verifies that every node can access each
+            repository, runs the repository cleanup API to delete the data no
+            longer referenced by any snapshot, and prints the space reclaimed
+            along with the number of snapshots and the oldest and newest
+            snapshot of each repository. It exits with 1 when a repository
+            fails verification.
+
+            Example usage:
+                escli utils snapshot create my-repo nightly-2025.01.01
+                escli utils snapshot create my-repo logs --indices 'logs-*' --interval 10s
+                escli utils snapshot restore my-repo nightly-2025.01.01 --indices 'logs-*' \
+                    --rename 'logs-(.*)=restored-$1'
+                escli utils snapshot verify
+                escli utils snapshot verify my-repo --no-cleanup
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+
+        match self.action {
+            SnapshotAction::Create(args) => args.run(&transport, t).await,
+            SnapshotAction::Restore(args) => args.run(&transport, t).await,
+            SnapshotAction::Verify(args) => args.run(&transport, t).await,
+        }
+    }
+}
+
+impl CreateArgs {
+    async fn run(
+        &self,
+        transport: &Transport,
+        t: Duration,
+    ) -> Result<Response, elasticsearch::Error> {
+        let path = format!("/_snapshot/{}/{}", self.repository, self.snapshot);
+        let mut body = json!({
+            "include_global_state": self.include_global_state,
+            "partial": self.partial,
+        });
+        if !self.indices.is_empty() {
+            body["indices"] = json!(self.indices.join(","));
+        }
+        let query = [("wait_for_completion", "false")];
+        let (status, created) =
+            send_json(transport, Method::Put, &path, &query, Some(&body), t).await?;
+        if !status.is_success() {
+            return Ok(response(status.as_u16(), created.to_string().into_bytes()));
+        }
+
+        let status_path = format!("{path}/_status");
+        loop {
+            let Some(status) =
+                send_json_ok(transport, Method::Get, &status_path, &[], None, t).await?
+            else {
+                return Ok(response(500, Vec::new()));
+            };
+            let snapshot = &status["snapshots"][0];
+            let state = snapshot["state"].as_str().unwrap_or("UNKNOWN");
+            if FINAL_STATES.contains(&state) {
+                let code = match state {
+                    "SUCCESS" => 200,
+                    _ => 400,
+                };
+                return Ok(response(code, render_summary(snapshot).into_bytes()));
+            }
+            eprint!("{}", render_progress(snapshot));
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+impl RestoreArgs {
+    async fn run(
+        &self,
+        transport: &Transport,
+        t: Duration,
+    ) -> Result<Response, elasticsearch::Error> {
+        let path = format!("/_snapshot/{}/{}/_restore", self.repository, self.snapshot);
+        let mut body = json!({ "include_aliases": self.include_aliases });
+        if !self.indices.is_empty() {
+            body["indices"] = json!(self.indices.join(","));
+        }
+        if let Some((pattern, replacement)) = &self.rename {
+            body["rename_pattern"] = json!(pattern);
+            body["rename_replacement"] = json!(replacement);
+        }
+        let query = [("wait_for_completion", "false")];
+        let (status, accepted) =
+            send_json(transport, Method::Post, &path, &query, Some(&body), t).await?;
+        if !status.is_success() {
+            return Ok(response(status.as_u16(), accepted.to_string().into_bytes()));
+        }
+
+        let mut empty_polls = 0;
+        let restored = loop {
+            let query = [("active_only", "false")];
+            let Some(recovery) =
+                send_json_ok(transport, Method::Get, "/_recovery", &query, None, t).await?
+            else {
+                return Ok(response(500, Vec::new()));
+            };
+            let shards = self.restored_shards(&recovery);
+            if shards.is_empty() {
+                empty_polls += 1;
+                if empty_polls >= MAX_EMPTY_POLLS {
+                    eprintln!("No shard is being restored from {}", self.snapshot);
+                    return Ok(response(400, Vec::new()));
+                }
+            } else if shards.iter().all(|s| s.stage == "DONE") {
+                break shards;
+            } else {
+                eprint!("{}", render_recovery(&shards));
+            }
+            tokio::time::sleep(self.interval).await;
+        };
+
+        // Restored index name to the name of the index in the snapshot.
+        let indices: BTreeMap<String, String> = restored
+            .into_iter()
+            .map(|s| (s.index, s.source_index))
+            .collect();
+        let query = [
+            ("format", "json"),
+            ("h", "index,docs.count"),
+            ("expand_wildcards", "all"),
+        ];
+        let Some(cat) =
+            send_json_ok(transport, Method::Get, "/_cat/indices", &query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let counts: BTreeMap<&str, &str> = cat
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|row| Some((row["index"].as_str()?, row["docs.count"].as_str()?)))
+            .collect();
+
+        let (table, mismatches) = verify_counts(&indices, &counts);
+        let mut out = table;
+        let status = match mismatches {
+            0 => 200,
+            n => {
+                out.push_str(&format!("\n{n} index(es) do not match their original\n"));
+                400
+            }
+        };
+        Ok(response(status, out.into_bytes()))
+    }
+
+    /// The shards recovering from this snapshot, according to `_recovery`.
+    fn restored_shards(&self, recovery: &Value) -> Vec<RestoredShard> {
+        let mut shards = Vec::new();
+        for (index, index_recovery) in recovery.as_object().into_iter().flatten() {
+            for shard in index_recovery["shards"].as_array().into_iter().flatten() {
+                let source = &shard["source"];
+                if shard["type"] != "SNAPSHOT"
+                    || source["repository"] != self.repository.as_str()
+                    || source["snapshot"] != self.snapshot.as_str()
+                {
+                    continue;
+                }
+                shards.push(RestoredShard {
+                    index: index.clone(),
+                    source_index: source["index"].as_str().unwrap_or(index).to_string(),
+                    shard: shard["id"].as_u64().unwrap_or_default(),
+                    stage: shard["stage"].as_str().unwrap_or_default().to_string(),
+                    percent: shard["index"]["size"]["percent"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                });
+            }
+        }
+        shards
+    }
+}
+
+/// A shard recovering from a snapshot.
+#[derive(Debug, Clone, PartialEq)]
+struct RestoredShard {
+    index: String,
+    source_index: String,
+    shard: u64,
+    stage: String,
+    percent: String,
+}
+
+fn render_recovery(shards: &[RestoredShard]) -> String {
+    let done = shards.iter().filter(|s| s.stage == "DONE").count();
+    let mut out = format!("{}/{} shards restored\n", done, shards.len());
+    let mut table = Table::new(&["index", "shard", "stage", "progress"]);
+    for shard in shards.iter().filter(|s| s.stage != "DONE") {
+        table.add_row(vec![
+            shard.index.clone(),
+            shard.shard.to_string(),
+            shard.stage.clone(),
+            shard.percent.clone(),
+        ]);
+    }
+    out.push_str(&table.render());
+    out
+}
+
+/// Renders the document count of each restored index, compared to its
+/// original when it was renamed and the original still exists, and returns
+/// the number of mismatches.
+fn verify_counts(
+    indices: &BTreeMap<String, String>,
+    counts: &BTreeMap<&str, &str>,
+) -> (String, usize) {
+    let mut table = Table::new(&["index", "docs.count", "original", "original.docs.count"]);
+    let mut mismatches = 0;
+    for (index, source_index) in indices {
+        let count = counts.get(index.as_str()).copied().unwrap_or("-");
+        let original = counts
+            .get(source_index.as_str())
+            .filter(|_| index != source_index);
+        let mut row = vec![index.clone(), count.to_string()];
+        if let Some(original_count) = original {
+            row.push(source_index.clone());
+            row.push(original_count.to_string());
+            if *original_count != count {
+                mismatches += 1;
+            }
+        }
+        table.add_row(row);
+    }
+    (table.render(), mismatches)
+}
+
+/// Parses a `PATTERN=REPLACEMENT` rename.
+fn parse_rename(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((pattern, replacement)) if !pattern.is_empty() => {
+            Ok((pattern.to_string(), replacement.to_string()))
+        }
+        _ => Err(format!(
+            "invalid rename '{s}', expected PATTERN=REPLACEMENT, e.g. 'logs-(.*)=restored-$1'"
+        )),
+    }
+}
+
+/// One line with the overall progress, then the shards not done yet.
+fn render_progress(snapshot: &Value) -> String {
+    let stats = &snapshot["shards_stats"];
+    let mut out = format!(
+        "{}: {}/{} shards done, {}\n",
+        snapshot["state"].as_str().unwrap_or("UNKNOWN"),
+        stats["done"].as_u64().unwrap_or_default(),
+        stats["total"].as_u64().unwrap_or_default(),
+        bytes_progress(&snapshot["stats"]),
+    );
+    let mut table = Table::new(&["index", "shard", "stage", "progress"]);
+    for (index, index_status) in snapshot["indices"].as_object().into_iter().flatten() {
+        for (shard, shard_status) in index_status["shards"].as_object().into_iter().flatten() {
+            let stage = shard_status["stage"].as_str().unwrap_or_default();
+            if stage == "DONE" {
+                continue;
+            }
+            table.add_row(vec![
+                index.clone(),
+                shard.clone(),
+                stage.to_string(),
+                bytes_progress(&shard_status["stats"]),
+            ]);
+        }
+    }
+    if !table.is_empty() {
+        out.push_str(&table.render());
+    }
+    out
+}
+
+fn render_summary(snapshot: &Value) -> String {
+    let stats = &snapshot["shards_stats"];
+    format!(
+        "Snapshot {}/{} {}: {}/{} shards done, {} failed, {}\n",
+        snapshot["repository"].as_str().unwrap_or_default(),
+        snapshot["snapshot"].as_str().unwrap_or_default(),
+        snapshot["state"].as_str().unwrap_or("UNKNOWN"),
+        stats["done"].as_u64().unwrap_or_default(),
+        stats["total"].as_u64().unwrap_or_default(),
+        stats["failed"].as_u64().unwrap_or_default(),
+        format_bytes(
+            snapshot["stats"]["total"]["size_in_bytes"]
+                .as_u64()
+                .unwrap_or_default()
+        ),
+    )
+}
+
+impl VerifyArgs {
+    async fn run(
+        &self,
+        transport: &Transport,
+        t: Duration,
+    ) -> Result<Response, elasticsearch::Error> {
+        let path = match self.repositories.is_empty() {
+            true => "/_snapshot".to_string(),
+            false => format!("/_snapshot/{}", self.repositories.join(",")),
+        };
+        let (status, repositories) = send_json(transport, Method::Get, &path, &[], None, t).await?;
+        if !status.is_success() {
+            return Ok(response(
+                status.as_u16(),
+                repositories.to_string().into_bytes(),
+            ));
+        }
+
+        let mut reports = Vec::new();
+        for (name, repository) in repositories.as_object().into_iter().flatten() {
+            let path = format!("/_snapshot/{name}/_verify");
+            let (status, verify) = send_json(transport, Method::Post, &path, &[], None, t).await?;
+            let verified = match status.is_success() {
+                true => Ok(verify["nodes"].as_object().map_or(0, |n| n.len())),
+                false => Err(verify["error"]["reason"]
+                    .as_str()
+                    .map_or(verify.to_string(), str::to_string)),
+            };
+
+            let reclaimed = match (&verified, self.no_cleanup) {
+                (Ok(_), false) => {
+                    let path = format!("/_snapshot/{name}/_cleanup");
+                    send_json_ok(transport, Method::Post, &path, &[], None, t)
+                        .await?
+                        .and_then(|c| c["results"]["deleted_bytes"].as_u64())
+                }
+                _ => None,
+            };
+
+            let path = format!("/_snapshot/{name}/_all");
+            let query = [("sort", "start_time"), ("index_names", "false")];
+            let snapshots = send_json_ok(transport, Method::Get, &path, &query, None, t).await?;
+
+            reports.push(RepositoryReport {
+                name: name.clone(),
+                kind: repository["type"].as_str().unwrap_or("-").to_string(),
+                verified,
+                reclaimed,
+                snapshots,
+            });
+        }
+
+        let failed = reports.iter().filter(|r| r.verified.is_err()).count();
+        let reclaimed = reports.iter().filter_map(|r| r.reclaimed).sum();
+        let mut out = render_repositories(&reports);
+        out.push_str(&format!(
+            "\n{} repositories, {failed} failed verification, {} reclaimed\n",
+            reports.len(),
+            format_bytes(reclaimed)
+        ));
+        let status = match failed {
+            0 => 200,
+            _ => 400,
+        };
+        Ok(response(status, out.into_bytes()))
+    }
+}
+
+/// Renders a row per repository, followed by the reasons of the failed
+/// verifications.
+fn render_repositories(reports: &[RepositoryReport]) -> String {
+    let mut table = Table::new(&[
+        "repository",
+        "type",
+        "verified",
+        "reclaimed",
+        "snapshots",
+        "oldest",
+        "newest",
+    ]);
+    for report in reports {
+        let snapshots: Vec<&Value> = report
+            .snapshots
+            .as_ref()
+            .and_then(|s| s["snapshots"].as_array())
+            .into_iter()
+            .flatten()
+            .collect();
+        let describe = |snapshot: Option<&&Value>| {
+            snapshot.map_or("-".to_string(), |s| {
+                let date = s["start_time_in_millis"]
+                    .as_u64()
+                    .map_or("-".to_string(), |ms| {
+                        format_timestamp(ms / 1000)[..10].to_string()
+                    });
+                format!("{} ({date})", s["snapshot"].as_str().unwrap_or_default())
+            })
+        };
+        table.add_row(vec![
+            report.name.clone(),
+            report.kind.clone(),
+            match &report.verified {
+                Ok(nodes) => format!("ok ({nodes} nodes)"),
+                Err(_) => "failed".to_string(),
+            },
+            report.reclaimed.map_or("-".to_string(), format_bytes),
+            report
+                .snapshots
+                .as_ref()
+                .map_or("-".to_string(), |_| snapshots.len().to_string()),
+            describe(snapshots.first()),
+            describe(snapshots.last()),
+        ]);
+    }
+    let mut out = table.render();
+    for report in reports {
+        if let Err(reason) = &report.verified {
+            out.push_str(&format!("\n{}: {reason}\n", report.name));
+        }
+    }
+    out
+}
+
+/// `<processed>/<total> (<percent>%)` of the incremental size, which is what
+/// the snapshot actually copies.
+fn bytes_progress(stats: &Value) -> String {
+    let processed = stats["processed"]["size_in_bytes"]
+        .as_u64()
+        .unwrap_or_default();
+    let total = stats["incremental"]["size_in_bytes"]
+        .as_u64()
+        .unwrap_or_default();
+    let percent = match total {
+        0 => 100,
+        total => processed * 100 / total,
+    };
+    format!(
+        "{}/{} ({}%)",
+        format_bytes(processed),
+        format_bytes(total),
+        percent
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_progress_lists_pending_shards() {
+        let snapshot = json!({
+            "state": "STARTED",
+            "shards_stats": {"done": 1, "total": 2},
+            "stats": {
+                "incremental": {"size_in_bytes": 2048},
+                "processed": {"size_in_bytes": 1024},
+            },
+            "indices": {"logs": {"shards": {
+                "0": {"stage": "DONE", "stats": {}},
+                "1": {"stage": "STARTED", "stats": {
+                    "incremental": {"size_in_bytes": 1024},
+                    "processed": {"size_in_bytes": 0},
+                }},
+            }}},
+        });
+        assert_eq!(
+            render_progress(&snapshot),
+            "STARTED: 1/2 shards done, 1kb/2kb (50%)\n\
+             index  shard  stage    progress\n\
+             logs   1      STARTED  0b/1kb (0%)\n"
+        );
+    }
+
+    #[test]
+    fn parse_rename_splits_pattern_and_replacement() {
+        assert_eq!(
+            parse_rename("logs-(.*)=restored-$1").unwrap(),
+            ("logs-(.*)".to_string(), "restored-$1".to_string())
+        );
+        assert!(parse_rename("logs-(.*)").is_err());
+        assert!(parse_rename("=x").is_err());
+    }
+
+    #[test]
+    fn verify_counts_compares_renamed_indices() {
+        let indices = BTreeMap::from([
+            ("restored-a".to_string(), "logs-a".to_string()),
+            ("restored-b".to_string(), "logs-b".to_string()),
+            ("logs-c".to_string(), "logs-c".to_string()),
+        ]);
+        let counts = BTreeMap::from([
+            ("restored-a", "10"),
+            ("logs-a", "10"),
+            ("restored-b", "5"),
+            ("logs-b", "7"),
+            ("logs-c", "3"),
+        ]);
+        let (table, mismatches) = verify_counts(&indices, &counts);
+        assert_eq!(mismatches, 1);
+        assert_eq!(
+            table,
+            "index       docs.count  original  original.docs.count\n\
+             logs-c      3\n\
+             restored-a  10          logs-a    10\n\
+             restored-b  5           logs-b    7\n"
+        );
+    }
+
+    #[test]
+    fn render_repositories_summarizes_snapshots() {
+        let reports = [
+            RepositoryReport {
+                name: "backups".to_string(),
+                kind: "s3".to_string(),
+                verified: Ok(3),
+                reclaimed: Some(2048),
+                snapshots: Some(json!({"snapshots": [
+                    {"snapshot": "nightly-1", "start_time_in_millis": 1_735_689_600_000u64},
+                    {"snapshot": "nightly-2", "start_time_in_millis": 1_735_776_000_000u64},
+                ]})),
+            },
+            RepositoryReport {
+                name: "old".to_string(),
+                kind: "fs".to_string(),
+                verified: Err("store location is not accessible".to_string()),
+                reclaimed: None,
+                snapshots: Some(json!({"snapshots": []})),
+            },
+        ];
+        assert_eq!(
+            render_repositories(&reports),
+            "repository  type  verified      reclaimed  snapshots  oldest                  newest\n\
+             backups     s3    ok (3 nodes)  2kb        2          nightly-1 (2025-01-01)  nightly-2 (2025-01-02)\n\
+             old         fs    failed        -          0          -                       -\n\
+             \n\
+             old: store location is not accessible\n"
+        );
+    }
+
+    #[test]
+    fn bytes_progress_handles_empty_snapshot() {
+        assert_eq!(bytes_progress(&json!({})), "0b/0b (100%)");
+    }
+}
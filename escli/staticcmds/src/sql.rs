@@ -0,0 +1,205 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::csv::format_record;
+use crate::request::{response, send_json, send_json_ok};
+use crate::table::Table;
+use clap::{Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Map, Value, json};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Sql {
+    #[arg(help = "SQL query to run")]
+    query: String,
+
+    #[arg(
+        short,
+        long,
+        value_enum,
+        help = "Output format",
+        default_value_t = SqlFormat::Table
+    )]
+    format: SqlFormat,
+
+    #[arg(long, help = "Number of rows fetched per page", default_value_t = 1000)]
+    fetch_size: usize,
+
+    #[arg(long, help = "Stop after this number of rows")]
+    max_rows: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Print the query DSL the SQL query translates to instead of running it"
+    )]
+    translate: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum SqlFormat {
+    /// Aligned columns, printed once all rows are read
+    Table,
+    /// Comma separated values with a header line
+    Csv,
+    /// One JSON object per row
+    Json,
+}
+
+impl Sql {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("sql")
+            .about("Run an SQL query and print all its rows, following the cursor.")
+            .long_about(
+                r#"
+            Run a query with the SQL API and print every row of the result.
+
+            The SQL API returns rows page by page, with a cursor to fetch the
+            next page: the cursor is followed until the last row, or until
+            --max-rows rows were read, in which case it is closed. Rows are
+            printed as they arrive with the csv and json formats, while the
+            table format waits for all of them to align the columns.
+
+            With --translate, the query DSL generated for the SQL query is
+            printed instead, which helps understanding or tuning a query.
+
+            Example usage:
+                escli utils sql "SELECT host, COUNT(*) FROM logs GROUP BY host"
+                escli utils sql "SELECT * FROM orders WHERE total > 100" --format csv > orders.csv
+                escli utils sql "SELECT * FROM logs WHERE level = 'error'" --translate
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let mut body = json!({ "query": self.query, "fetch_size": self.fetch_size });
+
+        if self.translate {
+            let (status, dsl) = send_json(
+                &transport,
+                Method::Post,
+                "/_sql/translate",
+                &[],
+                Some(&body),
+                t,
+            )
+            .await?;
+            let pretty = serde_json::to_string_pretty(&dsl).unwrap_or_default();
+            return Ok(response(
+                status.as_u16(),
+                format!("{pretty}\n").into_bytes(),
+            ));
+        }
+
+        let query = [("format", "json")];
+        let mut columns: Vec<String> = Vec::new();
+        let mut table: Option<Table> = None;
+        let mut read = 0;
+        loop {
+            let Some(page) =
+                send_json_ok(&transport, Method::Post, "/_sql", &query, Some(&body), t).await?
+            else {
+                return Ok(response(500, Vec::new()));
+            };
+            // Only the first page describes the columns.
+            if let Some(described) = page["columns"].as_array() {
+                columns = described
+                    .iter()
+                    .map(|c| c["name"].as_str().unwrap_or_default().to_string())
+                    .collect();
+                match self.format {
+                    SqlFormat::Table => {
+                        let headers: Vec<&str> = columns.iter().map(String::as_str).collect();
+                        table = Some(Table::new(&headers));
+                    }
+                    SqlFormat::Csv => print!("{}", format_record(&columns, ',')),
+                    SqlFormat::Json => {}
+                }
+            }
+
+            let rows = page["rows"].as_array().cloned().unwrap_or_default();
+            let limit = self.max_rows.map_or(rows.len(), |max| max - read);
+            for row in rows.iter().take(limit) {
+                let cells = row.as_array().cloned().unwrap_or_default();
+                match (&mut table, self.format) {
+                    (Some(table), _) => table.add_row(cells.iter().map(cell).collect()),
+                    (None, SqlFormat::Json) => println!("{}", row_object(&columns, cells)),
+                    (None, _) => {
+                        let cells: Vec<String> = cells.iter().map(cell).collect();
+                        print!("{}", format_record(&cells, ','));
+                    }
+                }
+                read += 1;
+            }
+
+            let Some(cursor) = page["cursor"].as_str() else {
+                break;
+            };
+            body = json!({ "cursor": cursor });
+            if self.max_rows.is_some_and(|max| read >= max) {
+                let _ =
+                    send_json(&transport, Method::Post, "/_sql/close", &[], Some(&body), t).await;
+                break;
+            }
+        }
+
+        let out = table.map(|t| t.render()).unwrap_or_default();
+        Ok(response(200, out.into_bytes()))
+    }
+}
+
+/// The text of a value, empty for nulls.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Pairs the values of a row with the column names.
+fn row_object(columns: &[String], cells: Vec<Value>) -> Value {
+    let object: Map<String, Value> = columns.iter().cloned().zip(cells).collect();
+    Value::Object(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_rendered_as_cells_and_objects() {
+        let cells = vec![json!("a, b"), json!(3), Value::Null];
+        assert_eq!(
+            cells.iter().map(cell).collect::<Vec<_>>(),
+            vec!["a, b", "3", ""]
+        );
+        let columns = vec!["name".to_string(), "count".to_string(), "note".to_string()];
+        assert_eq!(
+            row_object(&columns, cells),
+            json!({"name": "a, b", "count": 3, "note": null})
+        );
+    }
+}
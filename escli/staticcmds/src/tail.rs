@@ -0,0 +1,258 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_json_arg;
+use crate::request::{response, send_json_ok};
+use crate::units::parse_duration;
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::io::Write;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Tail {
+    #[arg(help = "Index, alias or data stream to follow")]
+    index: String,
+
+    #[arg(short, long, help = "Keep polling for new documents")]
+    follow: bool,
+
+    #[arg(
+        short = 'n',
+        long,
+        help = "Number of latest documents to print first",
+        default_value_t = 10
+    )]
+    lines: u32,
+
+    #[arg(
+        long,
+        help = "Field the documents are ordered by",
+        default_value = "@timestamp"
+    )]
+    timestamp_field: String,
+
+    #[arg(
+        short,
+        long,
+        help = "Only print documents matching this query, inline JSON or @file"
+    )]
+    query: Option<String>,
+
+    #[arg(
+        long,
+        help = "Time between two polls when following",
+        default_value = "2s",
+        value_parser = parse_duration
+    )]
+    interval: Duration,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of documents fetched per request",
+        default_value_t = 1000
+    )]
+    size: u32,
+}
+
+/// Position of the last printed document. Documents sharing the timestamp of
+/// the cursor are remembered by id, so that a poll starting at that timestamp
+/// does not print them twice.
+#[derive(Debug, Default)]
+struct Cursor {
+    sort: Option<Value>,
+    seen: HashSet<String>,
+}
+
+impl Cursor {
+    /// Moves the cursor to `hit`, returning false when it was already printed.
+    fn advance(&mut self, hit: &Value) -> bool {
+        let sort = &hit["sort"][0];
+        let id = hit["_id"].as_str().unwrap_or_default().to_string();
+        if self.sort.as_ref() != Some(sort) {
+            self.sort = Some(sort.clone());
+            self.seen.clear();
+        }
+        self.seen.insert(id)
+    }
+}
+
+impl Tail {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("tail")
+            .about("Print the latest documents of an index and follow new ones.")
+            .long_about(
+                r#"
+            Print the latest documents of an index, alias or data stream as
+            NDJSON, ordered by --timestamp-field, like tail does for a file.
+
+            With --follow the index is queried again every --interval for
+            documents at or after the last printed timestamp, paging through
+            them with search_after, and new ones are streamed as they arrive
+            until the command is interrupted.
+
+            Example usage:
+                escli utils tail logs-app -n 20
+                escli utils tail logs-app -f --query '{"term": {"log.level": "error"}}'
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let query = match &self.query {
+            Some(q) => Some(read_json_arg(q).await?),
+            None => None,
+        };
+        let path = format!("/{}/_search", self.index);
+        let mut cursor = Cursor::default();
+
+        let body = self.search_body(query.as_ref(), None, None, "desc", self.lines.max(1));
+        let Some(res) = send_json_ok(&transport, Method::Post, &path, &[], Some(&body), t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let mut hits = hits(&res);
+        hits.reverse();
+        let skip = hits.len().saturating_sub(self.lines as usize);
+        let mut stdout = std::io::stdout().lock();
+        for (i, hit) in hits.iter().enumerate() {
+            cursor.advance(hit);
+            if i >= skip {
+                let _ = writeln!(stdout, "{}", hit["_source"]);
+            }
+        }
+        let _ = stdout.flush();
+        drop(stdout);
+
+        while self.follow {
+            tokio::time::sleep(self.interval).await;
+            let mut search_after = None;
+            loop {
+                let body = self.search_body(
+                    query.as_ref(),
+                    cursor.sort.as_ref(),
+                    search_after.as_ref(),
+                    "asc",
+                    self.size,
+                );
+                let Some(res) =
+                    send_json_ok(&transport, Method::Post, &path, &[], Some(&body), t).await?
+                else {
+                    return Ok(response(500, Vec::new()));
+                };
+                let hits = hits(&res);
+                let mut stdout = std::io::stdout().lock();
+                for hit in &hits {
+                    if cursor.advance(hit) {
+                        let _ = writeln!(stdout, "{}", hit["_source"]);
+                    }
+                }
+                let _ = stdout.flush();
+                match hits.last() {
+                    Some(last) if hits.len() == self.size as usize => {
+                        search_after = Some(last["sort"].clone())
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        Ok(response(200, Vec::new()))
+    }
+
+    /// Builds a search request sorted on the timestamp field, restricted to
+    /// the documents at or after `from` when given.
+    fn search_body(
+        &self,
+        query: Option<&Value>,
+        from: Option<&Value>,
+        search_after: Option<&Value>,
+        order: &str,
+        size: u32,
+    ) -> Value {
+        let mut filter = Vec::new();
+        if let Some(query) = query {
+            filter.push(query.clone());
+        }
+        if let Some(from) = from {
+            filter.push(json!({"range": {&self.timestamp_field: {"gte": from}}}));
+        }
+        let mut body = json!({
+            "size": size,
+            "sort": [{&self.timestamp_field: {"order": order}}],
+            "query": {"bool": {"filter": filter}},
+        });
+        if let Some(search_after) = search_after {
+            body["search_after"] = search_after.clone();
+        }
+        body
+    }
+}
+
+fn hits(res: &Value) -> Vec<Value> {
+    res["hits"]["hits"].as_array().cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_skips_documents_already_printed_at_same_timestamp() {
+        let mut cursor = Cursor::default();
+        assert!(cursor.advance(&json!({"_id": "a", "sort": [1000]})));
+        assert!(cursor.advance(&json!({"_id": "b", "sort": [1000]})));
+        assert!(!cursor.advance(&json!({"_id": "a", "sort": [1000]})));
+        assert!(cursor.advance(&json!({"_id": "c", "sort": [2000]})));
+        assert_eq!(cursor.seen.len(), 1);
+    }
+
+    #[test]
+    fn search_body_filters_from_cursor() {
+        let tail = Tail::try_parse_from(["tail", "logs", "--timestamp-field", "ts"]).unwrap();
+        let body = tail.search_body(
+            Some(&json!({"term": {"a": 1}})),
+            Some(&json!(1000)),
+            Some(&json!([1000])),
+            "asc",
+            50,
+        );
+        assert_eq!(
+            body,
+            json!({
+                "size": 50,
+                "sort": [{"ts": {"order": "asc"}}],
+                "query": {"bool": {"filter": [
+                    {"term": {"a": 1}},
+                    {"range": {"ts": {"gte": 1000}}},
+                ]}},
+                "search_after": [1000],
+            })
+        );
+    }
+}
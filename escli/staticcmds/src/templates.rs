@@ -0,0 +1,560 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::diff_index::{diff, use_color};
+use crate::input::read_to_string;
+use crate::profile::profile_transport;
+use crate::request::{response, send_json_ok};
+use crate::table::Table;
+use clap::{Args, Command, CommandFactory, Parser, Subcommand};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Templates {
+    #[command(subcommand)]
+    action: TemplatesAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum TemplatesAction {
+    #[command(about = "Compare the templates of the cluster with a file or another cluster")]
+    Diff(DiffArgs),
+    #[command(about = "Report index templates with overlapping patterns and equal priorities")]
+    Conflicts(SourceArgs),
+    #[command(about = "Show which index templates match an index name")]
+    Match(MatchArgs),
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    #[arg(
+        short,
+        long,
+        required_unless_present = "to_profile",
+        conflicts_with = "to_profile",
+        help = "Templates file in YAML or JSON format, use - to read from stdin"
+    )]
+    file: Option<PathBuf>,
+
+    #[arg(long, help = "Profile of the cluster to compare with")]
+    to_profile: Option<String>,
+
+    #[arg(long, help = "Also compare the templates managed by Elasticsearch")]
+    include_managed: bool,
+
+    #[arg(long, help = "Never color the output")]
+    no_color: bool,
+}
+
+#[derive(Args, Debug)]
+struct SourceArgs {
+    #[arg(
+        short,
+        long,
+        help = "Templates file added over the cluster templates, in YAML or JSON format"
+    )]
+    file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct MatchArgs {
+    #[arg(help = "Name of the index or data stream to create")]
+    index: String,
+
+    #[command(flatten)]
+    source: SourceArgs,
+}
+
+/// Composable index templates and component templates, keyed by name.
+#[derive(Debug, Default, Deserialize)]
+struct TemplateSet {
+    #[serde(default, alias = "templates")]
+    index_templates: BTreeMap<String, Value>,
+    #[serde(default)]
+    component_templates: BTreeMap<String, Value>,
+}
+
+impl Templates {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("templates")
+            .about("Compare index templates and detect priority conflicts.")
+            .long_about(
+                r#"
+            Inspect the composable index templates and component templates of
+            the cluster.
+
+            diff compares them with a file or with the cluster of another
+            profile and prints the differences of each template, using the
+            same notation as diff-index. Templates managed by Elasticsearch
+            are skipped unless --include-managed is given.
+
+            conflicts lists the index templates whose patterns overlap: at the
+            same priority Elasticsearch cannot tell which one applies. Index
+            templates composed of missing component templates are reported
+            too. The command fails when a conflict is found.
+
+            match shows the index templates whose patterns match an index
+            name, by decreasing priority, and the one that would be applied.
+
+            conflicts and match add the templates of --file over the ones of
+            the cluster, to check a change before applying it. The file lists
+            templates by name, the templates key of an apply manifest is
+            accepted too:
+
+                index_templates:
+                  logs:
+                    index_patterns: ["logs-*"]
+                    priority: 100
+                    composed_of: [logs-mappings]
+                component_templates:
+                  logs-mappings:
+                    template:
+                      mappings: { properties: { message: { type: text } } }
+
+            Example usage:
+                escli utils templates diff -f templates.yaml
+                escli utils templates diff --to-profile staging
+                escli utils templates conflicts -f templates.yaml
+                escli utils templates match logs-app-2025.01.01
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let Some(cluster) = TemplateSet::fetch(&transport, t).await? else {
+            return Ok(response(500, Vec::new()));
+        };
+
+        match self.action {
+            TemplatesAction::Diff(args) => {
+                let (other, label) = match (&args.file, &args.to_profile) {
+                    (Some(path), _) => (TemplateSet::read(path).await?, path.display().to_string()),
+                    (None, Some(name)) => {
+                        let Some(other) = TemplateSet::fetch(&profile_transport(name)?, t).await?
+                        else {
+                            return Ok(response(500, Vec::new()));
+                        };
+                        (other, name.clone())
+                    }
+                    (None, None) => unreachable!("clap requires --file or --to-profile"),
+                };
+                Ok(response(
+                    200,
+                    render_diff(&cluster, &other, &label, &args).into_bytes(),
+                ))
+            }
+            TemplatesAction::Conflicts(args) => {
+                let set = cluster.overlay(args.file.as_deref()).await?;
+                let (table, conflicts) = conflicts(&set);
+                print!("{table}");
+                match conflicts {
+                    0 => Ok(response(200, b"No conflicts\n".to_vec())),
+                    n => Ok(response(
+                        409,
+                        format!("Found {n} conflict(s)\n").into_bytes(),
+                    )),
+                }
+            }
+            TemplatesAction::Match(args) => {
+                let set = cluster.overlay(args.source.file.as_deref()).await?;
+                Ok(response(
+                    200,
+                    render_matches(&set, &args.index).into_bytes(),
+                ))
+            }
+        }
+    }
+}
+
+impl TemplateSet {
+    async fn fetch(
+        transport: &Transport,
+        t: Duration,
+    ) -> Result<Option<Self>, elasticsearch::Error> {
+        let mut set = TemplateSet::default();
+        let Some(res) =
+            send_json_ok(transport, Method::Get, "/_index_template", &[], None, t).await?
+        else {
+            return Ok(None);
+        };
+        for entry in res["index_templates"].as_array().into_iter().flatten() {
+            if let Some(name) = entry["name"].as_str() {
+                set.index_templates
+                    .insert(name.to_string(), entry["index_template"].clone());
+            }
+        }
+        let Some(res) =
+            send_json_ok(transport, Method::Get, "/_component_template", &[], None, t).await?
+        else {
+            return Ok(None);
+        };
+        for entry in res["component_templates"].as_array().into_iter().flatten() {
+            if let Some(name) = entry["name"].as_str() {
+                set.component_templates
+                    .insert(name.to_string(), entry["component_template"].clone());
+            }
+        }
+        Ok(Some(set))
+    }
+
+    async fn read(path: &Path) -> Result<Self, IoError> {
+        serde_yaml::from_str(&read_to_string(path).await?).map_err(|e| {
+            eprintln!("Failed to parse templates file {:?}: {}", path, e);
+            IoError::new(IoErrorKind::InvalidData, e)
+        })
+    }
+
+    /// Adds the templates of the file over these ones, replacing the
+    /// templates of the same name.
+    async fn overlay(mut self, path: Option<&Path>) -> Result<Self, IoError> {
+        if let Some(path) = path {
+            let file = TemplateSet::read(path).await?;
+            self.index_templates.extend(file.index_templates);
+            self.component_templates.extend(file.component_templates);
+        }
+        Ok(self)
+    }
+}
+
+fn priority(template: &Value) -> u64 {
+    template["priority"].as_u64().unwrap_or(0)
+}
+
+fn patterns(template: &Value) -> Vec<&str> {
+    match &template["index_patterns"] {
+        Value::String(p) => vec![p.as_str()],
+        v => v
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect(),
+    }
+}
+
+fn is_managed(template: &Value) -> bool {
+    template["_meta"]["managed"].as_bool().unwrap_or(false)
+}
+
+/// Brings a template to the form returned by the cluster, so that a file
+/// written by hand compares equal: settings are flattened under the index
+/// prefix and their values turned to strings.
+fn normalize(template: &Value) -> Value {
+    let mut template = template.clone();
+    if let Some(settings) = template.pointer_mut("/template/settings") {
+        let mut flat = Map::new();
+        flatten_settings("", settings, &mut flat);
+        *settings = Value::Object(flat);
+    }
+    template
+}
+
+fn flatten_settings(prefix: &str, value: &Value, out: &mut Map<String, Value>) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                let key = match prefix.is_empty() {
+                    true => key.clone(),
+                    false => format!("{prefix}.{key}"),
+                };
+                flatten_settings(&key, child, out);
+            }
+        }
+        _ => {
+            let key = match prefix.starts_with("index.") {
+                true => prefix.to_string(),
+                false => format!("index.{prefix}"),
+            };
+            let value = match value {
+                Value::String(_) | Value::Array(_) | Value::Null => value.clone(),
+                other => Value::String(other.to_string()),
+            };
+            out.insert(key, value);
+        }
+    }
+}
+
+fn render_diff(cluster: &TemplateSet, other: &TemplateSet, label: &str, args: &DiffArgs) -> String {
+    let color = use_color(args.no_color);
+    let mut out = String::new();
+    for (kind, a, b) in [
+        (
+            "index template",
+            &cluster.index_templates,
+            &other.index_templates,
+        ),
+        (
+            "component template",
+            &cluster.component_templates,
+            &other.component_templates,
+        ),
+    ] {
+        let mut names: Vec<&String> = a.keys().chain(b.keys()).collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            let (left, right) = (a.get(name), b.get(name));
+            let managed = left.into_iter().chain(right).any(is_managed);
+            if managed && !args.include_managed {
+                continue;
+            }
+            match (left, right) {
+                (Some(_), None) => out.push_str(&format!("{kind} {name}: only in the cluster\n")),
+                (None, Some(_)) => out.push_str(&format!("{kind} {name}: only in {label}\n")),
+                (Some(left), Some(right)) => {
+                    let changes = diff(&normalize(left), &normalize(right));
+                    if changes.is_empty() {
+                        continue;
+                    }
+                    out.push_str(&format!("{kind} {name}:\n"));
+                    for change in changes {
+                        out.push_str(&format!("  {}\n", change.render(color)));
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+    }
+    if out.is_empty() {
+        out.push_str("No differences\n");
+    }
+    out
+}
+
+/// Lists the index templates with overlapping patterns, and the missing
+/// component templates. Returns the rendered table and the number of
+/// conflicts among them.
+fn conflicts(set: &TemplateSet) -> (String, usize) {
+    let mut table = Table::new(&["template", "priority", "overlaps", "priority", "status"]);
+    let mut conflicts = 0;
+    let templates: Vec<(&String, &Value)> = set.index_templates.iter().collect();
+    for (i, (name, template)) in templates.iter().enumerate() {
+        for (other_name, other) in &templates[i + 1..] {
+            let overlap = patterns(template)
+                .iter()
+                .any(|a| patterns(other).iter().any(|b| patterns_overlap(a, b)));
+            if !overlap {
+                continue;
+            }
+            let (p, q) = (priority(template), priority(other));
+            let status = match p.cmp(&q) {
+                std::cmp::Ordering::Equal => {
+                    conflicts += 1;
+                    "conflict".to_string()
+                }
+                std::cmp::Ordering::Greater => format!("{name} wins"),
+                std::cmp::Ordering::Less => format!("{other_name} wins"),
+            };
+            table.add_row(vec![
+                name.to_string(),
+                p.to_string(),
+                other_name.to_string(),
+                q.to_string(),
+                status,
+            ]);
+        }
+        for component in template["composed_of"].as_array().into_iter().flatten() {
+            let component = component.as_str().unwrap_or_default();
+            if !set.component_templates.contains_key(component) {
+                conflicts += 1;
+                table.add_row(vec![
+                    name.to_string(),
+                    priority(template).to_string(),
+                    component.to_string(),
+                    "-".to_string(),
+                    "missing component template".to_string(),
+                ]);
+            }
+        }
+    }
+    match table.is_empty() {
+        true => (String::new(), conflicts),
+        false => (table.render(), conflicts),
+    }
+}
+
+fn render_matches(set: &TemplateSet, index: &str) -> String {
+    let mut matching: Vec<(&String, &Value)> = set
+        .index_templates
+        .iter()
+        .filter(|(_, t)| patterns(t).iter().any(|p| glob_match(p, index)))
+        .collect();
+    if matching.is_empty() {
+        return format!("No index template matches {index}\n");
+    }
+    matching.sort_by_key(|(_, t)| std::cmp::Reverse(priority(t)));
+
+    let mut table = Table::new(&["template", "priority", "index_patterns", "status"]);
+    let top = priority(matching[0].1);
+    let tied = matching.iter().filter(|(_, t)| priority(t) == top).count();
+    for (i, (name, template)) in matching.iter().enumerate() {
+        let status = match (i, tied) {
+            (0, 1) => "applied",
+            (_, n) if n > 1 && priority(template) == top => "conflict",
+            _ => "shadowed",
+        };
+        table.add_row(vec![
+            name.to_string(),
+            priority(template).to_string(),
+            patterns(template).join(","),
+            status.to_string(),
+        ]);
+    }
+    table.render()
+}
+
+/// Matches a name against an index pattern, where `*` matches any sequence.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=name.len())
+                .filter(|i| name.is_char_boundary(*i))
+                .any(|i| glob_match(rest, &name[i..]))
+        }
+    }
+}
+
+/// Whether some index name matches both patterns.
+fn patterns_overlap(a: &str, b: &str) -> bool {
+    match (a.chars().next(), b.chars().next()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            patterns_overlap(&a[1..], b) || (!b.is_empty() && patterns_overlap(a, skip_char(b)))
+        }
+        (_, Some('*')) => {
+            patterns_overlap(a, &b[1..]) || (!a.is_empty() && patterns_overlap(skip_char(a), b))
+        }
+        (Some(x), Some(y)) => x == y && patterns_overlap(skip_char(a), skip_char(b)),
+        _ => false,
+    }
+}
+
+fn skip_char(s: &str) -> &str {
+    let mut chars = s.chars();
+    chars.next();
+    chars.as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn glob_match_handles_wildcards() {
+        assert!(glob_match("logs-*", "logs-app"));
+        assert!(glob_match("*-app-*", "logs-app-2025"));
+        assert!(!glob_match("logs-*", "metrics-app"));
+        assert!(glob_match("exact", "exact"));
+    }
+
+    #[test]
+    fn patterns_overlap_detects_common_names() {
+        assert!(patterns_overlap("logs-*", "logs-app-*"));
+        assert!(patterns_overlap("logs-*", "*-app"));
+        assert!(!patterns_overlap("logs-*", "metrics-*"));
+        assert!(!patterns_overlap("logs", "logs-*-x"));
+    }
+
+    #[test]
+    fn conflicts_flags_equal_priorities_and_missing_components() {
+        let set = TemplateSet {
+            index_templates: BTreeMap::from([
+                (
+                    "a".to_string(),
+                    json!({"index_patterns": ["logs-*"], "priority": 10}),
+                ),
+                (
+                    "b".to_string(),
+                    json!({"index_patterns": ["logs-app-*"], "priority": 10}),
+                ),
+                (
+                    "c".to_string(),
+                    json!({"index_patterns": ["logs-*"], "priority": 20, "composed_of": ["x"]}),
+                ),
+            ]),
+            component_templates: BTreeMap::new(),
+        };
+        let (table, conflicts) = conflicts(&set);
+        assert_eq!(conflicts, 2);
+        assert_eq!(
+            table,
+            "template  priority  overlaps  priority  status\n\
+             a         10        b         10        conflict\n\
+             a         10        c         20        c wins\n\
+             b         10        c         20        c wins\n\
+             c         20        x         -         missing component template\n"
+        );
+    }
+
+    #[test]
+    fn normalize_flattens_settings() {
+        let template = json!({"template": {"settings": {"number_of_replicas": 1, "index": {"codec": "best_compression"}}}});
+        assert_eq!(
+            normalize(&template),
+            json!({"template": {"settings": {
+                "index.number_of_replicas": "1",
+                "index.codec": "best_compression",
+            }}})
+        );
+    }
+
+    #[test]
+    fn render_matches_orders_by_priority() {
+        let set = TemplateSet {
+            index_templates: BTreeMap::from([
+                (
+                    "logs".to_string(),
+                    json!({"index_patterns": ["logs-*"], "priority": 10}),
+                ),
+                (
+                    "app".to_string(),
+                    json!({"index_patterns": ["logs-app-*"], "priority": 50}),
+                ),
+                (
+                    "other".to_string(),
+                    json!({"index_patterns": ["metrics-*"]}),
+                ),
+            ]),
+            component_templates: BTreeMap::new(),
+        };
+        assert_eq!(
+            render_matches(&set, "logs-app-1"),
+            "template  priority  index_patterns  status\n\
+             app       50        logs-app-*      applied\n\
+             logs      10        logs-*          shadowed\n"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json_ok};
+use crate::table::Table;
+use crate::units::{format_bytes, parse_duration};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+#[derive(Parser, Debug)]
+pub struct Top {
+    #[arg(
+        short = 'd',
+        long,
+        help = "Time between two refreshes",
+        default_value = "3s",
+        value_parser = parse_duration
+    )]
+    interval: Duration,
+
+    #[arg(
+        short = 'n',
+        long,
+        help = "Stop after this many refreshes, runs until interrupted otherwise"
+    )]
+    iterations: Option<u64>,
+}
+
+impl Top {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("top")
+            .about("Live dashboard of node load and cluster activity.")
+            .long_about(
+                r#"
+            Display a dashboard refreshed every --interval, like top for
+            Elasticsearch. The header shows the cluster health, pending tasks
+            and the cluster-wide indexing and search rates; the table lists
+            the CPU, load average and heap usage of each node along with its
+            own rates.
+
+            Rates are computed between two refreshes, so they are only shown
+            from the second one. The screen is only cleared between refreshes
+            when the output is a terminal.
+
+            Example usage:
+                escli utils top
+                escli utils top --interval 10s
+                escli utils top -n 2 > snapshot.txt
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let terminal = std::io::stdout().is_terminal();
+        let mut previous: Option<(Value, Instant)> = None;
+        let mut refreshes = 0;
+
+        loop {
+            let Some(health) =
+                send_json_ok(&transport, Method::Get, "/_cluster/health", &[], None, t).await?
+            else {
+                return Ok(response(502, Vec::new()));
+            };
+            let Some(stats) = send_json_ok(
+                &transport,
+                Method::Get,
+                "/_nodes/stats/os,jvm,indices",
+                &[],
+                None,
+                t,
+            )
+            .await?
+            else {
+                return Ok(response(502, Vec::new()));
+            };
+            let now = Instant::now();
+
+            let frame = render_dashboard(
+                &health,
+                &stats,
+                previous.as_ref().map(|(s, at)| (s, now - *at)),
+            );
+            let mut stdout = std::io::stdout().lock();
+            if terminal {
+                let _ = write!(stdout, "{CLEAR_SCREEN}");
+            }
+            let _ = writeln!(stdout, "{frame}");
+            let _ = stdout.flush();
+            drop(stdout);
+
+            refreshes += 1;
+            if self.iterations.is_some_and(|n| refreshes >= n) {
+                return Ok(response(200, Vec::new()));
+            }
+            previous = Some((stats, now));
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+/// The indexing and search operation counters of a node.
+fn counters(node: &Value) -> (u64, u64) {
+    (
+        node["indices"]["indexing"]["index_total"]
+            .as_u64()
+            .unwrap_or(0),
+        node["indices"]["search"]["query_total"]
+            .as_u64()
+            .unwrap_or(0),
+    )
+}
+
+/// Operations per second between two counter values.
+fn rate(current: u64, previous: u64, elapsed: Duration) -> f64 {
+    current.saturating_sub(previous) as f64 / elapsed.as_secs_f64().max(0.001)
+}
+
+/// Renders one refresh of the dashboard. `previous` holds the node stats of
+/// the last refresh and the time elapsed since, used to compute the rates.
+fn render_dashboard(health: &Value, stats: &Value, previous: Option<(&Value, Duration)>) -> String {
+    let empty = serde_json::Map::new();
+    let nodes = stats["nodes"].as_object().unwrap_or(&empty);
+    let mut nodes: Vec<(&String, &Value)> = nodes.iter().collect();
+    nodes.sort_by(|(_, a), (_, b)| a["name"].as_str().cmp(&b["name"].as_str()));
+
+    let mut table = Table::new(&[
+        "node",
+        "cpu",
+        "load_1m",
+        "heap.percent",
+        "heap.max",
+        "indexing/s",
+        "search/s",
+    ]);
+    let (mut indexing, mut search) = (0.0, 0.0);
+    for (id, node) in nodes {
+        let previous = previous.and_then(|(p, elapsed)| {
+            let node = p["nodes"].get(id)?;
+            Some((counters(node), elapsed))
+        });
+        let (index_total, query_total) = counters(node);
+        let rates = previous.map(|((index_prev, query_prev), elapsed)| {
+            (
+                rate(index_total, index_prev, elapsed),
+                rate(query_total, query_prev, elapsed),
+            )
+        });
+        if let Some((i, s)) = rates {
+            indexing += i;
+            search += s;
+        }
+
+        table.add_row(vec![
+            node["name"].as_str().unwrap_or(id).to_string(),
+            node["os"]["cpu"]["percent"]
+                .as_u64()
+                .map_or("-".to_string(), |p| format!("{p}%")),
+            node["os"]["cpu"]["load_average"]["1m"]
+                .as_f64()
+                .map_or("-".to_string(), |l| format!("{l:.2}")),
+            node["jvm"]["mem"]["heap_used_percent"]
+                .as_u64()
+                .map_or("-".to_string(), |p| format!("{p}%")),
+            node["jvm"]["mem"]["heap_max_in_bytes"]
+                .as_u64()
+                .map_or("-".to_string(), format_bytes),
+            rates.map_or("-".to_string(), |(i, _)| format!("{i:.1}")),
+            rates.map_or("-".to_string(), |(_, s)| format!("{s:.1}")),
+        ]);
+    }
+
+    let rates = match previous {
+        Some(_) => format!("indexing {indexing:.1}/s, search {search:.1}/s"),
+        None => "indexing -/s, search -/s".to_string(),
+    };
+    format!(
+        "cluster {} status {}, {} nodes, {} pending tasks, {} unassigned shards\n{}\n\n{}",
+        health["cluster_name"].as_str().unwrap_or("-"),
+        health["status"].as_str().unwrap_or("-"),
+        health["number_of_nodes"].as_u64().unwrap_or(0),
+        health["number_of_pending_tasks"].as_u64().unwrap_or(0),
+        health["unassigned_shards"].as_u64().unwrap_or(0),
+        rates,
+        table.render()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stats(index_total: u64, query_total: u64) -> Value {
+        json!({"nodes": {"n1": {
+            "name": "es-1",
+            "os": {"cpu": {"percent": 12, "load_average": {"1m": 0.5}}},
+            "jvm": {"mem": {"heap_used_percent": 40, "heap_max_in_bytes": 1073741824}},
+            "indices": {
+                "indexing": {"index_total": index_total},
+                "search": {"query_total": query_total},
+            },
+        }}})
+    }
+
+    #[test]
+    fn dashboard_computes_rates_from_previous_refresh() {
+        let health = json!({
+            "cluster_name": "prod",
+            "status": "green",
+            "number_of_nodes": 1,
+            "number_of_pending_tasks": 2,
+            "unassigned_shards": 0,
+        });
+        let previous = stats(100, 10);
+        let frame = render_dashboard(
+            &health,
+            &stats(400, 40),
+            Some((&previous, Duration::from_secs(3))),
+        );
+        assert!(frame.starts_with(
+            "cluster prod status green, 1 nodes, 2 pending tasks, 0 unassigned shards\n\
+             indexing 100.0/s, search 10.0/s\n"
+        ));
+        assert!(frame.contains("es-1  12%  0.50     40%           1gb       100.0       10.0"));
+    }
+
+    #[test]
+    fn dashboard_without_previous_refresh_has_no_rates() {
+        let frame = render_dashboard(&json!({}), &stats(400, 40), None);
+        assert!(frame.contains("indexing -/s, search -/s\n"));
+        assert!(frame.contains("1gb       -           -"));
+    }
+}
@@ -0,0 +1,327 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_json_arg;
+use crate::profile::profile_transport;
+use crate::request::{print_response, response, send_json, send_json_ok};
+use clap::{Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::headers::{CONTENT_TYPE, HeaderMap, HeaderValue};
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct Transfer {
+    #[arg(
+        long,
+        help = "Profile of the source cluster, defaults to the cluster of --url"
+    )]
+    from_profile: Option<String>,
+
+    #[arg(long, help = "Profile of the destination cluster")]
+    to_profile: String,
+
+    #[arg(
+        short,
+        long,
+        required = true,
+        value_delimiter = ',',
+        help = "Source indices, comma separated, wildcards allowed"
+    )]
+    index: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Destination index, defaults to the source index of each document"
+    )]
+    target_index: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Only transfer documents matching this query, inline JSON or @file"
+    )]
+    query: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Number of documents per search page and bulk request",
+        default_value_t = 1000
+    )]
+    size: usize,
+
+    #[arg(
+        long,
+        help = "How long the point in time is kept alive between pages",
+        default_value = "5m"
+    )]
+    keep_alive: String,
+}
+
+/// Totals of a transfer.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Progress {
+    transferred: usize,
+    errors: usize,
+}
+
+impl Transfer {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("transfer")
+            .about("Copy documents from one cluster to another without an intermediate file.")
+            .long_about(
+                r#"
+            Stream documents from indices of a source cluster directly into a
+            destination cluster.
+
+            The source is read page by page with a point in time and
+            search_after, each page being written to the destination with a
+            _bulk request, so that arbitrarily large indices can be copied
+            with constant memory. Document ids are preserved; documents keep
+            their index name unless --target-index is given.
+
+            Both clusters are designated by profiles. When --from-profile is
+            omitted, the cluster of --url is the source.
+
+            Only documents are copied: create the destination indices first,
+            or rely on index templates, to get the right mappings.
+
+            Example usage:
+                escli utils transfer --from-profile prod --to-profile staging --index 'logs-*'
+                escli utils transfer --from-profile prod --to-profile staging --index orders \
+                    --target-index orders-copy --query '{"range": {"@timestamp": {"gte": "now-1d"}}}'
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let source = match &self.from_profile {
+            Some(name) => profile_transport(name)?,
+            None => transport,
+        };
+        self.run(source, timeout).await
+    }
+
+    /// Runs a transfer whose source is given with --from-profile, without a
+    /// --url connection, and returns the exit code of the process.
+    pub async fn execute_standalone(self, timeout: Option<Duration>) -> i32 {
+        let Some(name) = self.from_profile.clone() else {
+            eprintln!("--from-profile is required without --url");
+            return 1;
+        };
+        let result = match profile_transport(&name) {
+            Ok(source) => self.run(source, timeout).await,
+            Err(e) => Err(e.into()),
+        };
+        print_response(result).await
+    }
+
+    async fn run(
+        &self,
+        source: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let destination = profile_transport(&self.to_profile)?;
+        let query = match &self.query {
+            Some(q) => Some(read_json_arg(q).await?),
+            None => None,
+        };
+
+        let path = format!("/{}/_pit", self.index.join(","));
+        let pit_query = [("keep_alive", self.keep_alive.as_str())];
+        let Some(pit) = send_json_ok(&source, Method::Post, &path, &pit_query, None, t).await?
+        else {
+            return Ok(response(500, Vec::new()));
+        };
+        let mut pit_id = pit["id"].as_str().unwrap_or_default().to_string();
+
+        let mut progress = Progress::default();
+        let mut search_after: Option<Value> = None;
+        let result = loop {
+            let body = self.search_body(&pit_id, query.as_ref(), search_after.as_ref());
+            let page =
+                match send_json_ok(&source, Method::Post, "/_search", &[], Some(&body), t).await {
+                    Ok(Some(page)) => page,
+                    Ok(None) => break Ok(false),
+                    Err(e) => break Err(e),
+                };
+            let hits = page["hits"]["hits"].as_array().cloned().unwrap_or_default();
+            let Some(last) = hits.last() else {
+                break Ok(true);
+            };
+            search_after = Some(last["sort"].clone());
+            if let Some(id) = page["pit_id"].as_str() {
+                pit_id = id.to_string();
+            }
+
+            match self.bulk(&destination, &hits, t).await {
+                Ok(errors) => {
+                    progress.transferred += hits.len() - errors;
+                    progress.errors += errors;
+                }
+                Err(e) => break Err(e),
+            }
+            eprintln!("Transferred {} documents", progress.transferred);
+        };
+
+        let close = json!({ "id": pit_id });
+        let _ = send_json(&source, Method::Delete, "/_pit", &[], Some(&close), t).await;
+
+        let completed = result?;
+        let summary = format!(
+            "Transferred {} documents to {}, {} errors\n",
+            progress.transferred, self.to_profile, progress.errors
+        );
+        let status = match completed && progress.errors == 0 {
+            true => 200,
+            false => 400,
+        };
+        Ok(response(status, summary.into_bytes()))
+    }
+
+    fn search_body(
+        &self,
+        pit_id: &str,
+        query: Option<&Value>,
+        search_after: Option<&Value>,
+    ) -> Value {
+        let mut body = json!({
+            "size": self.size,
+            "pit": { "id": pit_id, "keep_alive": self.keep_alive },
+            "sort": ["_shard_doc"],
+        });
+        if let Some(query) = query {
+            body["query"] = query.clone();
+        }
+        if let Some(search_after) = search_after {
+            body["search_after"] = search_after.clone();
+        }
+        body
+    }
+
+    /// Writes a page of hits to the destination and returns the number of
+    /// documents that failed.
+    async fn bulk(
+        &self,
+        destination: &Transport,
+        hits: &[Value],
+        timeout: Duration,
+    ) -> Result<usize, elasticsearch::Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        let res = destination
+            .send(
+                Method::Post,
+                "/_bulk",
+                headers,
+                Option::<&()>::None,
+                Some(bulk_body(hits, self.target_index.as_deref())),
+                Some(timeout),
+            )
+            .await?;
+        if !res.status_code().is_success() {
+            let status = res.status_code();
+            eprintln!(
+                "Bulk request failed with status {} - {}",
+                status,
+                res.text().await.unwrap_or_default()
+            );
+            return Ok(hits.len());
+        }
+        let body: Value = res.json().await?;
+        let mut errors = 0;
+        for item in body["items"].as_array().into_iter().flatten() {
+            let Some(error) = item["index"].get("error") else {
+                continue;
+            };
+            errors += 1;
+            eprintln!("  Error: {}", error);
+        }
+        Ok(errors)
+    }
+}
+
+/// Builds the `_bulk` payload indexing each hit under its id.
+fn bulk_body(hits: &[Value], target_index: Option<&str>) -> String {
+    let mut body = String::new();
+    for hit in hits {
+        let index = target_index.unwrap_or_else(|| hit["_index"].as_str().unwrap_or_default());
+        let action = json!({ "index": { "_index": index, "_id": hit["_id"] } });
+        body.push_str(&action.to_string());
+        body.push('\n');
+        body.push_str(&hit["_source"].to_string());
+        body.push('\n');
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bulk_body_keeps_ids_and_renames_index() {
+        let hits = vec![json!({"_index": "logs-1", "_id": "a", "_source": {"x": 1}})];
+        let lines = |body: String| -> Vec<Value> {
+            body.lines()
+                .map(|l| serde_json::from_str(l).unwrap())
+                .collect()
+        };
+        assert_eq!(
+            lines(bulk_body(&hits, None)),
+            vec![
+                json!({"index": {"_index": "logs-1", "_id": "a"}}),
+                json!({"x": 1})
+            ]
+        );
+        assert_eq!(
+            lines(bulk_body(&hits, Some("copy")))[0],
+            json!({"index": {"_index": "copy", "_id": "a"}})
+        );
+    }
+
+    #[test]
+    fn search_body_paginates_with_pit() {
+        let transfer = Transfer::try_parse_from([
+            "transfer",
+            "--to-profile",
+            "staging",
+            "--index",
+            "logs",
+            "--size",
+            "10",
+        ])
+        .unwrap();
+        let body = transfer.search_body("pit-1", None, Some(&json!([42])));
+        assert_eq!(body["pit"]["id"], "pit-1");
+        assert_eq!(body["size"], 10);
+        assert_eq!(body["search_after"], json!([42]));
+        assert!(body.get("query").is_none());
+    }
+}
@@ -0,0 +1,319 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A jq-like language to reshape documents, a pipeline of filters such as
+//! `.status = "archived" | .user = .owner | del(.owner, .tmp) | drop_nulls`.
+
+use crate::units::format_timestamp;
+use serde_json::{Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A parsed `--transform` expression, applied to each document in turn.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Transform {
+    filters: Vec<Filter>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Filter {
+    /// `.path = value`, `.path = .other` or `.path = now`.
+    Set(Vec<String>, Operand),
+    /// `del(.path, ...)`.
+    Delete(Vec<Vec<String>>),
+    /// `drop_nulls`, removing the null values at any depth.
+    DropNulls,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Path(Vec<String>),
+    Literal(Value),
+    /// The current time, as an ISO 8601 UTC timestamp.
+    Now,
+}
+
+impl Transform {
+    /// Parses an expression, used as a clap value parser.
+    pub(crate) fn parse(expr: &str) -> Result<Transform, String> {
+        let mut filters = Vec::new();
+        for filter in split_top_level(expr, '|') {
+            let filter = filter.trim();
+            if filter == "." {
+                continue;
+            }
+            if filter == "drop_nulls" {
+                filters.push(Filter::DropNulls);
+                continue;
+            }
+            if let Some(paths) = filter
+                .strip_prefix("del(")
+                .and_then(|f| f.strip_suffix(')'))
+            {
+                let paths = split_top_level(paths, ',')
+                    .into_iter()
+                    .map(parse_path)
+                    .collect::<Result<_, _>>()?;
+                filters.push(Filter::Delete(paths));
+                continue;
+            }
+            let Some((path, operand)) = split_assignment(filter) else {
+                return Err(format!(
+                    "invalid filter '{filter}', expected .field = value, del(.field), drop_nulls or ."
+                ));
+            };
+            let operand = match operand.trim() {
+                "now" => Operand::Now,
+                o if o.starts_with('.') => Operand::Path(parse_path(o)?),
+                o => Operand::Literal(
+                    serde_json::from_str(o).map_err(|e| format!("invalid value '{o}': {e}"))?,
+                ),
+            };
+            filters.push(Filter::Set(parse_path(path)?, operand));
+        }
+        Ok(Transform { filters })
+    }
+
+    /// Applies the filters to a document, in order.
+    pub(crate) fn apply(&self, doc: &mut Value) {
+        for filter in &self.filters {
+            match filter {
+                Filter::Set(path, operand) => {
+                    let value = match operand {
+                        Operand::Path(from) => get_path(doc, from).cloned().unwrap_or(Value::Null),
+                        Operand::Literal(value) => value.clone(),
+                        Operand::Now => {
+                            let now = SystemTime::now().duration_since(UNIX_EPOCH);
+                            Value::String(format_timestamp(now.map_or(0, |d| d.as_secs())))
+                        }
+                    };
+                    set_path(doc, path, value);
+                }
+                Filter::Delete(paths) => {
+                    for path in paths {
+                        delete_path(doc, path);
+                    }
+                }
+                Filter::DropNulls => drop_nulls(doc),
+            }
+        }
+    }
+}
+
+/// Splits `s` on `sep`, except inside strings, brackets and parentheses.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut in_string, mut escaped, mut start) = (0, false, false, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            c if c == sep && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Splits `.path = operand` at its `=`, outside of quoted field names.
+fn split_assignment(filter: &str) -> Option<(&str, &str)> {
+    let parts = split_top_level(filter, '=');
+    match parts.as_slice() {
+        [path, _, ..] => Some((*path, &filter[path.len() + 1..])),
+        _ => None,
+    }
+}
+
+/// Parses a path such as `.user.name` or `.labels."app.kubernetes.io/name"`,
+/// `.` being the whole document.
+fn parse_path(path: &str) -> Result<Vec<String>, String> {
+    let path = path.trim();
+    let invalid = || format!("invalid path '{path}', expected .field or .field.nested");
+    let mut rest = path.strip_prefix('.').ok_or_else(invalid)?;
+    let mut fields = Vec::new();
+    while !rest.is_empty() {
+        let field = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').ok_or_else(invalid)?;
+                rest = &quoted[end + 1..];
+                &quoted[..end]
+            }
+            None => {
+                let end = rest.find('.').unwrap_or(rest.len());
+                let field = &rest[..end];
+                rest = &rest[end..];
+                field
+            }
+        };
+        if field.is_empty() || field.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+        fields.push(field.to_string());
+        rest = match rest.strip_prefix('.') {
+            Some(next) if !next.is_empty() => next,
+            Some(_) => return Err(invalid()),
+            None if rest.is_empty() => rest,
+            None => return Err(invalid()),
+        };
+    }
+    Ok(fields)
+}
+
+fn get_path<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, field| value.get(field))
+}
+
+/// Sets the value at a path, creating the missing objects on the way and
+/// replacing the values that are not objects.
+fn set_path(value: &mut Value, path: &[String], new: Value) {
+    let Some((field, rest)) = path.split_first() else {
+        *value = new;
+        return;
+    };
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    let child = value
+        .as_object_mut()
+        .expect("an object")
+        .entry(field.clone())
+        .or_insert(Value::Null);
+    set_path(child, rest, new);
+}
+
+fn delete_path(value: &mut Value, path: &[String]) {
+    match path {
+        [] => {}
+        [field] => {
+            if let Some(object) = value.as_object_mut() {
+                object.remove(field);
+            }
+        }
+        [field, rest @ ..] => {
+            if let Some(child) = value.get_mut(field) {
+                delete_path(child, rest);
+            }
+        }
+    }
+}
+
+fn drop_nulls(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            object.retain(|_, v| !v.is_null());
+            object.values_mut().for_each(drop_nulls);
+        }
+        Value::Array(items) => {
+            items.retain(|v| !v.is_null());
+            items.iter_mut().for_each(drop_nulls);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn transform(expr: &str, mut doc: Value) -> Value {
+        Transform::parse(expr).unwrap().apply(&mut doc);
+        doc
+    }
+
+    #[test]
+    fn set_adds_constant_and_copied_fields() {
+        assert_eq!(
+            transform(
+                r#".meta.source = "prod" | .owner = .user.name"#,
+                json!({"user": {"name": "alice"}})
+            ),
+            json!({"user": {"name": "alice"}, "meta": {"source": "prod"}, "owner": "alice"})
+        );
+        assert_eq!(
+            transform(".missing = .nowhere", json!({})),
+            json!({"missing": null})
+        );
+    }
+
+    #[test]
+    fn now_sets_the_current_timestamp() {
+        let doc = transform(".restored_at = now", json!({}));
+        let now = doc["restored_at"].as_str().unwrap();
+        assert_eq!((now.len(), &now[10..11], &now[19..]), (20, "T", "Z"));
+    }
+
+    #[test]
+    fn rename_is_a_copy_then_a_delete() {
+        assert_eq!(
+            transform(
+                ".name = .title | del(.title)",
+                json!({"title": "a", "n": 1})
+            ),
+            json!({"name": "a", "n": 1})
+        );
+    }
+
+    #[test]
+    fn del_removes_nested_and_quoted_fields() {
+        assert_eq!(
+            transform(
+                r#"del(.a.b, ."x.y", .missing.path)"#,
+                json!({"a": {"b": 1, "c": 2}, "x.y": 3, "z": 4})
+            ),
+            json!({"a": {"c": 2}, "z": 4})
+        );
+    }
+
+    #[test]
+    fn drop_nulls_removes_nulls_at_any_depth() {
+        assert_eq!(
+            transform(
+                "drop_nulls",
+                json!({"a": null, "b": {"c": null, "d": [1, null]}})
+            ),
+            json!({"b": {"d": [1]}})
+        );
+    }
+
+    #[test]
+    fn literals_may_contain_separators() {
+        assert_eq!(
+            transform(
+                r#".note = "a | b = c" | .tags = ["x", "y"] | ."k=v" = {"n": 1}"#,
+                json!({})
+            ),
+            json!({"note": "a | b = c", "tags": ["x", "y"], "k=v": {"n": 1}})
+        );
+    }
+
+    #[test]
+    fn parse_rejects_invalid_expressions() {
+        assert!(Transform::parse("status = 1").is_err());
+        assert!(Transform::parse(".status = nope").is_err());
+        assert!(Transform::parse(".a..b = 1").is_err());
+        assert!(Transform::parse("map(.a)").is_err());
+        assert_eq!(Transform::parse(".").unwrap().filters, Vec::new());
+    }
+}
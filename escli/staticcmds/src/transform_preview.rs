@@ -0,0 +1,219 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::read_json_arg;
+use crate::request::{response, send_json};
+use crate::table::Table;
+use clap::{ArgGroup, Command, CommandFactory, Parser};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("transform").required(true).args(["id", "config"])))]
+pub struct TransformPreview {
+    #[arg(help = "Identifier of an existing transform to preview")]
+    id: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        value_name = "JSON",
+        help = "Transform configuration to preview, inline JSON or @file"
+    )]
+    config: Option<String>,
+
+    #[arg(short, long, help = "Number of documents shown", default_value_t = 10)]
+    size: usize,
+
+    #[arg(long, help = "Print the raw preview response instead of the tables")]
+    raw: bool,
+}
+
+impl TransformPreview {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("transform-preview")
+            .about("Preview a transform as tables of its destination mapping and documents.")
+            .long_about(
+                r#"
+            Run the preview transform API on an existing transform or on a
+            configuration that was not created yet, and print the mapping
+            Elasticsearch would generate for the destination index followed by
+            the first documents the transform would write, one column per
+            field.
+
+            Editing the configuration file and running the command again is a
+            quick way to iterate on the group_by and aggregations of a
+            transform before creating it.
+
+            Example usage:
+                escli utils transform-preview --config @transform.json
+                escli utils transform-preview ecommerce-customers --size 20
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let (path, body) = match (&self.id, &self.config) {
+            (Some(id), _) => (format!("/_transform/{id}/_preview"), None),
+            (None, Some(config)) => (
+                "/_transform/_preview".to_string(),
+                Some(read_json_arg(config).await?),
+            ),
+            (None, None) => unreachable!("the transform group is required"),
+        };
+        let (status, preview) =
+            send_json(&transport, Method::Post, &path, &[], body.as_ref(), t).await?;
+        if !status.is_success() || self.raw {
+            return Ok(response(status.as_u16(), preview.to_string().into_bytes()));
+        }
+        Ok(response(200, self.render(&preview).into_bytes()))
+    }
+
+    /// Renders the generated mapping and the first documents of a preview.
+    fn render(&self, preview: &Value) -> String {
+        let mut fields = BTreeMap::new();
+        mapping_fields(
+            "",
+            &preview["generated_dest_index"]["mappings"]["properties"],
+            &mut fields,
+        );
+        let mut mapping = Table::new(&["field", "type"]);
+        for (field, kind) in fields {
+            mapping.add_row(vec![field, kind]);
+        }
+
+        let docs: Vec<BTreeMap<String, String>> = preview["preview"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .take(self.size)
+            .map(|doc| {
+                let mut cells = BTreeMap::new();
+                cells_of("", doc, &mut cells);
+                cells
+            })
+            .collect();
+        let columns: BTreeSet<&String> = docs.iter().flat_map(|d| d.keys()).collect();
+        let headers: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+        let mut documents = Table::new(&headers);
+        for doc in &docs {
+            documents.add_row(
+                columns
+                    .iter()
+                    .map(|c| doc.get(*c).cloned().unwrap_or("-".to_string()))
+                    .collect(),
+            );
+        }
+
+        let total = preview["preview"].as_array().map_or(0, Vec::len);
+        format!(
+            "{}\n{}\n{} of {} preview documents shown\n",
+            mapping.render(),
+            documents.render(),
+            docs.len(),
+            total
+        )
+    }
+}
+
+/// Collects the dotted field paths of mapping properties with their types.
+pub(crate) fn mapping_fields(prefix: &str, properties: &Value, out: &mut BTreeMap<String, String>) {
+    for (name, field) in properties.as_object().into_iter().flatten() {
+        let path = match prefix {
+            "" => name.clone(),
+            prefix => format!("{prefix}.{name}"),
+        };
+        match field.get("properties") {
+            Some(children) => mapping_fields(&path, children, out),
+            None => {
+                let kind = field["type"].as_str().unwrap_or("object").to_string();
+                out.insert(path, kind);
+            }
+        }
+    }
+}
+
+/// Flattens a document into dotted paths and the text of their values.
+fn cells_of(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                let path = match prefix {
+                    "" => key.clone(),
+                    prefix => format!("{prefix}.{key}"),
+                };
+                cells_of(&path, child, out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(prefix.to_string(), s.clone());
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn render_prints_mapping_and_documents() {
+        let preview =
+            TransformPreview::try_parse_from(["transform-preview", "t1", "-s", "2"]).unwrap();
+        let response = json!({
+            "preview": [
+                {"customer": "a", "orders": {"count": 3}},
+                {"customer": "b", "orders": {"count": 1, "max": 9.5}},
+                {"customer": "c", "orders": {"count": 2}},
+            ],
+            "generated_dest_index": {"mappings": {"properties": {
+                "customer": {"type": "keyword"},
+                "orders": {"properties": {"count": {"type": "long"}, "max": {"type": "double"}}},
+            }}},
+        });
+        assert_eq!(
+            preview.render(&response),
+            [
+                "field         type",
+                "customer      keyword",
+                "orders.count  long",
+                "orders.max    double",
+                "",
+                "customer  orders.count  orders.max",
+                "a         3             -",
+                "b         1             9.5",
+                "",
+                "2 of 3 preview documents shown",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,361 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Units of the time values Elasticsearch accepts.
+const TIME_UNITS: &[&str] = &["nanos", "micros", "ms", "s", "m", "h", "d"];
+
+/// Width of the progress bars, in cells.
+const BAR_WIDTH: usize = 30;
+
+const BYTE_UNITS: &[(&str, u64)] = &[
+    ("pb", 1 << 50),
+    ("tb", 1 << 40),
+    ("gb", 1 << 30),
+    ("mb", 1 << 20),
+    ("kb", 1 << 10),
+    ("b", 1),
+];
+
+/// Parses an Elasticsearch time value such as `500ms`, `30s`, `5m`, `2h` or `7d`.
+/// A bare number is interpreted as seconds.
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration '{s}', expected e.g. 30s, 5m, 2h or 7d"))?;
+    let secs = match unit {
+        "ms" => return Ok(Duration::from_millis(value)),
+        "" | "s" => value,
+        "m" => value * 60,
+        "h" => value * 3600,
+        "d" => value * 86400,
+        _ => {
+            return Err(format!(
+                "invalid duration unit '{unit}' in '{s}', expected one of ms, s, m, h, d"
+            ));
+        }
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+/// A time value passed to Elasticsearch as is, such as `30s` or `-1`, checked
+/// when the arguments are parsed rather than rejected by the server.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeValue {
+    value: i64,
+    unit: &'static str,
+}
+
+impl FromStr for TimeValue {
+    type Err = String;
+
+    /// Parses a number followed by one of the units of Elasticsearch, in any
+    /// case, or `-1` and `0` which need none.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        if normalized == "-1" || normalized == "0" {
+            let value = normalized.parse().expect("an integer");
+            return Ok(TimeValue { value, unit: "" });
+        }
+        let split = normalized
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(normalized.len());
+        let (value, unit) = normalized.split_at(split);
+        let invalid =
+            || format!("invalid time value '{s}', expected e.g. 500ms, 30s, 1m or 2h, or -1");
+        let value = value.parse().map_err(|_| invalid())?;
+        let unit = TIME_UNITS
+            .iter()
+            .copied()
+            .find(|u| *u == unit)
+            .ok_or_else(|| match unit {
+                "" => format!(
+                    "time value '{s}' has no unit, expected one of {}",
+                    TIME_UNITS.join(", ")
+                ),
+                _ => invalid(),
+            })?;
+        Ok(TimeValue { value, unit })
+    }
+}
+
+impl fmt::Display for TimeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.unit)
+    }
+}
+
+impl Serialize for TimeValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A byte size such as `10mb` or `1.5GB`, checked when the arguments are
+/// parsed and passed to Elasticsearch in the largest unit that keeps it exact.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteSize {
+    bytes: u64,
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_bytes(s).map(|bytes| ByteSize { bytes })
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (unit, mult) = BYTE_UNITS
+            .iter()
+            .find(|(_, mult)| self.bytes > 0 && self.bytes % mult == 0)
+            .unwrap_or(&("b", 1));
+        write!(f, "{}{unit}", self.bytes / mult)
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Formats a duration using its two most significant units, e.g. `3d 4h`.
+pub(crate) fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (days, hours, minutes, seconds) = (
+        secs / 86400,
+        (secs % 86400) / 3600,
+        (secs % 3600) / 60,
+        secs % 60,
+    );
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else if secs > 0 {
+        format!("{seconds}s")
+    } else {
+        format!("{}ms", d.as_millis())
+    }
+}
+
+/// Formats a progress bar filled up to `ratio` followed by its percentage,
+/// e.g. `[###############...............]  50%`.
+pub(crate) fn format_bar(ratio: f64) -> String {
+    let ratio = ratio.clamp(0.0, 1.0);
+    let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+    format!(
+        "[{}{}] {:>3}%",
+        "#".repeat(filled),
+        ".".repeat(BAR_WIDTH - filled),
+        (ratio * 100.0).round()
+    )
+}
+
+/// Parses an Elasticsearch byte size value such as `512kb`, `10mb` or `1.5gb`.
+/// A bare number is interpreted as bytes.
+pub(crate) fn parse_bytes(s: &str) -> Result<u64, String> {
+    let lower = s.trim().to_ascii_lowercase();
+    // Elasticsearch also accepts the units without their trailing b, e.g. 5g.
+    let (number, multiplier) = BYTE_UNITS
+        .iter()
+        .find_map(|(unit, mult)| {
+            let short = &unit[..unit.len() - 1];
+            lower
+                .strip_suffix(unit)
+                .or_else(|| lower.strip_suffix(short).filter(|_| !short.is_empty()))
+                .map(|n| (n, *mult))
+        })
+        .unwrap_or((lower.as_str(), 1));
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid byte size '{s}', expected e.g. 512kb, 10mb or 1gb"))?;
+    if number < 0.0 {
+        return Err(format!("byte size '{s}' must not be negative"));
+    }
+    Ok((number * multiplier as f64).round() as u64)
+}
+
+/// Formats a byte count the way Elasticsearch does, e.g. `1.5gb`.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    for (unit, mult) in BYTE_UNITS {
+        if bytes >= *mult && *mult > 1 {
+            let value = bytes as f64 / *mult as f64;
+            return if value.fract() == 0.0 {
+                format!("{value}{unit}")
+            } else {
+                format!("{value:.1}{unit}")
+            };
+        }
+    }
+    format!("{bytes}b")
+}
+
+/// Formats seconds since the epoch as an ISO 8601 UTC timestamp.
+pub(crate) fn format_timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+// The conversions between civil dates and days since the epoch follow
+// http://howardhinnant.github.io/date_algorithms.html
+
+/// Converts days since the epoch to a `(year, month, day)` date.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Converts a `(year, month, day)` date to days since the epoch.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_supports_all_units() {
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(604800));
+        assert_eq!(parse_duration("42").unwrap(), Duration::from_secs(42));
+    }
+
+    #[test]
+    fn parse_duration_rejects_garbage() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("m5").is_err());
+    }
+
+    #[test]
+    fn time_value_normalizes_elasticsearch_units() {
+        let parse = |s: &str| s.parse::<TimeValue>().map(|t| t.to_string());
+        assert_eq!(parse("30s").unwrap(), "30s");
+        assert_eq!(parse(" 1M ").unwrap(), "1m");
+        assert_eq!(parse("100nanos").unwrap(), "100nanos");
+        assert_eq!(parse("-1").unwrap(), "-1");
+        assert_eq!(parse("0").unwrap(), "0");
+        assert!(parse("30").unwrap_err().contains("has no unit"));
+        assert!(parse("1.5h").is_err());
+        assert!(parse("5x").is_err());
+        assert!(parse("-5s").is_err());
+    }
+
+    #[test]
+    fn format_duration_uses_two_units() {
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 86400 + 4 * 3600)),
+            "3d 4h"
+        );
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1h 2m");
+        assert_eq!(format_duration(Duration::from_secs(61)), "1m 1s");
+        assert_eq!(format_duration(Duration::from_millis(20)), "20ms");
+    }
+
+    #[test]
+    fn format_bar_fills_cells_by_ratio() {
+        assert_eq!(format_bar(0.5), "[###############...............]  50%");
+        assert_eq!(format_bar(0.0), "[..............................]   0%");
+        assert_eq!(format_bar(1.5), "[##############################] 100%");
+    }
+
+    #[test]
+    fn parse_bytes_supports_units_and_fractions() {
+        assert_eq!(parse_bytes("512").unwrap(), 512);
+        assert_eq!(parse_bytes("1kb").unwrap(), 1024);
+        assert_eq!(parse_bytes("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_bytes("1.5gb").unwrap(), 3 * 512 * 1024 * 1024);
+        assert_eq!(parse_bytes("5g").unwrap(), 5 * 1024 * 1024 * 1024);
+        assert_eq!(parse_bytes("512k").unwrap(), 512 * 1024);
+        assert_eq!(parse_bytes("10M").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_bytes("2t").unwrap(), 2 << 40);
+        assert_eq!(parse_bytes("1p").unwrap(), 1 << 50);
+        assert_eq!(parse_bytes("7b").unwrap(), 7);
+        assert!(parse_bytes("ten").is_err());
+        assert!(parse_bytes("-1mb").is_err());
+    }
+
+    #[test]
+    fn byte_size_normalizes_to_the_largest_exact_unit() {
+        let parse = |s: &str| s.parse::<ByteSize>().map(|b| b.to_string());
+        assert_eq!(parse("10MB").unwrap(), "10mb");
+        assert_eq!(parse("1.5gb").unwrap(), "1536mb");
+        assert_eq!(parse("5g").unwrap(), "5gb");
+        assert_eq!(parse("2048").unwrap(), "2kb");
+        assert_eq!(parse("1000").unwrap(), "1000b");
+        assert_eq!(parse("0").unwrap(), "0b");
+        assert!(parse("10 apples").is_err());
+    }
+
+    #[test]
+    fn format_bytes_picks_largest_unit() {
+        assert_eq!(format_bytes(0), "0b");
+        assert_eq!(format_bytes(1023), "1023b");
+        assert_eq!(format_bytes(1024), "1kb");
+        assert_eq!(format_bytes(3 * 512 * 1024 * 1024), "1.5gb");
+    }
+
+    #[test]
+    fn format_timestamp_converts_epoch_seconds() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1_709_210_096), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn days_from_civil_inverts_civil_from_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2024, 2, 29), 19782);
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+    }
+}
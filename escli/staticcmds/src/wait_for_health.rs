@@ -0,0 +1,157 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::request::{response, send_json};
+use crate::units::{format_duration, parse_duration};
+use clap::{Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+pub struct WaitForHealth {
+    #[arg(short, long, value_enum, default_value_t = HealthStatus::Green, help = "Status to wait for")]
+    status: HealthStatus,
+
+    #[arg(
+        long,
+        help = "How long to wait before giving up",
+        default_value = "5m",
+        value_parser = parse_duration
+    )]
+    timeout: Duration,
+
+    #[arg(
+        long,
+        help = "Time between two health checks",
+        default_value = "2s",
+        value_parser = parse_duration
+    )]
+    interval: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub(crate) enum HealthStatus {
+    Red,
+    Yellow,
+    Green,
+}
+
+impl HealthStatus {
+    pub(crate) fn from_health(health: &Value) -> Option<Self> {
+        match health["status"].as_str()? {
+            "red" => Some(Self::Red),
+            "yellow" => Some(Self::Yellow),
+            "green" => Some(Self::Green),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Red => "red",
+            Self::Yellow => "yellow",
+            Self::Green => "green",
+        }
+    }
+}
+
+impl WaitForHealth {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("wait-for-health")
+            .about("Wait until the cluster reaches a health status.")
+            .long_about(
+                r#"
+            Poll _cluster/health every --interval until the cluster reaches
+            the requested status or better, printing each status change with
+            the time elapsed since the start.
+
+            The cluster does not need to be reachable when the command starts,
+            failed requests are reported as "unreachable" and retried, which
+            makes it suitable for deployment scripts waiting on a fresh
+            cluster. Exits with 0 once the status is reached and 1 when
+            --timeout expires first.
+
+            Example usage:
+                escli utils wait-for-health --status yellow --timeout 5m
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60)).min(self.timeout);
+        let start = Instant::now();
+        let mut last: Option<String> = None;
+
+        loop {
+            let current = match send_json(&transport, Method::Get, "/_cluster/health", &[], None, t)
+                .await
+            {
+                Ok((status, health)) if status.is_success() => HealthStatus::from_health(&health),
+                _ => None,
+            };
+            let label = current.map_or("unreachable", HealthStatus::as_str);
+            if last.as_deref() != Some(label) {
+                println!("[{}] {}", format_duration(start.elapsed()), label);
+                last = Some(label.to_string());
+            }
+
+            if current.is_some_and(|c| c >= self.status) {
+                let body = format!(
+                    "Cluster is {} after {}\n",
+                    label,
+                    format_duration(start.elapsed())
+                );
+                return Ok(response(200, body.into_bytes()));
+            }
+            if start.elapsed() + self.interval > self.timeout {
+                let body = format!(
+                    "Timed out after {} waiting for {}, cluster is {}\n",
+                    format_duration(self.timeout),
+                    self.status.as_str(),
+                    label
+                );
+                return Ok(response(408, body.into_bytes()));
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn health_statuses_are_ordered() {
+        assert!(HealthStatus::Green >= HealthStatus::Yellow);
+        assert!(HealthStatus::Red < HealthStatus::Yellow);
+        assert_eq!(
+            HealthStatus::from_health(&json!({"status": "yellow"})),
+            Some(HealthStatus::Yellow)
+        );
+        assert_eq!(HealthStatus::from_health(&json!({})), None);
+    }
+}
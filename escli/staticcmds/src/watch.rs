@@ -0,0 +1,348 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::input::{read_json_arg, read_json_file};
+use crate::request::{response, send_json};
+use crate::table::Table;
+use clap::{ArgGroup, Command, CommandFactory, Parser, ValueEnum};
+use elasticsearch::http::Method;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::transport::Transport;
+use serde_json::{Map, Value, json};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("watch").required(true).args(["id", "definition"])))]
+pub struct Watch {
+    #[arg(help = "Identifier of an existing watch to execute")]
+    id: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        value_name = "JSON",
+        help = "Watch definition to execute without storing it, inline JSON or @file"
+    )]
+    definition: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "JSON file used as the payload instead of running the input of the watch"
+    )]
+    alternative_input: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ActionMode::Simulate,
+        help = "Mode of all the actions"
+    )]
+    mode: ActionMode,
+
+    #[arg(
+        long,
+        value_name = "ACTION=MODE",
+        value_parser = parse_action_mode,
+        help = "Mode of a single action, overriding --mode, can be repeated"
+    )]
+    action_mode: Vec<(String, ActionMode)>,
+
+    #[arg(long, help = "Run the actions even when the condition is not met")]
+    ignore_condition: bool,
+
+    #[arg(long, help = "Store the execution in the watch history")]
+    record_execution: bool,
+
+    #[arg(long, help = "Print the raw execution response instead of the report")]
+    raw: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ActionMode {
+    Simulate,
+    ForceSimulate,
+    Execute,
+    ForceExecute,
+    Skip,
+}
+
+impl ActionMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            ActionMode::Simulate => "simulate",
+            ActionMode::ForceSimulate => "force_simulate",
+            ActionMode::Execute => "execute",
+            ActionMode::ForceExecute => "force_execute",
+            ActionMode::Skip => "skip",
+        }
+    }
+}
+
+fn parse_action_mode(s: &str) -> Result<(String, ActionMode), String> {
+    let (action, mode) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid action mode '{s}', expected ACTION=MODE"))?;
+    let mode = ActionMode::from_str(&mode.replace('_', "-"), true).map_err(|_| {
+        format!(
+            "invalid mode '{mode}', expected simulate, force-simulate, execute, force-execute or skip"
+        )
+    })?;
+    Ok((action.to_string(), mode))
+}
+
+impl Watch {
+    pub fn new_command() -> Command {
+        Self::command()
+            .name("watch")
+            .about("Execute a watch and report its condition and actions.")
+            .long_about(
+                r#"
+            Run the execute watch API on a stored watch or on a definition that
+            was not stored yet, and print whether its condition was met and
+            the outcome of each of its actions, to debug watches from the
+            command line.
+
+            Actions are simulated by default, so that no email is sent and no
+            webhook is called: the report shows what they would have done.
+            --mode changes the mode of all the actions and --action-mode the
+            mode of a single one. --alternative-input replaces the payload of
+            the input with the content of a file, to test the condition and
+            the actions against a given search response.
+
+            The command exits with 1 when the execution or one of its actions
+            failed.
+
+            Example usage:
+                escli utils watch cluster-health-watch
+                escli utils watch --definition @watch.json --alternative-input payload.json
+                escli utils watch log-errors --action-mode notify-slack=execute
+            "#,
+            )
+    }
+
+    pub async fn execute(
+        self,
+        transport: Transport,
+        timeout: Option<Duration>,
+    ) -> Result<Response, elasticsearch::Error> {
+        let t = timeout.unwrap_or(Duration::from_secs(60));
+        let mut body = self.body();
+        let path = match (&self.id, &self.definition) {
+            (Some(id), _) => format!("/_watcher/watch/{id}/_execute"),
+            (None, Some(definition)) => {
+                body["watch"] = read_json_arg(definition).await?;
+                "/_watcher/watch/_execute".to_string()
+            }
+            (None, None) => unreachable!("the watch group is required"),
+        };
+        if let Some(file) = &self.alternative_input {
+            body["alternative_input"] = read_json_file(file).await?;
+        }
+
+        let (status, executed) =
+            send_json(&transport, Method::Post, &path, &[], Some(&body), t).await?;
+        if !status.is_success() || self.raw {
+            return Ok(response(status.as_u16(), executed.to_string().into_bytes()));
+        }
+        let (report, failed) = render(&executed["watch_record"]);
+        Ok(response(
+            if failed { 400 } else { 200 },
+            report.into_bytes(),
+        ))
+    }
+
+    /// The execute watch request, without the watch and the alternative input.
+    fn body(&self) -> Value {
+        let mut modes = Map::new();
+        modes.insert("_all".to_string(), json!(self.mode.as_str()));
+        for (action, mode) in &self.action_mode {
+            modes.insert(action.clone(), json!(mode.as_str()));
+        }
+        json!({
+            "action_modes": modes,
+            "ignore_condition": self.ignore_condition,
+            "record_execution": self.record_execution,
+        })
+    }
+}
+
+/// Renders the condition and actions of a watch record, and whether the
+/// execution or one of its actions failed.
+fn render(record: &Value) -> (String, bool) {
+    let result = &record["result"];
+    let state = record["state"].as_str().unwrap_or("-");
+    let mut out = format!(
+        "Watch {}: {state}\n",
+        record["watch_id"].as_str().unwrap_or("_inlined_")
+    );
+    let input = &result["input"];
+    out.push_str(&format!(
+        "input      {} {}{}\n",
+        input["type"].as_str().unwrap_or("-"),
+        input["status"].as_str().unwrap_or("-"),
+        hits(&input["payload"]).map_or(String::new(), |h| format!(", {h} hits")),
+    ));
+    let condition = &result["condition"];
+    let met = match condition["met"].as_bool() {
+        Some(true) => "met",
+        Some(false) => "not met",
+        None => "-",
+    };
+    out.push_str(&format!(
+        "condition  {} {met}\n",
+        condition["type"].as_str().unwrap_or("-")
+    ));
+
+    let mut failed = state == "failed";
+    let actions = result["actions"].as_array().map_or(&[][..], Vec::as_slice);
+    if !actions.is_empty() {
+        let mut table = Table::new(&["action", "type", "status", "outcome"]);
+        for action in actions {
+            let status = action["status"].as_str().unwrap_or("-");
+            failed |= status == "failure";
+            table.add_row(vec![
+                action["id"].as_str().unwrap_or("-").to_string(),
+                action["type"].as_str().unwrap_or("-").to_string(),
+                status.to_string(),
+                outcome(action),
+            ]);
+        }
+        out.push('\n');
+        out.push_str(&table.render());
+    }
+    if let Some(reason) = record["messages"].as_array().and_then(|m| m.first()) {
+        out.push_str(&format!("\n{}\n", reason.as_str().unwrap_or_default()));
+    }
+    (out, failed)
+}
+
+/// The total hits of a search input payload.
+fn hits(payload: &Value) -> Option<u64> {
+    let total = &payload["hits"]["total"];
+    total["value"].as_u64().or(total.as_u64())
+}
+
+/// What an action did or would have done, from its result.
+fn outcome(action: &Value) -> String {
+    if let Some(reason) = action["error"]["reason"]
+        .as_str()
+        .or(action["reason"].as_str())
+    {
+        return reason.to_string();
+    }
+    let text = |v: &Value| v.as_str().unwrap_or("-").to_string();
+    match action["type"].as_str() {
+        Some("logging") => text(&action["logging"]["logged_text"]),
+        Some("email") => {
+            let message = &action["email"]["message"];
+            let to = message["to"]
+                .as_array()
+                .map(|to| {
+                    to.iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .unwrap_or_else(|| text(&message["to"]));
+            format!("to {to}: {}", text(&message["subject"]))
+        }
+        Some("webhook") => {
+            let request = &action["webhook"]["request"];
+            format!(
+                "{} {}:{}{}",
+                text(&request["method"]).to_uppercase(),
+                text(&request["host"]),
+                request["port"],
+                text(&request["path"])
+            )
+        }
+        Some("index") => {
+            let index = &action["index"]["response"];
+            let index = index["index"]
+                .as_str()
+                .or(action["index"]["request"]["index"].as_str());
+            format!("index into {}", index.unwrap_or("-"))
+        }
+        _ => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_sets_action_modes() {
+        let watch = Watch::try_parse_from([
+            "watch",
+            "w1",
+            "--action-mode",
+            "notify=force_execute",
+            "--ignore-condition",
+        ])
+        .unwrap();
+        assert_eq!(
+            watch.body(),
+            json!({
+                "action_modes": {"_all": "simulate", "notify": "force_execute"},
+                "ignore_condition": true,
+                "record_execution": false,
+            })
+        );
+        assert!(parse_action_mode("notify=later").is_err());
+    }
+
+    #[test]
+    fn render_reports_condition_and_actions() {
+        let record = json!({
+            "watch_id": "errors",
+            "state": "executed",
+            "result": {
+                "input": {"type": "search", "status": "success",
+                    "payload": {"hits": {"total": {"value": 12}}}},
+                "condition": {"type": "compare", "status": "success", "met": true},
+                "actions": [
+                    {"id": "log", "type": "logging", "status": "simulated",
+                        "logging": {"logged_text": "12 errors"}},
+                    {"id": "mail", "type": "email", "status": "simulated",
+                        "email": {"message": {"to": ["ops@example.com"], "subject": "Errors"}}},
+                    {"id": "hook", "type": "webhook", "status": "failure",
+                        "error": {"reason": "connection refused"}},
+                ],
+            },
+        });
+        let (report, failed) = render(&record);
+        assert!(failed);
+        assert_eq!(
+            report,
+            [
+                "Watch errors: executed",
+                "input      search success, 12 hits",
+                "condition  compare met",
+                "",
+                "action  type     status     outcome",
+                "log     logging  simulated  12 errors",
+                "mail    email    simulated  to ops@example.com: Errors",
+                "hook    webhook  failure    connection refused",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
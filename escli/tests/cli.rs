@@ -16,7 +16,9 @@
 // under the License.
 
 use assert_cmd::Command;
-use wiremock::matchers::{body_string, header, header_exists, method, path, query_param};
+use wiremock::matchers::{
+    body_partial_json, body_string, header, header_exists, method, path, query_param,
+};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 // --- helpers -----------------------------------------------------------------
@@ -50,9 +52,7 @@ async fn error_response_goes_to_stderr_and_exits_1() {
     let server = MockServer::start().await;
     Mock::given(method("GET"))
         .and(path("/"))
-        .respond_with(
-            ResponseTemplate::new(404).set_body_string(r#"{"error":"not found"}"#),
-        )
+        .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"error":"not found"}"#))
         .mount(&server)
         .await;
 
@@ -82,6 +82,84 @@ async fn info_command_sends_get_to_root() {
     server.verify().await;
 }
 
+#[test]
+fn help_groups_commands_by_category() {
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .arg("--help")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Search:"), "missing Search group: {stdout}");
+    assert!(
+        stdout.contains("Utilities:"),
+        "missing Utilities group: {stdout}"
+    );
+    assert!(stdout.contains("Options:"), "missing options: {stdout}");
+}
+
+#[test]
+fn show_experimental_reveals_hidden_commands_in_help() {
+    let help = |args: &[&str], env: Option<&str>| {
+        let mut cmd = Command::cargo_bin("escli").unwrap();
+        cmd.args(args).env_remove("ESCLI_EXPERIMENTAL");
+        if let Some(value) = env {
+            cmd.env("ESCLI_EXPERIMENTAL", value);
+        }
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stable = help(&["--help"], None);
+    assert!(
+        stable.contains("--show-experimental"),
+        "missing flag: {stable}"
+    );
+    let flag = help(&["--show-experimental", "--help"], None);
+    let env = help(&["--help"], Some("1"));
+    assert_eq!(flag, env);
+    assert!(flag.len() >= stable.len());
+    assert_eq!(help(&["--help"], Some("0")), stable);
+}
+
+#[test]
+fn long_help_links_to_the_documentation() {
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .args(["indices", "create", "--help"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Documentation: https://"),
+        "missing documentation URL: {stdout}"
+    );
+}
+
+#[test]
+fn docs_flag_prints_documentation_url() {
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .args(["--url", "http://127.0.0.1:1", "indices", "create", "--docs"])
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "required index should not be needed"
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("https://"),
+        "unexpected output: {stdout}"
+    );
+}
+
 // --- authentication ----------------------------------------------------------
 
 #[tokio::test]
@@ -317,6 +395,130 @@ async fn dotenv_file_is_loaded() {
     server.verify().await;
 }
 
+// --- profiles ----------------------------------------------------------------
+
+#[tokio::test]
+async fn profile_is_loaded_from_profiles_dir() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("prod.env"),
+        format!("ESCLI_URL={}\n", server.uri()),
+    )
+    .unwrap();
+
+    Command::cargo_bin("escli")
+        .unwrap()
+        .current_dir(dir.path())
+        .env("ESCLI_PROFILES_DIR", dir.path())
+        .args(["--profile", "prod", "info"])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn profile_with_equals_sign_is_loaded() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("prod.env"),
+        format!("ESCLI_URL={}\n", server.uri()),
+    )
+    .unwrap();
+
+    Command::cargo_bin("escli")
+        .unwrap()
+        .current_dir(dir.path())
+        .env("ESCLI_PROFILES_DIR", dir.path())
+        .env_remove("ESCLI_URL")
+        .args(["--profile=prod", "info"])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn profiles_fan_out_prints_results_keyed_by_profile() {
+    let eu = MockServer::start().await;
+    let us = MockServer::start().await;
+    for (server, status) in [(&eu, "green"), (&us, "yellow")] {
+        Mock::given(method("GET"))
+            .and(path("/_cluster/health"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(format!(r#"{{"status":"{status}"}}"#)),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("eu.env"),
+        format!("ESCLI_URL={}\n", eu.uri()),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("us.env"),
+        format!("ESCLI_URL={}\n", us.uri()),
+    )
+    .unwrap();
+
+    Command::cargo_bin("escli")
+        .unwrap()
+        .current_dir(dir.path())
+        .env("ESCLI_PROFILES_DIR", dir.path())
+        .args(["--all-profiles", "cluster", "health"])
+        .assert()
+        .success()
+        .stdout("{\"eu\":{\"status\":\"green\"},\"us\":{\"status\":\"yellow\"}}\n");
+
+    eu.verify().await;
+    us.verify().await;
+}
+
+#[tokio::test]
+async fn profiles_fan_out_fails_if_a_profile_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"status":"green"}"#))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("ok.env"),
+        format!("ESCLI_URL={}\n", server.uri()),
+    )
+    .unwrap();
+
+    Command::cargo_bin("escli")
+        .unwrap()
+        .current_dir(dir.path())
+        .env("ESCLI_PROFILES_DIR", dir.path())
+        .args(["--profiles", "ok,missing", "cluster", "health"])
+        .assert()
+        .code(1);
+}
+
 // --- connection errors -------------------------------------------------------
 
 /// Port 1 is privileged and never listening; this reliably triggers ECONNREFUSED.
@@ -330,7 +532,10 @@ fn connection_refused_shows_friendly_message() {
 
     assert!(!output.status.success());
     let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(!stderr.is_empty(), "stderr must not be empty on connection error");
+    assert!(
+        !stderr.is_empty(),
+        "stderr must not be empty on connection error"
+    );
     assert!(
         stderr.contains("Could not connect"),
         "expected friendly message, got: {stderr}"
@@ -343,9 +548,7 @@ async fn timeout_shows_friendly_message() {
     Mock::given(method("GET"))
         .and(path("/"))
         // Hold the response long enough that a 1-second timeout fires.
-        .respond_with(
-            ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(30)),
-        )
+        .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(30)))
         .mount(&server)
         .await;
 
@@ -432,199 +635,341 @@ async fn binary_response_bytes_are_not_utf8_encoded() {
     );
 }
 
-// --- utils dump --------------------------------------------------------------
-
-const PIT_OK: &str = r#"{"id":"test-pit-id"}"#;
-const EMPTY_SEARCH: &str = r#"{"pit_id":"test-pit-id","hits":{"hits":[]}}"#;
-const ONE_DOC_SEARCH: &str = r#"{"pit_id":"test-pit-id","hits":{"hits":[{"_id":"doc1","_source":{"field":"value"},"sort":[1]}]}}"#;
+// --- privilege preflight -----------------------------------------------------
 
 #[tokio::test]
-async fn dump_opens_pit_and_calls_search() {
+async fn check_privileges_stops_before_request_when_missing() {
     let server = MockServer::start().await;
-
     Mock::given(method("POST"))
-        .and(path("/my-index/_pit"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .and(path("/_security/user/_has_privileges"))
+        .and(body_partial_json(serde_json::json!({
+            "index": [{ "names": ["my-index"] }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"username":"bob","has_all_requested":false,"cluster":{},"index":{"my-index":{"create_index":false}}}"#,
+        ))
         .expect(1)
         .mount(&server)
         .await;
+    Mock::given(method("PUT"))
+        .and(path("/my-index"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(0)
+        .mount(&server)
+        .await;
 
-    // When the initial search is empty, dump skips the pagination loop entirely.
-    Mock::given(method("POST"))
-        .and(path("/_search"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+    let output = escli(&server)
+        .args(["indices", "create", "my-index", "--check-privileges"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("index 'my-index': create_index"),
+        "missing explanation: {stderr}"
+    );
+
+    server.verify().await;
+}
+
+// --- cat client-side columns, sort and filter --------------------------------
+
+#[tokio::test]
+async fn cat_columns_sort_and_filter_are_applied_client_side() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/indices"))
+        .and(header("accept", "application/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"index":"a","health":"green","docs.count":"5"},{"index":"b","health":"yellow","docs.count":"9"},{"index":"c","health":"green","docs.count":"40"}]"#,
+        ))
         .expect(1)
         .mount(&server)
         .await;
 
     escli(&server)
-        .args(["utils", "dump", "my-index"])
+        .args(["cat", "indices", "--columns", "index,docs.count"])
+        .args(["--sort", "docs.count:desc", "--filter", "health==green"])
         .assert()
-        .success();
+        .success()
+        .stdout("index  docs.count\nc      40\na      5\n");
 
     server.verify().await;
 }
 
 #[tokio::test]
-async fn dump_empty_result_writes_raw_response_to_stdout() {
+async fn list_query_params_are_repeatable_and_comma_separated() {
     let server = MockServer::start().await;
-
-    Mock::given(method("POST"))
-        .and(path("/my-index/_pit"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+    Mock::given(method("GET"))
+        .and(path("/_cat/indices"))
+        .and(query_param("expand_wildcards", "open,hidden,closed"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(""))
+        .expect(1)
         .mount(&server)
         .await;
 
-    Mock::given(method("POST"))
-        .and(path("/_search"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+    escli(&server)
+        .args(["cat", "indices", "--expand_wildcards", "open,hidden"])
+        .args(["--expand_wildcards", "closed"])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn numeric_query_params_are_validated_at_parse_time() {
+    let server = MockServer::start().await;
+    Mock::given(path("/_search"))
+        .and(query_param("size", "5"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
         .mount(&server)
         .await;
 
+    escli(&server)
+        .args(["search", "--size", "5"])
+        .write_stdin("{}")
+        .assert()
+        .success();
+
     let output = escli(&server)
-        .args(["utils", "dump", "my-index"])
+        .args(["search", "--size", "ten"])
+        .write_stdin("{}")
         .output()
         .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("invalid value 'ten' for '--size"),
+        "unexpected output: {stderr}"
+    );
 
-    assert!(output.status.success());
-    assert_eq!(String::from_utf8(output.stdout).unwrap(), EMPTY_SEARCH);
+    server.verify().await;
 }
 
 #[tokio::test]
-async fn dump_writes_ndjson_to_stdout() {
+async fn duration_query_params_are_validated_and_normalized() {
     let server = MockServer::start().await;
-
-    Mock::given(method("POST"))
-        .and(path("/my-index/_pit"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .and(query_param("master_timeout", "1m"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"status":"green"}"#))
+        .expect(1)
         .mount(&server)
         .await;
 
-    // Wiremock is FIFO: first-mounted mock has highest priority.
-    // One-doc response fires once (initial search), then falls through to empty (pagination check).
-    Mock::given(method("POST"))
-        .and(path("/_search"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
-        .up_to_n_times(1)
-        .mount(&server)
-        .await;
+    escli(&server)
+        .args(["cluster", "health", "--master_timeout", "1M"])
+        .assert()
+        .success();
+
+    let output = escli(&server)
+        .args(["cluster", "health", "--master_timeout", "30"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("time value '30' has no unit"),
+        "unexpected output: {stderr}"
+    );
+
+    server.verify().await;
+}
 
+#[tokio::test]
+async fn byte_size_query_params_are_validated_and_normalized() {
+    let server = MockServer::start().await;
     Mock::given(method("POST"))
-        .and(path("/_search"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .and(path("/_snapshot/backups/_analyze"))
+        .and(query_param("max_blob_size", "1536mb"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
         .mount(&server)
         .await;
 
+    escli(&server)
+        .args(["snapshot", "repository_analyze", "backups"])
+        .args(["--max_blob_size", "1.5GB"])
+        .assert()
+        .success();
+
     let output = escli(&server)
-        .args(["utils", "dump", "my-index"])
+        .args(["snapshot", "repository_analyze", "backups"])
+        .args(["--max_blob_size", "lots"])
         .output()
         .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("invalid byte size 'lots'"),
+        "unexpected output: {stderr}"
+    );
 
-    assert!(output.status.success());
-    let stdout = String::from_utf8(output.stdout).unwrap();
-    assert!(stdout.contains(r#"{"index":{"_index":"my-index"}}"#), "missing action line");
-    assert!(stdout.contains(r#"{"field":"value"}"#), "missing document");
+    server.verify().await;
 }
 
 #[tokio::test]
-async fn dump_paginates_until_empty() {
+async fn keyword_query_params_keep_their_name() {
     let server = MockServer::start().await;
-
-    Mock::given(method("POST"))
-        .and(path("/my-index/_pit"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+    Mock::given(method("GET"))
+        .and(path("/_nodes/hot_threads"))
+        .and(query_param("type", "wait"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("::: {node-1}\n"))
+        .expect(1)
         .mount(&server)
         .await;
 
-    // Two pages of results (FIFO: fires first), then falls through to empty.
-    Mock::given(method("POST"))
-        .and(path("/_search"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
-        .up_to_n_times(2)
+    escli(&server)
+        .args(["nodes", "hot_threads", "--type", "wait"])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn no_deprecation_warnings_is_accepted_and_silent() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"status":"green"}"#))
+        .expect(1)
         .mount(&server)
         .await;
 
-    // Fallback: empty (stops pagination).
+    let output = escli(&server)
+        .args(["--no-deprecation-warnings", "cluster", "health"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("Warning:"), "{stderr}");
+
+    server.verify().await;
+}
+
+// --- esql explain plan -------------------------------------------------------
+
+#[tokio::test]
+async fn esql_explain_plan_requests_profile_and_renders_stages() {
+    let server = MockServer::start().await;
     Mock::given(method("POST"))
-        .and(path("/_search"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .and(path("/_query"))
+        .and(body_partial_json(serde_json::json!({ "profile": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"took":3,"values":[],"profile":{"drivers":[{"description":"data","took_nanos":1000,"cpu_nanos":500,"iterations":1,"operators":[{"operator":"LuceneSourceOperator[maxPageSize = 1]","status":{"rows_emitted":0,"process_nanos":200}}]}]}}"#,
+        ))
+        .expect(1)
         .mount(&server)
         .await;
 
     let output = escli(&server)
-        .args(["utils", "dump", "my-index"])
+        .args(["esql", "query", "--explain-plan"])
+        .write_stdin(r#"{"query":"FROM test"}"#)
         .output()
         .unwrap();
 
     assert!(output.status.success());
     let stdout = String::from_utf8(output.stdout).unwrap();
-    // 2 pages × (1 action line + 1 doc line) = 4 lines
-    assert_eq!(stdout.lines().count(), 4, "expected 4 NDJSON lines for 2 pages");
+    assert!(
+        stdout.contains("Driver 1: data"),
+        "missing driver: {stdout}"
+    );
+    assert!(
+        stdout.contains("LuceneSourceOperator"),
+        "missing stage: {stdout}"
+    );
+
+    server.verify().await;
 }
 
 #[tokio::test]
-async fn dump_output_to_file() {
+async fn esql_query_binds_params() {
     let server = MockServer::start().await;
-
     Mock::given(method("POST"))
-        .and(path("/my-index/_pit"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .and(path("/_query"))
+        .and(body_partial_json(serde_json::json!({
+            "query": "FROM logs | WHERE level == ?level | LIMIT ?limit",
+            "params": [{ "level": "error" }, { "limit": 10 }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"columns":[],"values":[]}"#))
+        .expect(1)
         .mount(&server)
         .await;
 
-    Mock::given(method("POST"))
-        .and(path("/_search"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
-        .up_to_n_times(1)
-        .mount(&server)
-        .await;
+    escli(&server)
+        .args([
+            "esql",
+            "query",
+            "--param",
+            "level=error",
+            "--param",
+            "limit:long=10",
+        ])
+        .write_stdin(r#"{"query":"FROM logs | WHERE level == ?level | LIMIT ?limit"}"#)
+        .assert()
+        .success();
+}
 
+#[tokio::test]
+async fn esql_output_file_requests_arrow_and_rejects_other_formats() {
+    let server = MockServer::start().await;
     Mock::given(method("POST"))
-        .and(path("/_search"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .and(path("/_query"))
+        .and(header("accept", "application/vnd.apache.arrow.stream"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"columns":[],"values":[]}"#))
+        .expect(1)
         .mount(&server)
         .await;
-
     let dir = tempfile::TempDir::new().unwrap();
-    let out = dir.path().join("dump.ndjson");
+    let file = dir.path().join("result.parquet");
 
-    escli(&server)
-        .args(["utils", "dump", "my-index", "--output", out.to_str().unwrap()])
-        .assert()
-        .success()
-        .stdout("");  // nothing on stdout when writing to file
+    let output = escli(&server)
+        .args(["esql", "query", "--output-file"])
+        .arg(&file)
+        .write_stdin(r#"{"query":"FROM logs"}"#)
+        .output()
+        .unwrap();
 
-    let contents = std::fs::read_to_string(&out).unwrap();
-    assert!(contents.contains(r#"{"index":{"_index":"my-index"}}"#));
-    assert!(contents.contains(r#"{"field":"value"}"#));
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("--output-file requires an Arrow response"),
+        "{stderr}"
+    );
+    assert!(!file.exists());
 }
 
+// --- utils dump --------------------------------------------------------------
+
+const PIT_OK: &str = r#"{"id":"test-pit-id"}"#;
+const EMPTY_SEARCH: &str = r#"{"pit_id":"test-pit-id","hits":{"hits":[]}}"#;
+const ONE_DOC_SEARCH: &str = r#"{"pit_id":"test-pit-id","hits":{"hits":[{"_id":"doc1","_source":{"field":"value"},"sort":[1]}]}}"#;
+
 #[tokio::test]
-async fn dump_multiple_indices_opens_pit_for_each() {
+async fn dump_opens_pit_and_calls_search() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
-        .and(path("/index1/_pit"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
-        .expect(1)
-        .mount(&server)
-        .await;
-
-    Mock::given(method("POST"))
-        .and(path("/index2/_pit"))
+        .and(path("/my-index/_pit"))
         .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
         .expect(1)
         .mount(&server)
         .await;
 
+    // When the initial search is empty, dump skips the pagination loop entirely.
     Mock::given(method("POST"))
         .and(path("/_search"))
         .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .expect(1)
         .mount(&server)
         .await;
 
     escli(&server)
-        .args(["utils", "dump", "index1,index2"])
+        .args(["utils", "dump", "my-index"])
         .assert()
         .success();
 
@@ -632,27 +977,32 @@ async fn dump_multiple_indices_opens_pit_for_each() {
 }
 
 #[tokio::test]
-async fn dump_pit_failure_skips_index() {
+async fn dump_empty_result_writes_raw_response_to_stdout() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
-        .and(path("/bad-index/_pit"))
-        .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"error":"index not found"}"#))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
         .mount(&server)
         .await;
 
     let output = escli(&server)
-        .args(["utils", "dump", "bad-index"])
+        .args(["utils", "dump", "my-index"])
         .output()
         .unwrap();
 
-    // Should exit 0 and produce no documents — the index is skipped gracefully.
     assert!(output.status.success());
-    assert!(output.stdout.is_empty());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), EMPTY_SEARCH);
 }
 
 #[tokio::test]
-async fn dump_skip_index_name_omits_index_from_action() {
+async fn dump_writes_ndjson_to_stdout() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
@@ -661,6 +1011,8 @@ async fn dump_skip_index_name_omits_index_from_action() {
         .mount(&server)
         .await;
 
+    // Wiremock is FIFO: first-mounted mock has highest priority.
+    // One-doc response fires once (initial search), then falls through to empty (pagination check).
     Mock::given(method("POST"))
         .and(path("/_search"))
         .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
@@ -675,18 +1027,21 @@ async fn dump_skip_index_name_omits_index_from_action() {
         .await;
 
     let output = escli(&server)
-        .args(["utils", "dump", "my-index", "--skip-index-name"])
+        .args(["utils", "dump", "my-index"])
         .output()
         .unwrap();
 
     assert!(output.status.success());
     let stdout = String::from_utf8(output.stdout).unwrap();
-    assert!(stdout.contains(r#"{"index":{}}"#), "action line should have no _index");
-    assert!(!stdout.contains("_index"), "should not contain _index at all");
+    assert!(
+        stdout.contains(r#"{"index":{"_index":"my-index"}}"#),
+        "missing action line"
+    );
+    assert!(stdout.contains(r#"{"field":"value"}"#), "missing document");
 }
 
 #[tokio::test]
-async fn dump_add_id_includes_id_in_action() {
+async fn dump_paginates_until_empty() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
@@ -695,13 +1050,15 @@ async fn dump_add_id_includes_id_in_action() {
         .mount(&server)
         .await;
 
+    // Two pages of results (FIFO: fires first), then falls through to empty.
     Mock::given(method("POST"))
         .and(path("/_search"))
         .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
-        .up_to_n_times(1)
+        .up_to_n_times(2)
         .mount(&server)
         .await;
 
+    // Fallback: empty (stops pagination).
     Mock::given(method("POST"))
         .and(path("/_search"))
         .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
@@ -709,18 +1066,22 @@ async fn dump_add_id_includes_id_in_action() {
         .await;
 
     let output = escli(&server)
-        .args(["utils", "dump", "my-index", "--add-id"])
+        .args(["utils", "dump", "my-index"])
         .output()
         .unwrap();
 
     assert!(output.status.success());
     let stdout = String::from_utf8(output.stdout).unwrap();
-    assert!(stdout.contains(r#""_id":"doc1""#), "action line should contain _id");
-    assert!(stdout.contains(r#""_index":"my-index""#), "action line should still contain _index");
+    // 2 pages × (1 action line + 1 doc line) = 4 lines
+    assert_eq!(
+        stdout.lines().count(),
+        4,
+        "expected 4 NDJSON lines for 2 pages"
+    );
 }
 
 #[tokio::test]
-async fn dump_query_from_file_succeeds() {
+async fn dump_output_to_file() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
@@ -743,171 +1104,222 @@ async fn dump_query_from_file_succeeds() {
         .await;
 
     let dir = tempfile::TempDir::new().unwrap();
-    let query_file = dir.path().join("query.json");
-    std::fs::write(&query_file, r#"{"term":{"field":"value"}}"#).unwrap();
+    let out = dir.path().join("dump.ndjson");
 
-    let output = escli(&server)
-        .args(["utils", "dump", "my-index", "--query", query_file.to_str().unwrap()])
-        .output()
-        .unwrap();
+    escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--output",
+            out.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(""); // nothing on stdout when writing to file
 
-    assert!(output.status.success());
-    let stdout = String::from_utf8(output.stdout).unwrap();
-    assert!(stdout.contains(r#"{"field":"value"}"#));
+    let contents = std::fs::read_to_string(&out).unwrap();
+    assert!(contents.contains(r#"{"index":{"_index":"my-index"}}"#));
+    assert!(contents.contains(r#"{"field":"value"}"#));
 }
 
 #[tokio::test]
-async fn dump_query_bad_file_exits_1() {
+async fn dump_output_dir_writes_one_file_per_index() {
     let server = MockServer::start().await;
 
-    let output = escli(&server)
-        .args(["utils", "dump", "my-index", "--query", "/nonexistent/query.json"])
-        .output()
-        .unwrap();
-
-    assert!(!output.status.success());
-}
-
-// --- utils load --------------------------------------------------------------
+    for index in ["index1", "index2"] {
+        Mock::given(method("POST"))
+            .and(path(format!("/{index}/_pit")))
+            .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+            .mount(&server)
+            .await;
+    }
 
-const BULK_OK: &str = r#"{"errors":false,"items":[{"index":{"status":200}}]}"#;
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({"search_after": [1]})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
 
-#[tokio::test]
-async fn load_json_lines_posts_to_index_bulk() {
-    let server = MockServer::start().await;
     Mock::given(method("POST"))
-        .and(path("/my-index/_bulk"))
-        .and(header("content-type", "application/x-ndjson"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
-        .expect(1)
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
         .mount(&server)
         .await;
 
     let dir = tempfile::TempDir::new().unwrap();
-    let file = dir.path().join("docs.json");
-    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+    let out = dir.path().join("backup");
 
     escli(&server)
-        .args(["utils", "load", "--index", "my-index", file.to_str().unwrap()])
+        .args([
+            "utils",
+            "dump",
+            "index1,index2",
+            "--output-dir",
+            out.to_str().unwrap(),
+        ])
         .assert()
-        .success();
+        .success()
+        .stdout("");
 
-    server.verify().await;
+    for index in ["index1", "index2"] {
+        let contents = std::fs::read_to_string(out.join(format!("{index}.ndjson"))).unwrap();
+        assert_eq!(
+            contents,
+            format!("{{\"index\":{{\"_index\":\"{index}\"}}}}\n{{\"field\":\"value\"}}\n")
+        );
+    }
 }
 
 #[tokio::test]
-async fn load_ndjson_posts_to_bulk() {
+async fn dump_max_docs_per_file_rotates_parts() {
     let server = MockServer::start().await;
+
     Mock::given(method("POST"))
-        .and(path("/_bulk"))
-        .and(header("content-type", "application/x-ndjson"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
-        .expect(1)
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
         .mount(&server)
         .await;
 
-    let dir = tempfile::TempDir::new().unwrap();
-    let file = dir.path().join("docs.ndjson");
-    std::fs::write(
-        &file,
-        "{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n",
-    )
-    .unwrap();
-
-    escli(&server)
-        .args(["utils", "load", file.to_str().unwrap()])
-        .assert()
-        .success();
-
-    server.verify().await;
-}
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({"search_after": [3]})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
 
-#[tokio::test]
-async fn load_with_pipeline_includes_query_param() {
-    let server = MockServer::start().await;
     Mock::given(method("POST"))
-        .and(path("/my-index/_bulk"))
-        .and(query_param("pipeline", "my-pipeline"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
-        .expect(1)
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"test-pit-id","hits":{"hits":[
+                {"_id":"1","_source":{"n":1},"sort":[1]},
+                {"_id":"2","_source":{"n":2},"sort":[2]},
+                {"_id":"3","_source":{"n":3},"sort":[3]}
+            ]}}"#,
+        ))
         .mount(&server)
         .await;
 
     let dir = tempfile::TempDir::new().unwrap();
-    let file = dir.path().join("docs.json");
-    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+    let out = dir.path().join("dump.ndjson");
 
     escli(&server)
         .args([
-            "utils", "load",
-            "--index", "my-index",
-            "--pipeline", "my-pipeline",
-            file.to_str().unwrap(),
+            "utils",
+            "dump",
+            "my-index",
+            "--output",
+            out.to_str().unwrap(),
+            "--max-docs-per-file",
+            "2",
         ])
         .assert()
         .success();
 
-    server.verify().await;
+    let part = |n: u32| std::fs::read_to_string(dir.path().join(format!("dump-0000{n}.ndjson")));
+    assert_eq!(part(1).unwrap().lines().count(), 4);
+    assert_eq!(
+        part(2).unwrap(),
+        "{\"index\":{\"_index\":\"my-index\"}}\n{\"n\":3}\n"
+    );
+    assert!(part(3).is_err());
+    assert!(!out.exists());
 }
 
 #[tokio::test]
-async fn load_bulk_errors_are_reported_on_stderr() {
+async fn dump_max_file_size_requires_file_output() {
     let server = MockServer::start().await;
-    let bulk_err = r#"{"errors":true,"items":[{"index":{"status":400,"error":{"type":"mapper_exception","reason":"failed to parse"}}}]}"#;
-    Mock::given(method("POST"))
-        .and(path("/my-index/_bulk"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(bulk_err))
-        .mount(&server)
-        .await;
 
-    let dir = tempfile::TempDir::new().unwrap();
-    let file = dir.path().join("docs.json");
-    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
-
-    let output = escli(&server)
-        .args(["utils", "load", "--index", "my-index", file.to_str().unwrap()])
-        .output()
-        .unwrap();
-
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(!output.status.success(), "expected exit 1 on bulk errors");
-    assert!(stderr.contains("Error"), "expected error details on stderr, got: {stderr}");
+    escli(&server)
+        .args(["utils", "dump", "my-index", "--max-file-size", "1gb"])
+        .assert()
+        .failure();
 }
 
 #[tokio::test]
-async fn load_ndjson_from_stdin() {
+async fn dump_slices_reads_each_slice_of_the_pit() {
     let server = MockServer::start().await;
+
     Mock::given(method("POST"))
-        .and(path("/_bulk"))
-        .and(header("content-type", "application/x-ndjson"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({"search_after": [1]})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(
+            serde_json::json!({"slice": {"id": 0, "max": 2}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(
+            serde_json::json!({"slice": {"id": 1, "max": 2}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
         .expect(1)
         .mount(&server)
         .await;
 
     escli(&server)
-        .args(["utils", "load"])
-        .write_stdin("{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n")
+        .args(["utils", "dump", "my-index", "--slices", "2"])
         .assert()
-        .success();
+        .success()
+        .stdout("{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n");
 
     server.verify().await;
 }
 
 #[tokio::test]
-async fn load_json_from_stdin_with_format_flag() {
+async fn dump_closes_the_pit() {
     let server = MockServer::start().await;
+
     Mock::given(method("POST"))
-        .and(path("/my-index/_bulk"))
-        .and(header("content-type", "application/x-ndjson"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/_pit"))
+        .and(body_partial_json(serde_json::json!({"id": "test-pit-id"})))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"succeeded":true,"num_freed":1}"#),
+        )
         .expect(1)
         .mount(&server)
         .await;
 
     escli(&server)
-        .args(["utils", "load", "--format", "json", "--index", "my-index"])
-        .write_stdin("{\"field\":\"value\"}\n")
+        .args(["utils", "dump", "my-index"])
         .assert()
         .success();
 
@@ -915,137 +1327,5427 @@ async fn load_json_from_stdin_with_format_flag() {
 }
 
 #[tokio::test]
-async fn load_stdin_explicit_dash() {
+async fn dump_verify_reports_count_mismatch() {
     let server = MockServer::start().await;
+
     Mock::given(method("POST"))
-        .and(path("/_bulk"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_count"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"count":2}"#))
         .expect(1)
         .mount(&server)
         .await;
 
-    escli(&server)
-        .args(["utils", "load", "-"])
-        .write_stdin("{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n")
-        .assert()
-        .success();
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index", "--verify"])
+        .output()
+        .unwrap();
 
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "index     expected  written  status\nmy-index  2         1        mismatch\n"
+        )
+    );
+    assert!(stderr.contains("1 of 1 indices do not match their count"));
     server.verify().await;
 }
 
 #[tokio::test]
-async fn load_bulk_http_error_exits_1() {
+async fn dump_redacts_and_hashes_fields() {
     let server = MockServer::start().await;
+
     Mock::given(method("POST"))
-        .and(path("/my-index/_bulk"))
-        .respond_with(ResponseTemplate::new(503).set_body_string("Service Unavailable"))
+        .and(path("/users/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
         .mount(&server)
         .await;
 
-    let dir = tempfile::TempDir::new().unwrap();
-    let file = dir.path().join("docs.json");
-    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"test-pit-id","hits":{"hits":[
+                {"_id":"1","_source":{"email":"","user":{"phone":"555"},"n":1},"sort":[1]}
+            ]}}"#,
+        ))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
 
     escli(&server)
-        .args(["utils", "load", "--index", "my-index", file.to_str().unwrap()])
+        .args([
+            "utils",
+            "dump",
+            "users",
+            "--redact",
+            "user.phone",
+            "--hash-fields",
+            "email",
+        ])
         .assert()
-        .failure()
-        .code(1);
+        .success()
+        .stdout(
+            "{\"index\":{\"_index\":\"users\"}}\n\
+             {\"email\":\"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\",\
+             \"n\":1,\"user\":{\"phone\":\"REDACTED\"}}\n",
+        );
 }
 
 #[tokio::test]
-async fn load_multiple_batches_sends_multiple_requests() {
+async fn dump_with_metadata_writes_mapping_and_settings() {
     let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/my-index"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"my-index":{
+                "aliases":{},
+                "mappings":{"properties":{"field":{"type":"keyword"}}},
+                "settings":{"index":{"number_of_shards":"1","uuid":"abc","creation_date":"1"}}
+            }}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
     Mock::given(method("POST"))
-        .and(path("/my-index/_bulk"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
-        .expect(2)
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
         .mount(&server)
         .await;
 
     let dir = tempfile::TempDir::new().unwrap();
-    let file = dir.path().join("docs.json");
-    std::fs::write(&file, "{\"a\":1}\n{\"a\":2}\n").unwrap();
 
     escli(&server)
-        .args(["utils", "load", "--index", "my-index", "--size", "1", file.to_str().unwrap()])
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--output-dir",
+            dir.path().to_str().unwrap(),
+            "--with-metadata",
+        ])
         .assert()
         .success();
 
+    let read = |name: &str| -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join(name)).unwrap()).unwrap()
+    };
+    assert_eq!(
+        read("my-index.mapping.json"),
+        serde_json::json!({"properties": {"field": {"type": "keyword"}}})
+    );
+    assert_eq!(
+        read("my-index.settings.json"),
+        serde_json::json!({"index": {"number_of_shards": "1"}})
+    );
+    assert!(dir.path().join("my-index.ndjson").exists());
     server.verify().await;
 }
 
 #[tokio::test]
-async fn load_format_override_treats_file_as_json() {
+async fn dump_sort_passes_full_sort_values_to_search_after() {
     let server = MockServer::start().await;
+
     Mock::given(method("POST"))
-        .and(path("/my-index/_bulk"))
-        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .and(path("/logs/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "search_after": ["2024-01-01T00:00:00Z", 42]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
         .expect(1)
         .mount(&server)
         .await;
 
-    let dir = tempfile::TempDir::new().unwrap();
-    let file = dir.path().join("data.txt");
-    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "sort": [
+                {"@timestamp": {"order": "desc"}},
+                {"_shard_doc": {"order": "asc"}}
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"test-pit-id","hits":{"hits":[
+                {"_id":"1","_source":{"n":1},"sort":["2024-01-01T00:00:00Z",42]}
+            ]}}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
 
     escli(&server)
-        .args(["utils", "load", "--index", "my-index", "--format", "json", file.to_str().unwrap()])
+        .args(["utils", "dump", "logs", "--sort", "@timestamp:desc"])
         .assert()
         .success();
 
     server.verify().await;
 }
 
-#[test]
-fn load_file_not_found_fails() {
-    Command::cargo_bin("escli")
-        .unwrap()
-        .args(["--url", "http://127.0.0.1:1", "utils", "load", "--index", "my-index", "/tmp/does-not-exist-escli-test.json"])
-        .assert()
-        .failure()
-        .code(1);
-}
+#[tokio::test]
+async fn dump_uploads_output_to_object_storage() {
+    let server = MockServer::start().await;
 
-#[test]
-fn load_json_without_index_fails() {
-    let dir = tempfile::TempDir::new().unwrap();
-    let file = dir.path().join("docs.json");
-    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
 
-    Command::cargo_bin("escli")
-        .unwrap()
-        .args(["--url", "http://127.0.0.1:1", "utils", "load", file.to_str().unwrap()])
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PUT"))
+        .and(path("/backups/dumps/my-index.ndjson"))
+        .and(header_exists("authorization"))
+        .and(header_exists("x-amz-content-sha256"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .env("AWS_ENDPOINT_URL", server.uri())
+        .env("AWS_ACCESS_KEY_ID", "test")
+        .env("AWS_SECRET_ACCESS_KEY", "secret")
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--output",
+            "s3://backups/dumps/",
+        ])
         .assert()
-        .failure()
-        .code(1);
+        .success();
+
+    server.verify().await;
+    let requests = server.received_requests().await.unwrap();
+    let upload = requests
+        .iter()
+        .find(|r| r.method.as_str() == "PUT")
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&upload.body),
+        "{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n"
+    );
 }
 
-// --- argument validation -----------------------------------------------------
+#[tokio::test]
+async fn dump_object_storage_requires_credentials() {
+    let server = MockServer::start().await;
 
-#[test]
-fn missing_url_fails() {
-    Command::cargo_bin("escli")
-        .unwrap()
-        .arg("info")
-        .assert()
-        .failure();
+    let output = escli(&server)
+        .env_remove("GCS_ACCESS_KEY_ID")
+        .env_remove("GCS_SECRET_ACCESS_KEY")
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--output",
+            "gcs://backups/my-index.ndjson",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("GCS_ACCESS_KEY_ID and GCS_SECRET_ACCESS_KEY must be set")
+    );
 }
 
-#[test]
-fn username_without_password_fails() {
-    Command::cargo_bin("escli")
-        .unwrap()
-        .args(["--url", "http://localhost:9200", "--username", "foo", "info"])
+#[tokio::test]
+async fn dump_object_storage_rejects_parts() {
+    let server = MockServer::start().await;
+
+    escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--output",
+            "s3://backups/my-index.ndjson",
+            "--max-file-size",
+            "1gb",
+        ])
         .assert()
         .failure();
 }
 
-#[test]
-fn password_without_username_fails() {
-    Command::cargo_bin("escli")
-        .unwrap()
-        .args(["--url", "http://localhost:9200", "--password", "bar", "info"])
+#[tokio::test]
+async fn dump_falls_back_to_scroll_without_pit() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(
+            r#"{"error":"request [/my-index/_pit] contains unrecognized parameter: [keep_alive]"}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_search"))
+        .and(query_param("scroll", "1m"))
+        .and(body_partial_json(serde_json::json!({"sort": ["_doc"]})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"_scroll_id":"scroll-1","hits":{"hits":[{"_id":"doc1","_source":{"field":"value"},"sort":[0]}]}}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search/scroll"))
+        .and(body_partial_json(
+            serde_json::json!({"scroll_id": "scroll-1"}),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"_scroll_id":"scroll-2","hits":{"hits":[]}}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/_search/scroll"))
+        .and(body_partial_json(
+            serde_json::json!({"scroll_id": ["scroll-2"]}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"succeeded":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("falling back to scroll"));
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_strategy_scroll_skips_the_pit() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_search"))
+        .and(query_param("scroll", "1m"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"_scroll_id":"scroll-1","hits":{"hits":[]}}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/_search/scroll"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"succeeded":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "dump", "my-index", "--strategy", "scroll"])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_strategy_pit_does_not_fall_back() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(405).set_body_string("{}"))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index", "--strategy", "pit"])
+        .output()
+        .unwrap();
+
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to open PIT"));
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_transform_reshapes_documents() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--transform",
+            r#".renamed = .field | del(.field) | .source = "prod""#,
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"index\":{\"_index\":\"my-index\"}}\n{\"renamed\":\"value\",\"source\":\"prod\"}\n"
+    );
+}
+
+#[test]
+fn dump_rejects_invalid_transform() {
+    Command::cargo_bin("escli")
+        .unwrap()
+        .args(["utils", "dump", "my-index", "--transform", "map(.a)"])
+        .assert()
+        .failure();
+}
+
+#[tokio::test]
+async fn dump_order_by_sorts_and_skips_raw_empty_response() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "sort": [{"user.id": {"order": "asc"}}, {"_shard_doc": {"order": "asc"}}]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index", "--order-by", "user.id"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_order_by_rejects_slices() {
+    let server = MockServer::start().await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--order-by",
+            "_id",
+            "--slices",
+            "2",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--order-by cannot be used"));
+}
+
+#[tokio::test]
+async fn dump_progress_format_json_emits_events() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index", "--progress-format", "json"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let events: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let kinds: Vec<&str> = events
+        .iter()
+        .map(|e| e["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["batch", "index_done", "done"]);
+    assert_eq!(events[0]["index"], "my-index");
+    assert_eq!(events[0]["docs"], 1);
+    assert_eq!(events[1]["docs"], 1);
+    assert_eq!(events[2]["docs"], 1);
+    assert_eq!(events[2]["interrupted"], false);
+}
+
+#[tokio::test]
+async fn dump_retries_overloaded_searches() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(429).set_body_string(
+            r#"{"error":{"type":"es_rejected_execution_exception"},"status":429}"#,
+        ))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n"
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Retrying the search of index 'my-index'")
+    );
+}
+
+#[tokio::test]
+async fn dump_reopens_an_expired_pit() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(404).set_body_string(
+            r#"{"error":{"root_cause":[{"type":"search_context_missing_exception"}]},"status":404}"#,
+        ))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("{\"field\":\"value\"}"));
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_does_not_continue_in_a_new_pit_without_order_by() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(404).set_body_string(
+            r#"{"error":{"root_cause":[{"type":"search_context_missing_exception"}]},"status":404}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index"])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("cannot continue in a new one without --order-by"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("continuing in a new one"), "{stderr}");
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_fields_only_writes_doc_values() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "docvalue_fields": ["host.name", "cpu"],
+            "_source": false
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"test-pit-id","hits":{"hits":[{"_id":"doc1","fields":{"host.name":["web-1"],"cpu":[0.5,0.7]},"sort":[1]}]}}"#,
+        ))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--docvalue-fields",
+            "host.name,cpu",
+            "--fields-only",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"index\":{\"_index\":\"my-index\"}}\n{\"cpu\":[0.5,0.7],\"host.name\":\"web-1\"}\n"
+    );
+}
+
+#[tokio::test]
+async fn dump_runtime_mappings_requests_the_fields() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "runtime_mappings": {"day": {"type": "keyword"}},
+            "fields": ["day"]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"test-pit-id","hits":{"hits":[{"_id":"doc1","_source":{"field":"value"},"fields":{"day":["MONDAY"]},"sort":[1]}]}}"#,
+        ))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--runtime-mappings",
+            r#"{"day":{"type":"keyword"}}"#,
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"index\":{\"_index\":\"my-index\"}}\n{\"day\":\"MONDAY\",\"field\":\"value\"}\n"
+    );
+}
+
+#[tokio::test]
+async fn dump_resolves_wildcard_patterns() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/_resolve/index/logs-*,-logs-debug"))
+        .and(query_param("expand_wildcards", "open,hidden"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"indices":[{"name":"logs-b","attributes":["open"]},{"name":"logs-a","attributes":["open"]},{"name":"logs-sys","attributes":["hidden","system","open"]}],"aliases":[],"data_streams":[]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    for (index, opened) in [("logs-a", 1), ("logs-b", 1), ("logs-sys", 0)] {
+        Mock::given(method("POST"))
+            .and(path(format!("/{index}/_pit")))
+            .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+            .expect(opened)
+            .mount(&server)
+            .await;
+    }
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "logs-*,-logs-debug",
+            "--expand-wildcards",
+            "open,hidden",
+            "--exclude-system",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Dumping 2 indices matching 'logs-*': logs-a, logs-b"),
+        "unexpected output: {stderr}"
+    );
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_report_summarizes_each_index() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/bad-index/_pit"))
+        .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"error":"index not found"}"#))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let report = dir.path().join("report.json");
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index,bad-index", "--report"])
+        .arg(&report)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("retries"), "no summary: {stderr}");
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&report).unwrap()).unwrap();
+    assert_eq!(report["docs"], 1);
+    let indices = report["indices"].as_array().unwrap();
+    assert_eq!(indices.len(), 2);
+    assert_eq!(indices[0]["index"], "bad-index");
+    assert_eq!(indices[0]["status"], "failed");
+    assert!(
+        indices[0]["error"].as_str().unwrap().contains("404"),
+        "unexpected report: {report}"
+    );
+    assert_eq!(indices[1]["index"], "my-index");
+    assert_eq!(indices[1]["status"], "done");
+    assert_eq!(indices[1]["docs"], 1);
+    assert_eq!(indices[1]["retries"], 0);
+}
+
+#[tokio::test]
+async fn dump_multiple_indices_opens_pit_for_each() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/index1/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/index2/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "dump", "index1,index2"])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_concurrency_reads_indices_concurrently() {
+    let server = MockServer::start().await;
+
+    // index1 is slow to open, so that index2 is written first when both are
+    // read concurrently.
+    for (index, delay) in [("index1", 500), ("index2", 0)] {
+        Mock::given(method("POST"))
+            .and(path(format!("/{index}/_pit")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(format!(r#"{{"id":"pit-{index}"}}"#))
+                    .set_delay(std::time::Duration::from_millis(delay)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({"search_after": [1]})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "index1,index2", "--concurrency", "2"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let actions: Vec<&str> = stdout.lines().step_by(2).collect();
+    assert_eq!(
+        actions,
+        [
+            r#"{"index":{"_index":"index2"}}"#,
+            r#"{"index":{"_index":"index1"}}"#
+        ]
+    );
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_pit_failure_skips_index() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/bad-index/_pit"))
+        .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"error":"index not found"}"#))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "bad-index"])
+        .output()
+        .unwrap();
+
+    // Should exit 0 and produce no documents — the index is skipped gracefully.
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[tokio::test]
+async fn dump_skip_index_name_omits_index_from_action() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index", "--skip-index-name"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains(r#"{"index":{}}"#),
+        "action line should have no _index"
+    );
+    assert!(
+        !stdout.contains("_index"),
+        "should not contain _index at all"
+    );
+}
+
+#[tokio::test]
+async fn dump_add_id_includes_id_in_action() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "dump", "my-index", "--add-id"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains(r#""_id":"doc1""#),
+        "action line should contain _id"
+    );
+    assert!(
+        stdout.contains(r#""_index":"my-index""#),
+        "action line should still contain _index"
+    );
+}
+
+#[tokio::test]
+async fn dump_with_routing_and_original_index() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/logs/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"pit2","hits":{"hits":[{"_index":".ds-logs-000001","_id":"doc1","_routing":"user1","_source":{"a":1},"sort":[1]}]}}"#,
+        ))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "logs",
+            "--with-id",
+            "--with-routing",
+            "--original-index",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().next(),
+        Some(r#"{"index":{"_id":"doc1","_index":".ds-logs-000001","routing":"user1"}}"#)
+    );
+}
+
+#[tokio::test]
+async fn dump_checkpoint_records_dumped_indices() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let checkpoint = dir.path().join("dump.ckpt");
+
+    escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--checkpoint",
+            checkpoint.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&checkpoint).unwrap()).unwrap();
+    assert_eq!(
+        saved,
+        serde_json::json!({
+            "slices": 1,
+            "indices": {"my-index": {"search_after": [[1]], "docs": 1, "done": true}}
+        })
+    );
+}
+
+/// A Ctrl-C pressed while a page is being written must still stop the dump
+/// and save its checkpoint.
+#[cfg(unix)]
+#[tokio::test]
+async fn dump_interrupted_while_writing_saves_checkpoint() {
+    use std::io::Read;
+    use std::process::Stdio;
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    // Pages larger than the pipe buffer block the write until stdout is read.
+    let page = serde_json::json!({
+        "pit_id": "test-pit-id",
+        "hits": {"hits": [{"_id": "doc1", "_source": {"field": "x".repeat(1 << 17)}, "sort": [1]}]}
+    });
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(page))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let checkpoint = dir.path().join("dump.ckpt");
+
+    let bin = assert_cmd::cargo::cargo_bin("escli");
+    let mut child = std::process::Command::new(bin)
+        .args([
+            "--url",
+            &server.uri(),
+            "utils",
+            "dump",
+            "my-index",
+            "--order-by",
+            "_id",
+            "--checkpoint",
+            checkpoint.to_str().unwrap(),
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    let killed = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let mut stdout = child.stdout.take().unwrap();
+    let mut stderr = child.stderr.take().unwrap();
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(30),
+        tokio::task::spawn_blocking(move || {
+            std::io::copy(&mut stdout, &mut std::io::sink()).unwrap();
+            let mut errors = String::new();
+            stderr.read_to_string(&mut errors).unwrap();
+            (child.wait().unwrap(), errors)
+        }),
+    )
+    .await
+    .expect("the dump did not stop on Ctrl-C")
+    .unwrap();
+
+    let (status, stderr) = output;
+    assert_eq!(status.code(), Some(1), "stderr: {stderr}");
+    assert!(stderr.contains("Dump interrupted, continue it with --resume"));
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&checkpoint).unwrap()).unwrap();
+    assert_eq!(saved["indices"]["my-index"]["done"], false);
+    assert!(saved["indices"]["my-index"]["docs"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn dump_resume_continues_from_checkpoint() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/index2/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({"search_after": [41]})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({"search_after": [1]})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let out = dir.path().join("dump.ndjson");
+    std::fs::write(&out, "previous\n").unwrap();
+    let checkpoint = dir.path().join("dump.ckpt");
+    std::fs::write(
+        &checkpoint,
+        r#"{"slices":1,"order_by":[{"_id":{"order":"asc"}}],"indices":{
+            "index1":{"search_after":[[7]],"docs":7,"done":true},
+            "index2":{"search_after":[[41]],"docs":41,"done":false}
+        }}"#,
+    )
+    .unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "index1,index2",
+            "--output",
+            out.to_str().unwrap(),
+            "--order-by",
+            "_id",
+            "--resume",
+            checkpoint.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Index 'index1' was already dumped, skipping"));
+
+    assert_eq!(
+        std::fs::read_to_string(&out).unwrap(),
+        "previous\n{\"index\":{\"_index\":\"index2\"}}\n{\"field\":\"value\"}\n"
+    );
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&checkpoint).unwrap()).unwrap();
+    assert_eq!(saved["indices"]["index2"]["docs"], 42);
+    assert_eq!(saved["indices"]["index2"]["done"], true);
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_resume_refuses_partial_index_without_order_by() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let out = dir.path().join("dump.ndjson");
+    std::fs::write(&out, "previous\n").unwrap();
+    let checkpoint = dir.path().join("dump.ckpt");
+    std::fs::write(
+        &checkpoint,
+        r#"{"slices":1,"indices":{"index2":{"search_after":[[41]],"docs":41,"done":false}}}"#,
+    )
+    .unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "index2",
+            "--output",
+            out.to_str().unwrap(),
+            "--resume",
+            checkpoint.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Index 'index2' was partially dumped without --order-by"),
+        "{stderr}"
+    );
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "previous\n");
+
+    // A different --order-by than the interrupted dump is refused as well.
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "index2",
+            "--output",
+            out.to_str().unwrap(),
+            "--order-by",
+            "_id",
+            "--resume",
+            checkpoint.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("the checkpoint was written with --order-by none"),
+        "{stderr}"
+    );
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_max_docs_and_time_range_limit_the_export() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/logs/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "size": 2,
+            "query": {"bool": {"filter": [{"range": {"@timestamp": {"gte": "now-24h"}}}]}}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"test-pit-id","hits":{"hits":[
+                {"_id":"1","_source":{"n":1},"sort":[1]},
+                {"_id":"2","_source":{"n":2},"sort":[2]}
+            ]}}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "logs",
+            "--since",
+            "now-24h",
+            "--max-docs",
+            "3",
+            "--size",
+            "2",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "{\"index\":{\"_index\":\"logs\"}}\n{\"n\":1}\n\
+             {\"index\":{\"_index\":\"logs\"}}\n{\"n\":2}\n\
+             {\"index\":{\"_index\":\"logs\"}}\n{\"n\":1}\n",
+        );
+}
+
+#[tokio::test]
+async fn dump_query_from_file_succeeds() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ONE_DOC_SEARCH))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let query_file = dir.path().join("query.json");
+    std::fs::write(&query_file, r#"{"term":{"field":"value"}}"#).unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--query",
+            query_file.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#"{"field":"value"}"#));
+}
+
+#[tokio::test]
+async fn dump_query_file_flag_reads_the_query() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(
+            serde_json::json!({"query": {"term": {"field": "value"}}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let query_file = dir.path().join("query.json");
+    std::fs::write(&query_file, r#"{"term":{"field":"value"}}"#).unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--query-file",
+            query_file.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("deprecated"), "{stderr}");
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_inline_query_filters_search() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PIT_OK))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(
+            serde_json::json!({"query": {"term": {"status": "active"}}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_SEARCH))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--query",
+            r#"{"query":{"term":{"status":"active"}}}"#,
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn dump_query_bad_file_exits_1() {
+    let server = MockServer::start().await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "dump",
+            "my-index",
+            "--query",
+            "/nonexistent/query.json",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+// --- utils load --------------------------------------------------------------
+
+const BULK_OK: &str = r#"{"errors":false,"items":[{"index":{"status":200}}]}"#;
+
+#[tokio::test]
+async fn load_json_lines_posts_to_index_bulk() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .and(header("content-type", "application/x-ndjson"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_ndjson_posts_to_bulk() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(header("content-type", "application/x-ndjson"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.ndjson");
+    std::fs::write(
+        &file,
+        "{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n",
+    )
+    .unwrap();
+
+    escli(&server)
+        .args(["utils", "load", file.to_str().unwrap()])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_rename_rewrites_action_indices() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_id\":\"1\",\"_index\":\"archive-2024\"}}\n{\"field\":\"value\"}\n\
+             {\"delete\":{\"_id\":\"2\",\"_index\":\"metrics\"}}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.ndjson");
+    std::fs::write(
+        &file,
+        "{\"index\":{\"_index\":\"logs-2024\",\"_id\":\"1\"}}\n{\"field\":\"value\"}\n\
+         {\"delete\":{\"_index\":\"metrics\",\"_id\":\"2\"}}\n",
+    )
+    .unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            file.to_str().unwrap(),
+            "--rename",
+            "logs-(.*)=archive-$1",
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_index_override_targets_a_single_index() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/restored/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_index\":\"restored\"}}\n{\"field\":\"value\"}\n{\"index\":{}}\n{\"field\":\"other\"}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.ndjson");
+    std::fs::write(
+        &file,
+        "{\"index\":{\"_index\":\"users\"}}\n{\"field\":\"value\"}\n{\"index\":{}}\n{\"field\":\"other\"}\n",
+    )
+    .unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            file.to_str().unwrap(),
+            "--index-override",
+            "restored",
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_transform_rewrites_documents_and_metadata() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_index\":\"users\",\"_routing\":\"u1\"}}\n{\"restored\":true,\"user\":\"u1\"}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.ndjson");
+    std::fs::write(
+        &file,
+        "{\"index\":{\"_index\":\"users\"}}\n{\"user\":\"u1\",\"tmp\":1}\n",
+    )
+    .unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            file.to_str().unwrap(),
+            "--transform",
+            ".restored = true | ._routing = .user | del(.tmp)",
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_optimize_ingest_restores_settings() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/my-index/_settings"))
+        .and(query_param("flat_settings", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"my-index":{"settings":{"index.number_of_replicas":"1","index.number_of_shards":"1"}}}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/my-index/_settings"))
+        .and(body_partial_json(serde_json::json!({
+            "index.refresh_interval": "-1",
+            "index.number_of_replicas": 0
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/my-index/_settings"))
+        .and(body_partial_json(serde_json::json!({
+            "index.refresh_interval": null,
+            "index.number_of_replicas": "1"
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_refresh"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"_shards":{}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "--optimize-ingest",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_optimize_ingest_leaves_settings_when_setup_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/my-index/_settings"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"my-index":{"settings":{}}}"#))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/my-index/_settings"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+    let missing = dir.path().join("missing.ckpt");
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "--optimize-ingest",
+            "--resume",
+            missing.to_str().unwrap(),
+            file.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_optimize_ingest_requires_an_index() {
+    let server = MockServer::start().await;
+
+    let output = escli(&server)
+        .args(["utils", "load", "docs.ndjson", "--optimize-ingest"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--index"), "unexpected output: {stderr}");
+}
+
+#[tokio::test]
+async fn load_create_index_uses_the_dump_bundle() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/logs-restored"))
+        .and(body_string(
+            r#"{"mappings":{"properties":{"a":{"type":"keyword"}}},"settings":{"index":{"number_of_shards":"1"}}}"#,
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_index\":\"logs-restored\"}}\n{\"a\":\"x\"}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("dump.ndjson");
+    std::fs::write(&file, "{\"index\":{\"_index\":\"logs\"}}\n{\"a\":\"x\"}\n").unwrap();
+    std::fs::write(
+        dir.path().join("logs.mapping.json"),
+        r#"{"properties":{"a":{"type":"keyword"}}}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("logs.settings.json"),
+        r#"{"index":{"number_of_shards":"1","uuid":"abc"}}"#,
+    )
+    .unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            file.to_str().unwrap(),
+            "--create-index",
+            "--rename",
+            "logs=logs-restored",
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_create_index_aborts_when_creation_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/logs"))
+        .respond_with(
+            ResponseTemplate::new(400)
+                .set_body_string(r#"{"error":{"type":"resource_already_exists_exception"}}"#),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("logs.ndjson");
+    std::fs::write(&file, "{\"index\":{\"_index\":\"logs\"}}\n{\"a\":\"x\"}\n").unwrap();
+    std::fs::write(dir.path().join("logs.mapping.json"), "{}").unwrap();
+
+    let output = escli(&server)
+        .args(["utils", "load", file.to_str().unwrap(), "--create-index"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("resource_already_exists_exception"),
+        "unexpected output: {stderr}"
+    );
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_resume_skips_checkpointed_lines_and_creates() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(body_string(
+            "{\"create\":{\"_id\":\"2\",\"_index\":\"users\"}}\n{\"n\":2}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"errors":true,"items":[{"create":{"status":409,"error":{"type":"version_conflict_engine_exception"}}}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.ndjson");
+    std::fs::write(
+        &file,
+        "{\"index\":{\"_index\":\"users\",\"_id\":\"1\"}}\n{\"n\":1}\n{\"index\":{\"_index\":\"users\",\"_id\":\"2\"}}\n{\"n\":2}\n",
+    )
+    .unwrap();
+    let checkpoint = dir.path().join("load.ckpt");
+    std::fs::write(&checkpoint, r#"{"lines":2}"#).unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "load",
+            file.to_str().unwrap(),
+            "--op",
+            "create",
+            "--resume",
+            checkpoint.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Resuming the load after 2 lines"));
+    assert!(stderr.contains("Done: 0 documents indexed, 0 errors, 0 retried, 1 already existed"));
+
+    let saved: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&checkpoint).unwrap()).unwrap();
+    assert_eq!(saved, serde_json::json!({"lines": 4}));
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_checkpoint_records_the_lines_loaded() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_index\":\"users\"}}\n{\"n\":1}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"n\":1}\n").unwrap();
+    let checkpoint = dir.path().join("load.ckpt");
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            file.to_str().unwrap(),
+            "--index",
+            "users",
+            "--checkpoint",
+            checkpoint.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let saved: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&checkpoint).unwrap()).unwrap();
+    assert_eq!(saved, serde_json::json!({"lines": 1}));
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_failures_writes_the_rejected_items() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":400,"error":{"type":"mapper_parsing_exception"}}}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"n\":1}\n{\"n\":\"x\"}\n").unwrap();
+    let failures = dir.path().join("failed.ndjson");
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            file.to_str().unwrap(),
+            "--index",
+            "users",
+            "--failures",
+            failures.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+
+    assert_eq!(
+        std::fs::read_to_string(&failures).unwrap(),
+        "{\"error\":{\"type\":\"mapper_parsing_exception\"},\"index\":{\"_index\":\"users\"},\"status\":400}\n{\"n\":\"x\"}\n"
+    );
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_failures_file_loads_again_without_the_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_index\":\"users\"}}\n{\"n\":2}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("failed.ndjson");
+    std::fs::write(
+        &file,
+        "{\"error\":{\"type\":\"mapper_parsing_exception\"},\"index\":{\"_index\":\"users\"},\"status\":400}\n{\"n\":2}\n",
+    )
+    .unwrap();
+
+    escli(&server)
+        .args(["utils", "load", file.to_str().unwrap()])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_with_pipeline_includes_query_param() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .and(query_param("pipeline", "my-pipeline"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "--pipeline",
+            "my-pipeline",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_bulk_errors_are_reported_on_stderr() {
+    let server = MockServer::start().await;
+    let bulk_err = r#"{"errors":true,"items":[{"index":{"status":400,"error":{"type":"mapper_exception","reason":"failed to parse"}}}]}"#;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(bulk_err))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            file.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "expected exit 1 on bulk errors");
+    assert!(
+        stderr.contains("Error"),
+        "expected error details on stderr, got: {stderr}"
+    );
+}
+
+#[tokio::test]
+async fn load_ndjson_from_stdin() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(header("content-type", "application/x-ndjson"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "load"])
+        .write_stdin("{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n")
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_json_from_stdin_with_format_flag() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .and(header("content-type", "application/x-ndjson"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "load", "--format", "json", "--index", "my-index"])
+        .write_stdin("{\"field\":\"value\"}\n")
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_stdin_explicit_dash() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "load", "-"])
+        .write_stdin("{\"index\":{\"_index\":\"my-index\"}}\n{\"field\":\"value\"}\n")
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_bulk_http_error_exits_1() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("Service Unavailable"))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .code(1);
+}
+
+#[tokio::test]
+async fn load_multiple_batches_sends_multiple_requests() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"a\":1}\n{\"a\":2}\n").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "--size",
+            "1",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_retries_items_rejected_with_429() {
+    let server = MockServer::start().await;
+    let rejected = r#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":429,"error":{"type":"es_rejected_execution_exception"}}}]}"#;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_index\":\"my-index\"}}\n{\"a\":1}\n{\"index\":{\"_index\":\"my-index\"}}\n{\"a\":2}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(rejected))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_index\":\"my-index\"}}\n{\"a\":2}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"a\":1}\n{\"a\":2}\n").unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "--retry-backoff",
+            "10ms",
+            file.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 retried"), "missing summary: {stderr}");
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_gives_up_after_max_retries() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .respond_with(ResponseTemplate::new(429).set_body_string("Too Many Requests"))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"a\":1}\n").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "--max-retries",
+            "2",
+            "--retry-backoff",
+            "10ms",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_sends_batches_concurrently() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(4)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n{\"a\":4}\n").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "--size",
+            "1",
+            "--concurrency",
+            "2",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn load_format_override_treats_file_as_json() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_bulk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(BULK_OK))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("data.txt");
+    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "--format",
+            "json",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[test]
+fn load_file_not_found_fails() {
+    Command::cargo_bin("escli")
+        .unwrap()
+        .args([
+            "--url",
+            "http://127.0.0.1:1",
+            "utils",
+            "load",
+            "--index",
+            "my-index",
+            "/tmp/does-not-exist-escli-test.json",
+        ])
+        .assert()
+        .failure()
+        .code(1);
+}
+
+#[test]
+fn load_json_without_index_fails() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("docs.json");
+    std::fs::write(&file, "{\"field\":\"value\"}\n").unwrap();
+
+    Command::cargo_bin("escli")
+        .unwrap()
+        .args([
+            "--url",
+            "http://127.0.0.1:1",
+            "utils",
+            "load",
+            file.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .code(1);
+}
+
+// --- utils knn ---------------------------------------------------------------
+
+#[tokio::test]
+async fn knn_posts_knn_body_and_prints_hits() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/my-index/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "knn": { "field": "embedding", "k": 2, "query_vector": [0.5, 1.0] }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"hits":{"hits":[{"_index":"my-index","_id":"doc1","_score":0.9,"_source":{"title":"a"}}]}}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "knn",
+            "my-index",
+            "--field",
+            "embedding",
+            "--query-vector",
+            "[0.5, 1.0]",
+            "--k",
+            "2",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("my-index\tdoc1"), "missing hit: {stdout}");
+
+    server.verify().await;
+}
+
+#[test]
+fn knn_requires_a_vector_or_text() {
+    Command::cargo_bin("escli")
+        .unwrap()
+        .args([
+            "--url",
+            "http://127.0.0.1:1",
+            "utils",
+            "knn",
+            "my-index",
+            "--field",
+            "embedding",
+        ])
+        .assert()
+        .failure();
+}
+
+#[tokio::test]
+async fn knn_reads_vector_file_and_index_flag() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "knn": { "field": "embedding", "k": 10, "query_vector": [0.25, 0.75] }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"hits":{"hits":[]}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let vector = dir.path().join("vec.json");
+    std::fs::write(&vector, "[0.25, 0.75]").unwrap();
+
+    escli(&server)
+        .args([
+            "utils",
+            "knn",
+            "--index",
+            "my-index",
+            "--field",
+            "embedding",
+            "--vector-file",
+        ])
+        .arg(&vector)
+        .args(["--k", "10"])
+        .assert()
+        .success()
+        .stdout("No hits\n");
+}
+
+#[tokio::test]
+async fn knn_embeds_text_with_the_inference_api() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_inference/text_embedding/my-e5"))
+        .and(body_partial_json(
+            serde_json::json!({ "input": ["red shoes"] }),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"text_embedding":[{"embedding":[0.25,0.75]}]}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "knn": { "field": "embedding", "k": 10, "query_vector": [0.25, 0.75] }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"hits":{"hits":[]}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args([
+            "utils",
+            "knn",
+            "my-index",
+            "--field",
+            "embedding",
+            "--text",
+            "red shoes",
+            "--model-id",
+            "my-e5",
+        ])
+        .assert()
+        .success()
+        .stdout("No hits\n");
+
+    server.verify().await;
+}
+
+// --- utils forecast ----------------------------------------------------------
+
+#[tokio::test]
+async fn forecast_with_index_age_prints_report() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/_cat/indices/logs-*"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(
+                r#"[{"index":"logs-1","store.size":"1000","creation.date":"1000"}]"#,
+            ),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/_nodes/stats/fs"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"nodes":{"n1":{"name":"node-1","roles":["data_hot"],"fs":{"total":{"total_in_bytes":10000,"available_in_bytes":9000}}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/_cat/shards/logs-*"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"[{"node":"node-1","store":"1000"}]"#),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/_cluster/settings"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"persistent":{},"transient":{},"defaults":{}}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "forecast", "logs-*", "--use-index-age"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Growth rate:"),
+        "missing growth rate: {stdout}"
+    );
+    assert!(stdout.contains("node-1"), "missing node row: {stdout}");
+    assert!(
+        stdout.contains("flood_stage"),
+        "missing watermark column: {stdout}"
+    );
+
+    server.verify().await;
+}
+
+// --- utils shard-advisor -----------------------------------------------------
+
+#[tokio::test]
+async fn shard_advisor_flags_tiny_shards() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/_cat/shards/logs-*"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"index":"logs-1","prirep":"p","store":"1024","docs":"1"},{"index":"logs-1","prirep":"p","store":"1024","docs":"1"}]"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/_cat/nodes"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"[{"name":"node-1","node.role":"dm"}]"#),
+        )
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "shard-advisor", "logs-*"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("shrink to 1"),
+        "missing shrink advice: {stdout}"
+    );
+    assert!(
+        stdout.contains("POST /logs-1/_shrink/logs-1-shrunk"),
+        "missing shrink call: {stdout}"
+    );
+
+    server.verify().await;
+}
+
+// --- utils msearch -----------------------------------------------------------
+
+#[tokio::test]
+async fn msearch_sends_ndjson_and_labels_responses() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_msearch"))
+        .and(header("content-type", "application/x-ndjson"))
+        .and(body_string("{}\n{\"size\":0}\n{}\n{\"size\":1}\n"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"responses":[{"status":200,"hits":{"hits":[]}},{"status":200,"hits":{"hits":[]}}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let first = dir.path().join("first.json");
+    let second = dir.path().join("second.json");
+    std::fs::write(&first, r#"{"size": 0}"#).unwrap();
+    std::fs::write(&second, r#"{"size": 1}"#).unwrap();
+
+    let output = escli(&server)
+        .args(["utils", "msearch", "--index", "my-index"])
+        .args(["--query", first.to_str().unwrap()])
+        .args(["--query", second.to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("### first (200)"),
+        "missing label: {stdout}"
+    );
+    assert!(
+        stdout.contains("### second (200)"),
+        "missing label: {stdout}"
+    );
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn msearch_failed_search_exits_1() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_msearch"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"responses":[{"status":404,"error":{"type":"index_not_found_exception"}}]}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("missing.json");
+    std::fs::write(&file, r#"{"size": 0}"#).unwrap();
+
+    escli(&server)
+        .args(["utils", "msearch", "--query"])
+        .arg(format!("missing-index={}", file.to_str().unwrap()))
+        .assert()
+        .code(1);
+}
+
+#[tokio::test]
+async fn msearch_summary_prints_a_table() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_msearch"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"responses":[{"status":200,"took":3,"hits":{"total":{"value":42,"relation":"eq"}}}]}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("errors.json");
+    std::fs::write(&file, r#"{"size": 0}"#).unwrap();
+
+    escli(&server)
+        .args(["utils", "msearch", "--summary", "--query"])
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout("label   status  took  hits  error\nerrors  200     3ms   42\n");
+}
+
+// --- utils pit ---------------------------------------------------------------
+
+#[tokio::test]
+async fn pit_open_prints_only_the_id() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/my-index/_pit"))
+        .and(query_param("keep_alive", "10m"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id":"abc123"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "pit", "open", "my-index", "--keep-alive", "10m"])
+        .assert()
+        .success()
+        .stdout("abc123\n");
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn pit_close_sends_id_in_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/_pit"))
+        .and(body_partial_json(serde_json::json!({ "id": "abc123" })))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"succeeded":true,"num_freed":1}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "pit", "close", "abc123"])
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn search_pit_flag_is_injected_into_body() {
+    let server = MockServer::start().await;
+    Mock::given(path("/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "size": 10,
+            "pit": { "id": "abc123", "keep_alive": "1m" }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args([
+            "core",
+            "search",
+            "--pit",
+            "abc123",
+            "--pit-keep-alive",
+            "1m",
+        ])
+        .write_stdin(r#"{"size":10}"#)
+        .assert()
+        .success();
+
+    server.verify().await;
+}
+
+// --- utils apply -------------------------------------------------------------
+
+#[tokio::test]
+async fn apply_dry_run_prints_plan_without_changes() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/products"))
+        .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"status":404}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_alias/catalog"))
+        .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"status":404}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("cluster.yaml");
+    std::fs::write(
+        &file,
+        "indices:\n  products:\n    settings: { number_of_replicas: 1 }\naliases:\n  catalog: [products]\n",
+    )
+    .unwrap();
+
+    escli(&server)
+        .args(["utils", "apply", "--dry-run", "-f", file.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(
+            "+ index products\n+ alias catalog -> products\nPlan: 2 to create, 0 to update, 0 to remove.\n",
+        );
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn apply_updates_changed_settings() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/products"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"products":{"settings":{"index.number_of_replicas":"0"},"mappings":{}}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/products/_settings"))
+        .and(body_partial_json(
+            serde_json::json!({ "index.number_of_replicas": 1 }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("cluster.yaml");
+    std::fs::write(
+        &file,
+        "indices:\n  products:\n    settings: { number_of_replicas: 1 }\n",
+    )
+    .unwrap();
+
+    let output = escli(&server)
+        .args(["utils", "apply", "-f", file.to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("~ index products settings"),
+        "missing plan: {stdout}"
+    );
+    assert!(stdout.contains("applied"), "missing result: {stdout}");
+
+    server.verify().await;
+}
+
+// --- completions --------------------------------------------------------------
+
+#[test]
+fn completions_prints_script_without_url() {
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .args(["completions", "bash"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("escli"), "unexpected script: {stdout}");
+}
+
+#[test]
+fn completions_rejects_unknown_shell() {
+    Command::cargo_bin("escli")
+        .unwrap()
+        .args(["completions", "tcsh"])
+        .assert()
+        .failure();
+}
+
+#[tokio::test]
+async fn completions_complete_index_names_from_profile_cache() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/indices"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"[{"index":"logs-1"},{"index":"metrics"}]"#),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/aliases"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"alias":"logs"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_data_stream"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"data_streams":[{"name":"logs-app"}]}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("prod.env"),
+        format!("ESCLI_URL={}\n", server.uri()),
+    )
+    .unwrap();
+
+    // What the background refresh spawned while completing runs.
+    Command::cargo_bin("escli")
+        .unwrap()
+        .env("ESCLI_PROFILES_DIR", dir.path())
+        .env("XDG_CACHE_HOME", dir.path())
+        .env("ESCLI_REFRESH_INDEX_CACHE", "prod")
+        .assert()
+        .success();
+    let cache = std::fs::read_to_string(dir.path().join("escli/completions/prod.indices")).unwrap();
+    assert_eq!(cache, "logs\nlogs-1\nlogs-app\nmetrics\n");
+
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .env("ESCLI_PROFILES_DIR", dir.path())
+        .env("XDG_CACHE_HOME", dir.path())
+        .env("COMPLETE", "fish")
+        .args([
+            "--",
+            "escli",
+            "--profile",
+            "prod",
+            "indices",
+            "get",
+            "logs-",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("logs-1"), "missing index: {stdout}");
+    assert!(stdout.contains("logs-app"), "missing data stream: {stdout}");
+    assert!(!stdout.contains("metrics"), "unexpected index: {stdout}");
+}
+
+#[test]
+fn completions_offer_enum_values() {
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .env("COMPLETE", "fish")
+        .args(["--", "escli", "cluster", "health", "--wait_for_status", ""])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("yellow"), "missing enum value: {stdout}");
+}
+
+// --- plugins ------------------------------------------------------------------
+
+#[cfg(unix)]
+#[test]
+fn unknown_subcommand_runs_plugin_from_path() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let plugin = dir.path().join("escli-hello");
+    std::fs::write(
+        &plugin,
+        "#!/bin/sh\necho \"$ESCLI_URL $ESCLI_API_KEY $*\"\nexit 3\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(std::iter::once(dir.path().to_path_buf()).chain(
+        std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()),
+    ))
+    .unwrap();
+
+    Command::cargo_bin("escli")
+        .unwrap()
+        .env("PATH", path)
+        .args([
+            "--url",
+            "http://localhost:9200",
+            "--api-key",
+            "secret",
+            "hello",
+            "a",
+            "--b",
+        ])
+        .assert()
+        .code(3)
+        .stdout("http://localhost:9200/ secret a --b\n");
+}
+
+#[test]
+fn unknown_subcommand_without_plugin_fails() {
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .args(["--url", "http://localhost:9200", "no-such-plugin"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("escli-no-such-plugin"),
+        "unexpected error: {stderr}"
+    );
+}
+
+// --- self-update --------------------------------------------------------------
+
+async fn mount_release(server: &MockServer, tag: &str) {
+    let assets: Vec<serde_json::Value> = [
+        "escli-linux-amd64",
+        "escli-linux-arm64",
+        "escli-macos",
+        "escli-windows.exe",
+    ]
+    .iter()
+    .flat_map(|name| {
+        [
+            serde_json::json!({"name": name, "browser_download_url": format!("{}/download/binary", server.uri())}),
+            serde_json::json!({"name": format!("{name}.sha256"), "browser_download_url": format!("{}/download/checksum", server.uri())}),
+        ]
+    })
+    .collect();
+    Mock::given(method("GET"))
+        .and(path("/repos/Anaethelion/escli-rs/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(
+            serde_json::json!([{"tag_name": tag, "draft": false, "assets": assets}]),
+        ))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn self_update_check_reports_newer_release() {
+    let server = MockServer::start().await;
+    mount_release(&server, "v99.0.0").await;
+
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .env("ESCLI_UPDATE_API_URL", server.uri())
+        .args(["self-update", "--check"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("v99.0.0 is available"),
+        "unexpected output: {stdout}"
+    );
+}
+
+#[tokio::test]
+async fn self_update_is_noop_when_up_to_date() {
+    let server = MockServer::start().await;
+    mount_release(&server, "v0.0.1").await;
+
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .env("ESCLI_UPDATE_API_URL", server.uri())
+        .arg("self-update")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("is up to date"),
+        "unexpected output: {stdout}"
+    );
+}
+
+#[tokio::test]
+async fn self_update_rejects_checksum_mismatch() {
+    let server = MockServer::start().await;
+    mount_release(&server, "v99.0.0").await;
+    Mock::given(method("GET"))
+        .and(path("/download/binary"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("not escli"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/download/checksum"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(format!("{}  escli\n", "0".repeat(64))),
+        )
+        .mount(&server)
+        .await;
+
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .env("ESCLI_UPDATE_API_URL", server.uri())
+        .arg("self-update")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("checksum mismatch"),
+        "unexpected error: {stderr}"
+    );
+}
+
+// --- transfer -----------------------------------------------------------------
+
+#[tokio::test]
+async fn transfer_streams_documents_between_profiles() {
+    let source = MockServer::start().await;
+    let destination = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id":"pit-1"}"#))
+        .expect(1)
+        .mount(&source)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(serde_json::json!({ "sort": ["_shard_doc"] })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"pit-1","hits":{"hits":[{"_index":"logs","_id":"1","_source":{"a":1},"sort":[0]}]}}"#,
+        ))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&source)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(
+            serde_json::json!({ "search_after": [0] }),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"pit_id":"pit-1","hits":{"hits":[]}}"#),
+        )
+        .expect(1)
+        .mount(&source)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"succeeded":true}"#))
+        .expect(1)
+        .mount(&source)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_bulk"))
+        .and(wiremock::matchers::body_string_contains("\"_id\":\"1\""))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"errors":false,"items":[{"index":{"status":201}}]}"#),
+        )
+        .expect(1)
+        .mount(&destination)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("prod.env"),
+        format!("ESCLI_URL={}\n", source.uri()),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("staging.env"),
+        format!("ESCLI_URL={}\n", destination.uri()),
+    )
+    .unwrap();
+
+    Command::cargo_bin("escli")
+        .unwrap()
+        .current_dir(dir.path())
+        .env("ESCLI_PROFILES_DIR", dir.path())
+        .args([
+            "utils",
+            "transfer",
+            "--from-profile",
+            "prod",
+            "--to-profile",
+            "staging",
+            "--index",
+            "logs",
+        ])
+        .assert()
+        .success()
+        .stdout("Transferred 1 documents to staging, 0 errors\n");
+
+    source.verify().await;
+    destination.verify().await;
+}
+
+// --- copy-index ---------------------------------------------------------------
+
+const INDEX_DEFINITION: &str = r#"{"logs-1":{"aliases":{"logs":{}},"mappings":{"properties":{"a":{"type":"keyword"}}},"settings":{"index":{"number_of_shards":"1","uuid":"abc","creation_date":"1","version":{"created":"8500000"},"provided_name":"logs-1"}}}}"#;
+
+#[tokio::test]
+async fn copy_index_creates_target_without_managed_settings() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/logs-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(INDEX_DEFINITION))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/logs-2"))
+        .and(body_partial_json(serde_json::json!({
+            "settings": {"index": {"number_of_shards": "1"}},
+            "aliases": {"logs": {}},
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args([
+            "utils",
+            "copy-index",
+            "logs-1",
+            "logs-2",
+            "--include-aliases",
+        ])
+        .assert()
+        .success();
+
+    server.verify().await;
+    let requests = server.received_requests().await.unwrap();
+    let put = requests
+        .iter()
+        .find(|r| r.method.as_str() == "PUT")
+        .unwrap();
+    let body = String::from_utf8_lossy(&put.body);
+    assert!(
+        !body.contains("uuid"),
+        "managed settings were copied: {body}"
+    );
+}
+
+#[tokio::test]
+async fn copy_index_dry_run_prints_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/logs-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(INDEX_DEFINITION))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "copy-index", "logs-1", "logs-2", "--dry-run"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("PUT /logs-2\n"),
+        "unexpected output: {stdout}"
+    );
+    assert!(
+        !stdout.contains("aliases"),
+        "aliases copied without --include-aliases: {stdout}"
+    );
+}
+
+// --- snapshot -----------------------------------------------------------------
+
+#[tokio::test]
+async fn snapshot_create_polls_status_until_done() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/_snapshot/repo/snap"))
+        .and(query_param("wait_for_completion", "false"))
+        .and(body_partial_json(
+            serde_json::json!({ "indices": "logs-*" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"accepted":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_snapshot/repo/snap/_status"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"snapshots":[{"snapshot":"snap","repository":"repo","state":"STARTED","shards_stats":{"done":0,"total":1},"stats":{}}]}"#,
+        ))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_snapshot/repo/snap/_status"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"snapshots":[{"snapshot":"snap","repository":"repo","state":"SUCCESS","shards_stats":{"done":1,"total":1,"failed":0},"stats":{"total":{"size_in_bytes":2048}}}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args([
+            "utils",
+            "snapshot",
+            "create",
+            "repo",
+            "snap",
+            "--indices",
+            "logs-*",
+            "--interval",
+            "10ms",
+        ])
+        .assert()
+        .success()
+        .stdout("Snapshot repo/snap SUCCESS: 1/1 shards done, 0 failed, 2kb\n");
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn snapshot_create_fails_on_partial_snapshot() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/_snapshot/repo/snap"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"accepted":true}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_snapshot/repo/snap/_status"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"snapshots":[{"snapshot":"snap","repository":"repo","state":"PARTIAL","shards_stats":{"done":1,"total":2,"failed":1},"stats":{}}]}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "snapshot", "create", "repo", "snap"])
+        .assert()
+        .failure();
+}
+
+#[tokio::test]
+async fn snapshot_restore_waits_for_recovery_and_verifies_counts() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_snapshot/repo/snap/_restore"))
+        .and(body_partial_json(serde_json::json!({
+            "rename_pattern": "logs-(.*)",
+            "rename_replacement": "restored-$1",
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"accepted":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_recovery"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"restored-a":{"shards":[{"id":0,"type":"SNAPSHOT","stage":"DONE","source":{"repository":"repo","snapshot":"snap","index":"logs-a"},"index":{"size":{"percent":"100.0%"}}}]}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/indices"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"index":"restored-a","docs.count":"10"},{"index":"logs-a","docs.count":"10"}]"#,
+        ))
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "snapshot", "restore", "repo", "snap", "--rename", "logs-(.*)=restored-$1"])
+        .assert()
+        .success()
+        .stdout("index       docs.count  original  original.docs.count\nrestored-a  10          logs-a    10\n");
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn snapshot_verify_cleans_up_and_counts_snapshots() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_snapshot/backups"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "backups": {"type": "fs", "settings": {"location": "/mnt/backups"}}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_snapshot/backups/_verify"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "nodes": {"a": {"name": "n1"}, "b": {"name": "n2"}}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_snapshot/backups/_cleanup"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "results": {"deleted_bytes": 1024, "deleted_blobs": 3}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_snapshot/backups/_all"))
+        .and(query_param("sort", "start_time"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "snapshots": [{"snapshot": "s1", "start_time_in_millis": 1_735_689_600_000u64}]
+        })))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "snapshot", "verify", "backups"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "repository  type  verified      reclaimed  snapshots  oldest           newest\n\
+         backups     fs    ok (2 nodes)  1kb        1          s1 (2025-01-01)  s1 (2025-01-01)\n\
+         \n\
+         1 repositories, 0 failed verification, 1kb reclaimed\n"
+    );
+}
+
+// --- alias-swap ---------------------------------------------------------------
+
+#[tokio::test]
+async fn alias_swap_moves_alias_atomically() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .and(path("/products-v2"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_alias/products"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"products-v1":{"aliases":{"products":{}}}}"#),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_aliases"))
+        .and(body_partial_json(serde_json::json!({"actions": [
+            {"remove": {"index": "products-v1", "alias": "products"}},
+            {"add": {"index": "products-v2", "alias": "products", "is_write_index": true}},
+        ]})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "alias-swap",
+            "--alias",
+            "products",
+            "--to",
+            "products-v2",
+            "--write-index",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "Alias products moved from products-v1 to products-v2\n"
+    );
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn alias_swap_rejects_missing_target() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .and(path("/products-v2"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_aliases"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args([
+            "utils",
+            "alias-swap",
+            "--alias",
+            "products",
+            "--to",
+            "products-v2",
+        ])
+        .assert()
+        .failure();
+
+    server.verify().await;
+}
+
+// --- wait-for-health ----------------------------------------------------------
+
+#[tokio::test]
+async fn wait_for_health_succeeds_once_status_is_reached() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"status":"yellow"}"#))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "wait-for-health", "--status", "yellow"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("] yellow\n"), "unexpected output: {stdout}");
+    assert!(
+        stdout.contains("Cluster is yellow after"),
+        "unexpected output: {stdout}"
+    );
+}
+
+#[tokio::test]
+async fn wait_for_health_fails_on_timeout() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"status":"red"}"#))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "wait-for-health",
+            "--timeout",
+            "1s",
+            "--interval",
+            "1s",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("waiting for green, cluster is red"),
+        "unexpected output: {stderr}"
+    );
+}
+
+// --- ping ---------------------------------------------------------------------
+
+#[tokio::test]
+async fn ping_succeeds_without_output() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server).args(["utils", "ping"]).output().unwrap();
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn ping_fails_quietly_below_required_status() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"status":"yellow"}"#))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "ping", "--require-status", "green"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+// --- top ----------------------------------------------------------------------
+
+#[tokio::test]
+async fn top_prints_dashboard() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"cluster_name":"prod","status":"green","number_of_nodes":1}"#),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_nodes/stats/os,jvm,indices"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"nodes":{"n1":{"name":"es-1","os":{"cpu":{"percent":7}},"jvm":{"mem":{"heap_used_percent":30}}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "top", "-n", "2", "--interval", "0s"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("cluster prod status green").count(), 2);
+    assert!(
+        stdout.contains("indexing 0.0/s, search 0.0/s"),
+        "unexpected output: {stdout}"
+    );
+    assert!(stdout.contains("es-1  7%"), "unexpected output: {stdout}");
+    assert!(
+        !stdout.contains('\x1b'),
+        "screen cleared outside a terminal"
+    );
+}
+
+// --- tail ---------------------------------------------------------------------
+
+#[tokio::test]
+async fn tail_prints_latest_documents_in_order() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_search"))
+        .and(body_partial_json(serde_json::json!({
+            "size": 2,
+            "sort": [{"@timestamp": {"order": "desc"}}],
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"hits":{"hits":[
+                {"_id":"2","sort":[2000],"_source":{"msg":"second"}},
+                {"_id":"1","sort":[1000],"_source":{"msg":"first"}}
+            ]}}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "tail", "logs", "-n", "2"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "{\"msg\":\"first\"}\n{\"msg\":\"second\"}\n");
+    server.verify().await;
+}
+
+// --- diff-index ---------------------------------------------------------------
+
+#[tokio::test]
+async fn diff_index_prints_structural_differences() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/logs-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(INDEX_DEFINITION))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/logs-2"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"logs-2":{"aliases":{},"mappings":{"properties":{"a":{"type":"text"}}},"settings":{"index":{"number_of_shards":"1","uuid":"def","provided_name":"logs-2"}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "diff-index", "logs-1", "logs-2"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "--- logs-1\n+++ logs-2\n\
+         - aliases.logs: {}\n\
+         ~ mappings.properties.a.type: \"keyword\" -> \"text\"\n"
+    );
+}
+
+// --- templates ----------------------------------------------------------------
+
+async fn mount_templates(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/_index_template"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"index_templates":[{"name":"logs","index_template":{"index_patterns":["logs-*"],"priority":10,"composed_of":["logs-mappings"]}}]}"#,
+        ))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_component_template"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"component_templates":[{"name":"logs-mappings","component_template":{"template":{"settings":{"index":{"number_of_replicas":"1"}}}}}]}"#,
+        ))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn templates_conflicts_fails_on_file_template_with_same_priority() {
+    let server = MockServer::start().await;
+    mount_templates(&server).await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("templates.yaml");
+    std::fs::write(
+        &file,
+        "index_templates:\n  app:\n    index_patterns: [\"logs-app-*\"]\n    priority: 10\n",
+    )
+    .unwrap();
+
+    let output = escli(&server)
+        .args(["utils", "templates", "conflicts", "-f"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("app       10        logs      10        conflict"),
+        "unexpected output: {stdout}"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "Found 1 conflict(s)\n");
+}
+
+#[tokio::test]
+async fn templates_diff_ignores_settings_notation() {
+    let server = MockServer::start().await;
+    mount_templates(&server).await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("templates.yaml");
+    std::fs::write(
+        &file,
+        "component_templates:\n  logs-mappings:\n    template:\n      settings:\n        number_of_replicas: 2\n",
+    )
+    .unwrap();
+
+    let output = escli(&server)
+        .args(["utils", "templates", "diff", "--no-color", "-f"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "index template logs: only in the cluster\n\
+         component template logs-mappings:\n  \
+         ~ template.settings.index.number_of_replicas: \"1\" -> \"2\"\n"
+    );
+}
+
+// --- lint-mapping -------------------------------------------------------------
+
+#[test]
+fn lint_mapping_runs_without_cluster() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("index.json");
+    std::fs::write(
+        &file,
+        r#"{"mappings":{"properties":{"id":{"type":"text"},"tags":{"type":"keyword"}}}}"#,
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .args(["utils", "lint-mapping"])
+        .arg(&file)
+        .env_remove("ESCLI_URL")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "warning: id: identifiers are usually searched exactly, map them as keyword\n\
+         0 error(s), 1 warning(s)\n"
+    );
+}
+
+#[tokio::test]
+async fn lint_mapping_checks_cluster_version() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"version":{"number":"7.17.0"}}"#),
+        )
+        .mount(&server)
+        .await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("mapping.json");
+    std::fs::write(&file, r#"{"properties":{"body":{"type":"semantic_text"}}}"#).unwrap();
+
+    let output = escli(&server)
+        .args(["utils", "lint-mapping"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("requires Elasticsearch 8.15, the cluster runs 7.17"),
+        "unexpected output: {stdout}"
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "1 error(s), 0 warning(s)\n"
+    );
+}
+
+// --- infer-mapping ------------------------------------------------------------
+
+#[test]
+fn infer_mapping_reads_documents_from_stdin() {
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .args(["utils", "infer-mapping", "-"])
+        .env_remove("ESCLI_URL")
+        .write_stdin(
+            "{\"ts\":\"2025-01-31T12:00:00Z\",\"n\":1}\n{\"ts\":\"2025-02-01\",\"n\":2.5}\n",
+        )
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let mapping: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        mapping,
+        serde_json::json!({"mappings": {"properties": {
+            "n": {"type": "double"},
+            "ts": {"type": "date"},
+        }}})
+    );
+}
+
+// --- seed ---------------------------------------------------------------------
+
+#[tokio::test]
+async fn seed_generates_and_loads_documents() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/test/_bulk"))
+        .and(wiremock::matchers::body_string_contains(
+            "\"status\":\"active\"",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"errors":false,"items":[{"index":{"status":201}},{"index":{"status":201}}]}"#,
+        ))
+        .expect(3)
+        .mount(&server)
+        .await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let schema = dir.path().join("schema.yaml");
+    std::fs::write(
+        &schema,
+        "id: uuid\nstatus: { type: keyword, values: [active] }\n",
+    )
+    .unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "seed",
+            "--index",
+            "test",
+            "--count",
+            "5",
+            "--batch-size",
+            "2",
+            "--seed",
+            "1",
+            "--schema",
+        ])
+        .arg(&schema)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("across 3 batch(es)"),
+        "unexpected output: {stderr}"
+    );
+    server.verify().await;
+}
+
+// --- delete-by-query ----------------------------------------------------------
+
+#[tokio::test]
+async fn delete_by_query_follows_task_until_completion() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_delete_by_query"))
+        .and(query_param("wait_for_completion", "false"))
+        .and(query_param("requests_per_second", "100"))
+        .and(body_partial_json(
+            serde_json::json!({"query": {"term": {"a": 1}}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"task":"node:1"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_tasks/node:1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"completed":true,"task":{"status":{"total":4,"deleted":4}},"response":{"total":4,"deleted":4,"version_conflicts":0,"failures":[]}}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "delete-by-query",
+            "logs",
+            "--query",
+            r#"{"term":{"a":1}}"#,
+            "--requests-per-second",
+            "100",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("Deleted 4 of 4 documents in "),
+        "unexpected output: {stdout}"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("100% 4/4 deleted"),
+        "unexpected output: {stderr}"
+    );
+    server.verify().await;
+}
+
+/// A Ctrl-C pressed while the task is polled must cancel it on the cluster.
+#[cfg(unix)]
+#[tokio::test]
+async fn delete_by_query_interrupted_while_polling_cancels_the_task() {
+    use std::process::Stdio;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_delete_by_query"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"task":"node:1"}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_tasks/node:1"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"completed":false,"task":{"status":{"total":4}}}"#)
+                .set_delay(std::time::Duration::from_secs(5)),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_tasks/node:1/_cancel"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let bin = assert_cmd::cargo::cargo_bin("escli");
+    let child = std::process::Command::new(bin)
+        .args([
+            "--url",
+            &server.uri(),
+            "utils",
+            "delete-by-query",
+            "logs",
+            "--query",
+            r#"{"match_all":{}}"#,
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    let killed = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let output = tokio::task::spawn_blocking(move || child.wait_with_output().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Cancelled task node:1"),
+        "unexpected output: {stderr}"
+    );
+    server.verify().await;
+}
+
+// --- purge --------------------------------------------------------------------
+
+async fn mount_purge_indices(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/_cat/indices/logs-*"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"index":"logs-old","creation.date":"1000","docs.count":"3","store.size":"2048"},{"index":"logs-new","creation.date":"32503680000000","docs.count":"1","store.size":"1024"}]"#,
+        ))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn purge_deletes_old_indices_with_yes() {
+    let server = MockServer::start().await;
+    mount_purge_indices(&server).await;
+    Mock::given(method("DELETE"))
+        .and(path("/logs-old"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "purge",
+            "--pattern",
+            "logs-*",
+            "--older-than",
+            "30d",
+            "--yes",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("logs-old  1970-01-01T00:00:01Z"),
+        "unexpected output: {stdout}"
+    );
+    assert!(
+        !stdout.contains("logs-new"),
+        "recent index listed: {stdout}"
+    );
+    assert!(
+        stdout.ends_with("Deleted 1 indices\n"),
+        "unexpected output: {stdout}"
+    );
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn purge_refuses_without_confirmation() {
+    let server = MockServer::start().await;
+    mount_purge_indices(&server).await;
+    Mock::given(method("DELETE"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "purge",
+            "--pattern",
+            "logs-*",
+            "--older-than",
+            "30d",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("use --yes"), "unexpected output: {stderr}");
+    server.verify().await;
+}
+
+// --- resize -------------------------------------------------------------------
+
+#[tokio::test]
+async fn resize_runs_every_step_and_unblocks_source() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/logs/_settings"))
+        .and(body_partial_json(
+            serde_json::json!({"index.blocks.write": true}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_split/logs-split"))
+        .and(body_partial_json(
+            serde_json::json!({"settings": {"index.number_of_shards": 4}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health/logs-split"))
+        .and(query_param("wait_for_status", "green"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"status":"green","timed_out":false}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_aliases"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/logs/_settings"))
+        .and(body_partial_json(
+            serde_json::json!({"index.blocks.write": null}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "resize",
+            "logs",
+            "logs-split",
+            "--mode",
+            "split",
+            "--shards",
+            "4",
+            "--alias",
+            "current",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Split logs into logs-split\n"
+    );
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn resize_unblocks_source_when_a_step_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/logs/_settings"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_clone/logs-copy"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(r#"{"error":"already exists"}"#))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "resize", "logs", "logs-copy", "--mode", "clone"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Unblocking writes on logs"),
+        "unexpected output: {stderr}"
+    );
+    assert!(
+        stderr.contains("already exists"),
+        "unexpected output: {stderr}"
+    );
+    server.verify().await;
+}
+
+// --- allocation-explain -------------------------------------------------------
+
+#[tokio::test]
+async fn allocation_explain_summarizes_unassigned_shards() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/shards"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"index":"logs","shard":"0","prirep":"p","state":"STARTED"},{"index":"logs","shard":"0","prirep":"r","state":"UNASSIGNED"}]"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_cluster/allocation/explain"))
+        .and(body_partial_json(serde_json::json!({"index": "logs", "shard": 0, "primary": false})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"can_allocate":"no","node_allocation_decisions":[{"node_name":"n1","deciders":[{"decider":"same_shard","decision":"NO","explanation":"a copy of this shard is already allocated to this node [[logs][0], node[n1]]"}]}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "allocation-explain"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("1 unassigned shard(s), 1 distinct cause(s)\n"),
+        "unexpected output: {stdout}"
+    );
+    assert!(
+        stdout.contains("same_shard: 1 shard(s) on 1 node(s): logs[0]r\n"),
+        "unexpected output: {stdout}"
+    );
+    assert!(
+        stdout.contains("  fix: add nodes"),
+        "unexpected output: {stdout}"
+    );
+    server.verify().await;
+}
+
+// --- field-usage --------------------------------------------------------------
+
+#[tokio::test]
+async fn field_usage_reports_counts_limits_and_unused_fields() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/logs/_mapping"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"logs":{"mappings":{"properties":{"a":{"type":"keyword"},"b":{"type":"long"}}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/logs/_settings/index.mapping.total_fields.limit"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"logs":{"settings":{"index.mapping.total_fields.limit":"2"}}}"#,
+            ),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/logs/_field_usage_stats"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"logs":{"shards":[{"stats":{"fields":{"a":{"any":2}}}}]}}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "field-usage", "logs", "--show-unused"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "index  fields  limit  usage   used  unused\n\
+         logs   2       2      100% !  1     1\n\
+         \n\
+         logs:\n  \
+         b\n"
+    );
+}
+
+// --- disk ---------------------------------------------------------------------
+
+#[tokio::test]
+async fn disk_renders_node_index_and_field_tree() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/allocation"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"node":"n1","disk.indices":"2048","disk.used":"4096","disk.total":"8192"}]"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/shards/logs"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"[{"index":"logs","node":"n1","store":"2048"}]"#),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_disk_usage"))
+        .and(query_param("run_expensive_tasks", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"logs":{"store_size_in_bytes":2048,"fields":{"message":{"total_in_bytes":1024}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "disk", "--index", "logs", "--fields"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "cluster  4kb used of 8kb (50%)\n\
+         └── n1  4kb used of 8kb (50%), 2kb in indices\n    \
+         └── logs  2kb (100%)\n        \
+         └── message  1kb (50%)\n"
+    );
+}
+
+// --- ilm ----------------------------------------------------------------------
+
+#[tokio::test]
+async fn ilm_reports_and_retries_failed_steps() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/logs-*/_ilm/explain"))
+        .and(query_param("only_managed", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"indices":{"logs-1":{"managed":true,"policy":"logs","phase":"warm",
+                "action":"shrink","step":"ERROR","failed_step":"shrink",
+                "step_info":{"reason":"no node"}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/logs-1/_ilm/retry"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"acknowledged":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "ilm", "logs-*", "--retry"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "policy  phase  index   action  step            step.age  status\n\
+         logs    warm   logs-1  shrink  ERROR (shrink)  -         failed\n\
+         \n\
+         logs-1: no node\n\
+         1 managed indices, 0 stuck, 1 failed\n\
+         Retried 1 failed steps\n"
+    );
+}
+
+// --- security-audit -----------------------------------------------------------
+
+#[tokio::test]
+async fn security_audit_flags_superuser_grants_and_keys() {
+    let server = MockServer::start().await;
+    for (api, body) in [
+        (
+            "user",
+            r#"{"admin":{"enabled":true,"roles":["all_access"]}}"#,
+        ),
+        (
+            "role",
+            r#"{"all_access":{"cluster":["all"],"indices":[{"names":["*"],"privileges":["all"]}]}}"#,
+        ),
+        ("role_mapping", r#"{}"#),
+        (
+            "api_key",
+            r#"{"api_keys":[{"id":"k1","name":"ci","username":"admin","creation":0}]}"#,
+        ),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/_security/{api}")))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+    }
+
+    let output = escli(&server)
+        .args(["utils", "security-audit"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Users\n\
+         user   enabled  roles       flags\n\
+         admin  true     all_access  superuser\n\
+         \n\
+         Roles\n\
+         role        cluster  indices  flags\n\
+         all_access  all      *:all    superuser\n\
+         \n\
+         Role mappings\n\
+         none\n\
+         \n\
+         API keys\n\
+         id  name  owner  created               expires  privileges  flags\n\
+         k1  ci    admin  1970-01-01T00:00:00Z  never    inherited   never expires, superuser\n\
+         3 flagged item(s)\n"
+    );
+}
+
+// --- license ------------------------------------------------------------------
+
+#[tokio::test]
+async fn license_fails_when_expired() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_license"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"license":{"type":"trial","status":"expired","issued_to":"test",
+                "issue_date_in_millis":0,"expiry_date_in_millis":86400000,"max_nodes":1000}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_license/feature_usage"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"features":[{"family":null,"name":"security-dls","context":null,
+                "last_used":"2024-01-01T00:00:00.000Z","license_level":"platinum"}]}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "license", "--warn-days", "30"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "type   status   issued_to  issue_date            expiry_date           max_nodes\n\
+         trial  expired  test       1970-01-01T00:00:00Z  1970-01-02T00:00:00Z  1000\n\
+         \n\
+         feature       family  context  license_level  last_used\n\
+         security-dls  -       -        platinum       2024-01-01T00:00:00.000Z\n"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("The trial license has expired"));
+}
+
+// --- transform-preview --------------------------------------------------------
+
+#[tokio::test]
+async fn transform_preview_renders_mapping_and_documents() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_transform/_preview"))
+        .and(body_partial_json(
+            serde_json::json!({"source": {"index": "orders"}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"preview":[{"customer":"a","total":12.5}],
+                "generated_dest_index":{"mappings":{"properties":{
+                    "customer":{"type":"keyword"},"total":{"type":"double"}}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "transform-preview",
+            "--config",
+            r#"{"source":{"index":"orders"},"pivot":{}}"#,
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "field     type\n\
+         customer  keyword\n\
+         total     double\n\
+         \n\
+         customer  total\n\
+         a         12.5\n\
+         \n\
+         1 of 1 preview documents shown\n"
+    );
+}
+
+// --- count --------------------------------------------------------------------
+
+#[tokio::test]
+async fn count_counts_each_index_with_query_file() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_resolve/index/logs-*"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"indices":[{"name":"logs-1"},{"name":"logs-2"}],"aliases":[],"data_streams":[]}"#,
+        ))
+        .mount(&server)
+        .await;
+    for (index, count) in [("logs-1", 5), ("logs-2", 0)] {
+        Mock::given(method("POST"))
+            .and(path(format!("/{index}/_count")))
+            .and(body_partial_json(
+                serde_json::json!({"query": {"term": {"level": "error"}}}),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(format!(r#"{{"count":{count}}}"#)),
+            )
+            .mount(&server)
+            .await;
+    }
+    let dir = tempfile::TempDir::new().unwrap();
+    let query = dir.path().join("q.json");
+    std::fs::write(&query, r#"{"term":{"level":"error"}}"#).unwrap();
+
+    let output = escli(&server)
+        .args(["utils", "count", "logs-*", "--by", "index", "--query-file"])
+        .arg(&query)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "index   count\n\
+         logs-1  5\n\
+         logs-2  0\n\
+         total   5\n"
+    );
+}
+
+// --- import-csv ---------------------------------------------------------------
+
+#[tokio::test]
+async fn import_csv_bulk_loads_rows() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/sales/_bulk"))
+        .and(body_string(
+            "{\"index\":{\"_id\":\"o1\"}}\n{\"amount\":\"12.5\",\"note\":\"a, b\"}\n\
+             {\"index\":{\"_id\":\"o2\"}}\n{\"amount\":\"3\"}\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"errors":false,"items":[{"index":{"status":201}},{"index":{"status":201}}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("sales.csv");
+    std::fs::write(&file, "id;amount;note\no1;12.5;a, b\no2;3;\n").unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "import-csv",
+            "--index",
+            "sales",
+            "-d",
+            ";",
+            "--id-column",
+            "id",
+        ])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Done: 2 documents indexed, 0 errors"));
+}
+
+#[tokio::test]
+async fn import_csv_converts_typed_columns() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/sales/_bulk"))
+        .and(body_string(
+            "{\"index\":{}}\n{\"amount\":12.5,\"region\":\"eu\"}\n",
+        ))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"errors":false,"items":[{"index":{"status":201}}]}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("sales.csv");
+    std::fs::write(&file, "eu,12.5\n").unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "import-csv",
+            "--index",
+            "sales",
+            "--no-header",
+            "--columns",
+            "region,amount",
+            "--type",
+            "amount=double",
+        ])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+}
+
+// --- export -------------------------------------------------------------------
+
+#[tokio::test]
+async fn export_writes_flattened_csv() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/logs/_mapping"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"logs":{"mappings":{"properties":{"level":{"type":"keyword"},
+                "user":{"properties":{"name":{"type":"keyword"}}}}}}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id":"pit-1"}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .and(body_partial_json(
+            serde_json::json!({"_source": ["user.name", "level"]}),
+        ))
+        .and(wiremock::matchers::body_string_contains("search_after"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"hits":{"hits":[]}}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"pit_id":"pit-1","hits":{"hits":[
+                {"_source":{"level":"info","user":{"name":"Doe, J"}},"sort":[1]},
+                {"_source":{"level":"warn"},"sort":[2]}]}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/_pit"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"succeeded":true}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "export",
+            "--index",
+            "logs",
+            "--fields",
+            "user.name,level",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "user.name,level\n\"Doe, J\",info\n,warn\n"
+    );
+}
+
+// --- sql ----------------------------------------------------------------------
+
+#[tokio::test]
+async fn sql_follows_the_cursor() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_sql"))
+        .and(body_partial_json(serde_json::json!({"cursor": "c1"})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"rows":[["web-2",3]]}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_sql"))
+        .and(query_param("format", "json"))
+        .and(body_partial_json(serde_json::json!({"fetch_size": 1})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"columns":[{"name":"host","type":"keyword"},{"name":"count","type":"long"}],
+                "rows":[["web-1",12]],"cursor":"c1"}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args([
+            "utils",
+            "sql",
+            "SELECT host, COUNT(*) AS count FROM logs GROUP BY host",
+            "--fetch-size",
+            "1",
+        ])
+        .assert()
+        .success()
+        .stdout("host   count\nweb-1  12\nweb-2  3\n");
+}
+
+#[tokio::test]
+async fn sql_translate_prints_query_dsl() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_sql/translate"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"size":1000}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "sql", "SELECT * FROM logs", "--translate"])
+        .assert()
+        .success()
+        .stdout("{\n  \"size\": 1000\n}\n");
+}
+
+// --- assert -------------------------------------------------------------------
+
+#[tokio::test]
+async fn assert_runs_every_check_and_fails_on_any() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"status":"yellow"}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/orders/_count"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"count":1500}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .and(path("/missing"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_search"))
+        .and(body_partial_json(
+            serde_json::json!({"size": 0, "track_total_hits": true}),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"hits":{"total":{"value":3,"relation":"eq"}}}"#),
+        )
+        .mount(&server)
+        .await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let query = dir.path().join("errors.json");
+    std::fs::write(&query, r#"{"query":{"term":{"level":"error"}}}"#).unwrap();
+
+    let output = escli(&server)
+        .args([
+            "utils",
+            "assert",
+            "--health",
+            "green",
+            "--min-docs",
+            "orders=1000",
+            "--exists-index",
+            "missing",
+            "--query-hits-gt",
+        ])
+        .arg(format!("logs={}:0", query.display()))
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "FAIL  cluster health is yellow, expected green\n\
+         PASS  orders has 1500 documents, expected at least 1000\n\
+         FAIL  missing does not exist\n\
+         PASS  errors.json matches 3 documents, expected more than 0\n"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("2 of 4 checks passed"));
+}
+
+// --- exporter ----------------------------------------------------------------
+
+#[tokio::test]
+async fn exporter_once_prints_prometheus_metrics() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "cluster_name": "test", "status": "green", "unassigned_shards": 0
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_nodes/stats/os,jvm,fs,indices"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "nodes": {"x": {"name": "node-1", "os": {"cpu": {"percent": 12}}}}
+        })))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "exporter", "--once", "--no-index-metrics"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout
+            .contains("elasticsearch_cluster_health_status{cluster=\"test\",color=\"green\"} 1\n")
+    );
+    assert!(
+        stdout.contains("elasticsearch_cluster_health_unassigned_shards{cluster=\"test\"} 0\n")
+    );
+    assert!(stdout.contains("# TYPE elasticsearch_os_cpu_percent gauge\n"));
+    assert!(stdout.contains("elasticsearch_os_cpu_percent{node=\"node-1\"} 12\n"));
+    assert!(stdout.contains("elasticsearch_up 1\n"));
+}
+
+// --- bench -------------------------------------------------------------------
+
+#[tokio::test]
+async fn bench_replays_queries_and_reports_percentiles() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/logs/_search"))
+        .and(body_partial_json(
+            serde_json::json!({"query": {"match_all": {}}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"took": 1})))
+        .expect(5)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("q.json");
+    std::fs::write(&file, r#"{"query": {"match_all": {}}}"#).unwrap();
+
+    let output = escli(&server)
+        .args(["utils", "bench", "-i", "logs", "-q"])
+        .arg(&file)
+        .args(["--concurrency", "2", "--requests", "5"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("query  searches  errors  p50"));
+    assert!(stdout.contains("\n#1     5         0       "));
+    assert!(stdout.contains("5 searches in "));
+    assert!(stdout.ends_with(", 0 errors\n"));
+}
+
+// --- ccr ---------------------------------------------------------------------
+
+#[tokio::test]
+async fn ccr_reports_lag_and_resumes_paused_followers() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_all/_ccr/info"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "follower_indices": [
+                {"follower_index": "f1", "remote_cluster": "eu", "leader_index": "l1", "status": "active"},
+                {"follower_index": "f2", "remote_cluster": "eu", "leader_index": "l2", "status": "paused"},
+            ]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_ccr/stats"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "follow_stats": {"indices": [{"index": "f1", "shards": [{
+                "leader_global_checkpoint": 12, "follower_global_checkpoint": 2,
+                "time_since_last_read_millis": 500
+            }]}]}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/f2/_ccr/resume_follow"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({"acknowledged": true})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "ccr", "--resume"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "follower  leader  ops.behind  last.read  status\n\
+         f1        eu:l1   10          500ms      lagging\n\
+         f2        eu:l2   -           -          paused\n\
+         2 followers, 1 lagging, 0 stalled, 0 failed, 1 paused\n\
+         Resumed 1 followers\n"
+    );
+}
+
+// --- reroute -----------------------------------------------------------------
+
+#[tokio::test]
+async fn reroute_applies_stale_primary_allocation() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/shards"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {"index": "logs", "shard": "0", "prirep": "p", "state": "UNASSIGNED"},
+            {"index": "logs", "shard": "1", "prirep": "p", "state": "STARTED"},
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_cluster/allocation/explain"))
+        .and(body_partial_json(
+            serde_json::json!({"index": "logs", "shard": 0, "primary": true}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "index": "logs", "shard": 0, "primary": true,
+            "can_allocate": "no_valid_shard_copy",
+            "unassigned_info": {"reason": "NODE_LEFT"},
+            "node_allocation_decisions": [
+                {"node_name": "n2", "store": {"in_sync": false, "allocation_id": "abc"}}
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_cluster/reroute"))
+        .and(body_partial_json(
+            serde_json::json!({"commands": [{"allocate_stale_primary": {
+                "index": "logs", "shard": 0, "node": "n2", "accept_data_loss": true
+            }}]}),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({"acknowledged": true})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "reroute", "--apply", "--yes"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(
+        "shard     reason     decision             action\n\
+         logs[0]p  NODE_LEFT  no_valid_shard_copy  allocate_stale_primary on n2\n"
+    ));
+    assert!(stdout.contains("\nPOST _cluster/reroute\n"));
+    assert!(
+        stdout.ends_with("1 unassigned shards, 1 reroute commands\nApplied 1 reroute commands\n")
+    );
+}
+
+#[tokio::test]
+async fn reroute_apply_without_terminal_requires_yes() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/shards"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {"index": "logs", "shard": "0", "prirep": "p", "state": "UNASSIGNED"},
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_cluster/allocation/explain"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "index": "logs", "shard": 0, "primary": true,
+            "can_allocate": "no_valid_shard_copy",
+            "node_allocation_decisions": [
+                {"node_name": "n2", "store": {"allocation_id": "abc"}}
+            ]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_cluster/reroute"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "reroute", "--apply"])
+        .write_stdin("")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Refusing to continue without confirmation, use --yes"));
+}
+
+// --- rotate-api-key ----------------------------------------------------------
+
+#[tokio::test]
+async fn rotate_api_key_saves_new_key_and_invalidates_old() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_security/_authenticate"))
+        .and(header("authorization", "ApiKey b2xk"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "api_key": {"id": "old-id", "name": "ci"}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_security/_authenticate"))
+        .and(header("authorization", "ApiKey bmV3"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "api_key": {"id": "new-id", "name": "ci"}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_security/api_key"))
+        .and(query_param("id", "old-id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "api_keys": [{"id": "old-id", "name": "ci", "invalidated": false,
+                "role_descriptors": {"reader": {"cluster": ["monitor"]}}}]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_security/api_key"))
+        .and(body_partial_json(serde_json::json!({
+            "name": "ci", "role_descriptors": {"reader": {"cluster": ["monitor"]}}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "new-id", "name": "ci", "encoded": "bmV3"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/_security/api_key"))
+        .and(body_partial_json(serde_json::json!({"ids": ["old-id"]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "invalidated_api_keys": ["old-id"]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let profile = dir.path().join("prod.env");
+    std::fs::write(
+        &profile,
+        format!("ESCLI_URL={}\nESCLI_API_KEY=b2xk\n", server.uri()),
+    )
+    .unwrap();
+
+    Command::cargo_bin("escli")
+        .unwrap()
+        .current_dir(dir.path())
+        .env("ESCLI_PROFILES_DIR", dir.path())
+        .args(["utils", "rotate-api-key", "--profile", "prod"])
+        .assert()
+        .success()
+        .stdout(
+            "Created API key new-id to replace old-id\n\
+             Verified and saved the new key to profile 'prod'\n\
+             Invalidated API key old-id\n",
+        );
+    assert_eq!(
+        std::fs::read_to_string(&profile).unwrap(),
+        format!("ESCLI_URL={}\nESCLI_API_KEY=bmV3\n", server.uri())
+    );
+}
+
+// --- rolling-restart ---------------------------------------------------------
+
+#[tokio::test]
+async fn rolling_restart_waits_for_node_to_leave_and_rejoin() {
+    let server = MockServer::start().await;
+    let present = serde_json::json!([{"name": "es-1", "master": "*"}]);
+    Mock::given(method("GET"))
+        .and(path("/_cat/nodes"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&present))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/nodes"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_cat/nodes"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&present))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/_cluster/settings"))
+        .and(body_partial_json(serde_json::json!({
+            "persistent": {"cluster.routing.allocation.enable": "primaries"}
+        })))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({"acknowledged": true})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/_cluster/settings"))
+        .and(body_string(
+            r#"{"persistent":{"cluster.routing.allocation.enable":null}}"#,
+        ))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({"acknowledged": true})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/_flush"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_cluster/health"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({"status": "green"})),
+        )
+        .mount(&server)
+        .await;
+
+    escli(&server)
+        .args(["utils", "rolling-restart", "--yes", "--interval", "0s"])
+        .assert()
+        .success()
+        .stdout("Restarted 1 nodes\n");
+}
+
+// --- watch -------------------------------------------------------------------
+
+#[tokio::test]
+async fn watch_executes_with_alternative_input_and_simulated_actions() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/_watcher/watch/errors/_execute"))
+        .and(body_partial_json(serde_json::json!({
+            "action_modes": {"_all": "simulate"},
+            "alternative_input": {"hits": {"total": 3}}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "_id": "errors_1",
+            "watch_record": {
+                "watch_id": "errors",
+                "state": "executed",
+                "result": {
+                    "input": {"type": "simple", "status": "success", "payload": {"hits": {"total": 3}}},
+                    "condition": {"type": "always", "status": "success", "met": true},
+                    "actions": [{"id": "log", "type": "logging", "status": "simulated",
+                        "logging": {"logged_text": "3 errors"}}]
+                }
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let input = dir.path().join("payload.json");
+    std::fs::write(&input, r#"{"hits": {"total": 3}}"#).unwrap();
+
+    escli(&server)
+        .args(["utils", "watch", "errors", "--alternative-input"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(
+            "Watch errors: executed\n\
+             input      simple success, 3 hits\n\
+             condition  always met\n\
+             \n\
+             action  type     status     outcome\n\
+             log     logging  simulated  3 errors\n",
+        );
+}
+
+// --- deprecations ------------------------------------------------------------
+
+#[tokio::test]
+async fn deprecations_fail_on_critical_issues() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/_migration/deprecations"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "cluster_settings": [],
+            "node_settings": [{"level": "warning", "message": "Old setting", "url": "https://ela.st/s"}],
+            "index_settings": {"logs": [{"level": "critical", "message": "Old index", "url": "https://ela.st/i"}]}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_migration/system_features"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "features": [], "migration_status": "NO_MIGRATION_NEEDED"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = escli(&server)
+        .args(["utils", "deprecations", "--fail-on", "critical"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "level     scope  affects  issue        url\n\
+         critical  index  logs     Old index    https://ela.st/i\n\
+         warning   node   -        Old setting  https://ela.st/s\n"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("1 critical, 1 warning, 0 info issues"));
+}
+
+// --- argument validation -----------------------------------------------------
+
+#[test]
+fn invalid_enum_values_list_the_possible_ones() {
+    let output = Command::cargo_bin("escli")
+        .unwrap()
+        .args([
+            "--url",
+            "http://localhost:9200",
+            "cluster",
+            "health",
+            "--wait_for_status",
+            "purple",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("invalid value 'purple' for '--wait_for_status"),
+        "unexpected output: {stderr}"
+    );
+    assert!(
+        stderr.contains("[possible values: ") && stderr.contains("yellow"),
+        "missing possible values: {stderr}"
+    );
+}
+
+#[test]
+fn missing_url_fails() {
+    Command::cargo_bin("escli")
+        .unwrap()
+        .arg("info")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn username_without_password_fails() {
+    Command::cargo_bin("escli")
+        .unwrap()
+        .args([
+            "--url",
+            "http://localhost:9200",
+            "--username",
+            "foo",
+            "info",
+        ])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn password_without_username_fails() {
+    Command::cargo_bin("escli")
+        .unwrap()
+        .args([
+            "--url",
+            "http://localhost:9200",
+            "--password",
+            "bar",
+            "info",
+        ])
         .assert()
         .failure();
 }
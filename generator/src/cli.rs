@@ -52,8 +52,8 @@ pub fn generate() -> Tokens {
         #[derive(Parser, Debug)]
         #[clap(author, version, about, long_about = None)]
         pub struct Config {
-            #[clap(short, long, env = "ESCLI_URL", help = "Elasticsearch cluster url", long_help = "The URL of the Elasticsearch cluster to connect to. This should be in the format 'http://localhost:9200' or 'https://localhost:9200'.")]
-            url: Url,
+            #[clap(short, long, env = "ESCLI_URL", required_unless_present_any = ["profiles", "all_profiles"], help = "Elasticsearch cluster url", long_help = "The URL of the Elasticsearch cluster to connect to. This should be in the format 'http://localhost:9200' or 'https://localhost:9200'.")]
+            url: Option<Url>,
 
             #[clap(short, long, env = "ESCLI_TIMEOUT", help = "CLI request timeout in seconds", default_value = "60", value_parser = |s: &str| s.parse().map(std::time::Duration::from_secs))]
             timeout: Option<std::time::Duration>,
@@ -75,6 +75,21 @@ pub fn generate() -> Tokens {
 
             #[clap(long, help = "Load credentials and settings from this env file instead of .env")]
             env_file: Option<std::path::PathBuf>,
+
+            #[clap(long, env = "ESCLI_PROFILE", help = "Load credentials and settings from this profile", long_help = "Load credentials and settings from the env file <name>.env of the profiles directory: ESCLI_PROFILES_DIR if set, otherwise escli/profiles in the user configuration directory.")]
+            profile: Option<String>,
+
+            #[clap(long, value_delimiter = ',', help = "Run the command against each of these profiles concurrently, comma separated")]
+            profiles: Vec<String>,
+
+            #[clap(long, conflicts_with = "profiles", help = "Run the command against every profile concurrently")]
+            all_profiles: bool,
+
+            #[clap(long, env = "ESCLI_NO_DEPRECATION_WARNINGS", help = "Do not warn about deprecated commands and parameters")]
+            no_deprecation_warnings: bool,
+
+            #[clap(long, global = true, env = "ESCLI_EXPERIMENTAL", help = "Show experimental and beta commands in the help", long_help = "Show experimental and beta commands in the help. They are hidden by default but can always be run.")]
+            show_experimental: bool,
         }
 
         // Entry point for the CLI application.
@@ -89,14 +104,36 @@ pub fn generate() -> Tokens {
         async fn main() {
             clap_complete::CompleteEnv::with_factory(cmd::command).complete();
 
-            // Pre-scan args for --env-file before clap parses, because clap reads
+            // Set on the background process refreshing the index names used by completions.
+            if let Ok(profile) = std::env::var(staticcmds::REFRESH_INDEX_CACHE_ENV) {
+                let code = match staticcmds::refresh_index_cache(&profile).await {
+                    Ok(()) => 0,
+                    Err(_) => 1,
+                };
+                std::process::exit(code);
+            }
+
+            // Pre-scan args for --env-file and --profile before clap parses, because clap reads
             // env vars that dotenv must set first.
             let _args: Vec<String> = std::env::args().collect();
             let _env_file_path = _args.windows(2)
                 .find(|w| w[0] == "--env-file")
-                .map(|w| std::path::PathBuf::from(&w[1]));
+                .map(|w| w[1].clone())
+                .or_else(|| _args.iter().find_map(|a| a.strip_prefix("--env-file=").map(String::from)))
+                .map(std::path::PathBuf::from);
+            let _profile = _args.windows(2)
+                .find(|w| w[0] == "--profile")
+                .map(|w| w[1].clone())
+                .or_else(|| _args.iter().find_map(|a| a.strip_prefix("--profile=").map(String::from)))
+                .or_else(|| std::env::var("ESCLI_PROFILE").ok());
             if let Some(ref path) = _env_file_path {
                 from_path(path).ok();
+            } else if let Some(ref name) = _profile {
+                let path = staticcmds::profile_path(name);
+                if let Err(e) = from_path(&path) {
+                    eprintln!("Failed to load profile '{name}' from {}: {e}", path.display());
+                    std::process::exit(1);
+                }
             } else {
                 dotenv().ok();
             }
@@ -108,8 +145,61 @@ pub fn generate() -> Tokens {
                 Err(e) => e.exit(),
             };
 
+            if let Some(sub_matches) = matches.subcommand_matches("completions") {
+                let completions = match staticcmds::Completions::from_arg_matches(sub_matches) {
+                    Ok(c) => c,
+                    Err(e) => e.exit(),
+                };
+                std::process::exit(completions.execute());
+            }
+
+            if let Some(sub_matches) = matches.subcommand_matches("self-update") {
+                let self_update = match staticcmds::SelfUpdate::from_arg_matches(sub_matches) {
+                    Ok(c) => c,
+                    Err(e) => e.exit(),
+                };
+                std::process::exit(self_update.execute(env!("CARGO_PKG_VERSION")).await);
+            }
+
+            // Subcommands unknown to escli are external plugins.
+            if let Some((name, sub_matches)) = matches.subcommand().filter(|(name, _)| cmd.find_subcommand(name).is_none()) {
+                if !config.profiles.is_empty() || config.all_profiles {
+                    cmd.error(ErrorKind::ArgumentConflict, "--profiles and --all-profiles are not supported by plugins").exit();
+                }
+                let args: Vec<std::ffi::OsString> = sub_matches.get_many::<std::ffi::OsString>("").into_iter().flatten().cloned().collect();
+                match staticcmds::run_plugin(name, &args, &plugin_env(&config)) {
+                    Ok(code) => std::process::exit(code),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        cmd.error(ErrorKind::InvalidSubcommand, format!("unrecognized subcommand '{name}': {e}")).exit();
+                    }
+                    Err(e) => {
+                        eprintln!("{e}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            if !config.profiles.is_empty() || config.all_profiles {
+                run_profiles(&mut cmd, &matches, &config).await;
+            }
+
+            // Some utilities only connect to clusters designated by profiles.
+            let standalone = match matches.subcommand() {
+                Some(("utils", utils_matches)) if config.url.is_none() => {
+                    staticcmds::run_standalone_command(utils_matches, config.timeout).await
+                }
+                _ => None,
+            };
+            if let Some(code) = standalone {
+                std::process::exit(code);
+            }
+
+            let Some(url) = config.url.clone() else {
+                cmd.error(ErrorKind::MissingRequiredArgument, "--url is required").exit();
+            };
+
             let transport = if config.insecure.is_some() {
-                match TransportBuilder::new(SingleNodeConnectionPool::new(config.url))
+                match TransportBuilder::new(SingleNodeConnectionPool::new(url.clone()))
                     .cert_validation(CertificateValidation::None)
                     .build()
                 {
@@ -120,7 +210,7 @@ pub fn generate() -> Tokens {
                     }
                 }
             } else {
-                match TransportBuilder::new(SingleNodeConnectionPool::new(config.url)).build() {
+                match TransportBuilder::new(SingleNodeConnectionPool::new(url)).build() {
                     Ok(t) => t,
                     Err(e) => {
                         eprintln!("{}", error::EscliError::from(e));
@@ -166,6 +256,7 @@ pub fn generate() -> Tokens {
             let mut stderr = io::stderr();
 
             let res: Result<elasticsearch::http::response::Response, elasticsearch::Error>;
+            let mut post_process: Option<namespaces::PostProcess> = None;
             // Check if the subcommand is "utils" to run static commands
             if matches.subcommand_matches("utils").is_some() {
                 res = staticcmds::run_command(cmd, matches.subcommand().unwrap().1, transport, config.timeout).await;
@@ -178,6 +269,22 @@ pub fn generate() -> Tokens {
                         std::process::exit(1);
                     }
                 };
+                warn_deprecations(&args, &config).await;
+                if let Some(required) = &args.privileges {
+                    match staticcmds::check_privileges(&transport, required, config.timeout).await {
+                        Ok(None) => {}
+                        Ok(Some(missing)) => {
+                            stderr.write_all(format!("{missing}\n").as_bytes()).await.ok();
+                            stderr.flush().await.ok();
+                            std::process::exit(1);
+                        }
+                        Err(e) => {
+                            stderr.write_all(format!("{}\n", error::EscliError::from(e)).as_bytes()).await.ok();
+                            stderr.flush().await.ok();
+                            std::process::exit(1);
+                        }
+                    }
+                }
                 if config.verbose {
                     let qs = serde_urlencoded::to_string(&args.query_string).unwrap_or_default();
                     stderr.write(format!("Request: {:?} {}?{}\n", args.method, args.path, qs).as_bytes()).await.ok();
@@ -191,6 +298,7 @@ pub fn generate() -> Tokens {
                     stderr.write("\n".as_bytes()).await.ok();
                     stderr.flush().await.ok();
                 }
+                post_process = args.post_process;
                 res = transport.send(
                     args.method,
                     &args.path,
@@ -232,6 +340,17 @@ pub fn generate() -> Tokens {
                     // Is status code 2xx or 3xx, write the body to stdout
                     // Otherwise, write the body to stderr
                     if (200..400).contains(&istatus_code) {
+                        let body = match post_process {
+                            Some(f) => match f(&body) {
+                                Ok(b) => b.into(),
+                                Err(e) => {
+                                    stderr.write_all(format!("{e}\n").as_bytes()).await.ok();
+                                    stderr.flush().await.ok();
+                                    std::process::exit(1);
+                                }
+                            },
+                            None => body,
+                        };
                         match stdout.write_all(&body).await {
                             Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
                                 tokio::io::stderr()
@@ -265,5 +384,99 @@ pub fn generate() -> Tokens {
                 }
             }
         }
+
+        // Warns on stderr about the deprecated command, path and arguments used, unless
+        // --no-deprecation-warnings is set.
+        async fn warn_deprecations(args: &namespaces::TransportArgs, config: &Config) {
+            if config.no_deprecation_warnings || args.deprecations.is_empty() {
+                return;
+            }
+            let mut stderr = io::stderr();
+            for warning in &args.deprecations {
+                stderr.write_all(format!("Warning: {warning}\n").as_bytes()).await.ok();
+            }
+            stderr.flush().await.ok();
+        }
+
+        // The connection settings forwarded to plugins, under the names escli reads them from.
+        fn plugin_env(config: &Config) -> Vec<(&'static str, String)> {
+            let mut env = vec![("ESCLI_VERBOSE", config.verbose.to_string())];
+            if let Some(url) = &config.url {
+                env.push(("ESCLI_URL", url.to_string()));
+            }
+            if let Some(timeout) = config.timeout {
+                env.push(("ESCLI_TIMEOUT", timeout.as_secs().to_string()));
+            }
+            if let Some(username) = &config.username {
+                env.push(("ESCLI_USERNAME", username.clone()));
+            }
+            if let Some(password) = &config.password {
+                env.push(("ESCLI_PASSWORD", password.clone()));
+            }
+            if let Some(api_key) = &config.api_key {
+                env.push(("ESCLI_API_KEY", api_key.clone()));
+            }
+            if let Some(insecure) = config.insecure {
+                env.push(("ESCLI_INSECURE", insecure.to_string()));
+            }
+            if config.show_experimental {
+                env.push(("ESCLI_EXPERIMENTAL", "1".to_string()));
+            }
+            env
+        }
+
+        // Runs the command against several profiles concurrently and exits.
+        //
+        // The request is built once from the command line, then sent to each
+        // profile's cluster. The process exits with 1 if any of them failed.
+        async fn run_profiles(cmd: &mut clap::Command, matches: &clap::ArgMatches, config: &Config) {
+            let mut stdout = io::stdout();
+            let mut stderr = io::stderr();
+
+            let profiles = if config.all_profiles {
+                match staticcmds::list_profiles() {
+                    Ok(p) => p,
+                    Err(e) => {
+                        stderr.write_all(format!("{e}\n").as_bytes()).await.ok();
+                        stderr.flush().await.ok();
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                config.profiles.clone()
+            };
+
+            if matches.subcommand_matches("utils").is_some() {
+                cmd.error(ErrorKind::ArgumentConflict, "--profiles and --all-profiles are not supported by utils commands").exit();
+            }
+            let args = match cmd::dispatch(cmd, matches).await {
+                Ok(args) => args,
+                Err(e) => {
+                    stderr.write_all(format!("{e}\n").as_bytes()).await.ok();
+                    stderr.flush().await.ok();
+                    std::process::exit(1);
+                }
+            };
+            warn_deprecations(&args, config).await;
+            if args.post_process.is_some() {
+                cmd.error(ErrorKind::ArgumentConflict, "--profiles and --all-profiles cannot be combined with client-side output options").exit();
+            }
+
+            let qs = serde_urlencoded::to_string(&args.query_string).unwrap_or_default();
+            let query: Vec<(String, String)> = serde_urlencoded::from_str(&qs).unwrap_or_default();
+            let (out, ok) = staticcmds::fan_out(
+                &profiles,
+                args.method,
+                &args.path,
+                args.headers,
+                query,
+                args.body,
+                config.timeout,
+            ).await;
+
+            stdout.write_all(&out).await.ok();
+            stdout.flush().await.ok();
+            std::process::exit(if ok { 0 } else { 1 });
+        }
     }
 }
@@ -39,6 +39,10 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
             acc
         });
 
+    let command_groups = render_command_groups(&command_groups(&endpoints_by_namespace, true));
+    let stable_command_groups =
+        render_command_groups(&command_groups(&endpoints_by_namespace, false));
+
     quote! {
         use crate::{Config, namespaces, error};
         use crate::namespaces::Executor;
@@ -48,6 +52,7 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
         pub async fn dispatch(cmd: &mut Command, matches: &ArgMatches) -> Result<namespaces::TransportArgs, error::EscliError> {
             if let Some((namespace, sub_matches)) = matches.subcommand() {
                 if let Some((command, arg_matches)) = sub_matches.subcommand() {
+                    show_docs(namespace, command, arg_matches);
                     match (namespace, command) {
                         $(for (_, endpoints) in &endpoints_by_namespace =>
                             $(for endpoint in endpoints =>
@@ -64,6 +69,7 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
                         }
                     }
                 } else if let Some((command, arg_matches)) = matches.subcommand() {
+                    show_docs("core", command, arg_matches);
                     match ("core", command) {
                         $(for endpoint in &core_endpoints =>
                             $(&endpoint.generate_match_arm())
@@ -85,6 +91,34 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
             }
         }
 
+        // Prints the documentation URL of a command and exits when `--docs` is set.
+        fn show_docs(namespace: &str, command: &str, arg_matches: &ArgMatches) {
+            if arg_matches.try_get_one::<bool>("docs").ok().flatten() != Some(&true) {
+                return;
+            }
+            let open = arg_matches.get_flag("docs_open");
+            std::process::exit(staticcmds::show_docs(doc_url(namespace, command), open));
+        }
+
+        // Whether experimental and beta commands are shown in the help, with
+        // --show-experimental or ESCLI_EXPERIMENTAL=1. Looked up before parsing since
+        // it decides which commands are hidden.
+        fn show_experimental() -> bool {
+            std::env::args_os().any(|arg| arg == "--show-experimental")
+                || std::env::var("ESCLI_EXPERIMENTAL")
+                    .is_ok_and(|v| !matches!(v.to_lowercase().as_str(), "" | "0" | "false" | "no" | "off"))
+        }
+
+        // Returns the documentation URL of a command, as found in the schema.
+        fn doc_url(namespace: &str, command: &str) -> Option<&'static str> {
+            match (namespace, command) {
+                $(for endpoint in endpoints =>
+                    $(endpoint.generate_doc_url_arm())
+                )
+                _ => None,
+            }
+        }
+
         // Generates the main CLI command.
         //
         // This function defines the structure of the CLI application, including subcommands
@@ -94,6 +128,7 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
         //
         // A `Command` object representing the CLI application.
         pub fn command() -> Command {
+            let show_unstable = show_experimental();
             let after_help_heading: &str = color_print::cstr!(r#"<underline><bold>Examples:</bold><underline>"#);
             let after_help: String = format!(
         "{}{}",
@@ -105,6 +140,35 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
 ./escli esql query --format txt <<< 'FROM <index> LIMIT 10'
 \"#")
         );
+            let command_groups: &[(&str, &str)] = match show_unstable {
+                true => &[
+                    $(for (heading, body) in &command_groups =>
+                        (color_print::cstr!($(quoted(format!("<underline><bold>{heading}:</bold></underline>")))), $(quoted(body))),$['\r']
+                    )
+                ],
+                false => &[
+                    $(for (heading, body) in &stable_command_groups =>
+                        (color_print::cstr!($(quoted(format!("<underline><bold>{heading}:</bold></underline>")))), $(quoted(body))),$['\r']
+                    )
+                ],
+            };
+            let command_groups: String = command_groups
+            .iter()
+            .map(|(heading, body)| format!("{heading}\n{body}\n"))
+            .collect();
+            // External `escli-<name>` executables found on PATH.
+            let plugins: String = match staticcmds::list_plugins() {
+                plugins if plugins.is_empty() => String::new(),
+                plugins => format!(
+                    "{}\n{}\n",
+                    color_print::cstr!("<underline><bold>Plugins:</bold></underline>"),
+                    plugins.iter().map(|p| format!("  {p}\n")).collect::<String>(),
+                ),
+            };
+            let help_template = format!(
+                "{{about-with-newline}}\n{{usage-heading}} {{usage}}\n\n{command_groups}{plugins}{}\n{{options}}{{after-help}}",
+                color_print::cstr!("<underline><bold>Options:</bold></underline>"),
+            );
             Config::command()
                 .name("escli")
                 .author("Elastic")
@@ -112,7 +176,16 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
                 .about("You know, for search.")
                 .long_about("The shortest way between your cli and your cluster. You know, for search.")
                 .subcommand_required(true)
+                // Required global options such as --url are checked once the subcommand is
+                // known, so that `completions` works without a cluster.
+                .subcommand_negates_reqs(true)
+                // Unknown subcommands run the matching `escli-<name>` plugin, see main.
+                .allow_external_subcommands(true)
+                .external_subcommand_value_parser(clap::value_parser!(std::ffi::OsString))
                 .after_help(after_help)
+                .help_template(help_template)
+                .subcommand(staticcmds::Completions::new_command())
+                .subcommand(staticcmds::SelfUpdate::new_command())
                 .subcommand(
                     Command::new("utils")
                         .about("Utility commands")
@@ -126,6 +199,9 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
                 $(for (namespace, endpoints) in &endpoints_by_namespace =>
                     .subcommand(
                         Command::new($(quoted(namespace)))
+                        $(if endpoints.iter().all(|e| e.stability().is_some()) {
+                            .hide(!show_unstable)
+                        })
                         .subcommands([
                             $(for endpoint in endpoints =>
                                 $(endpoint.generate_new_command())
@@ -136,3 +212,160 @@ pub(crate) fn generate(endpoints: &[endpoint::Endpoint]) -> Tokens {
         }
     }
 }
+
+// Groups the top-level commands by documentation category for the root help.
+//
+// Core endpoints are listed individually with their short description under
+// their own `doc_tag`. Namespaces are listed under the most common `doc_tag`
+// of their endpoints. Unless `show_unstable` is set, experimental and beta core
+// endpoints are left out, as are namespaces with only such endpoints.
+//
+// # Returns
+//
+// A map of help headings to the `(name, description)` of their commands.
+fn command_groups(
+    endpoints_by_namespace: &BTreeMap<String, Vec<&endpoint::Endpoint>>,
+    show_unstable: bool,
+) -> BTreeMap<String, Vec<(String, String)>> {
+    let mut groups: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for (namespace, endpoints) in endpoints_by_namespace {
+        let endpoints: Vec<&endpoint::Endpoint> = endpoints
+            .iter()
+            .filter(|e| show_unstable || e.stability().is_none())
+            .copied()
+            .collect();
+        if namespace == "core" {
+            for endpoint in endpoints {
+                groups
+                    .entry(help_heading(&endpoint.doc_tag()))
+                    .or_default()
+                    .push((endpoint.short_name(), endpoint.about()));
+            }
+            continue;
+        }
+        if endpoints.is_empty() {
+            continue;
+        }
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for endpoint in endpoints {
+            *counts.entry(endpoint.doc_tag()).or_default() += 1;
+        }
+        // Ties go to the alphabetically first tag.
+        let tag = counts
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            .map(|(tag, _)| tag)
+            .unwrap_or_default();
+        groups
+            .entry(help_heading(&tag))
+            .or_default()
+            .push((namespace.clone(), String::new()));
+    }
+    let utilities = groups.entry("Utilities".to_string()).or_default();
+    utilities.push(("utils".to_string(), "Utility commands".to_string()));
+    utilities.push((
+        "completions".to_string(),
+        "Print or install shell completion scripts".to_string(),
+    ));
+    utilities.push((
+        "self-update".to_string(),
+        "Update escli to the latest release".to_string(),
+    ));
+    for commands in groups.values_mut() {
+        commands.sort();
+    }
+    groups
+}
+
+// Renders the command groups as aligned help sections.
+//
+// # Returns
+//
+// The `(heading, body)` of each section, with "Other" and "Utilities" last.
+fn render_command_groups(
+    groups: &BTreeMap<String, Vec<(String, String)>>,
+) -> Vec<(String, String)> {
+    let width = groups
+        .values()
+        .flatten()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    let mut sections: Vec<(String, String)> = groups
+        .iter()
+        .map(|(heading, commands)| {
+            let body = commands
+                .iter()
+                .map(|(name, about)| {
+                    format!("  {name:<width$}  {about}").trim_end().to_string() + "\n"
+                })
+                .collect();
+            (heading.clone(), body)
+        })
+        .collect();
+    sections.sort_by_key(|(heading, _)| {
+        (
+            heading == "Other" || heading == "Utilities",
+            heading == "Utilities",
+        )
+    });
+    sections
+}
+
+// Turns a schema `doc_tag` into a help heading, e.g. "ml anomaly" into "ML Anomaly".
+fn help_heading(tag: &str) -> String {
+    match tag {
+        "document" => "Documents".to_string(),
+        "index" | "indices" => "Index Management".to_string(),
+        "esql" => "ES|QL".to_string(),
+        _ => tag
+            .split_whitespace()
+            .map(|word| match word {
+                "ml" | "sql" | "eql" | "ilm" | "slm" | "ccr" | "api" => word.to_uppercase(),
+                _ => {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|c| c.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_heading() {
+        assert_eq!(help_heading("search"), "Search");
+        assert_eq!(help_heading("ml anomaly"), "ML Anomaly");
+        assert_eq!(help_heading("indices"), "Index Management");
+        assert_eq!(help_heading("other"), "Other");
+    }
+
+    #[test]
+    fn test_render_command_groups_orders_and_aligns() {
+        let mut groups = BTreeMap::new();
+        groups.insert(
+            "Utilities".to_string(),
+            vec![("utils".to_string(), "Utility commands".to_string())],
+        );
+        groups.insert(
+            "Other".to_string(),
+            vec![("misc".to_string(), String::new())],
+        );
+        groups.insert(
+            "Search".to_string(),
+            vec![("search".to_string(), "Run a search".to_string())],
+        );
+        let sections = render_command_groups(&groups);
+        let headings: Vec<&str> = sections.iter().map(|(h, _)| h.as_str()).collect();
+        assert_eq!(headings, vec!["Search", "Other", "Utilities"]);
+        assert_eq!(sections[0].1, "  search  Run a search\n");
+        assert_eq!(sections[1].1, "  misc\n");
+    }
+}
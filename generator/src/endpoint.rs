@@ -19,7 +19,10 @@ use crate::enumeration::Enum;
 use crate::field::Field;
 use crate::path_parameter::PathParameter;
 
-use clients_schema::{Body, IndexedModel, ServerDefault, TypeDefinition, TypeName, ValueOf};
+use clients_schema::{
+    Body, Deprecation, Flavor, IndexedModel, ServerDefault, Stability, TypeDefinition, TypeName,
+    ValueOf,
+};
 use convert_case::{Case, Casing};
 use genco::tokens::quoted;
 use genco::{Tokens, quote};
@@ -32,6 +35,36 @@ use std::sync::LazyLock;
 static PATH_PARAM_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"\{([^}]+)}").expect("regex failed to compile"));
 
+// The ES|QL query endpoint, which accepts `--explain-plan`.
+const ESQL_QUERY: &str = "esql.query";
+
+// The fields the generator adds to the command structs, declared by the argument
+// generators below. A schema field named the same gets a `_` suffix.
+const INPUT: &str = "input";
+const PIT: &str = "pit";
+const PIT_KEEP_ALIVE: &str = "pit_keep_alive";
+const EXPLAIN_PLAN: &str = "explain_plan";
+const OUTPUT_FILE: &str = "output_file";
+const PARAM: &str = "param";
+const COLUMNS: &str = "columns";
+const SORT: &str = "sort";
+const FILTER: &str = "filter";
+const CHECK_PRIVILEGES: &str = "check_privileges";
+const HEADER: &str = "header";
+pub(crate) const GENERATED_FIELDS: &[&str] = &[
+    INPUT,
+    PIT,
+    PIT_KEEP_ALIVE,
+    EXPLAIN_PLAN,
+    OUTPUT_FILE,
+    PARAM,
+    COLUMNS,
+    SORT,
+    FILTER,
+    CHECK_PRIVILEGES,
+    HEADER,
+];
+
 // Represents an API endpoint with its associated metadata and parameters.
 //
 // This struct encapsulates the details of an API endpoint, including its path
@@ -51,6 +84,8 @@ pub struct Endpoint {
     paths_selection: Tokens,
     // Indicates whether the endpoint requires a request body.
     has_request: bool,
+    // Indicates whether the request body accepts a point in time (`pit`).
+    has_pit: bool,
 }
 
 impl Endpoint {
@@ -79,6 +114,7 @@ impl Endpoint {
             enums: HashMap::new(),
             paths_selection: Default::default(),
             has_request: false,
+            has_pit: false,
         };
 
         // Populate path parameters based on the schema model.
@@ -92,8 +128,12 @@ impl Endpoint {
 
         // Check if the endpoint has a request body and update the `has_request` flag accordingly.
         if let Some(r) = e.request(model) {
-            match r.body {
+            match &r.body {
                 Body::NoBody(_) => {}
+                Body::Properties(body) => {
+                    e.has_request = true;
+                    e.has_pit = body.properties.iter().any(|p| p.name == "pit");
+                }
                 _ => {
                     e.has_request = true;
                 }
@@ -133,7 +173,7 @@ impl Endpoint {
     // # Returns
     //
     // A `String` representing the short name of the endpoint.
-    fn short_name(&self) -> String {
+    pub fn short_name(&self) -> String {
         if let Some((_, name)) = self.e.name.rsplit_once('.') {
             if name.eq("help") {
                 "_help".to_string()
@@ -173,6 +213,21 @@ impl Endpoint {
         }
     }
 
+    // Returns the documentation tag of the endpoint.
+    //
+    // The `doc_tag` of the schema groups endpoints into categories such as
+    // "search" or "security". Endpoints without a tag fall into "other".
+    //
+    // # Returns
+    //
+    // A `String` containing the documentation tag.
+    pub fn doc_tag(&self) -> String {
+        self.e
+            .doc_tag
+            .clone()
+            .unwrap_or_else(|| "other".to_string())
+    }
+
     // Returns the short description for the endpoint.
     //
     // This function extracts only the first line of the endpoint's description.
@@ -181,7 +236,7 @@ impl Endpoint {
     // # Returns
     //
     // A `String` containing the first line of the endpoint's description.
-    fn short_description(&self) -> String {
+    pub fn short_description(&self) -> String {
         self.e
             .description
             .clone()
@@ -191,6 +246,26 @@ impl Endpoint {
             .to_string()
     }
 
+    // Returns the stability of the endpoint on the stack, "experimental" or "beta",
+    // if it is not stable yet.
+    pub fn stability(&self) -> Option<&'static str> {
+        let availability = self.e.availability.as_ref()?.get(&Flavor::Stack)?;
+        match availability.stability {
+            Some(Stability::Experimental) => Some("experimental"),
+            Some(Stability::Beta) => Some("beta"),
+            _ => None,
+        }
+    }
+
+    // Returns the short description of the endpoint, prefixed with its stability
+    // when it is not stable, e.g. "[beta] Get the inference endpoints".
+    pub fn about(&self) -> String {
+        match self.stability() {
+            Some(stability) => format!("[{stability}] {}", self.short_description()),
+            None => self.short_description(),
+        }
+    }
+
     // Returns the full description of the endpoint.
     //
     // This function retrieves the complete description of the endpoint and escapes
@@ -198,9 +273,39 @@ impl Endpoint {
     //
     // # Returns
     //
-    // A `String` containing the full escaped description of the endpoint.
+    // A `String` containing the full escaped description of the endpoint, followed by
+    // its required privileges and its documentation URL if any.
     fn description(&self) -> String {
-        self.e.description.clone().escape_default().to_string()
+        let mut description = self.e.description.clone();
+        if let Some((cluster, index)) = self.required_privileges() {
+            description.push_str("\n\nRequired privileges:");
+            if !cluster.is_empty() {
+                description.push_str(&format!("\n  cluster: {}", cluster.join(", ")));
+            }
+            if !index.is_empty() {
+                description.push_str(&format!("\n  index: {}", index.join(", ")));
+            }
+        }
+        if let Some(url) = &self.e.doc_url {
+            description.push_str(&format!("\n\nDocumentation: {url}"));
+            if let Some(doc_id) = &self.e.doc_id {
+                description.push_str(&format!(" ({doc_id})"));
+            }
+        }
+        description.escape_default().to_string()
+    }
+
+    // Returns the privileges the endpoint requires, as listed in the schema.
+    //
+    // # Returns
+    //
+    // The cluster and index privileges, or `None` if the schema lists none.
+    fn required_privileges(&self) -> Option<(Vec<String>, Vec<String>)> {
+        let privileges = self.e.privileges.as_ref()?;
+        if privileges.cluster.is_empty() && privileges.index.is_empty() {
+            return None;
+        }
+        Some((privileges.cluster.clone(), privileges.index.clone()))
     }
 
     // Retrieves the enums associated with the endpoint.
@@ -265,7 +370,8 @@ impl Endpoint {
                         p.required,
                         ty,
                         None,
-                    );
+                    )
+                    .with_deprecation(p.deprecation.as_ref().map(deprecation_message));
                     if self
                         .path_parameters
                         .iter()
@@ -302,7 +408,8 @@ impl Endpoint {
                             p.required,
                             ty,
                             default_value,
-                        );
+                        )
+                        .with_deprecation(p.deprecation.as_ref().map(deprecation_message));
                         if self
                             .path_parameters
                             .iter()
@@ -359,6 +466,7 @@ impl Endpoint {
                         ty,
                         None,
                     )
+                    .with_deprecation(p.deprecation.as_ref().map(deprecation_message))
                 })
                 .collect();
 
@@ -386,8 +494,11 @@ impl Endpoint {
     // # Behavior
     //
     // - Maps built-in types to their Rust equivalents (e.g., `string` -> `String`).
+    // - Maps the numeric aliases of `_types` to the Rust type of their range (e.g., `uint` -> `u32`),
+    //   `Duration` to `staticcmds::TimeValue` and `ByteSize` to `staticcmds::ByteSize`, so that
+    //   invalid values fail when parsing.
     // - Resolves interfaces, enums, and type aliases using the schema model.
-    // - Handles arrays by returning a placeholder type (`String` for now).
+    // - Maps arrays, and unions of a type with arrays of it such as `Indices`, to `Vec`.
     fn resolve_value_of(&mut self, v: &ValueOf, model: &IndexedModel) -> String {
         match v {
             ValueOf::InstanceOf(i) => {
@@ -398,12 +509,20 @@ impl Endpoint {
                         "long" => return "i64".to_string(),
                         "float" => return "f32".to_string(),
                         "double" => return "f64".to_string(),
+                        "number" => return "f64".to_string(),
                         "boolean" => return "bool".to_string(),
                         _ => {
                             return "String".to_string();
                         }
                     }
                 }
+                let alias = match i.typ.namespace == "_types" {
+                    true => alias_type(i.typ.name.as_str()),
+                    false => None,
+                };
+                if let Some(ty) = alias {
+                    return ty.to_string();
+                }
                 let td = model.get_type(&i.typ);
                 if let Ok(td) = td {
                     match td {
@@ -423,6 +542,9 @@ impl Endpoint {
                                             (m.name.clone(), code)
                                         })
                                         .collect(),
+                                )
+                                .with_descriptions(
+                                    e.members.iter().map(|m| m.description.clone()).collect(),
                                 ),
                             );
                             e.base.name.name.to_string()
@@ -438,6 +560,14 @@ impl Endpoint {
                 let inner = self.resolve_value_of(a.value.as_ref(), model);
                 format!("Vec<{inner}>")
             }
+            ValueOf::UnionOf(u) => {
+                let items: Vec<String> = u
+                    .items
+                    .iter()
+                    .map(|item| self.resolve_value_of(item, model))
+                    .collect();
+                union_type(&items)
+            }
             _ => "String".to_string(),
         }
     }
@@ -490,14 +620,15 @@ impl Endpoint {
             } else {
                 "GET".to_string()
             };
-            let params: HashSet<String> = PATH_PARAM_RE
-                .captures_iter(&url.path)
-                .filter_map(|cap| cap.get(1).map(|cap| cap.as_str().to_string()))
-                .map(|f| match f.as_str() {
-                    "type" => "ty".to_string(),
-                    _ => f,
-                })
-                .collect();
+            // URL placeholders are named after the identifiers of their fields.
+            let mut path = url.path.clone();
+            let mut wire_names = HashMap::new();
+            for cap in PATH_PARAM_RE.captures_iter(&url.path) {
+                let ident = Field::sanitize_field_name(&cap[1]);
+                path = path.replace(&cap[0], &format!("{{{ident}}}"));
+                wire_names.insert(ident, cap[1].to_string());
+            }
+            let params: HashSet<String> = wire_names.keys().cloned().collect();
             let endpoints_params: Vec<String> = self
                 .path_parameters
                 .iter()
@@ -506,26 +637,51 @@ impl Endpoint {
             let tmp_params: HashSet<String> = HashSet::from_iter(endpoints_params.clone());
             for param in params.sub(&tmp_params) {
                 self.path_parameters.push(Field::new(
-                    param.clone(),
+                    wire_names[&param].clone(),
                     "".to_string(),
                     true,
                     "String".to_string(),
                     None,
                 ));
             }
-            path_params.push(PathParameter::new(
-                url.path.replace("{type}", "{ty}").clone(),
-                endpoints_params,
-                params.sub(optional_parameters),
-                optional_parameters.intersection(&params).cloned().collect(),
-                method.to_case(Case::Pascal),
-            ));
+            let deprecation = url
+                .deprecation
+                .as_ref()
+                .map(|d| format!("the path {} is {}", url.path, deprecation_message(d)));
+            path_params.push(
+                PathParameter::new(
+                    path,
+                    endpoints_params,
+                    params.sub(optional_parameters),
+                    optional_parameters.intersection(&params).cloned().collect(),
+                    method.to_case(Case::Pascal),
+                )
+                .with_deprecation(deprecation),
+            );
         }
         path_params
     }
 
     /// Generates the path selection tokens for the endpoint.
+    ///
+    /// The tokens define `url`, `method` and `url_deprecation`, the warning of the
+    /// selected path if it is deprecated.
     fn generate_path_selection_tokens(&self, toks: &mut Tokens, path_params: &[PathParameter]) {
+        let deprecated = path_params.iter().any(|p| p.deprecation().is_some());
+        match (
+            deprecated,
+            path_params.first().and_then(|p| p.deprecation()),
+        ) {
+            (true, _) if path_params.len() > 1 => toks.append(quote! {
+                let mut url_deprecation: Option<&str> = None;$['\r']
+            }),
+            (_, Some(warning)) => toks.append(quote! {
+                let url_deprecation = Some($(quoted(warning)));$['\r']
+            }),
+            _ => toks.append(quote! {
+                let url_deprecation: Option<&str> = None;$['\r']
+            }),
+        }
         if path_params.len() == 1 {
             let path_param = path_params.first().unwrap();
             let method = path_param.method();
@@ -565,14 +721,61 @@ impl Endpoint {
     // Generates the command for creating a new endpoint.
     //
     // This function constructs the logic for generating a new command for the endpoint
-    // based on its namespace and camel case name.
+    // based on its namespace and camel case name. Experimental and beta endpoints are
+    // hidden from the help with `.hide(!show_unstable)`.
     //
     // # Returns
     //
     // A `Tokens` object representing the new command.
     pub fn generate_new_command(&self) -> Tokens {
+        let hide = match self.stability() {
+            Some(_) => quote!(.hide(!show_unstable)),
+            None => quote!(),
+        };
         quote! {
-            namespaces::$(&self.namespace())::$(&self.camel_case_name())::new_command(),$['\r']
+            namespaces::$(&self.namespace())::$(&self.camel_case_name())::new_command()$hide,$['\r']
+        }
+    }
+
+    pub fn generate_doc_url_arm(&self) -> Tokens {
+        match &self.e.doc_url {
+            Some(url) => quote! {
+                ($(quoted(&self.namespace())), $(quoted(&self.short_name()))) => Some($(quoted(url))),$['\r']
+            },
+            None => quote! {},
+        }
+    }
+
+    // Generates the warnings about the deprecated command and arguments used, followed
+    // by the one of the selected path.
+    //
+    // # Returns
+    //
+    // A `Tokens` object evaluating to a `Vec<String>`.
+    fn deprecations(&self) -> Tokens {
+        let command = match self.namespace().as_str() {
+            "core" => self.short_name(),
+            namespace => format!("{namespace} {}", self.short_name()),
+        };
+        let endpoint = self.e.deprecation.as_ref().map(|d| {
+            let warning = format!("{command} is {}", deprecation_message(d));
+            quote!((true, $(quoted(warning))))
+        });
+        let warnings: Vec<Tokens> = endpoint
+            .into_iter()
+            .chain(
+                self.path_parameters
+                    .iter()
+                    .chain(self.query_parameters.iter())
+                    .filter_map(|f| f.deprecation_warning()),
+            )
+            .collect();
+        quote! {
+            [$(for warning in &warnings join (, ) => $warning)]
+                .into_iter()
+                .filter_map(|(set, warning): (bool, &str)| set.then(|| warning.to_string()))
+                .chain(url_deprecation.map(String::from))
+                .collect()
         }
     }
 
@@ -628,7 +831,7 @@ impl Endpoint {
             true => {
                 quote! {
                     #[arg(long, help = "Input file or '-' for stdin")]
-                    input: Option<String>,$['\r']
+                    $(INPUT): Option<String>,$['\r']
                 }
             }
             false => {
@@ -637,6 +840,283 @@ impl Endpoint {
         }
     }
 
+    // Generates the `--pit` arguments for endpoints accepting a point in time.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the argument definitions, or an empty `Tokens`
+    // object if the request body has no `pit` property.
+    fn pit_arg(&self) -> Tokens {
+        match self.has_pit {
+            true => quote! {
+                #[arg(long, help = "Point in time id to inject into the request body")]
+                $(PIT): Option<String>,$['\r']
+                #[arg(long, requires = "pit", help = "Keep alive to extend the point in time by, e.g. 1m")]
+                $(PIT_KEEP_ALIVE): Option<String>,$['\r']
+            },
+            false => quote! {},
+        }
+    }
+
+    // Injects the `--pit` id into the request body.
+    //
+    // The body read from the input (or an empty object) is parsed as JSON and its
+    // `pit` property is replaced.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the injection logic.
+    fn pit_handling(&self) -> Tokens {
+        match self.has_pit {
+            true => quote! {
+                if let Some(pit) = &self.pit {
+                    let mut value: serde_json::Value = if body.trim().is_empty() {
+                        serde_json::json!({})
+                    } else {
+                        serde_json::from_str(&body).map_err(|e| {
+                            error::EscliError::Command(format!("Failed to parse request body as JSON: {e}"))
+                        })?
+                    };
+                    let Some(object) = value.as_object_mut() else {
+                        return Err(error::EscliError::Command("--pit requires the request body to be a JSON object".to_string()));
+                    };
+                    let mut pit_value = serde_json::json!({ "id": pit });
+                    if let Some(keep_alive) = &self.pit_keep_alive {
+                        pit_value["keep_alive"] = serde_json::json!(keep_alive);
+                    }
+                    object.insert("pit".to_string(), pit_value);
+                    body = value.to_string();
+                }
+            },
+            false => quote! {},
+        }
+    }
+
+    // Generates the `--explain-plan` and `--output-file` arguments for the ES|QL query endpoint.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the argument definition, or an empty `Tokens`
+    // object for any other endpoint.
+    fn explain_plan_arg(&self) -> Tokens {
+        match self.e.name == ESQL_QUERY {
+            true => quote! {
+                #[arg(long, help = "Profile the query and render its pipeline stages with row counts and timings")]
+                $(EXPLAIN_PLAN): bool,$['\r']
+                #[arg(long, value_name = "FILE", conflicts_with = "explain_plan", help = "Request the Arrow format and write the result to an Arrow IPC file, or a Parquet file when FILE ends with .parquet")]
+                $(OUTPUT_FILE): Option<std::path::PathBuf>,$['\r']
+            },
+            false => quote! {},
+        }
+    }
+
+    // Generates the repeatable `--param` argument for the ES|QL query endpoint.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the argument definition, or an empty `Tokens`
+    // object for any other endpoint.
+    fn esql_params_arg(&self) -> Tokens {
+        match self.e.name == ESQL_QUERY {
+            true => quote! {
+                #[arg(long = "param", value_name = "NAME[:TYPE]=VALUE", help = "Bind a value to ?NAME in the query through the params array. Repeatable")]
+                $(PARAM): Vec<String>,$['\r']
+            },
+            false => quote! {},
+        }
+    }
+
+    // Adds the `--param` values to the `params` array of the request body.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the injection logic.
+    fn esql_params_handling(&self) -> Tokens {
+        match self.e.name == ESQL_QUERY {
+            true => quote! {
+                if !self.param.is_empty() {
+                    let params = staticcmds::esql_params(&self.param).map_err(error::EscliError::Command)?;
+                    let mut value: serde_json::Value = serde_json::from_str(&body).map_err(|e| {
+                        error::EscliError::Command(format!("Failed to parse request body as JSON: {e}"))
+                    })?;
+                    let Some(object) = value.as_object_mut() else {
+                        return Err(error::EscliError::Command("--param requires the request body to be a JSON object".to_string()));
+                    };
+                    object.insert("params".to_string(), params);
+                    body = value.to_string();
+                }
+            },
+            false => quote! {},
+        }
+    }
+
+    // Requests the query profile when `--explain-plan` is set.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the injection logic.
+    fn explain_plan_handling(&self) -> Tokens {
+        match self.e.name == ESQL_QUERY {
+            true => quote! {
+                if self.explain_plan {
+                    let mut value: serde_json::Value = serde_json::from_str(&body).map_err(|e| {
+                        error::EscliError::Command(format!("Failed to parse request body as JSON: {e}"))
+                    })?;
+                    let Some(object) = value.as_object_mut() else {
+                        return Err(error::EscliError::Command("--explain-plan requires the request body to be a JSON object".to_string()));
+                    };
+                    object.insert("profile".to_string(), serde_json::Value::Bool(true));
+                    body = value.to_string();
+                }
+            },
+            false => quote! {},
+        }
+    }
+
+    // Requests the Arrow format of the ES|QL response when `--output-file` is set.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the header logic.
+    fn esql_headers(&self) -> Tokens {
+        match self.e.name == ESQL_QUERY {
+            true => quote! {
+                if self.output_file.is_some() {
+                    headers.insert(
+                        elasticsearch::http::headers::ACCEPT,
+                        elasticsearch::http::headers::HeaderValue::from_static("application/vnd.apache.arrow.stream"),
+                    );
+                }
+            },
+            false => quote! {},
+        }
+    }
+
+    // Checks whether the endpoint is a cat API returning tabular data.
+    //
+    // # Returns
+    //
+    // A `bool` indicating whether `--columns`, `--sort` and `--filter` apply.
+    fn is_cat(&self) -> bool {
+        self.namespace() == "cat" && self.e.name != "cat.help"
+    }
+
+    // Generates the client-side `--columns`, `--sort` and `--filter` arguments of cat APIs.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the argument definitions, or an empty `Tokens`
+    // object for any other endpoint.
+    fn cat_args(&self) -> Tokens {
+        match self.is_cat() {
+            true => quote! {
+                #[arg(long, value_delimiter = ',', help = "Columns to display, comma separated, applied client-side")]
+                $(COLUMNS): Vec<String>,$['\r']
+                #[arg(long, value_delimiter = ',', help = "Columns to sort by, comma separated, suffixed with :desc for descending order")]
+                $(SORT): Vec<String>,$['\r']
+                #[arg(long, help = "Only display rows matching <column><op><value>, op is one of == != >= <= > < ~. Repeatable")]
+                $(FILTER): Vec<String>,$['\r']
+            },
+            false => quote! {},
+        }
+    }
+
+    // Requests the JSON form of the cat response when client-side processing is enabled.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the header logic.
+    fn cat_headers(&self) -> Tokens {
+        match self.is_cat() {
+            true => quote! {
+                if !self.columns.is_empty() || !self.sort.is_empty() || !self.filter.is_empty() {
+                    headers.insert(
+                        elasticsearch::http::headers::ACCEPT,
+                        elasticsearch::http::headers::HeaderValue::from_static("application/json"),
+                    );
+                }
+            },
+            false => quote! {},
+        }
+    }
+
+    // Generates the post-processing applied to a successful response body.
+    //
+    // # Returns
+    //
+    // A `Tokens` object evaluating to an `Option<PostProcess>`.
+    fn post_process(&self) -> Tokens {
+        if self.e.name == ESQL_QUERY {
+            quote! {
+                match (self.explain_plan, &self.output_file) {
+                    (true, _) => Some(Box::new(staticcmds::explain_plan) as crate::namespaces::PostProcess),
+                    (false, Some(path)) => Some(Box::new(staticcmds::esql_output_file(path.clone())) as crate::namespaces::PostProcess),
+                    (false, None) => None,
+                }
+            }
+        } else if self.is_cat() {
+            quote! {
+                match !self.columns.is_empty() || !self.sort.is_empty() || !self.filter.is_empty() {
+                    true => Some(Box::new(staticcmds::cat_view(
+                        self.columns.clone(),
+                        self.sort.clone(),
+                        self.filter.clone(),
+                    )) as crate::namespaces::PostProcess),
+                    false => None,
+                }
+            }
+        } else {
+            quote! { None }
+        }
+    }
+
+    // Generates the `--check-privileges` argument for endpoints with known privileges.
+    //
+    // # Returns
+    //
+    // A `Tokens` object representing the argument definition, or an empty `Tokens`
+    // object if the schema lists no privileges for the endpoint.
+    fn check_privileges_arg(&self) -> Tokens {
+        match self.required_privileges() {
+            Some(_) => quote! {
+                #[arg(long, help = "Check that the current user has the required privileges before sending the request")]
+                $(CHECK_PRIVILEGES): bool,$['\r']
+            },
+            None => quote! {},
+        }
+    }
+
+    // Generates the privileges checked before sending the request.
+    //
+    // Index privileges are checked against the `index` path parameter, or every
+    // index if the endpoint has none.
+    //
+    // # Returns
+    //
+    // A `Tokens` object evaluating to an `Option<staticcmds::RequiredPrivileges>`.
+    fn preflight_privileges(&self) -> Tokens {
+        let Some((cluster, index)) = self.required_privileges() else {
+            return quote! { None };
+        };
+        let indices = match self.path_parameters.iter().find(|f| f.name() == "index") {
+            Some(f) if f.required() => quote! { self.index.split(',').map(String::from).collect() },
+            Some(_) => {
+                quote! { self.index.as_deref().unwrap_or("*").split(',').map(String::from).collect() }
+            }
+            None => quote! { vec!["*".to_string()] },
+        };
+        quote! {
+            match self.check_privileges {
+                true => Some(staticcmds::RequiredPrivileges {
+                    cluster: vec![$(for p in &cluster => $(quoted(p)).to_string(),)],
+                    index: vec![$(for p in &index => $(quoted(p)).to_string(),)],
+                    indices: $indices,
+                }),
+                false => None,
+            }
+        }
+    }
+
     // Checks whether the endpoint requires a request body.
     //
     // This function determines if the endpoint has a request body based on its
@@ -716,9 +1196,19 @@ impl Endpoint {
 
                 $(self.input_arg())
 
+                $(self.pit_arg())
+
+                $(self.explain_plan_arg())
+
+                $(self.esql_params_arg())
+
+                $(self.cat_args())
+
+                $(self.check_privileges_arg())
+
                 /// Custom HTTP headers to include in the request. Repeatable.
                 #[arg(short = 'H', long = "header", value_name = "HEADER", help = "Add a custom header (key:value)", num_args = 0.., action = clap::ArgAction::Append, value_parser = parse_header)]
-                pub header: Vec<(String, String)>,
+                pub $(HEADER): Vec<(String, String)>,
             }
 
             impl $(&self.camel_case_name()) {
@@ -729,8 +1219,26 @@ impl Endpoint {
                 // A `Command` object representing the CLI command.
                 pub fn new_command() -> Command {
                     Self::command()
-                    .about($(quoted(&self.short_description())))
+                    .about($(quoted(&self.about())))
                     .long_about($(quoted(self.description())))
+                    // Required arguments can be omitted when only asking for the documentation.
+                    .mut_args(|arg| match arg.is_required_set() {
+                        true => arg.required(false).required_unless_present("docs"),
+                        false => arg,
+                    })
+                    .arg(
+                        clap::Arg::new("docs")
+                            .long("docs")
+                            .action(clap::ArgAction::SetTrue)
+                            .help("Print the documentation URL of this command and exit"),
+                    )
+                    .arg(
+                        clap::Arg::new("docs_open")
+                            .long("open")
+                            .action(clap::ArgAction::SetTrue)
+                            .requires("docs")
+                            .help("Open the documentation in the default browser, with --docs"),
+                    )
                 }
             }
 
@@ -753,19 +1261,27 @@ impl Endpoint {
                     #[derive(serde::Serialize)]
                     struct Q {
                         $(for field in &self.query_parameters =>
-                            $(&field.original_field_name()): $(&field.q_typ()),$['\r']
+                            $(field.q_field()),$['\r']
                         )
                     }
 
                     let q = Q {
                         $(for field in &self.query_parameters =>
-                            $(&field.original_field_name()): $(field.q_assign()),$['\r']
+                            $(field.name()): $(field.q_assign()),$['\r']
                         )
                     };
 
                     $(self.input_handling())
 
+                    $(self.pit_handling())
+
+                    $(self.explain_plan_handling())
+
+                    $(self.esql_params_handling())
+
                     let mut headers = HeaderMap::new();
+                    $(self.cat_headers())
+                    $(self.esql_headers())
                     for (k, v) in &self.header {
                         if let (Ok(header_name), Ok(header_value)) = (
                             elasticsearch::http::headers::HeaderName::from_bytes(k.as_bytes()),
@@ -787,6 +1303,9 @@ impl Endpoint {
                             } else {
                                 Option::<String>::None
                         }),
+                        post_process: $(self.post_process()),
+                        privileges: $(self.preflight_privileges()),
+                        deprecations: $(self.deprecations()),
                     })
                 }
             }
@@ -794,6 +1313,58 @@ impl Endpoint {
     }
 }
 
+// Describes a deprecation, e.g. "deprecated since 8.0.0: use the index API instead".
+fn deprecation_message(deprecation: &Deprecation) -> String {
+    let description = deprecation.description.lines().next().unwrap_or("").trim();
+    let message = match description.is_empty() {
+        true => format!("deprecated since {}", deprecation.version),
+        false => format!("deprecated since {}: {description}", deprecation.version),
+    };
+    message.escape_default().to_string()
+}
+
+// Returns the Rust type of an alias of the `_types` namespace validated when parsing the
+// arguments, if `name` is one.
+fn alias_type(name: &str) -> Option<&'static str> {
+    match name {
+        "Duration" => Some("staticcmds::TimeValue"),
+        "ByteSize" => Some("staticcmds::ByteSize"),
+        _ => number_type(name),
+    }
+}
+
+// Returns the Rust type of a numeric alias of the `_types` namespace, if `name` is one.
+// These alias `number`: their range lets clap reject the out of range values.
+fn number_type(name: &str) -> Option<&'static str> {
+    match name {
+        "byte" => Some("i8"),
+        "short" => Some("i16"),
+        "integer" => Some("i32"),
+        "long" => Some("i64"),
+        "uint" => Some("u32"),
+        "ulong" => Some("u64"),
+        "float" => Some("f32"),
+        "double" => Some("f64"),
+        _ => None,
+    }
+}
+
+// Returns the Rust type of a union given the types of its items.
+//
+// A union of a type and arrays of it, such as `IndexName | IndexName[]`, is a list
+// accepting a single value, so it becomes a `Vec` of that type. Any other union is
+// passed through as a `String`.
+fn union_type(items: &[String]) -> String {
+    let Some(list) = items.iter().find(|ty| ty.starts_with("Vec<")) else {
+        return "String".to_string();
+    };
+    let inner = &list["Vec<".len()..list.len() - 1];
+    match items.iter().all(|ty| ty == list || ty == inner) {
+        true => list.clone(),
+        false => "String".to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -801,6 +1372,129 @@ mod tests {
     use crate::path_parameter::PathParameter;
     use std::collections::HashSet;
 
+    #[test]
+    fn deprecation_message_uses_the_first_line_of_the_description() {
+        let deprecation = Deprecation {
+            version: "8.0.0".to_string(),
+            description: "Use the \"index\" API.\nMore details.".to_string(),
+        };
+        assert_eq!(
+            deprecation_message(&deprecation),
+            "deprecated since 8.0.0: Use the \\\"index\\\" API."
+        );
+    }
+
+    #[test]
+    fn number_type_keeps_the_range_of_numeric_aliases() {
+        assert_eq!(number_type("integer"), Some("i32"));
+        assert_eq!(number_type("uint"), Some("u32"));
+        assert_eq!(number_type("double"), Some("f64"));
+        assert_eq!(number_type("Duration"), None);
+        assert_eq!(alias_type("Duration"), Some("staticcmds::TimeValue"));
+        assert_eq!(alias_type("ByteSize"), Some("staticcmds::ByteSize"));
+        assert_eq!(alias_type("long"), Some("i64"));
+    }
+
+    #[test]
+    fn union_type_of_a_type_and_its_arrays_is_a_vec() {
+        let items = |items: &[&str]| items.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            union_type(&items(&["String", "Vec<String>"])),
+            "Vec<String>"
+        );
+        assert_eq!(
+            union_type(&items(&["ExpandWildcard", "Vec<ExpandWildcard>"])),
+            "Vec<ExpandWildcard>"
+        );
+        assert_eq!(union_type(&items(&["String", "i64"])), "String");
+        assert_eq!(union_type(&items(&["i64", "Vec<String>"])), "String");
+    }
+
+    #[test]
+    fn description_ends_with_the_documentation_url() {
+        let endpoint = Endpoint {
+            e: clients_schema::Endpoint {
+                name: "indices.create".to_string(),
+                description: "Create an index.".to_string(),
+                doc_url: Some("https://www.elastic.co/docs/api/indices-create".to_string()),
+                doc_id: Some("indices-create-index".to_string()),
+                ext_doc_id: None,
+                ext_doc_url: None,
+                ext_doc_description: None,
+                ext_previous_version_doc_url: None,
+                deprecation: None,
+                availability: None,
+                urls: vec![],
+                request_media_type: vec![],
+                response_media_type: vec![],
+                request: None,
+                request_body_required: false,
+                doc_tag: None,
+                response: None,
+                privileges: None,
+            },
+            path_parameters: vec![],
+            query_parameters: vec![],
+            enums: HashMap::new(),
+            paths_selection: Tokens::new(),
+            has_request: false,
+            has_pit: false,
+        };
+        assert_eq!(
+            endpoint.description(),
+            "Create an index.\\n\\nDocumentation: https://www.elastic.co/docs/api/indices-create (indices-create-index)"
+        );
+    }
+
+    #[test]
+    fn unstable_endpoints_are_labelled_and_hidden() {
+        let endpoint = |stability: &str| Endpoint {
+            e: clients_schema::Endpoint {
+                name: "inference.get".to_string(),
+                description: "Get an inference endpoint\nMore details.".to_string(),
+                doc_url: None,
+                doc_id: None,
+                ext_doc_id: None,
+                ext_doc_url: None,
+                ext_doc_description: None,
+                ext_previous_version_doc_url: None,
+                deprecation: None,
+                availability: serde_json::from_value(
+                    serde_json::json!({ "stack": { "stability": stability } }),
+                )
+                .ok(),
+                urls: vec![],
+                request_media_type: vec![],
+                response_media_type: vec![],
+                request: None,
+                request_body_required: false,
+                doc_tag: None,
+                response: None,
+                privileges: None,
+            },
+            path_parameters: vec![],
+            query_parameters: vec![],
+            enums: HashMap::new(),
+            paths_selection: Tokens::new(),
+            has_request: false,
+            has_pit: false,
+        };
+        let beta = endpoint("beta");
+        assert_eq!(beta.stability(), Some("beta"));
+        assert_eq!(beta.about(), "[beta] Get an inference endpoint");
+        let command = beta.generate_new_command().to_string().unwrap_or_default();
+        assert!(command.contains(".hide(!show_unstable)"));
+
+        let stable = endpoint("stable");
+        assert_eq!(stable.stability(), None);
+        assert_eq!(stable.about(), "Get an inference endpoint");
+        let command = stable
+            .generate_new_command()
+            .to_string()
+            .unwrap_or_default();
+        assert!(!command.contains("hide"));
+    }
+
     #[test]
     fn test_collect_optional_parameters() {
         let endpoint = Endpoint {
@@ -844,6 +1538,7 @@ mod tests {
             enums: HashMap::new(),
             paths_selection: Tokens::new(),
             has_request: false,
+            has_pit: false,
         };
         let optional = endpoint.collect_optional_parameters();
         let mut expected = HashSet::new();
@@ -889,6 +1584,7 @@ mod tests {
             enums: HashMap::new(),
             paths_selection: Tokens::new(),
             has_request: false,
+            has_pit: false,
         };
         let optional = HashSet::new();
         let params = endpoint.build_path_parameters(&optional);
@@ -936,6 +1632,7 @@ mod tests {
             enums: HashMap::new(),
             paths_selection: Tokens::new(),
             has_request: false,
+            has_pit: false,
         };
         endpoint.generate_path_selection_tokens(&mut toks, &path_params);
         let toks_str = toks.to_string().unwrap_or_default();
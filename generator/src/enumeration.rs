@@ -30,16 +30,46 @@ pub(crate) struct Enum {
     // wire_name: the value sent over the wire (used in serde rename, Display, FromStr).
     // code_name: the identifier used for the Rust variant (safe to convert to PascalCase).
     members: Vec<(String, String)>,
+    // The first line of the description of each member, shown next to the possible values.
+    descriptions: Vec<String>,
 }
 
 impl Enum {
     pub fn new(name: &str, members: Vec<(String, String)>) -> Self {
         Enum {
             name: name.to_string(),
+            descriptions: vec![String::new(); members.len()],
             members,
         }
     }
 
+    // Sets the descriptions of the members, in the same order.
+    pub fn with_descriptions(mut self, descriptions: Vec<Option<String>>) -> Self {
+        self.descriptions = descriptions
+            .into_iter()
+            .map(|d| {
+                d.unwrap_or_default()
+                    .lines()
+                    .next()
+                    .unwrap_or("")
+                    .to_string()
+            })
+            .collect();
+        self
+    }
+
+    // Returns the possible value of a member, with its description as help if any.
+    fn possible_value(&self, index: usize) -> Tokens {
+        let wire = &self.members[index].0;
+        match self.descriptions.get(index).filter(|d| !d.is_empty()) {
+            Some(description) => {
+                let description = description.escape_default().to_string();
+                quote!(clap::builder::PossibleValue::new($(quoted(wire))).help($(quoted(description))))
+            }
+            None => quote!(clap::builder::PossibleValue::new($(quoted(wire)))),
+        }
+    }
+
     pub fn generate(&self) -> Tokens {
         quote! {
             // The enumeration definition.
@@ -80,6 +110,65 @@ impl Enum {
                     }
                 }
             }
+
+            // Implements `clap::ValueEnum` so that the possible values are listed in the help,
+            // validated when parsing and offered during shell completion.
+            impl clap::ValueEnum for $(&self.name) {
+                fn value_variants<'a>() -> &'a [Self] {
+                    &[$(for (_, code) in &self.members join (, ) => Self::$(code.to_case(Case::Pascal)))]
+                }
+
+                fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+                    let value = match self {
+                        $(
+                            for (i, (_, code)) in self.members.iter().enumerate() =>
+                            Self::$(code.to_case(Case::Pascal)) => $(self.possible_value(i)),$['\r']
+                        )
+                    };
+                    Some(value)
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_implements_value_enum_with_wire_names() {
+        let e = Enum::new(
+            "HealthStatus",
+            vec![
+                ("green".to_string(), "green".to_string()),
+                ("yellow".to_string(), "yellow".to_string()),
+            ],
+        );
+        let tokens = e.generate().to_string().unwrap_or_default();
+        assert!(tokens.contains("impl clap::ValueEnum for HealthStatus"));
+        assert!(tokens.contains("Self::Green, Self::Yellow"));
+        assert!(tokens.contains("Self::Yellow => \"yellow\","));
+        assert!(tokens.contains("Self::Yellow => clap::builder::PossibleValue::new(\"yellow\"),"));
+    }
+
+    #[test]
+    fn generate_shows_member_descriptions_as_possible_value_help() {
+        let e = Enum::new(
+            "Refresh",
+            vec![
+                ("true".to_string(), "true".to_string()),
+                ("wait_for".to_string(), "wait_for".to_string()),
+            ],
+        )
+        .with_descriptions(vec![
+            None,
+            Some("Wait for a refresh.\nMore details.".to_string()),
+        ]);
+        let tokens = e.generate().to_string().unwrap_or_default();
+        assert!(tokens.contains("Self::True => clap::builder::PossibleValue::new(\"true\"),"));
+        assert!(tokens.contains(
+            "Self::WaitFor => clap::builder::PossibleValue::new(\"wait_for\").help(\"Wait for a refresh.\"),"
+        ));
+    }
+}
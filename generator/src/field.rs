@@ -15,15 +15,31 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::endpoint::GENERATED_FIELDS;
 use genco::tokens::quoted;
 use genco::{Tokens, quote};
 
+// Rust keywords, strict and reserved, which cannot name a field.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// Flags of clap, and the documentation flags the generator adds to the commands, which
+// a field named the same would clash with. A long `--h` would read like clap's `-h`.
+const RESERVED_FLAGS: &[&str] = &["help", "version", "h", "docs", "open"];
+
 // Represents a field in an API endpoint.
 // A field contains metadata such as its name, description, type, and whether it is required.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Field {
-    // The name of the field.
+    // The Rust identifier of the field.
     name: String,
+    // The name of the field in the schema, sent to Elasticsearch.
+    wire_name: String,
     // A description of the field.
     description: String,
     // Indicates whether the field is required.
@@ -32,6 +48,8 @@ pub struct Field {
     ty: String,
     // An optional default value for the field.
     default_value: Option<String>,
+    // The deprecation of the field, e.g. "deprecated since 8.0.0", if it is deprecated.
+    deprecation: Option<String>,
 }
 
 impl Field {
@@ -54,7 +72,8 @@ impl Field {
         ty: String,
         default_value: Option<String>,
     ) -> Self {
-        let name = Self::sanitize_field_name(&name);
+        let wire_name = name;
+        let name = Self::sanitize_field_name(&wire_name);
 
         let description = if description.is_empty() {
             "".to_string()
@@ -64,13 +83,36 @@ impl Field {
 
         Field {
             name,
+            wire_name,
             description,
             required,
             ty,
             default_value,
+            deprecation: None,
         }
     }
 
+    // Sets the deprecation of the field, warned about when the field is set.
+    pub fn with_deprecation(mut self, deprecation: Option<String>) -> Self {
+        self.deprecation = deprecation;
+        self
+    }
+
+    // Returns the `(is_set, warning)` pair of a deprecated field, evaluated in `execute`.
+    pub fn deprecation_warning(&self) -> Option<Tokens> {
+        let deprecation = self.deprecation.as_ref()?;
+        let warning = format!("--{} is {deprecation}", self.flag());
+        let name = &self.name;
+        let is_set = if self.is_vec() {
+            quote!(!self.$name.is_empty())
+        } else if self.required {
+            quote!(true)
+        } else {
+            quote!(self.$name.is_some())
+        };
+        Some(quote!(($is_set, $(quoted(warning)))))
+    }
+
     pub fn typ(&self) -> String {
         if self.is_vec() {
             self.ty.clone()
@@ -103,6 +145,16 @@ impl Field {
         }
     }
 
+    // Returns the declaration of this field in the Q struct, renamed to its wire name
+    // when its identifier differs.
+    pub fn q_field(&self) -> Tokens {
+        let rename = match self.name == self.wire_name {
+            true => quote!(),
+            false => quote!(#[serde(rename = $(quoted(&self.wire_name)))]),
+        };
+        quote!($rename $(&self.name): $(self.q_typ()))
+    }
+
     // Returns the expression to assign this field in the Q struct.
     // Vec fields are joined into a comma-separated string (or None if empty).
     pub fn q_assign(&self) -> Tokens {
@@ -119,22 +171,39 @@ impl Field {
         &self.name
     }
 
-    fn sanitize_field_name(name: &str) -> String {
-        match name {
-            "type" => "ty".to_string(),
-            "help" => "help_".to_string(),
-            "h" => "h_".to_string(),
-            // Add more reserved words as needed
-            _ => name.to_string(),
+    // Returns the Rust identifier for a schema name: characters an identifier cannot
+    // hold become `_`, and keywords or reserved flags get a `_` suffix.
+    //
+    // Raw identifiers are not an option, `format!` cannot capture them in URL templates.
+    pub(crate) fn sanitize_field_name(name: &str) -> String {
+        let mut ident: String = name
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c,
+                false => '_',
+            })
+            .collect();
+        if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+            ident.insert(0, '_');
+        }
+        if KEYWORDS.contains(&ident.as_str()) || Self::is_reserved(&ident) {
+            ident.push('_');
         }
+        ident
     }
 
-    pub(crate) fn original_field_name(&self) -> String {
-        match self.name.as_str() {
-            "ty" => "r#type".to_string(),
-            "help_" => "help".to_string(),
-            "h_" => "h".to_string(),
-            _ => self.name.to_string(),
+    // Returns if a field named `name` would clash with a flag of clap, or with a field
+    // the generator adds to the commands.
+    fn is_reserved(name: &str) -> bool {
+        RESERVED_FLAGS.contains(&name) || GENERATED_FIELDS.contains(&name)
+    }
+
+    // Returns the long flag of the field: its schema name, unless that clashes with a
+    // reserved flag.
+    fn flag(&self) -> &str {
+        match Self::is_reserved(&self.wire_name) {
+            true => &self.name,
+            false => &self.wire_name,
         }
     }
 
@@ -161,22 +230,29 @@ impl Field {
     pub fn arg(&self) -> Tokens {
         let short_help = self.short_help().escape_default().to_string();
         let long_help = self.long_help().escape_default().to_string();
-        let name = self.name.escape_default().to_string();
+        let name = self.flag().escape_default().to_string();
+        // Index names complete from the cluster of the active profile.
+        let completer = match self.name.as_str() {
+            "index" => {
+                quote!(, add = clap_complete::engine::ArgValueCompleter::new(staticcmds::complete_index))
+            }
+            _ => quote!(),
+        };
 
         if self.is_vec() {
             return quote! {
-                #[arg(long($(quoted(&name))), help = $(quoted(&short_help)), long_help = $(quoted(&long_help)), num_args = 0.., value_delimiter = ',')]
+                #[arg(long($(quoted(&name))), help = $(quoted(&short_help)), long_help = $(quoted(&long_help)), num_args = 0.., value_delimiter = ','$(&completer))]
                 $(&self.name): $(&self.typ()),$['\r']
             };
         }
 
         let base_quote = |action: Option<&str>| match action {
             Some(action) => quote! {
-                #[arg(long($(quoted(&name))), help = $(quoted(&short_help)), long_help = $(quoted(&long_help)), action=$(action))]
+                #[arg(long($(quoted(&name))), help = $(quoted(&short_help)), long_help = $(quoted(&long_help)), action=$(action)$(&completer))]
                 $(&self.name): $(&self.typ()),$['\r']
             },
             None => quote! {
-                #[arg(long($(quoted(&name))), help = $(quoted(&short_help)), long_help = $(quoted(&long_help)))]
+                #[arg(long($(quoted(&name))), help = $(quoted(&short_help)), long_help = $(quoted(&long_help))$(&completer))]
                 $(&self.name): $(&self.typ()),$['\r']
             },
         };
@@ -185,7 +261,7 @@ impl Field {
             match self.ty.as_str() {
                 "bool" => base_quote(None),
                 _ => quote! {
-                    #[arg(help = $(quoted(&short_help)), long_help = $(quoted(&long_help)))]
+                    #[arg(help = $(quoted(&short_help)), long_help = $(quoted(&long_help))$(&completer))]
                     $(&self.name): $(&self.typ()),$['\r']
                 },
             }
@@ -216,10 +292,12 @@ mod tests {
     fn short_help_returns_first_line_of_description() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "First line.\nSecond line.".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.short_help(), "First line.");
     }
@@ -228,10 +306,12 @@ mod tests {
     fn short_help_returns_empty_string_when_description_is_empty() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.short_help(), "");
     }
@@ -240,10 +320,12 @@ mod tests {
     fn short_help_handles_single_line_description() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "Single line description.".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.short_help(), "Single line description.");
     }
@@ -252,10 +334,12 @@ mod tests {
     fn long_help_returns_full_description() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "Full description text.".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.long_help(), "Full description text.");
     }
@@ -264,10 +348,12 @@ mod tests {
     fn long_help_returns_empty_string_when_description_is_empty() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.long_help(), "");
     }
@@ -276,10 +362,12 @@ mod tests {
     fn long_help_handles_multiline_description() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "Line one.\nLine two.\nLine three.".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.long_help(), "Line one.\nLine two.\nLine three.");
     }
@@ -288,10 +376,12 @@ mod tests {
     fn arg_generates_correct_tokens_for_required_bool_field() {
         let field = Field {
             name: "flag".to_string(),
+            wire_name: "flag".to_string(),
             description: "A boolean flag.".to_string(),
             required: true,
             ty: "bool".to_string(),
             default_value: None,
+            deprecation: None,
         };
         let tokens = field.arg().to_string().unwrap_or_default();
         assert!(
@@ -306,10 +396,12 @@ mod tests {
     fn arg_generates_correct_tokens_for_required_non_bool_field() {
         let field = Field {
             name: "value".to_string(),
+            wire_name: "value".to_string(),
             description: "A required value.".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         let tokens = field.arg().to_string().unwrap_or_default();
         assert!(
@@ -324,10 +416,12 @@ mod tests {
     fn arg_generates_correct_tokens_for_optional_field() {
         let field = Field {
             name: "optional_value".to_string(),
+            wire_name: "optional_value".to_string(),
             description: "An optional value.".to_string(),
             required: false,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         let tokens = field.arg().to_string().unwrap_or_default();
         assert!(tokens.contains(
@@ -336,14 +430,33 @@ mod tests {
         assert!(tokens.contains("optional_value: Option<String>,"));
     }
 
+    #[test]
+    fn arg_completes_index_names() {
+        let field = Field {
+            name: "index".to_string(),
+            wire_name: "index".to_string(),
+            description: "A list of index names.".to_string(),
+            required: true,
+            ty: "String".to_string(),
+            default_value: None,
+            deprecation: None,
+        };
+        let tokens = field.arg().to_string().unwrap_or_default();
+        assert!(tokens.contains(
+            "add = clap_complete::engine::ArgValueCompleter::new(staticcmds::complete_index)"
+        ));
+    }
+
     #[test]
     fn arg_handles_empty_description_correctly() {
         let field = Field {
             name: "empty_desc".to_string(),
+            wire_name: "empty_desc".to_string(),
             description: "".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         let tokens = field.arg().to_string().unwrap_or_default();
         assert!(tokens.contains("#[arg(help = \"\", long_help = \"\")]"));
@@ -354,10 +467,12 @@ mod tests {
     fn typ_returns_original_type_when_field_is_required() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "A required field.".to_string(),
             required: true,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.typ(), "String");
     }
@@ -366,10 +481,12 @@ mod tests {
     fn typ_returns_option_wrapped_type_when_field_is_not_required() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "An optional field.".to_string(),
             required: false,
             ty: "String".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.typ(), "Option<String>");
     }
@@ -378,10 +495,12 @@ mod tests {
     fn typ_handles_empty_type_correctly() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "A field with no type.".to_string(),
             required: true,
             ty: "".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.typ(), "");
     }
@@ -390,22 +509,92 @@ mod tests {
     fn typ_handles_non_standard_type_correctly() {
         let field = Field {
             name: "example".to_string(),
+            wire_name: "example".to_string(),
             description: "A field with a custom type.".to_string(),
             required: false,
             ty: "CustomType".to_string(),
             default_value: None,
+            deprecation: None,
         };
         assert_eq!(field.typ(), "Option<CustomType>");
     }
 
+    #[test]
+    fn sanitize_field_name_handles_keywords_reserved_flags_and_symbols() {
+        assert_eq!(Field::sanitize_field_name("type"), "type_");
+        assert_eq!(Field::sanitize_field_name("async"), "async_");
+        assert_eq!(Field::sanitize_field_name("help"), "help_");
+        assert_eq!(Field::sanitize_field_name("h"), "h_");
+        assert_eq!(Field::sanitize_field_name("param"), "param_");
+        assert_eq!(Field::sanitize_field_name("sort"), "sort_");
+        assert_eq!(Field::sanitize_field_name("docs.count"), "docs_count");
+        assert_eq!(Field::sanitize_field_name("1st"), "_1st");
+        assert_eq!(Field::sanitize_field_name("index"), "index");
+    }
+
+    #[test]
+    fn keyword_fields_keep_their_flag_and_wire_name() {
+        let field = Field::new(
+            "type".to_string(),
+            "A type.".to_string(),
+            false,
+            "String".to_string(),
+            None,
+        );
+        let arg = field.arg().to_string().unwrap_or_default();
+        assert!(arg.contains("#[arg(long(\"type\")"));
+        assert!(arg.contains("type_: Option<String>,"));
+        let q = field.q_field().to_string().unwrap_or_default();
+        assert!(q.contains("#[serde(rename = \"type\")]"));
+        assert!(q.contains("type_: Option<String>"));
+    }
+
+    #[test]
+    fn reserved_flags_are_suffixed() {
+        let field = Field::new(
+            "help".to_string(),
+            "".to_string(),
+            false,
+            "bool".to_string(),
+            None,
+        );
+        let arg = field.arg().to_string().unwrap_or_default();
+        assert!(arg.contains("#[arg(long(\"help_\")"));
+        let q = field.q_field().to_string().unwrap_or_default();
+        assert!(q.contains("#[serde(rename = \"help\")]"));
+        assert!(q.contains("help_: Option<bool>"));
+    }
+
+    #[test]
+    fn deprecation_warning_checks_whether_the_field_is_set() {
+        let field = Field::new(
+            "level".to_string(),
+            "".to_string(),
+            false,
+            "String".to_string(),
+            None,
+        );
+        assert!(field.deprecation_warning().is_none());
+        let warning = field
+            .with_deprecation(Some("deprecated since 8.0.0".to_string()))
+            .deprecation_warning()
+            .unwrap()
+            .to_string()
+            .unwrap_or_default();
+        assert!(warning.contains("self.level.is_some()"));
+        assert!(warning.contains("\"--level is deprecated since 8.0.0\""));
+    }
+
     #[test]
     fn arg_optional_bool_with_default_false_sets_settrue_action() {
         let field = Field {
             name: "flag".to_string(),
+            wire_name: "flag".to_string(),
             description: "Optional flag.".to_string(),
             required: false,
             ty: "bool".to_string(),
             default_value: Some("false".to_string()),
+            deprecation: None,
         };
         let tokens = field.arg().to_string().unwrap_or_default();
         assert!(tokens.contains("action=clap::ArgAction::SetTrue"));
@@ -416,10 +605,12 @@ mod tests {
     fn arg_optional_bool_with_default_true_sets_setfalse_action() {
         let field = Field {
             name: "flag".to_string(),
+            wire_name: "flag".to_string(),
             description: "Optional flag.".to_string(),
             required: false,
             ty: "bool".to_string(),
             default_value: Some("true".to_string()),
+            deprecation: None,
         };
         let tokens = field.arg().to_string().unwrap_or_default();
         assert!(tokens.contains("action=clap::ArgAction::SetFalse"));
@@ -430,10 +621,12 @@ mod tests {
     fn arg_optional_bool_with_nonstandard_default_omits_action() {
         let field = Field {
             name: "flag".to_string(),
+            wire_name: "flag".to_string(),
             description: "Optional flag.".to_string(),
             required: false,
             ty: "bool".to_string(),
             default_value: Some("maybe".to_string()),
+            deprecation: None,
         };
         let tokens = field.arg().to_string().unwrap_or_default();
         assert!(!tokens.contains("action=clap::ArgAction::SetTrue"));
@@ -445,10 +638,12 @@ mod tests {
     fn arg_optional_bool_with_no_default_omits_action() {
         let field = Field {
             name: "flag".to_string(),
+            wire_name: "flag".to_string(),
             description: "Optional flag.".to_string(),
             required: false,
             ty: "bool".to_string(),
             default_value: None,
+            deprecation: None,
         };
         let tokens = field.arg().to_string().unwrap_or_default();
         assert!(!tokens.contains("action=clap::ArgAction::SetTrue"));
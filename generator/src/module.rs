@@ -42,12 +42,19 @@ pub fn generate(namespaces: &[String]) -> Tokens {
             Ok((k.to_string(), v.to_string()))
         }
 
+        // Rewrites a successful response body before it is written to stdout.
+        pub type PostProcess = Box<dyn FnOnce(&[u8]) -> Result<Vec<u8>, String> + Send>;
+
         pub struct TransportArgs {
             pub method: Method,
             pub path: String,
             pub headers: HeaderMap,
             pub query_string: Box<dyn erased_serde::Serialize>,
             pub body: Option<String>,
+            pub post_process: Option<PostProcess>,
+            pub privileges: Option<staticcmds::RequiredPrivileges>,
+            // Warnings about the deprecated command, path and arguments used.
+            pub deprecations: Vec<String>,
         }
 
         pub trait Executor {
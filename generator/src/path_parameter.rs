@@ -31,6 +31,8 @@ pub struct PathParameter {
     optional_parameters: HashSet<String>,
     // The HTTP method for the path.
     method: String,
+    // The deprecation warning of the path, if it is deprecated.
+    deprecation: Option<String>,
 }
 
 impl PathParameter {
@@ -63,6 +65,25 @@ impl PathParameter {
             mandatory_parameters,
             optional_parameters,
             method,
+            deprecation: None,
+        }
+    }
+
+    // Sets the deprecation warning printed when the path is selected.
+    pub fn with_deprecation(mut self, deprecation: Option<String>) -> Self {
+        self.deprecation = deprecation;
+        self
+    }
+
+    pub fn deprecation(&self) -> Option<&str> {
+        self.deprecation.as_deref()
+    }
+
+    // Records the deprecation warning of the path in `url_deprecation` when it is selected.
+    fn record_deprecation(&self) -> Tokens {
+        match &self.deprecation {
+            Some(warning) => quote!(url_deprecation = Some($(quoted(warning)));),
+            None => quote!(),
         }
     }
 
@@ -78,6 +99,7 @@ impl PathParameter {
         if self.params().is_empty() {
             quote! {
                 _ => {
+                    $(self.record_deprecation())
                     (
                     $(quoted(&self.path)).into(),
                     Method::$(self.method.clone())
@@ -87,6 +109,7 @@ impl PathParameter {
         } else {
             quote! {
                 $(self.pattern_params()) => {
+                    $(self.record_deprecation())
                     (
                     format!($(quoted(&self.path))),
                     Method::$(self.method.clone())
@@ -170,6 +193,7 @@ mod tests {
             mandatory_parameters: HashSet::from(["param1".to_string(), "param2".to_string()]),
             optional_parameters: HashSet::from(["param3".to_string()]),
             method: "GET".to_string(),
+            deprecation: None,
         };
         let mut result = path_param.params();
         result.sort();
@@ -191,6 +215,7 @@ mod tests {
             mandatory_parameters: HashSet::new(),
             optional_parameters: HashSet::new(),
             method: "GET".to_string(),
+            deprecation: None,
         };
         let result = path_param.params();
         assert!(result.is_empty());
@@ -204,6 +229,7 @@ mod tests {
             mandatory_parameters: HashSet::from(["param1".to_string()]),
             optional_parameters: HashSet::new(),
             method: "GET".to_string(),
+            deprecation: None,
         };
         let result = path_param.pattern_params();
         assert_eq!(result, "param1");
@@ -217,6 +243,7 @@ mod tests {
             mandatory_parameters: HashSet::new(),
             optional_parameters: HashSet::from(["param1".to_string()]),
             method: "GET".to_string(),
+            deprecation: None,
         };
         let result = path_param.pattern_params();
         assert_eq!(result, "Some(param1)");
@@ -230,6 +257,7 @@ mod tests {
             mandatory_parameters: HashSet::new(),
             optional_parameters: HashSet::new(),
             method: "GET".to_string(),
+            deprecation: None,
         };
         let result = path_param.pattern_params();
         assert_eq!(result, "(None,None)");
@@ -243,6 +271,7 @@ mod tests {
             mandatory_parameters: HashSet::from(["param1".to_string()]),
             optional_parameters: HashSet::from(["param2".to_string()]),
             method: "GET".to_string(),
+            deprecation: None,
         };
         let result = path_param.pattern_params();
         assert_eq!(result, "(param1,Some(param2))");
@@ -256,8 +285,27 @@ mod tests {
             mandatory_parameters: HashSet::from(["param1".to_string()]),
             optional_parameters: HashSet::from(["param2".to_string()]),
             method: "GET".to_string(),
+            deprecation: None,
         };
         let result = path_param.pattern_params();
         assert_eq!(result, "()");
     }
+
+    #[test]
+    fn generate_records_the_deprecation_of_the_path() {
+        let path_param = PathParameter::new(
+            "/_xpack/{id}".to_string(),
+            vec!["id".to_string()],
+            HashSet::from(["id".to_string()]),
+            HashSet::new(),
+            "Get".to_string(),
+        )
+        .with_deprecation(Some(
+            "the path /_xpack/{id} is deprecated since 7.0.0".to_string(),
+        ));
+        let tokens = path_param.generate().to_string().unwrap_or_default();
+        assert!(tokens.contains(
+            "url_deprecation = Some(\"the path /_xpack/{id} is deprecated since 7.0.0\");"
+        ));
+    }
 }